@@ -9,6 +9,7 @@ notification-in-progress = File operations are in progress.
 trash = Trash
 recents = Recents
 undo = Undo
+redo = Redo
 today = Today
 
 # Quick Access
@@ -46,6 +47,23 @@ modified = Modified
 trashed-on = Trashed
 size = Size
 
+# Search
+search-names = Names
+search-contents = Contents
+search-contents-regex = Contents (regex)
+search-match-line = {$line}: {$text}
+search-match-more = {$count} more {$count ->
+    [one] match
+    *[other] matches
+  }
+search-error-quote = Missing closing quote
+search-error-missing = {$key}: needs a value
+search-error-size = Invalid size "{$value}", use for example size:>100M
+search-error-modified = Invalid time "{$value}", use for example modified:<7d or modified:>2024-01-31
+search-error-type = Unknown type "{$value}", use for example type:image, type:dir or type:text/plain
+search-error-owner = Unknown user "{$value}"
+search-error-regex = Invalid regular expression: {$error}
+
 # Progress footer
 details = Details
 dismiss = Dismiss message
@@ -62,11 +80,23 @@ resume = Resume
 
 # Dialogs
 
+## Add to Archive Dialog
+add-to-archive = Add to archive
+add-to-archive-question = Add {$target} to "{$archive}"? Items with the same names in the archive are replaced.
+add = Add
+
 ## Compress Dialog
 create-archive = Create archive
+compression-level = Compression level
+estimated-size = Estimated size: {$size}
+estimating = Estimating size...
+zip-encryption = Encryption
+zip-crypto-warning = ZipCrypto is easily broken, only use it for programs that cannot open AES-256.
 
 ## Extract Dialog
 extract-password-required = Password required
+extract-password-wrong = Wrong password
+extract-password-wrong-retry = The password was wrong, try again.
 extract-to = Extract To...
 extract-to-title = Extract to folder
 
@@ -114,6 +144,14 @@ permanently-delete-question = Permanently delete
 delete = Delete
 permanently-delete-warning = Are you sure you want to permanently delete {$target}? This cannot be undone.
 
+## Shred Dialog
+shred-question = Shred
+shred = Shred
+shred-warning = Are you sure you want to overwrite and permanently delete {$target}? This cannot be undone.
+shred-unreliable-remote = This is a network location, the server may keep copies of the overwritten data.
+shred-unreliable-copy-on-write = This filesystem writes changes to new locations, so the original data may remain on the disk.
+shred-unreliable-flash = This is a solid state drive, which may keep the original data in blocks that cannot be overwritten.
+
 ## Rename Dialog
 rename-file = Rename file
 rename-folder = Rename folder
@@ -127,6 +165,7 @@ original-file = Original file
 replace-with = Replace with
 apply-to-all = Apply to all
 keep-both = Keep both
+apply-rule-to-all = Apply a rule to all remaining conflicts
 skip = Skip
 
 ## Set as Executable and Launch Dialog
@@ -156,6 +195,19 @@ read-write = Read and write
 ### Mode 7
 read-write-execute = Read, write, and execute
 
+## Permissions Dialog
+edit-permissions = Edit permissions
+edit-permissions-description = Change the permissions and ownership of {$target}.
+file-permissions = Files
+folder-permissions = Folders
+permissions-set = Set these permissions
+permissions-add = Only add these permissions
+permissions-remove = Only remove these permissions
+permissions-invalid = Permissions must be octal numbers like 644, or both left empty
+apply-recursively = Apply to everything inside of folders
+unchanged = Unchanged
+apply = Apply
+
 ## Favorite Path Error Dialog
 favorite-path-error = Error opening directory
 favorite-path-error-description =
@@ -166,8 +218,98 @@ favorite-path-error-description =
 remove = Remove
 keep = Keep
 
+## Interrupted Operation Dialog
+interrupted-operation = Operation interrupted
+interrupted-operation-description =
+    This operation did not finish the last time the application ran:
+    {$operation}
+
+    Would you like to resume it where it stopped, or discard the partially written item?
+resume = Resume
+discard = Discard
+
+## Verify Failed Dialog
+verify-failed = Copy verification failed
+verify-failed-description =
+    The copy of "{$name}" does not match the original and was removed.
+    Try again to copy it and continue the operation.
+
+## Failure Report Dialog
+failure-report = Some items failed
+failure-report-description = {$items} {$items ->
+        [one] item was
+        *[other] items were
+    } skipped because of errors. The rest of the operation completed.
+retry-failed = Retry failed items
+close = Close
+
+## Plan Preview Dialog
+plan-preview = Review before starting
+planning = Looking through the items...
+plan-items = {$files} {$files ->
+        [one] file
+        *[other] files
+    }, {$dirs} {$dirs ->
+        [one] folder
+        *[other] folders
+    }, {$size} in total
+plan-links = {$links} {$links ->
+        [one] link
+        *[other] links
+    }
+plan-conflicts = {$items} {$items ->
+        [one] item already exists
+        *[other] items already exist
+    } at the destination
+plan-skipped = {$items} existing {$items ->
+        [one] folder
+        *[other] folders
+    } will be skipped
+plan-special = {$items} special {$items ->
+        [one] file
+        *[other] files
+    } such as sockets or devices cannot be copied
+plan-failures = {$items} {$items ->
+        [one] item
+        *[other] items
+    } cannot be read and will be skipped
+plan-space-remaining = {$size} will remain free at the destination
+plan-more = ...and {$items} more
+start = Start
+
+## Unsupported Items Dialog
+unsupported-items = The destination cannot store some items
+unsupported-items-description = {$items} {$items ->
+        [one] problem was
+        *[other] problems were
+    } found with the {$fs} filesystem at the destination. Nothing was copied.
+sanitize-names = Replace characters the destination does not allow
+violation-no-space = Not enough space: {$needed} needed, {$available} available
+violation-too-large = {$path} is {$size}, larger than the {$max} the destination allows
+violation-invalid-name = {$path} contains characters the destination does not allow
+violation-name-collision = {$path} and {$other} differ only in case, which the destination ignores
+violation-name-too-long = Name too long for the destination: {$path}
+violation-path-too-long = Path too long: {$path}
+
 # Context Pages
 
+## Command line
+cli-usage = Usage:
+      vortex-fm [--no-daemon] [--trash | --recents | --network | LOCATION...]
+      vortex-fm copy | move | extract | compress [OPTION...] SOURCE... DESTINATION
+      vortex-fm trash [OPTION...] ITEM...
+
+    Options:
+      --on-conflict=cancel|replace|skip|keep-both
+          Answer to items that already exist at the destination, cancel by default
+      --keep-going
+          Continue past items that fail, and report them at the end
+cli-invalid-conflict = Invalid value for --on-conflict: "{$value}"
+cli-unknown-option = Unknown option: {$option}
+cli-missing-paths = Missing items to operate on
+cli-unknown-archive = Unknown archive type of "{$path}", expected one of: {$extensions}
+cli-conflict-cancelled = Cancelled, "{$path}" already exists
+
 ## About
 repository = Repository
 support = Support
@@ -202,13 +344,63 @@ cancelled = Cancelled
 edit-history = Edit history
 history = History
 no-history = No items in history.
+undo-history = Undo history
+operation-warnings = Completed with {$count} {$count ->
+        [one] warning
+        *[other] warnings
+    }: {$warning}
+checksum-mismatch = The copy of "{$name}" does not match the original
+error-permission-denied = Permission denied: {$path}
+error-no-space = Not enough space: {$path}
+error-name-too-long = Name too long: {$path}
+error-io = {$path}: {$message}
+remedy-permission-denied = Check that you have permission to read the source and write to the destination.
+remedy-no-space = Free up space on the destination, or choose another location.
+remedy-name-too-long = Rename the item, or choose a destination that allows longer names.
+remedy-unsupported = Rename the items, free up space, or choose a destination with another filesystem.
+error-unsupported = The {$fs} filesystem cannot store {$items} {$items ->
+        [one] item
+        *[other] items
+    }
+error-unsafe-entry = Refused to extract "{$name}": {$reason}
+error-corrupt-entry = "{$name}" in "{$archive}" is corrupt: {$message}
+error-corrupt-archive = "{$name}" is corrupt: {$message}
+remedy-corrupt-entry = Download or copy the archive again, the corrupt items cannot be extracted.
+remedy-unsafe-entry = Only extract archives from sources you trust. The size limits can be changed in the settings.
+unsafe-entry-traversal = its path leads outside of the destination
+unsafe-entry-symlink = it links or writes outside of the destination
+unsafe-entry-hard-link = it is a hard link to a file outside of the destination
+unsafe-entry-special = it is a device or a named pipe
+unsafe-entry-too-large = the extracted files would be larger than the size limit
+unsafe-entry-too-compressed = the extracted files would be larger than the compression ratio limit allows
+hard-link-cross-device = Cannot hard link "{$name}": hard links cannot point to another drive. Paste as a link instead.
+hard-link-directory = Cannot hard link "{$name}": folders cannot be hard linked. Paste as a link instead.
+undo-refused = Cannot undo, "{$name}" changed since the operation completed.
+redo-refused = Cannot redo, "{$name}" already exists.
 pending = Pending
+queued = Queued, {$position} in line
+run-next = Run next
+move-up = Move up
+move-down = Move down
 progress = {$percent}%
 progress-cancelled = {$percent}%, cancelled
 progress-failed = {$percent}%, failed
 progress-paused = {$percent}%, paused
+progress-details-eta = {$name} — {$rate}/s, {$eta} remaining
+progress-details-stalled = {$name} — stalled
+duration-hours = {$hours} h {$minutes} min
+duration-minutes = {$minutes} min {$seconds} s
+duration-seconds = {$seconds} s
 failed = Failed
 complete = Complete
+adding-to-archive = Adding {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" to "{$to}" ({$progress})...
+added-to-archive = Added {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" to "{$to}"
 compressing = Compressing {$items} {$items ->
         [one] item
         *[other] items
@@ -236,6 +428,14 @@ deleted = Deleted {$items} {$items ->
         [one] item
         *[other] items
     } from {trash}
+testing-archives = Testing {$items} {$items ->
+        [one] archive
+        *[other] archives
+    } ({$progress})...
+tested-archives = Tested {$items} {$items ->
+        [one] archive
+        *[other] archives
+    }
 emptying-trash = Emptying {trash} ({$progress})...
 emptied-trash = Emptied {trash}
 extracting = Extracting {$items} {$items ->
@@ -250,6 +450,30 @@ setting-executable-and-launching = Setting "{$name}" as executable and launching
 set-executable-and-launched = Set "{$name}" as executable and launched
 setting-permissions = Setting permissions for "{$name}" to {$mode}
 set-permissions = Set permissions for "{$name}" to {$mode}
+setting-modes = Setting permissions of {$items} {$items ->
+        [one] item
+        *[other] items
+    } ({$progress})...
+set-modes = Set permissions of {$items} {$items ->
+        [one] item
+        *[other] items
+    }
+setting-owner = Changing the owner of {$items} {$items ->
+        [one] item
+        *[other] items
+    } ({$progress})...
+set-owner = Changed the owner of {$items} {$items ->
+        [one] item
+        *[other] items
+    }
+linking = Linking {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" to "{$to}" ({$progress})...
+linked = Linked {$items} {$items ->
+        [one] item
+        *[other] items
+    } from "{$from}" to "{$to}"
 moving = Moving {$items} {$items ->
         [one] item
         *[other] items
@@ -266,6 +490,14 @@ permanently-deleted = Permanently deleted {$items} {$items ->
         [one] item
         *[other] items
     }
+shredding = Shredding {$items} {$items ->
+        [one] item
+        *[other] items
+    } ({$progress})...
+shredded = Shredded {$items} {$items ->
+        [one] item
+        *[other] items
+    }
 removing-from-recents = Removing {$items} {$items ->
         [one] item
         *[other] items
@@ -317,16 +549,64 @@ type-to-search = Type to Search
 type-to-search-recursive = Searches the current folder and all subfolders
 type-to-search-enter-path = Enters the path to the directory or file
 
+### Verify copies
+verify-copies = Verify copies
+verify-copies-off = Do not verify copied files
+verify-copies-md5 = Compare MD5 checksums after copying
+verify-copies-sha256 = Compare SHA-256 checksums after copying
+
+### Preserve attributes
+preserve-attributes = Preserve attributes when copying
+preserve-timestamps = Access and modification times
+preserve-mode = Permissions
+preserve-ownership = Owner and group, where permitted
+preserve-xattrs = Extended attributes
+preserve-acls = Access control lists
+
+### Conflicts
+conflicts = Conflicts
+conflicts-files = Existing files
+conflicts-folders = Existing folders
+conflicts-rename-pattern = Rename pattern
+conflict-ask = Ask
+conflict-keep-newer = Keep newer
+conflict-keep-larger = Keep larger
+conflict-replace-if-different = Replace if size or date differ
+conflict-replace-if-contents-differ = Replace if contents differ
+conflict-rename = Rename
+conflict-merge = Merge
+continue-on-error = Skip items that fail and report them at the end
+preview-operations = Review copies and moves before they start
+queue-by-device = Run operations on the same drive one at a time
+relative-links = Paste links with paths relative to their location
+smart-extract = Only extract archives into a new folder when they have several items at the top
+extract-max-size = Largest total size of extracted files
+extract-max-ratio = Largest compression ratio of extracted files
+extract-no-limit = No limit
+extract-ratio = {$ratio} times the archive size
+shred-passes = Overwrite passes
+shred-passes-zeros = Zeros
+shred-passes-random = {$count} random {$count ->
+        [one] pass
+        *[other] passes
+    }
+shred-passes-random-then-zeros = {$count} random {$count ->
+        [one] pass
+        *[other] passes
+    }, then zeros
+
 # Context menu
 add-to-sidebar = Add to sidebar
 compress = Compress
 delete-permanently = Delete permanently
 eject = Eject
 extract-here = Extract
+test-archive = Test archive
 new-file = New file...
 new-folder = New folder...
 open-in-terminal = Open in terminal
 move-to-trash = Move to trash
+shred-items = Shred...
 restore-from-trash = Restore from trash
 remove-from-sidebar = Remove from sidebar
 sort-by-name = Sort by name
@@ -357,6 +637,8 @@ edit = Edit
 cut = Cut
 copy = Copy
 paste = Paste
+paste-as-link = Paste as link
+paste-as-hard-link = Paste as hard link
 select-all = Select all
 
 ## View
@@ -73,7 +73,7 @@ use crate::{
         TimeConfig, TypeToSearch,
     },
     views::dialogs::dialog::{Dialog, DialogKind, DialogMessage, DialogResult},
-    views::dialogs::dialog_pages::{ArchiveType, DialogPage, DialogPages},
+    views::dialogs::dialog_pages::{ArchiveType, DialogPage, DialogPages, ZipEncryption},
     views::ribbon_toolbar::{RibbonMessage, RibbonToolbar},
     fl, home_dir,
     utils::key_bind::key_binds,
@@ -83,12 +83,16 @@ use crate::{
     utils::mime_icon,
     core::services::mount::{MOUNTERS, MounterAuth, MounterItem, MounterItems, MounterKey, MounterMessage},
     core::operations::{
-        Controller, Operation, OperationError, OperationErrorType, OperationSelection,
-        ReplaceResult,
+        Accounts, Checksum, ConflictPolicy, Controller, ControllerState, DirPolicy, ExtractLimits,
+        FailedItem, FilePolicy, Inverse, LinkKind, ModeChange, ModeMask, Operation, OperationError,
+        OperationErrorType, OperationSelection, PendingUndo, PlanSummary, Preserve, ReplaceResult,
+        Scheduler, ShredPass, UndoEntry, UndoSnapshot, UndoStack, journal, permissions, plan,
+        shred,
     },
     utils::spawn_detached::spawn_detached,
     tab::{
-        self, HOVER_DURATION, HeadingOptions, ItemMetadata, Location, SORT_OPTION_FALLBACK, Tab,
+        self, HOVER_DURATION, HeadingOptions, ItemMetadata, Location, SORT_OPTION_FALLBACK,
+        SearchMode, Tab,
     },
 };
 use crate::{core::config::State, views::dialogs::dialog::DialogSettings};
@@ -147,11 +151,14 @@ pub enum Action {
     OpenTerminal,
     OpenWith,
     Paste,
+    PasteHardLink,
+    PasteLink,
     PermanentlyDelete,
     Preview,
     Reload,
     RemoveFromRecents,
     Rename,
+    Redo,
     RestoreFromTrash,
     SearchActivate,
     FilterActivate,
@@ -161,16 +168,19 @@ pub enum Action {
     SelectAll,
     SetSort(HeadingOptions, bool),
     Settings,
+    Shred,
     TabClose,
     TabNew,
     TabNext,
     TabPrev,
     TabViewGrid,
     TabViewList,
+    TestArchive,
     ToggleFoldersFirst,
     ToggleShowHidden,
     ToggleSort(HeadingOptions),
     ToggleTerminal,
+    Undo,
     WindowClose,
     WindowNew,
     ZoomDefault,
@@ -222,8 +232,11 @@ impl Action {
             Action::OpenTerminal => Message::OpenTerminal(entity_opt),
             Action::OpenWith => Message::OpenWithDialog(entity_opt),
             Action::Paste => Message::Paste(entity_opt),
+            Action::PasteHardLink => Message::PasteLink(entity_opt, true),
+            Action::PasteLink => Message::PasteLink(entity_opt, false),
             Action::PermanentlyDelete => Message::PermanentlyDelete(entity_opt),
             Action::Preview => Message::Preview(entity_opt),
+            Action::Redo => Message::Redo,
             Action::Reload => Message::TabMessage(entity_opt, tab::Message::Reload),
             Action::RemoveFromRecents => Message::RemoveFromRecents(entity_opt),
             Action::Rename => Message::Rename(entity_opt),
@@ -239,18 +252,21 @@ impl Action {
                 Message::TabMessage(entity_opt, tab::Message::SetSort(*sort, *dir))
             }
             Action::Settings => Message::ToggleContextPage(ContextPage::Settings),
+            Action::Shred => Message::Shred(entity_opt),
             Action::TabClose => Message::TabClose(entity_opt),
             Action::TabNew => Message::TabNew,
             Action::TabNext => Message::TabNext,
             Action::TabPrev => Message::TabPrev,
             Action::TabViewGrid => Message::TabView(entity_opt, tab::View::Grid),
             Action::TabViewList => Message::TabView(entity_opt, tab::View::List),
+            Action::TestArchive => Message::TestArchive(entity_opt),
             Action::ToggleFoldersFirst => Message::ToggleFoldersFirst,
             Action::ToggleShowHidden => Message::ToggleShowHidden,
             Action::ToggleSort(sort) => {
                 Message::TabMessage(entity_opt, tab::Message::ToggleSort(*sort))
             }
             Action::ToggleTerminal => Message::TerminalToggle,
+            Action::Undo => Message::Undo,
             Action::WindowClose => Message::WindowClose,
             Action::WindowNew => Message::WindowNew,
             Action::ZoomDefault => Message::ZoomDefault(entity_opt),
@@ -313,6 +329,7 @@ pub enum Message {
     AppTheme(AppTheme),
     CloseToast(widget::ToastId),
     Compress(Option<Entity>),
+    CompressEstimate(ArchiveType, u32, u64),
     Config(Config),
     Copy(Option<Entity>),
     CosmicSettings(&'static str),
@@ -323,6 +340,7 @@ pub enum Message {
     DesktopDialogs(bool),
     DialogCancel,
     DialogComplete,
+    DiscardInterrupted,
     Eject,
     FileDialogMessage(DialogMessage),
     DialogPush(DialogPage),
@@ -354,7 +372,9 @@ pub enum Message {
     NetworkResult(MounterKey, String, Result<bool, String>),
     NewItem(Option<Entity>, bool),
     #[cfg(feature = "notify")]
-    Notification(Arc<Mutex<notify_rust::NotificationHandle>>),
+    Notification(Arc<Mutex<Option<notify_rust::NotificationHandle>>>),
+    #[cfg(feature = "notify")]
+    NotificationRefresh,
     NotifyEvents(Vec<DebouncedEvent>),
     NotifyWatcher(WatcherWrapper),
     OpenTerminal(Option<Entity>),
@@ -368,15 +388,25 @@ pub enum Message {
     Overlap(window::Id, OverlapNotifyEvent),
     Paste(Option<Entity>),
     PasteContents(PathBuf, ClipboardPaste),
+    PasteLink(Option<Entity>, bool),
+    PasteLinkContents(PathBuf, ClipboardPaste, LinkKind),
     PendingCancel(u64),
     PendingCancelAll,
     PendingComplete(u64, OperationSelection),
+    PendingDevices(u64, Vec<u64>),
     PendingDismiss,
     PendingError(u64, OperationError),
     PendingPause(u64, bool),
     PendingPauseAll(bool),
+    PendingPrioritize(u64),
+    PendingReorder(u64, bool),
     PermanentlyDelete(Option<Entity>),
+    PermissionsAccounts(Vec<PathBuf>, Accounts, Accounts),
+    PlanConflicts(ConflictPolicy),
+    PlanPreserve(Preserve),
+    PlanReady(Operation, Result<PlanSummary, String>),
     Preview(Option<Entity>),
+    Redo,
     RescanRecents,
     RescanTrash,
     RemoveFromRecents(Option<Entity>),
@@ -388,11 +418,23 @@ pub enum Message {
     SearchActivate,
     SearchClear,
     SearchInput(String),
+    SearchMode(SearchMode),
     FilterActivate,
     FilterClear,
     FilterInput(String),
     SetShowDetails(bool),
     SetTypeToSearch(TypeToSearch),
+    SetVerifyCopies(Option<Checksum>),
+    SetPreserve(Preserve),
+    SetConflictPolicy(ConflictPolicy),
+    SetContinueOnError(bool),
+    SetQueueByDevice(bool),
+    SetRelativeLinks(bool),
+    SetSmartExtract(bool),
+    SetExtractLimits(ExtractLimits),
+    SetPreviewOperations(bool),
+    SetShredPasses(Vec<ShredPass>),
+    Shred(Option<Entity>),
     SystemThemeModeChange,
     Size(window::Id, Size),
     TabActivate(Entity),
@@ -410,6 +452,7 @@ pub enum Message {
         Option<Vec<PathBuf>>,
     ),
     TabView(Option<Entity>, tab::View),
+    TestArchive(Option<Entity>),
     TimeConfigChange(TimeConfig),
     ToggleContextPage(ContextPage),
     ToggleFoldersFirst,
@@ -417,7 +460,8 @@ pub enum Message {
     CustomColor(cosmic::iced::Color),
     SelectColorScheme(String),
     ApplyCustomTheme,
-    Undo(usize),
+    Undo,
+    UndoRestore(UndoEntry, Vec<TrashItem>),
     UndoTrash(widget::ToastId, Arc<[PathBuf]>),
     UndoTrashStart(Vec<TrashItem>),
     WindowClose,
@@ -520,6 +564,132 @@ impl PartialEq for WatcherWrapper {
     }
 }
 
+fn file_name_lossy(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn file_policy_name(policy: FilePolicy) -> String {
+    match policy {
+        FilePolicy::Ask => fl!("conflict-ask"),
+        FilePolicy::Replace => fl!("replace"),
+        FilePolicy::Skip => fl!("skip"),
+        FilePolicy::KeepNewer => fl!("conflict-keep-newer"),
+        FilePolicy::KeepLarger => fl!("conflict-keep-larger"),
+        FilePolicy::ReplaceIfDifferent => fl!("conflict-replace-if-different"),
+        FilePolicy::ReplaceIfContentsDiffer => fl!("conflict-replace-if-contents-differ"),
+        FilePolicy::Rename => fl!("conflict-rename"),
+    }
+}
+
+fn dir_policy_name(policy: DirPolicy) -> String {
+    match policy {
+        DirPolicy::Merge => fl!("conflict-merge"),
+        DirPolicy::Skip => fl!("skip"),
+        DirPolicy::Rename => fl!("conflict-rename"),
+    }
+}
+
+fn search_mode_name(mode: SearchMode) -> String {
+    match mode {
+        SearchMode::Name => fl!("search-names"),
+        SearchMode::Content => fl!("search-contents"),
+        SearchMode::ContentRegex => fl!("search-contents-regex"),
+    }
+}
+
+/// Plan a copy or move on a blocking thread, reporting the summary to the preview dialog
+fn plan_operation(operation: Operation, controller: Controller) -> Task<Message> {
+    Task::perform(
+        async move {
+            let planned = operation.clone();
+            let result = tokio::task::spawn_blocking(move || plan::preview(&planned, &controller))
+                .await
+                .map_err(|err| err.to_string())
+                .and_then(|result| result.map_err(|err| err.to_string()));
+            (operation, result)
+        },
+        |(operation, result)| cosmic::Action::App(Message::PlanReady(operation, result)),
+    )
+}
+
+/// Estimate the size of an archive on a blocking thread, reporting it to the compress dialog
+fn estimate_archive(paths: Vec<PathBuf>, archive_type: ArchiveType, level: u32) -> Task<Message> {
+    Task::perform(
+        async move {
+            tokio::task::spawn_blocking(move || {
+                crate::utils::archive::estimate_size(&paths, archive_type, level)
+            })
+            .await
+            .map_err(|err| err.to_string())
+            .and_then(|result| result.map_err(|err| err.to_string()))
+        },
+        move |result| match result {
+            Ok(size) => cosmic::Action::App(Message::CompressEstimate(archive_type, level, size)),
+            Err(err) => {
+                log::warn!("failed to estimate archive size: {}", err);
+                cosmic::Action::App(Message::None)
+            }
+        },
+    )
+}
+
+/// List users and groups on a blocking thread, then open the permissions dialog for `paths`
+fn load_accounts(paths: Vec<PathBuf>) -> Task<Message> {
+    Task::perform(
+        async move {
+            let (users, groups) =
+                tokio::task::spawn_blocking(|| (Accounts::users(), Accounts::groups()))
+                    .await
+                    .unwrap_or_else(|err| {
+                        log::warn!("failed to list users and groups: {}", err);
+                        (Accounts::default(), Accounts::default())
+                    });
+            (paths, users, groups)
+        },
+        |(paths, users, groups)| {
+            cosmic::Action::App(Message::PermissionsAccounts(paths, users, groups))
+        },
+    )
+}
+
+fn shred_passes_name(passes: &[ShredPass]) -> String {
+    let random = passes
+        .iter()
+        .filter(|&&pass| pass == ShredPass::Random)
+        .count();
+    match (random, passes.last()) {
+        (0, _) => fl!("shred-passes-zeros"),
+        (_, Some(ShredPass::Zeros)) => fl!("shred-passes-random-then-zeros", count = random),
+        _ => fl!("shred-passes-random", count = random),
+    }
+}
+
+/// Find the trash items that were trashed from the given paths
+async fn trash_items(recently_trashed: Arc<[PathBuf]>, icon_sizes: IconSizes) -> Vec<TrashItem> {
+    let mut trash_items = Vec::with_capacity(recently_trashed.len());
+    match tokio::task::spawn_blocking(move || Location::Trash.scan(icon_sizes)).await {
+        Ok((_parent_item_opt, items)) => {
+            for path in &*recently_trashed {
+                for item in &items {
+                    if let ItemMetadata::Trash { ref entry, .. } = item.metadata {
+                        let original_path = entry.original_path();
+                        if &original_path == path {
+                            trash_items.push(entry.clone());
+                        }
+                    }
+                }
+            }
+        }
+        Err(err) => {
+            log::warn!("failed to rescan: {}", err);
+        }
+    }
+    trash_items
+}
+
 // The [`App`] stores application-specific state.
 pub struct App {
     core: Core,
@@ -533,6 +703,13 @@ pub struct App {
     state: State,
     mode: Mode,
     app_themes: Vec<String>,
+    file_policy_names: Vec<String>,
+    file_rule_names: Vec<String>,
+    dir_policy_names: Vec<String>,
+    search_mode_names: Vec<String>,
+    shred_pass_names: Vec<String>,
+    extract_size_names: Vec<String>,
+    extract_ratio_names: Vec<String>,
     selected_custom_theme: Option<String>,
     compio_tx: mpsc::Sender<Pin<Box<dyn Future<Output = ()> + Send>>>,
     context_page: ContextPage,
@@ -547,14 +724,18 @@ pub struct App {
     network_drive_connecting: Option<(MounterKey, String)>,
     network_drive_input: String,
     #[cfg(feature = "notify")]
-    notification_opt: Option<Arc<Mutex<notify_rust::NotificationHandle>>>,
+    /// Emptied when closed, as closing needs the handle itself
+    notification_opt: Option<Arc<Mutex<Option<notify_rust::NotificationHandle>>>>,
     #[cfg(all(feature = "wayland", feature = "desktop-applet"))]
     overlap: HashMap<String, (window::Id, Rectangle)>,
     pending_operation_id: u64,
     pending_operations: BTreeMap<u64, (Operation, Controller)>,
+    scheduler: Scheduler,
     progress_operations: BTreeSet<u64>,
     complete_operations: BTreeMap<u64, Operation>,
     failed_operations: BTreeMap<u64, (Operation, Controller, String)>,
+    undo_pending: BTreeMap<u64, PendingUndo>,
+    undo_stack: UndoStack,
     scrollable_id: widget::Id,
     search_id: widget::Id,
     filter_id: widget::Id,
@@ -911,9 +1092,16 @@ impl App {
     }
 
     fn extract_to(&mut self, paths: &[impl AsRef<Path>]) -> Task<Message> {
+        // Items in browsed archives start in the directory of the archive
         if let Some(destination) = paths
             .first()
-            .and_then(|first| first.as_ref().parent())
+            .and_then(|first| {
+                first
+                    .as_ref()
+                    .ancestors()
+                    .skip(1)
+                    .find(|ancestor| ancestor.is_dir())
+            })
             .map(|parent| parent.to_path_buf())
         {
             let (mut dialog, dialog_task) = Dialog::new(
@@ -1092,6 +1280,18 @@ impl App {
         .1
     }
 
+    // Dropping with Ctrl+Shift held creates symbolic links instead of copying or moving
+    fn drop_contents(&mut self, to: PathBuf, contents: ClipboardPaste) -> Task<Message> {
+        if self.modifiers.control() && self.modifiers.shift() {
+            let kind = LinkKind::Symbolic {
+                relative: self.config.relative_links,
+            };
+            self.update(Message::PasteLinkContents(to, contents, kind))
+        } else {
+            self.update(Message::PasteContents(to, contents))
+        }
+    }
+
     // This wrapper ensures that local folders use trash and remote folders permanently delete with a dialog
     fn delete(&mut self, paths: Vec<PathBuf>) -> Task<Message> {
         let mut dialog_paths = Vec::new();
@@ -1126,17 +1326,101 @@ impl App {
     }
 
     fn operation(&mut self, operation: Operation) -> Task<Message> {
+        let undo = PendingUndo::Record(UndoSnapshot::capture(&operation));
+        self.operation_with_undo(operation, undo)
+    }
+
+    // Copies and moves wait for confirmation in the preview dialog if enabled
+    fn operation_or_preview(&mut self, operation: Operation) -> Task<Message> {
+        if !self.config.preview_operations {
+            return self.operation(operation);
+        }
+        let controller = Controller::default();
+        let task = plan_operation(operation.clone(), controller.clone());
+        Task::batch([
+            self.dialog_pages.push_back(DialogPage::PlanPreview {
+                operation,
+                summary: None,
+                controller,
+            }),
+            task,
+        ])
+    }
+
+    /// Change the operation of the plan preview and plan it again
+    fn replan(&mut self, change: impl FnOnce(&mut Operation)) -> Task<Message> {
+        let Some(DialogPage::PlanPreview {
+            operation,
+            controller,
+            ..
+        }) = self.dialog_pages.front()
+        else {
+            return Task::none();
+        };
+        controller.cancel();
+        let mut operation = operation.clone();
+        change(&mut operation);
+        let controller = Controller::default();
+        let task = plan_operation(operation.clone(), controller.clone());
+        self.dialog_pages.update_front(DialogPage::PlanPreview {
+            operation,
+            summary: None,
+            controller,
+        });
+        task
+    }
+
+    fn operation_with_undo(&mut self, operation: Operation, undo: PendingUndo) -> Task<Message> {
         let id = self.pending_operation_id;
         let controller = Controller::default();
-        let compio_tx = self.compio_tx.clone();
+        controller.set_continue_on_error(self.config.continue_on_error);
 
         self.pending_operation_id += 1;
+        self.undo_pending.insert(id, undo);
         if operation.show_progress_notification() {
             self.progress_operations.insert(id);
         }
+        if !self.config.queue_by_device {
+            self.pending_operations.insert(id, (operation, controller));
+            self.scheduler.push(id, Vec::new());
+            return self.start_queued();
+        }
         self.pending_operations
-            .insert(id, (operation.clone(), controller.clone()));
+            .insert(id, (operation.clone(), controller));
+        self.scheduler.push_unknown(id);
+        // Finding the devices stats every path, which blocks on unresponsive mounts
+        Task::perform(
+            async move {
+                let devices = tokio::task::spawn_blocking(move || operation.devices())
+                    .await
+                    .unwrap_or_default();
+                cosmic::action::app(Message::PendingDevices(id, devices))
+            },
+            |x| x,
+        )
+    }
+
+    /// Start queued operations whose devices are no longer in use
+    fn start_queued(&mut self) -> Task<Message> {
+        let tasks: Vec<_> = self
+            .scheduler
+            .start_ready()
+            .into_iter()
+            .filter_map(|id| {
+                let (operation, controller) = self.pending_operations.get(&id)?;
+                Some(self.spawn_operation(id, operation.clone(), controller.clone()))
+            })
+            .collect();
+        Task::batch(tasks)
+    }
 
+    fn spawn_operation(
+        &self,
+        id: u64,
+        operation: Operation,
+        controller: Controller,
+    ) -> Task<Message> {
+        let compio_tx = self.compio_tx.clone();
         // Use a task to send operations to the compio runtime thread.
         cosmic::Task::stream(cosmic::iced_futures::stream::channel(
             4,
@@ -1330,6 +1614,25 @@ impl App {
         }
     }
 
+    fn search_error_get(&self) -> Option<&str> {
+        let tab = self.tab_model.active_data::<Tab>()?;
+        tab.search_error.as_deref()
+    }
+
+    fn search_mode_dropdown(&self) -> Element<'_, Message> {
+        let mode = self
+            .tab_model
+            .active_data::<Tab>()
+            .map(|tab| tab.location.search_mode())
+            .unwrap_or_default();
+        widget::dropdown(
+            &self.search_mode_names,
+            SearchMode::all().iter().position(|&other| other == mode),
+            |index| Message::SearchMode(SearchMode::all()[index]),
+        )
+        .into()
+    }
+
     fn filter_get(&self) -> Option<&str> {
         let entity = self.tab_model.active();
         let tab = self.tab_model.data::<Tab>(entity)?;
@@ -1356,6 +1659,7 @@ impl App {
                             path.to_path_buf(),
                             term,
                             tab.config.show_hidden,
+                            tab.location.search_mode(),
                             Instant::now(),
                         ),
                         true,
@@ -1400,6 +1704,41 @@ impl App {
         paths
     }
 
+    /// Paths of the selected items in a browsed archive, see [`Location::archive_entry_path`]
+    fn selected_archive_entries(&self, entity_opt: Option<Entity>) -> Vec<PathBuf> {
+        let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
+        self.tab_model
+            .data::<Tab>(entity)
+            .map(|tab| {
+                tab.selected_locations()
+                    .iter()
+                    .filter_map(Location::archive_entry_path)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Extract the archive entries among `paths` into `to`, returning the other paths
+    fn extract_archive_entries(
+        &mut self,
+        paths: Vec<PathBuf>,
+        to: &Path,
+        tasks: &mut Vec<Task<Message>>,
+    ) -> Vec<PathBuf> {
+        let (paths, archives) = crate::utils::archive::group_entry_paths(paths);
+        for (archive, entries) in archives {
+            tasks.push(self.operation(Operation::ExtractEntries {
+                archive,
+                entries,
+                to: to.to_path_buf(),
+                password: None,
+                conflicts: self.config.conflicts.clone(),
+                limits: self.config.extract_limits,
+            }));
+        }
+        paths
+    }
+
     fn set_cut(&mut self, entity_opt: Option<Entity>) {
         let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
         if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
@@ -1589,10 +1928,10 @@ impl App {
                 return Task::perform(
                     async move {
                         tokio::task::spawn_blocking(move || {
-                            //TODO: this is nasty
-                            let notification_mutex = Arc::try_unwrap(notification_arc).unwrap();
-                            let notification = notification_mutex.into_inner().unwrap();
-                            notification.close();
+                            // The lock waits for a pending refresh of the notification to finish
+                            if let Some(notification) = notification_arc.lock().unwrap().take() {
+                                notification.close();
+                            }
                         })
                         .await
                         .unwrap();
@@ -1601,11 +1940,43 @@ impl App {
                     |x| x,
                 );
             }
+        } else {
+            // Show the progress of running operations in the notification
+            #[cfg(feature = "notify")]
+            if let Some(notification_arc) = self.notification_opt.clone() {
+                let body = self.notification_body();
+                return Task::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || {
+                            if let Some(notification) = notification_arc.lock().unwrap().as_mut() {
+                                notification.body(&body);
+                                notification.update();
+                            }
+                        })
+                        .await
+                        .unwrap();
+                        cosmic::action::app(Message::None)
+                    },
+                    |x| x,
+                );
+            }
         }
 
         Task::none()
     }
 
+    #[cfg(feature = "notify")]
+    fn notification_body(&self) -> String {
+        let mut lines = Vec::new();
+        for (_id, (op, controller)) in self.pending_operations.iter() {
+            if op.show_progress_notification() {
+                lines.push(op.pending_text(controller.progress(), controller.state()));
+                lines.extend(controller.details().text());
+            }
+        }
+        lines.join("\n")
+    }
+
     fn update_title(&mut self) -> Task<Message> {
         let window_title = match self.tab_model.text(self.tab_model.active()) {
             Some(tab_title) => format!("{tab_title} — {}", fl!("cosmic-files")),
@@ -1785,51 +2156,108 @@ impl App {
         //TODO: get height from theme?
         let progress_bar_height = Length::Fixed(4.0);
 
+        if self.undo_stack.peek_undo().is_some() || self.undo_stack.peek_redo().is_some() {
+            let mut section = widget::settings::section().title(fl!("undo-history"));
+            if let Some(entry) = self.undo_stack.peek_redo() {
+                section = section.add(
+                    widget::settings::item::builder(entry.operation.completed_text())
+                        .control(widget::button::standard(fl!("redo")).on_press(Message::Redo)),
+                );
+            }
+            for (i, entry) in self.undo_stack.undo_entries().enumerate() {
+                let item = widget::settings::item::builder(entry.operation.completed_text());
+                // Only the most recent operation can be undone
+                section = section.add(if i == 0 {
+                    item.control(widget::button::standard(fl!("undo")).on_press(Message::Undo))
+                } else {
+                    item.control(widget::horizontal_space())
+                });
+            }
+            children.push(section.into());
+        }
+
         if !self.pending_operations.is_empty() {
             let mut section = widget::settings::section().title(fl!("pending"));
-            for (id, (op, controller)) in self.pending_operations.iter().rev() {
+            // Running operations come first, then queued ones in the order they will start
+            let queued: Vec<u64> = self.scheduler.queued().collect();
+            let running = self
+                .pending_operations
+                .keys()
+                .rev()
+                .filter(|id| !queued.contains(id));
+            for id in running.chain(queued.iter()) {
+                let Some((op, controller)) = self.pending_operations.get(id) else {
+                    continue;
+                };
                 let progress = controller.progress();
-                section = section.add(widget::column::with_children(vec![
-                    widget::row::with_children(vec![
-                        widget::progress_bar(0.0..=1.0, progress)
-                            .height(progress_bar_height)
-                            .into(),
-                        if controller.is_paused() {
-                            widget::tooltip(
-                                widget::button::icon(icon::from_name(
-                                    "media-playback-start-symbolic",
-                                ))
-                                .on_press(Message::PendingPause(*id, false))
-                                .padding(8),
-                                widget::text::body(fl!("resume")),
-                                widget::tooltip::Position::Top,
-                            )
-                            .into()
-                        } else {
-                            widget::tooltip(
-                                widget::button::icon(icon::from_name(
-                                    "media-playback-pause-symbolic",
-                                ))
-                                .on_press(Message::PendingPause(*id, true))
-                                .padding(8),
-                                widget::text::body(fl!("pause")),
-                                widget::tooltip::Position::Top,
-                            )
-                            .into()
-                        },
-                        widget::tooltip(
-                            widget::button::icon(icon::from_name("window-close-symbolic"))
-                                .on_press(Message::PendingCancel(*id))
+                let position = self.scheduler.position(*id);
+                let mut row = widget::row::with_capacity(6).align_y(Alignment::Center);
+                row =
+                    row.push(widget::progress_bar(0.0..=1.0, progress).height(progress_bar_height));
+                if position.is_some() {
+                    for (icon_name, tooltip, message) in [
+                        (
+                            "go-top-symbolic",
+                            fl!("run-next"),
+                            Message::PendingPrioritize(*id),
+                        ),
+                        (
+                            "go-up-symbolic",
+                            fl!("move-up"),
+                            Message::PendingReorder(*id, true),
+                        ),
+                        (
+                            "go-down-symbolic",
+                            fl!("move-down"),
+                            Message::PendingReorder(*id, false),
+                        ),
+                    ] {
+                        row = row.push(widget::tooltip(
+                            widget::button::icon(icon::from_name(icon_name))
+                                .on_press(message)
                                 .padding(8),
-                            widget::text::body(fl!("cancel")),
+                            widget::text::body(tooltip),
                             widget::tooltip::Position::Top,
-                        )
-                        .into(),
-                    ])
-                    .align_y(Alignment::Center)
-                    .into(),
+                        ));
+                    }
+                }
+                row = row.push(if controller.is_paused() {
+                    widget::tooltip(
+                        widget::button::icon(icon::from_name("media-playback-start-symbolic"))
+                            .on_press(Message::PendingPause(*id, false))
+                            .padding(8),
+                        widget::text::body(fl!("resume")),
+                        widget::tooltip::Position::Top,
+                    )
+                } else {
+                    widget::tooltip(
+                        widget::button::icon(icon::from_name("media-playback-pause-symbolic"))
+                            .on_press(Message::PendingPause(*id, true))
+                            .padding(8),
+                        widget::text::body(fl!("pause")),
+                        widget::tooltip::Position::Top,
+                    )
+                });
+                row = row.push(widget::tooltip(
+                    widget::button::icon(icon::from_name("window-close-symbolic"))
+                        .on_press(Message::PendingCancel(*id))
+                        .padding(8),
+                    widget::text::body(fl!("cancel")),
+                    widget::tooltip::Position::Top,
+                ));
+                let mut column = widget::column::with_children(vec![
+                    row.into(),
                     widget::text::body(op.pending_text(progress, controller.state())).into(),
-                ]));
+                ]);
+                if let Some(position) = position {
+                    column = column.push(widget::text::caption(fl!(
+                        "queued",
+                        position = position + 1
+                    )));
+                } else if let Some(details) = controller.details().text() {
+                    column = column.push(widget::text::caption(details));
+                }
+                section = section.add(column);
             }
             children.push(section.into());
         }
@@ -1869,9 +2297,11 @@ impl App {
         kind: &'a PreviewKind,
         context_drawer: bool,
     ) -> Element<'a, tab::Message> {
-        let cosmic_theme::Spacing { space_l, .. } = theme::active().cosmic().spacing;
+        let cosmic_theme::Spacing {
+            space_m, space_l, ..
+        } = theme::active().cosmic().spacing;
 
-        let mut children = Vec::with_capacity(1);
+        let mut children = Vec::with_capacity(2);
         let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
         let military_time = self.config.tab.military_time;
         match kind {
@@ -1914,11 +2344,21 @@ impl App {
                                 );
                             }
                         }
+                        // Permissions of all selected items can be changed at once
+                        #[cfg(unix)]
+                        if !children.is_empty() {
+                            children.push(
+                                widget::button::standard(fl!("edit-permissions"))
+                                    .on_press(tab::Message::EditPermissions)
+                                    .into(),
+                            );
+                        }
                     }
                 }
             }
         }
         widget::column::with_children(children)
+            .spacing(space_m)
             .padding(if context_drawer {
                 [0, 0, 0, 0]
             } else {
@@ -1929,6 +2369,9 @@ impl App {
 
     fn settings(&self) -> Element<'_, Message> {
         let tab_config = self.config.tab;
+        let preserve = self.config.preserve;
+        let conflicts = &self.config.conflicts;
+        let extract_limits = self.config.extract_limits;
 
         // Get current theme index based on available options
         let app_theme_selected = self.get_theme_index_from_app_theme();
@@ -2021,6 +2464,195 @@ impl App {
                     Message::SetTypeToSearch,
                 ))
                 .into(),
+            widget::settings::section()
+                .title(fl!("verify-copies"))
+                .add(widget::radio(
+                    widget::text::body(fl!("verify-copies-off")),
+                    None,
+                    Some(self.config.verify_copies),
+                    Message::SetVerifyCopies,
+                ))
+                .add(widget::radio(
+                    widget::text::body(fl!("verify-copies-md5")),
+                    Some(Checksum::Md5),
+                    Some(self.config.verify_copies),
+                    Message::SetVerifyCopies,
+                ))
+                .add(widget::radio(
+                    widget::text::body(fl!("verify-copies-sha256")),
+                    Some(Checksum::Sha256),
+                    Some(self.config.verify_copies),
+                    Message::SetVerifyCopies,
+                ))
+                .into(),
+            widget::settings::section()
+                .title(fl!("preserve-attributes"))
+                .add(
+                    widget::settings::item::builder(fl!("preserve-timestamps")).toggler(
+                        preserve.timestamps,
+                        move |timestamps| {
+                            Message::SetPreserve(Preserve {
+                                timestamps,
+                                ..preserve
+                            })
+                        },
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("preserve-mode")).toggler(
+                        preserve.mode,
+                        move |mode| Message::SetPreserve(Preserve { mode, ..preserve }),
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("preserve-ownership")).toggler(
+                        preserve.ownership,
+                        move |ownership| {
+                            Message::SetPreserve(Preserve {
+                                ownership,
+                                ..preserve
+                            })
+                        },
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("preserve-xattrs")).toggler(
+                        preserve.xattrs,
+                        move |xattrs| Message::SetPreserve(Preserve { xattrs, ..preserve }),
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("preserve-acls")).toggler(
+                        preserve.acls,
+                        move |acls| Message::SetPreserve(Preserve { acls, ..preserve }),
+                    ),
+                )
+                .into(),
+            widget::settings::section()
+                .title(fl!("conflicts"))
+                .add({
+                    let conflicts = conflicts.clone();
+                    widget::settings::item::builder(fl!("conflicts-files")).control(
+                        widget::dropdown(
+                            &self.file_policy_names,
+                            FilePolicy::all()
+                                .iter()
+                                .position(|&policy| policy == conflicts.files),
+                            move |index| {
+                                Message::SetConflictPolicy(ConflictPolicy {
+                                    files: FilePolicy::all()[index],
+                                    ..conflicts.clone()
+                                })
+                            },
+                        ),
+                    )
+                })
+                .add({
+                    let conflicts = conflicts.clone();
+                    widget::settings::item::builder(fl!("conflicts-folders")).control(
+                        widget::dropdown(
+                            &self.dir_policy_names,
+                            DirPolicy::all()
+                                .iter()
+                                .position(|&policy| policy == conflicts.dirs),
+                            move |index| {
+                                Message::SetConflictPolicy(ConflictPolicy {
+                                    dirs: DirPolicy::all()[index],
+                                    ..conflicts.clone()
+                                })
+                            },
+                        ),
+                    )
+                })
+                .add({
+                    let conflicts = conflicts.clone();
+                    widget::settings::item::builder(fl!("conflicts-rename-pattern")).control(
+                        widget::text_input(" ({n})", &self.config.conflicts.rename_pattern)
+                            .on_input(move |rename_pattern| {
+                                Message::SetConflictPolicy(ConflictPolicy {
+                                    rename_pattern,
+                                    ..conflicts.clone()
+                                })
+                            }),
+                    )
+                })
+                .add(
+                    widget::settings::item::builder(fl!("continue-on-error")).toggler(
+                        self.config.continue_on_error,
+                        Message::SetContinueOnError,
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("preview-operations")).toggler(
+                        self.config.preview_operations,
+                        Message::SetPreviewOperations,
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("queue-by-device")).toggler(
+                        self.config.queue_by_device,
+                        Message::SetQueueByDevice,
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("relative-links")).toggler(
+                        self.config.relative_links,
+                        Message::SetRelativeLinks,
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("smart-extract")).toggler(
+                        self.config.smart_extract,
+                        Message::SetSmartExtract,
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("extract-max-size")).control(
+                        widget::dropdown(
+                            &self.extract_size_names,
+                            ExtractLimits::sizes()
+                                .iter()
+                                .position(|&size| size == extract_limits.max_size),
+                            move |index| {
+                                Message::SetExtractLimits(ExtractLimits {
+                                    max_size: ExtractLimits::sizes()[index],
+                                    ..extract_limits
+                                })
+                            },
+                        ),
+                    ),
+                )
+                .add(
+                    widget::settings::item::builder(fl!("extract-max-ratio")).control(
+                        widget::dropdown(
+                            &self.extract_ratio_names,
+                            ExtractLimits::ratios()
+                                .iter()
+                                .position(|&ratio| ratio == extract_limits.max_ratio),
+                            move |index| {
+                                Message::SetExtractLimits(ExtractLimits {
+                                    max_ratio: ExtractLimits::ratios()[index],
+                                    ..extract_limits
+                                })
+                            },
+                        ),
+                    ),
+                )
+                .into(),
+            widget::settings::section()
+                .title(fl!("shred"))
+                .add(
+                    widget::settings::item::builder(fl!("shred-passes")).control(
+                        widget::dropdown(
+                            &self.shred_pass_names,
+                            ShredPass::presets()
+                                .iter()
+                                .position(|&passes| passes == self.config.shred_passes.as_slice()),
+                            |index| Message::SetShredPasses(ShredPass::presets()[index].to_vec()),
+                        ),
+                    ),
+                )
+                .into(),
             widget::settings::section()
                 .title(fl!("other"))
                 .add({
@@ -2233,7 +2865,40 @@ impl Application for App {
             state: flags.state,
             mode: flags.mode,
             app_themes,
-            selected_custom_theme: None,
+            file_policy_names: FilePolicy::all()
+                .iter()
+                .map(|&policy| file_policy_name(policy))
+                .collect(),
+            file_rule_names: FilePolicy::rules()
+                .iter()
+                .map(|&policy| file_policy_name(policy))
+                .collect(),
+            dir_policy_names: DirPolicy::all()
+                .iter()
+                .map(|&policy| dir_policy_name(policy))
+                .collect(),
+            search_mode_names: SearchMode::all()
+                .iter()
+                .map(|&mode| search_mode_name(mode))
+                .collect(),
+            shred_pass_names: ShredPass::presets()
+                .iter()
+                .map(|passes| shred_passes_name(passes))
+                .collect(),
+            extract_size_names: ExtractLimits::sizes()
+                .iter()
+                .map(|&size| size.map_or_else(|| fl!("extract-no-limit"), tab::format_size))
+                .collect(),
+            extract_ratio_names: ExtractLimits::ratios()
+                .iter()
+                .map(|&ratio| {
+                    ratio.map_or_else(
+                        || fl!("extract-no-limit"),
+                        |ratio| fl!("extract-ratio", ratio = ratio),
+                    )
+                })
+                .collect(),
+            selected_custom_theme: None,
             compio_tx,
             context_page: ContextPage::Preview(None, PreviewKind::Selected),
             dialog_pages: DialogPages::new(),
@@ -2252,9 +2917,12 @@ impl Application for App {
             overlap: HashMap::new(),
             pending_operation_id: 0,
             pending_operations: BTreeMap::new(),
+            scheduler: Scheduler::default(),
             progress_operations: BTreeSet::new(),
             complete_operations: BTreeMap::new(),
             failed_operations: BTreeMap::new(),
+            undo_pending: BTreeMap::new(),
+            undo_stack: UndoStack::default(),
             scrollable_id: widget::Id::unique(),
             search_id: widget::Id::unique(),
             filter_id: widget::Id::unique(),
@@ -2318,6 +2986,16 @@ impl Application for App {
             }
         }
 
+        // Offer to resume copies and moves interrupted by a crash
+        if matches!(app.mode, Mode::App) {
+            for interrupted in journal::interrupted() {
+                commands.push(
+                    app.dialog_pages
+                        .push_back(DialogPage::InterruptedOperation(interrupted)),
+                );
+            }
+        }
+
         // Sync ribbon toolbar with the first tab's state after initialization
         if let Some(entity) = app.tab_model.iter().next() {
             if let Some(tab) = app.tab_model.data::<Tab>(entity) {
@@ -2667,6 +3345,7 @@ impl Application for App {
         } = theme::active().cosmic().spacing;
 
         let mut title = String::new();
+        let mut details_opt = None;
         let mut total_progress = 0.0;
         let mut count = 0;
         let mut all_paused = true;
@@ -2678,6 +3357,7 @@ impl Application for App {
                 let progress = controller.progress();
                 if title.is_empty() {
                     title = op.pending_text(progress, controller.state());
+                    details_opt = controller.details().text();
                 }
                 total_progress += progress;
                 count += 1;
@@ -2694,6 +3374,7 @@ impl Application for App {
         let finished = count - running;
         total_progress /= count as f32;
         if running > 1 {
+            details_opt = None;
             if finished > 0 {
                 title = fl!(
                     "operations-running-finished",
@@ -2715,6 +3396,12 @@ impl Application for App {
         let progress_bar =
             widget::progress_bar(0.0..=1.0, total_progress).height(progress_bar_height);
 
+        let mut title_column =
+            widget::column::with_children(vec![widget::text::body(title).into()]);
+        if let Some(details) = details_opt {
+            title_column = title_column.push(widget::text::caption(details));
+        }
+
         let container = widget::layer_container(widget::column::with_children(vec![
             widget::row::with_children(vec![
                 progress_bar.into(),
@@ -2748,7 +3435,7 @@ impl Application for App {
             ])
             .align_y(Alignment::Center)
             .into(),
-            widget::text::body(title).into(),
+            title_column.into(),
             widget::Space::with_height(space_s).into(),
             widget::row::with_children(vec![
                 widget::button::link(fl!("details"))
@@ -2795,14 +3482,16 @@ impl Application for App {
                         .into(),
                 );
             } else {
-                elements.push(
-                    widget::text_input::search_input("", term)
-                        .width(Length::Fixed(240.0))
-                        .id(self.search_id.clone())
-                        .on_clear(Message::SearchClear)
-                        .on_input(Message::SearchInput)
-                        .into(),
-                );
+                let mut search_input = widget::text_input::search_input("", term)
+                    .width(Length::Fixed(240.0))
+                    .id(self.search_id.clone())
+                    .on_clear(Message::SearchClear)
+                    .on_input(Message::SearchInput);
+                if let Some(error) = self.search_error_get() {
+                    search_input = search_input.error(error);
+                }
+                elements.push(search_input.into());
+                elements.push(self.search_mode_dropdown());
             }
         } else {
             elements.push(
@@ -2857,13 +3546,21 @@ impl Application for App {
 
         if self.core.is_condensed() {
             if let Some(term) = self.search_get() {
+                let mut search_input = widget::text_input::search_input("", term)
+                    .width(Length::Fill)
+                    .id(self.search_id.clone())
+                    .on_clear(Message::SearchClear)
+                    .on_input(Message::SearchInput);
+                if let Some(error) = self.search_error_get() {
+                    search_input = search_input.error(error);
+                }
                 tab_column = tab_column.push(
                     widget::container(
-                        widget::text_input::search_input("", term)
-                            .width(Length::Fill)
-                            .id(self.search_id.clone())
-                            .on_clear(Message::SearchClear)
-                            .on_input(Message::SearchInput),
+                        widget::row::with_children(vec![
+                            search_input.into(),
+                            self.search_mode_dropdown(),
+                        ])
+                        .spacing(space_xxs),
                     )
                     .padding(space_xxs),
                 )
@@ -3121,23 +3818,44 @@ impl Application for App {
             Message::Compress(entity_opt) => {
                 let paths = self.selected_paths(entity_opt);
                 if let Some(current_path) = paths.first() {
-                    if let Some(destination) = current_path.parent().zip(current_path.file_stem()) {
-                        let to = destination.0.to_path_buf();
-                        let name = destination.1.to_str().unwrap_or_default().to_string();
-                        let archive_type = ArchiveType::default();
+                    let archive_type = ArchiveType::default();
+                    if let Some((to, name)) = current_path
+                        .parent()
+                        .zip(archive_type.base_name(current_path))
+                    {
+                        let to = to.to_path_buf();
+                        let level = archive_type.default_level();
                         return Task::batch([
+                            estimate_archive(paths.clone(), archive_type, level),
                             self.dialog_pages.push_back(DialogPage::Compress {
                                 paths,
                                 to,
                                 name,
                                 archive_type,
+                                level,
                                 password: None,
+                                encryption: ZipEncryption::default(),
+                                estimate: None,
                             }),
                             widget::text_input::focus(self.dialog_text_input.clone()),
                         ]);
                     }
                 }
             }
+            Message::CompressEstimate(estimated_type, estimated_level, size) => {
+                if let Some(DialogPage::Compress {
+                    archive_type,
+                    level,
+                    estimate,
+                    ..
+                }) = self.dialog_pages.front_mut()
+                {
+                    // Estimates for a previous format or level are ignored
+                    if *archive_type == estimated_type && *level == estimated_level {
+                        *estimate = Some(size);
+                    }
+                }
+            }
             Message::Config(config) => {
                 if config != self.config {
                     log::info!("update config");
@@ -3154,7 +3872,8 @@ impl Application for App {
                         tab.refresh_cut(&[]);
                     }
                 }
-                let paths = self.selected_paths(entity_opt);
+                let mut paths = self.selected_paths(entity_opt);
+                paths.extend(self.selected_archive_entries(entity_opt));
                 let contents = ClipboardCopy::new(ClipboardKind::Copy, &paths);
                 return clipboard::write_data(contents);
             }
@@ -3278,6 +3997,31 @@ impl Application for App {
                 }
             }
             Message::DialogCancel => {
+                if let Some(DialogPage::PlanPreview { controller, .. }) = self.dialog_pages.front()
+                {
+                    controller.cancel();
+                }
+                if let Some((_page, task)) = self.dialog_pages.pop_front() {
+                    return task;
+                }
+            }
+            Message::DiscardInterrupted => {
+                let journal_opt = match self.dialog_pages.front() {
+                    Some(DialogPage::InterruptedOperation(interrupted)) => {
+                        Some(&interrupted.journal)
+                    }
+                    Some(DialogPage::VerifyFailed { journal, .. }) => journal.as_ref(),
+                    _ => return Task::none(),
+                };
+                if let Some(journal) = journal_opt {
+                    if let Err(err) = journal::discard(journal) {
+                        log::warn!(
+                            "failed to discard interrupted operation {:?}: {}",
+                            journal,
+                            err
+                        );
+                    }
+                }
                 if let Some((_page, task)) = self.dialog_pages.pop_front() {
                     return task;
                 }
@@ -3286,12 +4030,18 @@ impl Application for App {
                 if let Some((dialog_page, task)) = self.dialog_pages.pop_front() {
                     let mut tasks = vec![task];
                     match dialog_page {
+                        DialogPage::ArchiveAdd { archive, paths } => {
+                            tasks.push(self.operation(Operation::ArchiveAdd { paths, archive }));
+                        }
                         DialogPage::Compress {
                             paths,
                             to,
                             name,
                             archive_type,
+                            level,
                             password,
+                            encryption,
+                            ..
                         } => {
                             let extension = archive_type.extension();
                             let name = format!("{}{}", name, extension);
@@ -3300,7 +4050,9 @@ impl Application for App {
                                 paths,
                                 to,
                                 archive_type,
+                                level,
                                 password,
+                                encryption,
                             }));
                         }
                         DialogPage::EmptyTrash => {
@@ -3309,11 +4061,51 @@ impl Application for App {
                         DialogPage::FailedOperation(id) => {
                             log::warn!("TODO: retry operation {}", id);
                         }
-                        DialogPage::ExtractPassword { id, password } => {
+                        DialogPage::FailureReport { retry, .. } => {
+                            for operation in retry {
+                                tasks.push(self.operation(operation));
+                            }
+                        }
+                        DialogPage::InterruptedOperation(interrupted) => {
+                            tasks.push(self.operation(Operation::Resume {
+                                journal: interrupted.journal,
+                                operation: Box::new(interrupted.operation),
+                            }));
+                        }
+                        DialogPage::ExtractPassword { id, password, .. } => {
                             let (operation, _, _err) = self.failed_operations.get(&id).unwrap();
                             let new_op = match &operation {
-                                Operation::Extract { to, paths, .. } => Operation::Extract {
+                                Operation::Extract {
+                                    to,
+                                    paths,
+                                    conflicts,
+                                    smart,
+                                    limits,
+                                    ..
+                                } => Operation::Extract {
+                                    to: to.clone(),
+                                    paths: paths.clone(),
+                                    password: Some(password),
+                                    conflicts: conflicts.clone(),
+                                    smart: *smart,
+                                    limits: *limits,
+                                },
+                                Operation::ExtractEntries {
+                                    archive,
+                                    entries,
+                                    to,
+                                    conflicts,
+                                    limits,
+                                    ..
+                                } => Operation::ExtractEntries {
+                                    archive: archive.clone(),
+                                    entries: entries.clone(),
                                     to: to.clone(),
+                                    password: Some(password),
+                                    conflicts: conflicts.clone(),
+                                    limits: *limits,
+                                },
+                                Operation::ArchiveTest { paths, .. } => Operation::ArchiveTest {
                                     paths: paths.clone(),
                                     password: Some(password),
                                 },
@@ -3403,6 +4195,48 @@ impl Application for App {
                         DialogPage::PermanentlyDelete { paths } => {
                             tasks.push(self.operation(Operation::PermanentlyDelete { paths }));
                         }
+                        DialogPage::Permissions {
+                            paths,
+                            files,
+                            dirs,
+                            mask,
+                            recursive,
+                            owner,
+                            group,
+                            ..
+                        } => {
+                            let change = match (
+                                permissions::parse_mode(&files),
+                                permissions::parse_mode(&dirs),
+                            ) {
+                                (Some(files), Some(dirs)) => Some(ModeChange { files, dirs, mask }),
+                                _ => None,
+                            };
+                            // Changing the owner may clear the setuid bits, so both are done by
+                            // one operation, setting the modes after the owner of each item
+                            if owner.is_some() || group.is_some() {
+                                tasks.push(self.operation(Operation::SetOwner {
+                                    paths,
+                                    owner,
+                                    group,
+                                    change,
+                                    recursive,
+                                }));
+                            } else if let Some(change) = change {
+                                tasks.push(self.operation(Operation::SetModes {
+                                    paths,
+                                    change,
+                                    recursive,
+                                }));
+                            }
+                        }
+                        DialogPage::PlanPreview { operation, .. } => {
+                            tasks.push(self.operation(operation));
+                        }
+                        DialogPage::Shred { paths, .. } => {
+                            let passes = self.config.shred_passes.clone();
+                            tasks.push(self.operation(Operation::Shred { paths, passes }));
+                        }
                         DialogPage::RenameItem {
                             from, parent, name, ..
                         } => {
@@ -3412,6 +4246,52 @@ impl Application for App {
                         DialogPage::Replace { .. } => {
                             log::warn!("replace dialog should be completed with replace result");
                         }
+                        DialogPage::Unsupported { id, .. } => {
+                            // Try again with names the destination allows
+                            if let Some((operation, _, _)) = self.failed_operations.get(&id) {
+                                let mut operation = operation.clone();
+                                if let Operation::Copy { conflicts, .. }
+                                | Operation::Move { conflicts, .. } = &mut operation
+                                {
+                                    conflicts.sanitize_names = true;
+                                }
+                                tasks.push(self.operation(operation));
+                            }
+                        }
+                        DialogPage::VerifyFailed {
+                            id,
+                            from,
+                            path,
+                            journal,
+                        } => {
+                            let operation_opt = self
+                                .failed_operations
+                                .get(&id)
+                                .map(|(operation, _, _)| Box::new(operation.clone()));
+                            match (journal, operation_opt) {
+                                // Resume from the journal, which only copies the corrupted file
+                                (Some(journal), Some(operation)) => {
+                                    let resume = Operation::Resume { journal, operation };
+                                    tasks.push(self.operation(resume));
+                                }
+                                // Otherwise only the corrupted file is copied again
+                                (None, Some(operation)) => {
+                                    let failed = FailedItem {
+                                        kind: OperationErrorType::ChecksumMismatch {
+                                            from: from.clone(),
+                                            path: path.clone(),
+                                            journal: None,
+                                        },
+                                        from,
+                                        to: path,
+                                    };
+                                    for retry in operation.retry(&[failed]) {
+                                        tasks.push(self.operation(retry));
+                                    }
+                                }
+                                (_, None) => {}
+                            }
+                        }
                         DialogPage::SetExecutableAndLaunch { path } => {
                             tasks.push(self.operation(Operation::SetExecutableAndLaunch { path }));
                         }
@@ -3433,7 +4313,27 @@ impl Application for App {
                 return self.dialog_pages.push_back(dialog_page);
             }
             Message::DialogUpdate(dialog_page) => {
+                let mut task = Task::none();
+                if let (
+                    Some(DialogPage::Compress {
+                        archive_type: old_type,
+                        level: old_level,
+                        ..
+                    }),
+                    DialogPage::Compress {
+                        paths,
+                        archive_type,
+                        level,
+                        ..
+                    },
+                ) = (self.dialog_pages.front(), &dialog_page)
+                {
+                    if old_type != archive_type || old_level != level {
+                        task = estimate_archive(paths.clone(), *archive_type, *level);
+                    }
+                }
                 self.dialog_pages.update_front(dialog_page);
+                return task;
             }
             Message::DialogUpdateComplete(dialog_page) => {
                 return Task::batch([
@@ -3452,11 +4352,16 @@ impl Application for App {
                         paths,
                         to: destination,
                         password: None,
+                        conflicts: self.config.conflicts.clone(),
+                        smart: self.config.smart_extract,
+                        limits: self.config.extract_limits,
                     });
                 }
             }
             Message::ExtractTo(entity_opt) => {
-                return self.extract_to(&self.selected_paths(entity_opt));
+                let mut paths = self.selected_paths(entity_opt);
+                paths.extend(self.selected_archive_entries(entity_opt));
+                return self.extract_to(&paths);
             }
             Message::ExtractToResult(result) => {
                 match result {
@@ -3472,11 +4377,21 @@ impl Application for App {
                         if let Some(archive_paths) = archive_paths {
                             if !selected_paths.is_empty() {
                                 self.file_dialog_opt = None;
-                                return self.operation(Operation::Extract {
-                                    paths: archive_paths,
-                                    to: selected_paths[0].clone(),
-                                    password: None,
-                                });
+                                let to = selected_paths[0].clone();
+                                let mut tasks = Vec::new();
+                                let archive_paths =
+                                    self.extract_archive_entries(archive_paths, &to, &mut tasks);
+                                if !archive_paths.is_empty() {
+                                    tasks.push(self.operation(Operation::Extract {
+                                        paths: archive_paths,
+                                        to,
+                                        password: None,
+                                        conflicts: self.config.conflicts.clone(),
+                                        smart: self.config.smart_extract,
+                                        limits: self.config.extract_limits,
+                                    }));
+                                }
+                                return Task::batch(tasks);
                             }
                         }
                     }
@@ -3563,6 +4478,7 @@ impl Application for App {
             Message::MaybeExit => {
                 if self.core.main_window_id().is_none() && self.pending_operations.is_empty() {
                     // Exit if window is closed and there are no pending operations
+                    crate::utils::archive::clear_caches();
                     process::exit(0);
                 }
             }
@@ -3738,6 +4654,10 @@ impl Application for App {
             Message::Notification(notification) => {
                 self.notification_opt = Some(notification);
             }
+            #[cfg(feature = "notify")]
+            Message::NotificationRefresh => {
+                return self.update_notification();
+            }
             Message::NotifyEvents(events) => {
                 log::debug!("{:?}", events);
 
@@ -3976,35 +4896,166 @@ impl Application for App {
             }
             Message::PasteContents(to, mut contents) => {
                 contents.paths.retain(|p| p != &to);
+                // Items in browsed archives are extracted instead
+                let mut tasks = Vec::new();
+                contents.paths = self.extract_archive_entries(contents.paths, &to, &mut tasks);
                 if !contents.paths.is_empty() {
-                    return match contents.kind {
-                        ClipboardKind::Copy => self.operation(Operation::Copy {
+                    let operation = match contents.kind {
+                        ClipboardKind::Copy => Operation::Copy {
                             paths: contents.paths,
                             to,
-                        }),
-                        ClipboardKind::Cut { is_dnd } => self.operation(Operation::Move {
+                            verify: self.config.verify_copies,
+                            preserve: self.config.preserve,
+                            conflicts: self.config.conflicts.clone(),
+                        },
+                        ClipboardKind::Cut { is_dnd } => Operation::Move {
                             paths: contents.paths,
                             to,
                             cross_device_copy: is_dnd,
-                        }),
+                            verify: self.config.verify_copies,
+                            preserve: self.config.preserve,
+                            conflicts: self.config.conflicts.clone(),
+                        },
                     };
+                    tasks.push(self.operation_or_preview(operation));
+                }
+                return Task::batch(tasks);
+            }
+            Message::PasteLink(entity_opt, hard) => {
+                let kind = if hard {
+                    LinkKind::Hard
+                } else {
+                    LinkKind::Symbolic {
+                        relative: self.config.relative_links,
+                    }
+                };
+                let entity = entity_opt.unwrap_or_else(|| self.tab_model.active());
+                if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
+                    if let Some(path) = tab.location.path_opt() {
+                        let to = path.clone();
+                        return clipboard::read_data::<ClipboardPaste>().map(move |contents_opt| {
+                            match contents_opt {
+                                Some(contents) => cosmic::action::app(Message::PasteLinkContents(
+                                    to.clone(),
+                                    contents,
+                                    kind,
+                                )),
+                                None => cosmic::action::none(),
+                            }
+                        });
+                    }
+                }
+            }
+            Message::PasteLinkContents(to, mut contents, kind) => {
+                contents.paths.retain(|p| p != &to);
+                if !contents.paths.is_empty() {
+                    return self.operation(Operation::Link {
+                        paths: contents.paths,
+                        to,
+                        kind,
+                    });
+                }
+            }
+            Message::PlanConflicts(policy) => {
+                // Conflicts are resolved while planning, so the plan is built again
+                return self.replan(|operation| {
+                    if let Operation::Copy { conflicts, .. } | Operation::Move { conflicts, .. } =
+                        operation
+                    {
+                        *conflicts = policy;
+                    }
+                });
+            }
+            Message::PlanPreserve(new_preserve) => {
+                // Only a plan of the same operation is shown, so it is built again too
+                return self.replan(|operation| {
+                    if let Operation::Copy { preserve, .. } | Operation::Move { preserve, .. } =
+                        operation
+                    {
+                        *preserve = new_preserve;
+                    }
+                });
+            }
+            Message::PermissionsAccounts(paths, users, groups) => {
+                return self.dialog_pages.push_back(DialogPage::Permissions {
+                    paths,
+                    files: String::new(),
+                    dirs: String::new(),
+                    mask: ModeMask::Set,
+                    recursive: false,
+                    owner: None,
+                    group: None,
+                    users,
+                    groups,
+                });
+            }
+            Message::PlanReady(planned, result) => {
+                if let Some(DialogPage::PlanPreview {
+                    operation,
+                    controller,
+                    ..
+                }) = self.dialog_pages.front()
+                {
+                    // Plans replaced by a newer one are ignored
+                    if *operation == planned {
+                        let controller = controller.clone();
+                        self.dialog_pages.update_front(DialogPage::PlanPreview {
+                            operation: planned,
+                            summary: Some(result),
+                            controller,
+                        });
+                    }
                 }
             }
             Message::PendingCancel(id) => {
                 if let Some((_, controller)) = self.pending_operations.get(&id) {
+                    // Queued operations never started, so they will not report the cancellation
+                    if self.scheduler.is_queued(id) {
+                        let err =
+                            OperationError::from_state(ControllerState::Cancelled, controller);
+                        return self.update(Message::PendingError(id, err));
+                    }
                     controller.cancel();
                     self.progress_operations.remove(&id);
                 }
             }
             Message::PendingCancelAll => {
+                let queued: Vec<u64> = self.scheduler.queued().collect();
                 for (id, (_, controller)) in self.pending_operations.iter() {
                     controller.cancel();
                     self.progress_operations.remove(id);
                 }
+                return Task::batch(queued.into_iter().filter_map(|id| {
+                    let (_, controller) = self.pending_operations.get(&id)?;
+                    let err = OperationError::from_state(ControllerState::Cancelled, controller);
+                    Some(cosmic::task::message(cosmic::action::app(
+                        Message::PendingError(id, err),
+                    )))
+                }));
             }
             Message::PendingComplete(id, op_sel) => {
                 let mut commands = Vec::with_capacity(4);
-                if let Some((op, _)) = self.pending_operations.remove(&id) {
+                self.scheduler.finish(id);
+                commands.push(self.start_queued());
+                if let Some((op, controller)) = self.pending_operations.remove(&id) {
+                    // Record the operation so it can be undone, or move an undone one to redo
+                    match self.undo_pending.remove(&id) {
+                        Some(PendingUndo::Record(before)) => {
+                            if let Some(entry) = UndoEntry::new(op.clone(), &before, &op_sel) {
+                                self.undo_stack.push(entry);
+                            }
+                        }
+                        Some(PendingUndo::Undo(entry)) => {
+                            self.undo_stack.push_redo(entry);
+                        }
+                        Some(PendingUndo::Redo(before)) => {
+                            if let Some(entry) = UndoEntry::new(op.clone(), &before, &op_sel) {
+                                self.undo_stack.push_undo(entry);
+                            }
+                        }
+                        None => {}
+                    }
+
                     // Show toast for some operations
                     if let Some(description) = op.toast() {
                         if let Operation::Delete { ref paths } = op {
@@ -4028,6 +5079,30 @@ impl Application for App {
                         }
                     }
 
+                    // Report problems that did not fail the operation, like attributes not preserved
+                    let warnings = controller.warnings();
+                    if let Some(warning) = warnings.first() {
+                        commands.push(
+                            self.toasts
+                                .push(widget::toaster::Toast::new(fl!(
+                                    "operation-warnings",
+                                    count = warnings.len(),
+                                    warning = warning.as_str()
+                                )))
+                                .map(cosmic::Action::App),
+                        );
+                    }
+
+                    // Report items that were skipped after failing, so they can be retried
+                    let failures = controller.failures();
+                    if !failures.is_empty() {
+                        let retry = op.retry(&failures);
+                        commands.push(
+                            self.dialog_pages
+                                .push_back(DialogPage::FailureReport { failures, retry }),
+                        );
+                    }
+
                     // If a favorite for a path has been renamed or moved, update it.
                     if let Operation::Rename { ref from, ref to } = op {
                         if self.update_favorites(&[(from.clone(), to.clone())]) {
@@ -4071,26 +5146,70 @@ impl Application for App {
 
                 return Task::batch(commands);
             }
+            Message::PendingDevices(id, devices) => {
+                if self.scheduler.set_devices(id, devices) {
+                    return self.start_queued();
+                }
+            }
             Message::PendingDismiss => {
                 self.progress_operations.clear();
             }
             Message::PendingError(id, err) => {
                 let mut tasks = Vec::new();
+                self.scheduler.finish(id);
+                tasks.push(self.start_queued());
+                // A failed operation leaves an unknown state behind, so it cannot be undone
+                self.undo_pending.remove(&id);
                 if let Some((op, controller)) = self.pending_operations.remove(&id) {
                     // Only show dialog if not cancelled
                     if !controller.is_cancelled() {
-                        tasks.push(self.dialog_pages.push_back(match err.kind {
-                            OperationErrorType::Generic(_) => DialogPage::FailedOperation(id),
+                        tasks.push(self.dialog_pages.push_back(match &err.kind {
+                            OperationErrorType::Generic(_)
+                            | OperationErrorType::PermissionDenied(_)
+                            | OperationErrorType::NoSpace(_)
+                            | OperationErrorType::NameTooLong(_)
+                            | OperationErrorType::Io { .. }
+                            | OperationErrorType::UnsafeEntry { .. }
+                            | OperationErrorType::CorruptEntry { .. } => {
+                                DialogPage::FailedOperation(id)
+                            }
+                            OperationErrorType::ChecksumMismatch {
+                                from,
+                                path,
+                                journal,
+                            } => DialogPage::VerifyFailed {
+                                id,
+                                from: from.clone(),
+                                path: path.clone(),
+                                journal: journal.clone(),
+                            },
                             OperationErrorType::PasswordRequired => DialogPage::ExtractPassword {
                                 id,
                                 password: String::from(""),
+                                wrong: false,
+                            },
+                            OperationErrorType::WrongPassword => DialogPage::ExtractPassword {
+                                id,
+                                password: String::from(""),
+                                wrong: true,
+                            },
+                            OperationErrorType::Unsupported {
+                                fs_type,
+                                violations,
+                            } => DialogPage::Unsupported {
+                                id,
+                                fs_type: fs_type.clone(),
+                                violations: violations.clone(),
                             },
                         }));
                     }
                     // Remove from progress
                     self.progress_operations.remove(&id);
-                    self.failed_operations
-                        .insert(id, (op, controller, err.to_string()));
+                    let message = match err.kind.remedy() {
+                        Some(remedy) => format!("{}\n{}", err, remedy),
+                        None => err.to_string(),
+                    };
+                    self.failed_operations.insert(id, (op, controller, message));
                 }
                 // Close progress notification if all relevant operations are finished
                 if !self
@@ -4112,15 +5231,29 @@ impl Application for App {
                         controller.unpause();
                     }
                 }
+                // A paused operation that has not started lets others on its devices run
+                if self.scheduler.hold(id, pause) {
+                    return self.start_queued();
+                }
             }
             Message::PendingPauseAll(pause) => {
-                for (_id, (_, controller)) in self.pending_operations.iter() {
+                for (id, (_, controller)) in self.pending_operations.iter() {
                     if pause {
                         controller.pause();
                     } else {
                         controller.unpause();
                     }
+                    self.scheduler.hold(*id, pause);
                 }
+                return self.start_queued();
+            }
+            Message::PendingPrioritize(id) => {
+                self.scheduler.prioritize(id);
+                return self.start_queued();
+            }
+            Message::PendingReorder(id, earlier) => {
+                self.scheduler.reorder(id, earlier);
+                return self.start_queued();
             }
             Message::PermanentlyDelete(entity_opt) => {
                 let paths = self.selected_paths(entity_opt);
@@ -4291,6 +5424,16 @@ impl Application for App {
             Message::SearchClear => {
                 return self.search_set_active(None);
             }
+            Message::SearchMode(mode) => {
+                let entity = self.tab_model.active();
+                if let Some(tab) = self.tab_model.data_mut::<Tab>(entity) {
+                    if let Location::Search(_, term, _, search_mode, _) = &mut tab.location {
+                        *search_mode = mode;
+                        let term = term.clone();
+                        return self.search_set_active(Some(term));
+                    }
+                }
+            }
             Message::SearchInput(input) => {
                 // Check if terminal has focus - if so, bypass search and send to terminal
                 if self.terminal_visible && self.terminal_has_focus {
@@ -4349,6 +5492,55 @@ impl Application for App {
                 config_set!(type_to_search, type_to_search);
                 return self.update_config();
             }
+            Message::SetVerifyCopies(verify_copies) => {
+                config_set!(verify_copies, verify_copies);
+                return self.update_config();
+            }
+            Message::SetPreserve(preserve) => {
+                config_set!(preserve, preserve);
+                return self.update_config();
+            }
+            Message::SetConflictPolicy(conflicts) => {
+                config_set!(conflicts, conflicts);
+                return self.update_config();
+            }
+            Message::SetContinueOnError(continue_on_error) => {
+                config_set!(continue_on_error, continue_on_error);
+                return self.update_config();
+            }
+            Message::SetQueueByDevice(queue_by_device) => {
+                config_set!(queue_by_device, queue_by_device);
+                return self.update_config();
+            }
+            Message::SetRelativeLinks(relative_links) => {
+                config_set!(relative_links, relative_links);
+                return self.update_config();
+            }
+            Message::SetSmartExtract(smart_extract) => {
+                config_set!(smart_extract, smart_extract);
+                return self.update_config();
+            }
+            Message::SetExtractLimits(extract_limits) => {
+                config_set!(extract_limits, extract_limits);
+                return self.update_config();
+            }
+            Message::SetPreviewOperations(preview_operations) => {
+                config_set!(preview_operations, preview_operations);
+                return self.update_config();
+            }
+            Message::SetShredPasses(shred_passes) => {
+                config_set!(shred_passes, shred_passes);
+                return self.update_config();
+            }
+            Message::Shred(entity_opt) => {
+                let paths = self.selected_paths(entity_opt);
+                if !paths.is_empty() {
+                    let warning = paths.iter().find_map(|path| shred::unreliable_reason(path));
+                    return self
+                        .dialog_pages
+                        .push_back(DialogPage::Shred { paths, warning });
+                }
+            }
             Message::SystemThemeModeChange => {
                 return self.update_config();
             }
@@ -4628,6 +5820,12 @@ impl Application for App {
                             self.context_page = ContextPage::NetworkDrive;
                             self.set_show_context(true);
                         }
+                        tab::Command::AddToArchive(archive, paths) => {
+                            commands.push(
+                                self.dialog_pages
+                                    .push_back(DialogPage::ArchiveAdd { archive, paths }),
+                            );
+                        }
                         tab::Command::AddToSidebar(path) => {
                             let mut favorites = self.config.favorites.clone();
                             let favorite = Favorite::from_path(path);
@@ -4729,7 +5927,10 @@ impl Application for App {
                         }
                         tab::Command::Delete(paths) => commands.push(self.delete(paths)),
                         tab::Command::DropFiles(to, from) => {
-                            commands.push(self.update(Message::PasteContents(to, from)));
+                            commands.push(self.drop_contents(to, from));
+                        }
+                        tab::Command::EditPermissions(paths) => {
+                            commands.push(load_accounts(paths));
                         }
                         tab::Command::EmptyTrash => {
                             return self.dialog_pages.push_back(DialogPage::EmptyTrash);
@@ -4910,6 +6111,15 @@ impl Application for App {
                     tab.refresh_cut(&paths);
                 }
             }
+            Message::TestArchive(entity_opt) => {
+                let paths = self.selected_paths(entity_opt);
+                if !paths.is_empty() {
+                    return self.operation(Operation::ArchiveTest {
+                        paths,
+                        password: None,
+                    });
+                }
+            }
             Message::TimeConfigChange(time_config) => {
                 self.config.tab.military_time = time_config.military_time;
                 return self.update_config();
@@ -4931,37 +6141,66 @@ impl Application for App {
                     )));
                 }
             }
-            Message::Undo(_id) => {
-                // TODO: undo
+            Message::Redo => {
+                let Some(entry) = self.undo_stack.pop_redo() else {
+                    return Task::none();
+                };
+                if let Err(path) = entry.check_redo() {
+                    let description = fl!("redo-refused", name = file_name_lossy(path));
+                    self.undo_stack.push_redo(entry);
+                    return self
+                        .toasts
+                        .push(widget::toaster::Toast::new(description))
+                        .map(cosmic::Action::App);
+                }
+                let undo = PendingUndo::Redo(UndoSnapshot::capture(&entry.operation));
+                return self.operation_with_undo(entry.operation, undo);
+            }
+            Message::Undo => {
+                let Some(entry) = self.undo_stack.pop_undo() else {
+                    return Task::none();
+                };
+                // Never clobber data that changed after the operation completed
+                if let Err(path) = entry.check_undo() {
+                    let description = fl!("undo-refused", name = file_name_lossy(path));
+                    self.undo_stack.push_undo(entry);
+                    return self
+                        .toasts
+                        .push(widget::toaster::Toast::new(description))
+                        .map(cosmic::Action::App);
+                }
+                match entry.inverse.clone() {
+                    Inverse::Operation(operation) => {
+                        return self.operation_with_undo(operation, PendingUndo::Undo(entry));
+                    }
+                    Inverse::RestoreFromTrash(paths) => {
+                        let icon_sizes = self.config.tab.icon_sizes;
+                        return cosmic::task::future(async move {
+                            let items = trash_items(paths.into(), icon_sizes).await;
+                            Message::UndoRestore(entry, items)
+                        });
+                    }
+                }
+            }
+            Message::UndoRestore(entry, items) => {
+                if items.is_empty() {
+                    log::warn!("no trash items found to undo {:?}", entry.operation);
+                    return Task::none();
+                }
+                return self
+                    .operation_with_undo(Operation::Restore { items }, PendingUndo::Undo(entry));
             }
             Message::UndoTrash(id, recently_trashed) => {
                 self.toasts.remove(id);
 
-                let mut paths = Vec::with_capacity(recently_trashed.len());
-                let icon_sizes = self.config.tab.icon_sizes;
+                // The toast reverted this operation, so it can no longer be undone
+                self.undo_stack.forget(&Operation::Delete {
+                    paths: recently_trashed.to_vec(),
+                });
 
+                let icon_sizes = self.config.tab.icon_sizes;
                 return cosmic::task::future(async move {
-                    match tokio::task::spawn_blocking(move || Location::Trash.scan(icon_sizes))
-                        .await
-                    {
-                        Ok((_parent_item_opt, items)) => {
-                            for path in &*recently_trashed {
-                                for item in &items {
-                                    if let ItemMetadata::Trash { ref entry, .. } = item.metadata {
-                                        let original_path = entry.original_path();
-                                        if &original_path == path {
-                                            paths.push(entry.clone());
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            log::warn!("failed to rescan: {}", err);
-                        }
-                    }
-
-                    Message::UndoTrashStart(paths)
+                    Message::UndoTrashStart(trash_items(recently_trashed, icon_sizes).await)
                 });
             }
             Message::UndoTrashStart(items) => {
@@ -5075,13 +6314,13 @@ impl Application for App {
                         _ => ClipboardKind::Copy,
                     };
                     let ret = match location {
-                        Location::Path(p) => self.update(Message::PasteContents(
+                        Location::Path(p) => self.drop_contents(
                             p.clone(),
                             ClipboardPaste {
                                 kind,
                                 paths: data.paths,
                             },
-                        )),
+                        ),
                         Location::Trash if matches!(action, DndAction::Move) => {
                             self.delete(data.paths)
                         }
@@ -5139,13 +6378,13 @@ impl Application for App {
                             self.delete(data.paths)
                         }
                         _ => match tab.location.path_opt() {
-                            Some(path) => self.update(Message::PasteContents(
+                            Some(path) => self.drop_contents(
                                 path.clone(),
                                 ClipboardPaste {
                                     kind,
                                     paths: data.paths,
                                 },
-                            )),
+                            ),
                             None => {
                                 log::warn!("{:?} to {:?} is not supported.", action, tab.location);
                                 Task::none()
@@ -5745,12 +6984,35 @@ impl Application for App {
         } = theme::active().cosmic().spacing;
 
         let dialog = match dialog_page {
+            DialogPage::ArchiveAdd { archive, paths } => {
+                let target = if paths.len() == 1 {
+                    format!("\"{}\"", file_name_lossy(&paths[0]))
+                } else {
+                    fl!("selected-items", items = paths.len())
+                };
+                widget::dialog()
+                    .title(fl!("add-to-archive"))
+                    .body(fl!(
+                        "add-to-archive-question",
+                        target = target,
+                        archive = file_name_lossy(archive)
+                    ))
+                    .primary_action(
+                        widget::button::suggested(fl!("add")).on_press(Message::DialogComplete),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    )
+            }
             DialogPage::Compress {
                 paths,
                 to,
                 name,
                 archive_type,
+                level,
                 password,
+                encryption,
+                estimate,
             } => {
                 let mut dialog = widget::dialog().title(fl!("create-archive"));
 
@@ -5781,7 +7043,11 @@ impl Application for App {
                     }
                 };
 
-                let archive_types = ArchiveType::all();
+                // Single files can also be compressed without an archive around them
+                let archive_types = match paths.as_slice() {
+                    [path] if path.is_file() => ArchiveType::all(),
+                    _ => ArchiveType::archives(),
+                };
                 let selected = archive_types.iter().position(|&x| x == *archive_type);
                 dialog = dialog
                     .primary_action(
@@ -5803,7 +7069,10 @@ impl Application for App {
                                             to: to.clone(),
                                             name: name.clone(),
                                             archive_type: *archive_type,
+                                            level: *level,
                                             password: password.clone(),
+                                            encryption: *encryption,
+                                            estimate: *estimate,
                                         })
                                     })
                                     .on_submit_maybe(
@@ -5816,12 +7085,26 @@ impl Application for App {
                                     move |index| index,
                                 ))
                                 .map(|index| {
+                                    // Names that were not changed follow the format
+                                    let new_type = archive_types[index];
+                                    let name = match paths.first() {
+                                        Some(path)
+                                            if archive_type.base_name(path).as_ref()
+                                                == Some(name) =>
+                                        {
+                                            new_type.base_name(path).unwrap_or_default()
+                                        }
+                                        _ => name.clone(),
+                                    };
                                     Message::DialogUpdate(DialogPage::Compress {
                                         paths: paths.clone(),
                                         to: to.clone(),
-                                        name: name.clone(),
-                                        archive_type: archive_types[index],
+                                        name,
+                                        archive_type: new_type,
+                                        level: new_type.default_level(),
                                         password: password.clone(),
+                                        encryption: *encryption,
+                                        estimate: None,
                                     })
                                 }),
                             ])
@@ -5832,6 +7115,37 @@ impl Application for App {
                         .spacing(space_xxs),
                     );
 
+                let estimate = match estimate {
+                    Some(size) => fl!("estimated-size", size = tab::format_size(*size)),
+                    None => fl!("estimating"),
+                };
+                dialog = dialog.control(
+                    widget::column::with_children(vec![
+                        widget::text::body(fl!("compression-level")).into(),
+                        widget::row::with_children(vec![
+                            widget::slider(archive_type.levels(), *level, move |level| {
+                                Message::DialogUpdate(DialogPage::Compress {
+                                    paths: paths.clone(),
+                                    to: to.clone(),
+                                    name: name.clone(),
+                                    archive_type: *archive_type,
+                                    level,
+                                    password: password.clone(),
+                                    encryption: *encryption,
+                                    estimate: None,
+                                })
+                            })
+                            .into(),
+                            widget::text::body(level.to_string()).into(),
+                        ])
+                        .align_y(Alignment::Center)
+                        .spacing(space_xxs)
+                        .into(),
+                        widget::text::caption(estimate).into(),
+                    ])
+                    .spacing(space_xxs),
+                );
+
                 if *archive_type == ArchiveType::Zip {
                     let password_unwrapped = password.clone().unwrap_or_else(String::default);
                     dialog = dialog.control(widget::column::with_children(vec![
@@ -5844,7 +7158,10 @@ impl Application for App {
                                     to: to.clone(),
                                     name: name.clone(),
                                     archive_type: *archive_type,
+                                    level: *level,
                                     password: Some(password_unwrapped),
+                                    encryption: *encryption,
+                                    estimate: *estimate,
                                 })
                             })
                             .on_submit_maybe(
@@ -5852,6 +7169,37 @@ impl Application for App {
                             )
                             .into(),
                     ]));
+
+                    if password
+                        .as_ref()
+                        .is_some_and(|password| !password.is_empty())
+                    {
+                        let encryptions = ZipEncryption::all();
+                        let selected = encryptions.iter().position(|&x| x == *encryption);
+                        let mut column = vec![
+                            widget::text::body(fl!("zip-encryption")).into(),
+                            Element::from(widget::dropdown(encryptions, selected, move |index| {
+                                index
+                            }))
+                            .map(|index| {
+                                Message::DialogUpdate(DialogPage::Compress {
+                                    paths: paths.clone(),
+                                    to: to.clone(),
+                                    name: name.clone(),
+                                    archive_type: *archive_type,
+                                    level: *level,
+                                    password: password.clone(),
+                                    encryption: encryptions[index],
+                                    estimate: *estimate,
+                                })
+                            }),
+                        ];
+                        if *encryption == ZipEncryption::ZipCrypto {
+                            column.push(widget::text::caption(fl!("zip-crypto-warning")).into());
+                        }
+                        dialog = dialog
+                            .control(widget::column::with_children(column).spacing(space_xxs));
+                    }
                 }
 
                 dialog
@@ -5879,18 +7227,95 @@ impl Application for App {
                         widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
                     )
             }
-            DialogPage::ExtractPassword { id, password } => {
-                widget::dialog()
+            DialogPage::FailureReport { failures, retry } => {
+                let mut column = widget::column::with_capacity(failures.len()).spacing(space_xxs);
+                for failure in failures {
+                    column = column.push(widget::text::body(failure.kind.to_string()));
+                }
+                let mut remedies = Vec::<String>::new();
+                for remedy in failures.iter().filter_map(|failure| failure.kind.remedy()) {
+                    if !remedies.contains(&remedy) {
+                        remedies.push(remedy);
+                    }
+                }
+
+                let mut controls = vec![
+                    widget::scrollable(column)
+                        .height(Length::Fixed(
+                            self.size
+                                .map_or(240.0, |size| (size.height - 320.0).clamp(96.0, 240.0)),
+                        ))
+                        .into(),
+                ];
+                controls.extend(
+                    remedies
+                        .into_iter()
+                        .map(|remedy| widget::text::caption(remedy).into()),
+                );
+
+                let mut dialog = widget::dialog()
+                    .title(fl!("failure-report"))
+                    .body(fl!("failure-report-description", items = failures.len()))
+                    .icon(icon::from_name("dialog-warning").size(64))
+                    .control(widget::column::with_children(controls).spacing(space_s))
+                    .secondary_action(
+                        widget::button::standard(fl!("close")).on_press(Message::DialogCancel),
+                    );
+                if !retry.is_empty() {
+                    dialog = dialog.primary_action(
+                        widget::button::suggested(fl!("retry-failed"))
+                            .on_press(Message::DialogComplete),
+                    );
+                }
+                dialog
+            }
+            DialogPage::InterruptedOperation(interrupted) => widget::dialog()
+                .title(fl!("interrupted-operation"))
+                .body(fl!(
+                    "interrupted-operation-description",
+                    operation = interrupted.operation.pending_text(
+                        interrupted.completed as f32 / interrupted.total.max(1) as f32,
+                        ControllerState::Paused
+                    )
+                ))
+                .icon(icon::from_name("dialog-warning").size(64))
+                .primary_action(
+                    widget::button::suggested(fl!("resume")).on_press(Message::DialogComplete),
+                )
+                .secondary_action(
+                    widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                )
+                .tertiary_action(
+                    widget::button::destructive(fl!("discard"))
+                        .on_press(Message::DiscardInterrupted),
+                ),
+            DialogPage::ExtractPassword {
+                id,
+                password,
+                wrong,
+            } => {
+                let mut dialog = widget::dialog()
                     .title(fl!("extract-password-required"))
                     .icon(icon::from_name("dialog-error").size(64))
                     .control(widget::text_input("", password).password().on_input(
                         move |password| {
-                            Message::DialogUpdate(DialogPage::ExtractPassword { id: *id, password })
+                            Message::DialogUpdate(DialogPage::ExtractPassword {
+                                id: *id,
+                                password,
+                                wrong: *wrong,
+                            })
                         },
-                    ))
+                    ));
+                if *wrong {
+                    dialog = dialog.body(fl!("extract-password-wrong-retry"));
+                }
+                let action = match self.failed_operations.get(id) {
+                    Some((Operation::ArchiveTest { .. }, ..)) => fl!("test-archive"),
+                    _ => fl!("extract-here"),
+                };
+                dialog
                     .primary_action(
-                        widget::button::suggested(fl!("extract-here"))
-                            .on_press(Message::DialogComplete),
+                        widget::button::suggested(action).on_press(Message::DialogComplete),
                     )
                     .secondary_action(
                         widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
@@ -6247,6 +7672,400 @@ impl Application for App {
                         target = target
                     )))
             }
+            DialogPage::Permissions {
+                paths,
+                files,
+                dirs,
+                mask,
+                recursive,
+                owner,
+                group,
+                users,
+                groups,
+            } => {
+                let update = move |files: String,
+                                   dirs: String,
+                                   mask: ModeMask,
+                                   recursive: bool,
+                                   owner: Option<u32>,
+                                   group: Option<u32>| {
+                    Message::DialogUpdate(DialogPage::Permissions {
+                        paths: paths.clone(),
+                        files,
+                        dirs,
+                        mask,
+                        recursive,
+                        owner,
+                        group,
+                        users: users.clone(),
+                        groups: groups.clone(),
+                    })
+                };
+
+                let target = if paths.len() == 1 {
+                    format!("\"{}\"", file_name_lossy(&paths[0]))
+                } else {
+                    fl!("selected-items", items = paths.len())
+                };
+                let modes_valid = (files.is_empty() && dirs.is_empty())
+                    || (permissions::parse_mode(files).is_some()
+                        && permissions::parse_mode(dirs).is_some());
+                let changed = !files.is_empty() || owner.is_some() || group.is_some();
+
+                let mut dialog = widget::dialog()
+                    .title(fl!("edit-permissions"))
+                    .body(fl!("edit-permissions-description", target = target))
+                    .primary_action(widget::button::suggested(fl!("apply")).on_press_maybe(
+                        (modes_valid && changed).then_some(Message::DialogComplete),
+                    ))
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    );
+                if !modes_valid {
+                    dialog = dialog.tertiary_action(widget::text::body(fl!("permissions-invalid")));
+                }
+
+                let mut masks = widget::column::with_capacity(3).spacing(space_xxs);
+                for (value, label) in [
+                    (ModeMask::Set, fl!("permissions-set")),
+                    (ModeMask::Add, fl!("permissions-add")),
+                    (ModeMask::Remove, fl!("permissions-remove")),
+                ] {
+                    masks = masks.push(widget::radio(
+                        widget::text::body(label),
+                        value,
+                        Some(*mask),
+                        move |mask| {
+                            update(
+                                files.clone(),
+                                dirs.clone(),
+                                mask,
+                                *recursive,
+                                *owner,
+                                *group,
+                            )
+                        },
+                    ));
+                }
+
+                dialog
+                    .control(
+                        widget::row::with_children(vec![
+                            widget::column::with_children(vec![
+                                widget::text::body(fl!("file-permissions")).into(),
+                                widget::text_input("644", files.as_str())
+                                    .on_input(move |files| {
+                                        update(
+                                            files,
+                                            dirs.clone(),
+                                            *mask,
+                                            *recursive,
+                                            *owner,
+                                            *group,
+                                        )
+                                    })
+                                    .into(),
+                            ])
+                            .spacing(space_xxs)
+                            .into(),
+                            widget::column::with_children(vec![
+                                widget::text::body(fl!("folder-permissions")).into(),
+                                widget::text_input("755", dirs.as_str())
+                                    .on_input(move |dirs| {
+                                        update(
+                                            files.clone(),
+                                            dirs,
+                                            *mask,
+                                            *recursive,
+                                            *owner,
+                                            *group,
+                                        )
+                                    })
+                                    .into(),
+                            ])
+                            .spacing(space_xxs)
+                            .into(),
+                        ])
+                        .spacing(space_s),
+                    )
+                    .control(masks)
+                    .control(
+                        widget::row::with_children(vec![
+                            widget::column::with_children(vec![
+                                widget::text::body(fl!("owner")).into(),
+                                widget::dropdown(
+                                    &users.names,
+                                    users.position(*owner),
+                                    move |index| {
+                                        update(
+                                            files.clone(),
+                                            dirs.clone(),
+                                            *mask,
+                                            *recursive,
+                                            users.ids[index],
+                                            *group,
+                                        )
+                                    },
+                                )
+                                .into(),
+                            ])
+                            .spacing(space_xxs)
+                            .into(),
+                            widget::column::with_children(vec![
+                                widget::text::body(fl!("group")).into(),
+                                widget::dropdown(
+                                    &groups.names,
+                                    groups.position(*group),
+                                    move |index| {
+                                        update(
+                                            files.clone(),
+                                            dirs.clone(),
+                                            *mask,
+                                            *recursive,
+                                            *owner,
+                                            groups.ids[index],
+                                        )
+                                    },
+                                )
+                                .into(),
+                            ])
+                            .spacing(space_xxs)
+                            .into(),
+                        ])
+                        .spacing(space_s),
+                    )
+                    .control(
+                        widget::checkbox(fl!("apply-recursively"), *recursive).on_toggle(
+                            move |recursive| {
+                                update(
+                                    files.clone(),
+                                    dirs.clone(),
+                                    *mask,
+                                    recursive,
+                                    *owner,
+                                    *group,
+                                )
+                            },
+                        ),
+                    )
+            }
+            DialogPage::PlanPreview {
+                operation, summary, ..
+            } => {
+                let can_start = matches!(summary, Some(Ok(_)));
+                let mut column = widget::column::with_capacity(8).spacing(space_xxs);
+                let summary = match summary {
+                    Some(Ok(summary)) => Some(summary),
+                    Some(Err(err)) => {
+                        column = column.push(widget::text::body(err.as_str()));
+                        None
+                    }
+                    None => {
+                        column = column.push(widget::text::body(fl!("planning")));
+                        None
+                    }
+                };
+                if let Some(summary) = summary {
+                    column = column.push(widget::text::body(fl!(
+                        "plan-items",
+                        files = summary.files,
+                        dirs = summary.dirs,
+                        size = tab::format_size(summary.bytes)
+                    )));
+                    if summary.links > 0 {
+                        column = column
+                            .push(widget::text::body(fl!("plan-links", links = summary.links)));
+                    }
+                    let conflicts = summary.conflicts.len();
+                    let skipped = summary.skipped.len();
+                    let special = summary.special.len();
+                    let failures = summary.failures.len();
+                    for (items, text) in [
+                        (conflicts, fl!("plan-conflicts", items = conflicts)),
+                        (skipped, fl!("plan-skipped", items = skipped)),
+                        (special, fl!("plan-special", items = special)),
+                        (failures, fl!("plan-failures", items = failures)),
+                    ] {
+                        if items > 0 {
+                            column = column.push(widget::text::body(text));
+                        }
+                    }
+                    // Missing space is one of the violations
+                    if let Some(remaining) = summary.remaining().filter(|&remaining| remaining >= 0)
+                    {
+                        column = column.push(widget::text::caption(fl!(
+                            "plan-space-remaining",
+                            size = tab::format_size(remaining as u64)
+                        )));
+                    }
+                    if !summary.violations.is_empty() {
+                        column = column.push(widget::text::body(fl!(
+                            "unsupported-items-description",
+                            items = summary.violations.len(),
+                            fs = summary.fs_type.as_str()
+                        )));
+                    }
+                    // The failure dialog lists all of them if the operation is started anyway
+                    let shown = 5;
+                    for violation in summary.violations.iter().take(shown) {
+                        column = column.push(widget::text::caption(violation.to_string()));
+                    }
+                    if summary.violations.len() > shown {
+                        column = column.push(widget::text::caption(fl!(
+                            "plan-more",
+                            items = summary.violations.len() - shown
+                        )));
+                    }
+                }
+
+                let conflicts = match operation {
+                    Operation::Copy { conflicts, .. } | Operation::Move { conflicts, .. } => {
+                        conflicts.clone()
+                    }
+                    _ => ConflictPolicy::default(),
+                };
+                let files = {
+                    let conflicts = conflicts.clone();
+                    widget::dropdown(
+                        &self.file_policy_names,
+                        FilePolicy::all()
+                            .iter()
+                            .position(|&policy| policy == conflicts.files),
+                        move |index| {
+                            Message::PlanConflicts(ConflictPolicy {
+                                files: FilePolicy::all()[index],
+                                ..conflicts.clone()
+                            })
+                        },
+                    )
+                };
+                let dirs = {
+                    let conflicts = conflicts.clone();
+                    widget::dropdown(
+                        &self.dir_policy_names,
+                        DirPolicy::all()
+                            .iter()
+                            .position(|&policy| policy == conflicts.dirs),
+                        move |index| {
+                            Message::PlanConflicts(ConflictPolicy {
+                                dirs: DirPolicy::all()[index],
+                                ..conflicts.clone()
+                            })
+                        },
+                    )
+                };
+                // Changing the policy plans the operation again
+                let mut policies = widget::column::with_capacity(9).spacing(space_xxs);
+                for (label, dropdown) in [
+                    (fl!("conflicts-files"), Element::from(files)),
+                    (fl!("conflicts-folders"), Element::from(dirs)),
+                ] {
+                    policies = policies.push(
+                        widget::row::with_children(vec![
+                            widget::text::body(label).width(Length::Fill).into(),
+                            dropdown,
+                        ])
+                        .align_y(Alignment::Center)
+                        .spacing(space_xxs),
+                    );
+                }
+                let sanitizable = summary.is_some_and(|summary| {
+                    summary
+                        .violations
+                        .iter()
+                        .any(|violation| violation.sanitizable())
+                });
+                if sanitizable || conflicts.sanitize_names {
+                    policies = policies.push(
+                        widget::checkbox(fl!("sanitize-names"), conflicts.sanitize_names)
+                            .on_toggle(move |sanitize_names| {
+                                Message::PlanConflicts(ConflictPolicy {
+                                    sanitize_names,
+                                    ..conflicts.clone()
+                                })
+                            }),
+                    );
+                }
+
+                // Attributes of this operation, starting from the settings
+                let preserve = match operation {
+                    Operation::Copy { preserve, .. } | Operation::Move { preserve, .. } => {
+                        *preserve
+                    }
+                    _ => Preserve::default(),
+                };
+                policies = policies
+                    .push(widget::text::body(fl!("preserve-attributes")))
+                    .push(
+                        widget::checkbox(fl!("preserve-timestamps"), preserve.timestamps)
+                            .on_toggle(move |timestamps| {
+                                Message::PlanPreserve(Preserve {
+                                    timestamps,
+                                    ..preserve
+                                })
+                            }),
+                    )
+                    .push(
+                        widget::checkbox(fl!("preserve-mode"), preserve.mode).on_toggle(
+                            move |mode| Message::PlanPreserve(Preserve { mode, ..preserve }),
+                        ),
+                    )
+                    .push(
+                        widget::checkbox(fl!("preserve-ownership"), preserve.ownership).on_toggle(
+                            move |ownership| {
+                                Message::PlanPreserve(Preserve {
+                                    ownership,
+                                    ..preserve
+                                })
+                            },
+                        ),
+                    )
+                    .push(
+                        widget::checkbox(fl!("preserve-xattrs"), preserve.xattrs).on_toggle(
+                            move |xattrs| Message::PlanPreserve(Preserve { xattrs, ..preserve }),
+                        ),
+                    )
+                    .push(
+                        widget::checkbox(fl!("preserve-acls"), preserve.acls).on_toggle(
+                            move |acls| Message::PlanPreserve(Preserve { acls, ..preserve }),
+                        ),
+                    );
+
+                widget::dialog()
+                    .title(fl!("plan-preview"))
+                    .control(column)
+                    .control(policies)
+                    .primary_action(
+                        widget::button::suggested(fl!("start"))
+                            .on_press_maybe(can_start.then_some(Message::DialogComplete)),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    )
+            }
+            DialogPage::Shred { paths, warning } => {
+                let target = if paths.len() == 1 {
+                    format!("\"{}\"", file_name_lossy(&paths[0]))
+                } else {
+                    fl!("selected-items", items = paths.len())
+                };
+
+                let mut dialog = widget::dialog()
+                    .title(fl!("shred-question"))
+                    .body(fl!("shred-warning", target = target))
+                    .primary_action(
+                        widget::button::destructive(fl!("shred")).on_press(Message::DialogComplete),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    );
+                if let Some(warning) = warning {
+                    dialog = dialog
+                        .icon(icon::from_name("dialog-warning").size(64))
+                        .control(widget::text::body(warning.as_str()));
+                }
+                dialog
+            }
             DialogPage::RenameItem {
                 from,
                 parent,
@@ -6361,6 +8180,19 @@ impl Application for App {
                                 },
                             ),
                         )
+                        .control(
+                            widget::row::with_children(vec![
+                                widget::text::body(fl!("apply-rule-to-all")).into(),
+                                widget::horizontal_space().into(),
+                                widget::dropdown(&self.file_rule_names, None, |index| {
+                                    Message::ReplaceResult(ReplaceResult::Policy(
+                                        FilePolicy::rules()[index],
+                                    ))
+                                })
+                                .into(),
+                            ])
+                            .align_y(Alignment::Center),
+                        )
                         .secondary_action(
                             widget::button::standard(fl!("skip")).on_press(Message::ReplaceResult(
                                 ReplaceResult::Skip(*apply_to_all),
@@ -6404,6 +8236,54 @@ impl Application for App {
                         name = name
                     )))
             }
+            DialogPage::Unsupported {
+                fs_type,
+                violations,
+                ..
+            } => {
+                let mut column = widget::column::with_capacity(violations.len()).spacing(space_xxs);
+                for violation in violations {
+                    column = column.push(widget::text::body(violation.to_string()));
+                }
+
+                let mut dialog = widget::dialog()
+                    .title(fl!("unsupported-items"))
+                    .body(fl!(
+                        "unsupported-items-description",
+                        items = violations.len(),
+                        fs = fs_type.as_str()
+                    ))
+                    .icon(icon::from_name("dialog-warning").size(64))
+                    .control(
+                        widget::scrollable(column).height(Length::Fixed(
+                            self.size
+                                .map_or(240.0, |size| (size.height - 320.0).clamp(96.0, 240.0)),
+                        )),
+                    )
+                    .secondary_action(
+                        widget::button::standard(fl!("cancel")).on_press(Message::DialogCancel),
+                    );
+                if violations.iter().any(|violation| violation.sanitizable()) {
+                    dialog = dialog.primary_action(
+                        widget::button::suggested(fl!("sanitize-names"))
+                            .on_press(Message::DialogComplete),
+                    );
+                }
+                dialog
+            }
+            DialogPage::VerifyFailed { path, .. } => widget::dialog()
+                .title(fl!("verify-failed"))
+                .body(fl!(
+                    "verify-failed-description",
+                    name = file_name_lossy(path)
+                ))
+                .icon(icon::from_name("dialog-error").size(64))
+                .primary_action(
+                    widget::button::suggested(fl!("try-again")).on_press(Message::DialogComplete),
+                )
+                .secondary_action(
+                    widget::button::standard(fl!("cancel")).on_press(Message::DiscardInterrupted),
+                ),
             DialogPage::FavoritePathError { path, .. } => widget::dialog()
                 .title(fl!("favorite-path-error"))
                 .body(fl!(
@@ -6799,6 +8679,11 @@ impl Application for App {
                 // Handle notification when window is closed and operations are in progress
                 #[cfg(feature = "notify")]
                 {
+                    subscriptions.push(
+                        cosmic::iced::time::every(Duration::from_secs(1))
+                            .map(|_| Message::NotificationRefresh),
+                    );
+
                     struct NotificationSubscription;
                     subscriptions.push(Subscription::run_with_id(
                         TypeId::of::<NotificationSubscription>(),
@@ -6816,7 +8701,7 @@ impl Application for App {
                                                 .lock()
                                                 .await
                                                 .send(Message::Notification(Arc::new(Mutex::new(
-                                                    notification,
+                                                    Some(notification),
                                                 ))))
                                                 .await
                                         });
@@ -0,0 +1,389 @@
+// Running operations from the command line without opening a window
+//
+// Operations are performed exactly like the application does, with conflicts answered by
+// the --on-conflict option instead of the replace dialog.
+
+use cosmic::iced::futures::{StreamExt, channel::mpsc};
+use std::{
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+use tokio::sync::Mutex as TokioMutex;
+
+use crate::{
+    app::Message,
+    core::{
+        config::Config,
+        operations::{
+            Controller, FilePolicy, Operation, OperationError, OperationSelection, ReplaceResult,
+        },
+    },
+    fl,
+    views::dialogs::dialog_pages::{ArchiveType, DialogPage, ZipEncryption},
+};
+
+/// Exit code of a failed or cancelled operation
+const EXIT_FAILED: i32 = 1;
+/// Exit code of invalid arguments
+pub const EXIT_USAGE: i32 = 2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Verb {
+    Copy,
+    Move,
+    Trash,
+    Extract,
+    Compress,
+}
+
+impl Verb {
+    fn parse(arg: &str) -> Option<Self> {
+        match arg {
+            "copy" => Some(Self::Copy),
+            "move" => Some(Self::Move),
+            "trash" => Some(Self::Trash),
+            "extract" => Some(Self::Extract),
+            "compress" => Some(Self::Compress),
+            _ => None,
+        }
+    }
+}
+
+/// Answer to every conflict, mapped onto the result of the replace dialog
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OnConflict {
+    /// Stop the operation and fail
+    #[default]
+    Cancel,
+    Replace,
+    Skip,
+    KeepBoth,
+}
+
+impl OnConflict {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "cancel" => Some(Self::Cancel),
+            "replace" => Some(Self::Replace),
+            "skip" => Some(Self::Skip),
+            "keep-both" => Some(Self::KeepBoth),
+            _ => None,
+        }
+    }
+
+    fn replace_result(self) -> ReplaceResult {
+        match self {
+            Self::Cancel => ReplaceResult::Cancel,
+            Self::Replace => ReplaceResult::Replace(true),
+            Self::Skip => ReplaceResult::Skip(true),
+            Self::KeepBoth => ReplaceResult::KeepBoth,
+        }
+    }
+}
+
+/// An operation requested on the command line
+#[derive(Debug, Eq, PartialEq)]
+pub struct Command {
+    verb: Verb,
+    paths: Vec<PathBuf>,
+    to: Option<PathBuf>,
+    /// Set if --on-conflict was given, otherwise the configured file policy applies
+    on_conflict: Option<OnConflict>,
+    keep_going: bool,
+}
+
+impl Command {
+    /// Parse the arguments after the program name, returning `None` if they do not start with
+    /// a subcommand
+    pub fn parse(args: &[String]) -> Option<Result<Self, String>> {
+        let (verb, args) = args.split_first()?;
+        let verb = Verb::parse(verb)?;
+        Some(Self::parse_args(verb, args))
+    }
+
+    fn parse_args(verb: Verb, args: &[String]) -> Result<Self, String> {
+        let mut on_conflict = None;
+        let mut keep_going = false;
+        let mut paths = Vec::new();
+        let mut options = true;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if !options || !arg.starts_with("--") {
+                paths.push(PathBuf::from(arg));
+                continue;
+            }
+            let (option, value) = match arg.split_once('=') {
+                Some((option, value)) => (option, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            match option {
+                "--" => options = false,
+                "--keep-going" => keep_going = true,
+                "--on-conflict" => {
+                    let value = value.or_else(|| args.next().cloned()).unwrap_or_default();
+                    on_conflict = Some(
+                        OnConflict::parse(&value)
+                            .ok_or_else(|| fl!("cli-invalid-conflict", value = value))?,
+                    );
+                }
+                _ => return Err(fl!("cli-unknown-option", option = option)),
+            }
+        }
+
+        let to = if verb == Verb::Trash {
+            None
+        } else {
+            paths.pop()
+        };
+        if paths.is_empty() {
+            return Err(fl!("cli-missing-paths"));
+        }
+        Ok(Self {
+            verb,
+            paths,
+            to,
+            on_conflict,
+            keep_going,
+        })
+    }
+
+    fn operation(&self, config: &Config) -> Result<Operation, String> {
+        let absolute = |item: &PathBuf| std::path::absolute(item).map_err(|err| err.to_string());
+        let paths = self
+            .paths
+            .iter()
+            .map(absolute)
+            .collect::<Result<Vec<_>, _>>()?;
+        let to = self
+            .to
+            .as_ref()
+            .map(absolute)
+            .transpose()?
+            .unwrap_or_default();
+        let mut conflicts = config.conflicts.clone();
+        if self.on_conflict.is_some() {
+            // Every conflict is asked about, and answered with the option
+            conflicts.files = FilePolicy::Ask;
+        }
+        Ok(match self.verb {
+            Verb::Copy => Operation::Copy {
+                paths,
+                to,
+                verify: config.verify_copies,
+                preserve: config.preserve,
+                conflicts,
+            },
+            Verb::Move => Operation::Move {
+                paths,
+                to,
+                cross_device_copy: false,
+                verify: config.verify_copies,
+                preserve: config.preserve,
+                conflicts,
+            },
+            Verb::Trash => Operation::Delete { paths },
+            Verb::Extract => Operation::Extract {
+                paths,
+                to,
+                password: None,
+                conflicts,
+                smart: config.smart_extract,
+                limits: config.extract_limits,
+            },
+            Verb::Compress => {
+                let name = to.to_string_lossy();
+                let archive_type = ArchiveType::from_name(&name).ok_or_else(|| {
+                    let extensions = ArchiveType::all()
+                        .iter()
+                        .map(ArchiveType::extension)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    fl!(
+                        "cli-unknown-archive",
+                        path = name.as_ref(),
+                        extensions = extensions
+                    )
+                })?;
+                Operation::Compress {
+                    paths,
+                    to,
+                    archive_type,
+                    level: archive_type.default_level(),
+                    password: None,
+                    encryption: ZipEncryption::default(),
+                }
+            }
+        })
+    }
+}
+
+/// Run `command`, printing progress to the terminal, and return the exit code
+pub fn run(command: Command) -> i32 {
+    let (_, config) = Config::load();
+    let operation = match command.operation(&config) {
+        Ok(operation) => operation,
+        Err(err) => {
+            eprintln!("{err}");
+            return EXIT_USAGE;
+        }
+    };
+    let controller = Controller::default();
+    controller.set_continue_on_error(command.keep_going);
+
+    let done = Arc::new(AtomicBool::new(false));
+    let progress = io::stderr().is_terminal().then(|| {
+        let operation = operation.clone();
+        let controller = controller.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                let mut text = operation.pending_text(controller.progress(), controller.state());
+                if let Some(details) = controller.details().text() {
+                    text = format!("{text}  {details}");
+                }
+                eprint!("\r\x1b[2K{text}");
+                let _ = io::stderr().flush();
+                thread::sleep(Duration::from_millis(200));
+            }
+            eprint!("\r\x1b[2K");
+        })
+    });
+
+    let result = compio::runtime::RuntimeBuilder::new()
+        .build()
+        .map_err(|err| OperationError::from_err(err, &controller))
+        .and_then(|runtime| {
+            runtime.block_on(perform(
+                operation.clone(),
+                controller.clone(),
+                command.on_conflict.unwrap_or_default(),
+            ))
+        });
+    done.store(true, Ordering::Relaxed);
+    if let Some(progress) = progress {
+        let _ = progress.join();
+    }
+
+    let failures = controller.failures();
+    for failure in &failures {
+        eprintln!("{}", failure.kind);
+    }
+    match result {
+        Ok((_, cancelled)) if !cancelled.is_empty() => {
+            for path in cancelled {
+                eprintln!(
+                    "{}",
+                    fl!("cli-conflict-cancelled", path = path.display().to_string())
+                );
+            }
+            EXIT_FAILED
+        }
+        Ok(_) if !failures.is_empty() => EXIT_FAILED,
+        Ok(_) => {
+            println!("{}", operation.completed_text());
+            0
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            EXIT_FAILED
+        }
+    }
+}
+
+/// Perform `operation`, answering conflicts with `on_conflict`. Also returns the items whose
+/// conflict cancelled the operation.
+async fn perform(
+    operation: Operation,
+    controller: Controller,
+    on_conflict: OnConflict,
+) -> Result<(OperationSelection, Vec<PathBuf>), OperationError> {
+    let (msg_tx, mut msg_rx) = mpsc::channel(1);
+    let perform = async move {
+        let msg_tx = Arc::new(TokioMutex::new(msg_tx));
+        let result = operation.perform(&msg_tx, controller).await;
+        // Dropping the sender ends the message loop
+        drop(msg_tx);
+        result
+    };
+    let handle_messages = async move {
+        let mut cancelled = Vec::new();
+        while let Some(message) = msg_rx.next().await {
+            if let Message::DialogPush(DialogPage::Replace { to, tx, .. }) = message {
+                if on_conflict == OnConflict::Cancel {
+                    cancelled.extend(to.path_opt().cloned());
+                }
+                let _ = tx.send(on_conflict.replace_result()).await;
+            }
+        }
+        cancelled
+    };
+    let (cancelled, result) = futures::future::join(handle_messages, perform).await;
+    result.map(|op_sel| (op_sel, cancelled))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{Command, OnConflict, Verb};
+    use crate::views::dialogs::dialog_pages::ArchiveType;
+
+    fn parse(args: &[&str]) -> Option<Result<Command, String>> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Command::parse(&args)
+    }
+
+    #[test]
+    fn parse_subcommands() {
+        assert_eq!(parse(&["/home"]), None);
+        assert_eq!(
+            parse(&["copy", "--on-conflict", "skip", "a", "b", "dst"]),
+            Some(Ok(Command {
+                verb: Verb::Copy,
+                paths: vec![PathBuf::from("a"), PathBuf::from("b")],
+                to: Some(PathBuf::from("dst")),
+                on_conflict: Some(OnConflict::Skip),
+                keep_going: false,
+            }))
+        );
+        assert_eq!(
+            parse(&["trash", "--keep-going", "--", "--a"]),
+            Some(Ok(Command {
+                verb: Verb::Trash,
+                paths: vec![PathBuf::from("--a")],
+                to: None,
+                on_conflict: None,
+                keep_going: true,
+            }))
+        );
+        assert!(matches!(parse(&["move", "a"]), Some(Err(_))));
+        assert!(matches!(
+            parse(&["move", "--on-conflict=overwrite", "a", "b"]),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            parse(&["extract", "--force", "a", "b"]),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn archive_type_from_name() {
+        assert_eq!(ArchiveType::from_name("out.tar.gz"), Some(ArchiveType::Tgz));
+        assert_eq!(ArchiveType::from_name("out.tgz"), Some(ArchiveType::Tgz));
+        assert_eq!(ArchiveType::from_name("out.log.gz"), Some(ArchiveType::Gz));
+        assert_eq!(ArchiveType::from_name("out.zip"), Some(ArchiveType::Zip));
+        assert_eq!(ArchiveType::from_name("out.rar"), None);
+        #[cfg(feature = "zstd")]
+        assert_eq!(
+            ArchiveType::from_name("out.tar.zst"),
+            Some(ArchiveType::TarZst)
+        );
+    }
+}
@@ -13,6 +13,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     app::App,
+    core::operations::{Checksum, ConflictPolicy, ExtractLimits, Preserve, ShredPass},
     tab::{HeadingOptions, Location, View},
 };
 
@@ -248,6 +249,22 @@ pub struct Config {
     pub show_details: bool,
     pub tab: TabConfig,
     pub type_to_search: TypeToSearch,
+    pub verify_copies: Option<Checksum>,
+    pub preserve: Preserve,
+    pub conflicts: ConflictPolicy,
+    /// Skip items that fail and report them once the operation finishes
+    pub continue_on_error: bool,
+    /// Run operations on the same device one at a time
+    pub queue_by_device: bool,
+    /// Show what a copy or move will do and wait for confirmation before starting it
+    pub preview_operations: bool,
+    /// Point symbolic links created by pasting at their target with a relative path
+    pub relative_links: bool,
+    /// Extract archives with a single item at the top without a folder around them
+    pub smart_extract: bool,
+    pub extract_limits: ExtractLimits,
+    /// Passes used to overwrite shredded files
+    pub shred_passes: Vec<ShredPass>,
 }
 
 impl Config {
@@ -334,6 +351,16 @@ impl Default for Config {
             show_details: false,
             tab: TabConfig::default(),
             type_to_search: TypeToSearch::Recursive,
+            verify_copies: None,
+            preserve: Preserve::default(),
+            conflicts: ConflictPolicy::default(),
+            continue_on_error: false,
+            queue_by_device: true,
+            relative_links: false,
+            smart_extract: true,
+            extract_limits: ExtractLimits::default(),
+            preview_operations: false,
+            shred_passes: ShredPass::default_passes(),
         }
     }
 }
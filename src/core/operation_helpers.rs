@@ -14,6 +14,7 @@ pub(crate) fn create_operation_selection(selected: Vec<PathBuf>) -> OperationSel
     OperationSelection {
         ignored: Vec::new(),
         selected,
+        ..Default::default()
     }
 }
 
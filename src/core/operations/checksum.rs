@@ -0,0 +1,84 @@
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Hash used to verify copied files
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Checksum {
+    Md5,
+    Sha256,
+}
+
+impl Checksum {
+    pub fn all() -> &'static [Self] {
+        &[Self::Md5, Self::Sha256]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|checksum| checksum.name() == name)
+    }
+
+    pub fn hasher(&self) -> Hasher {
+        match self {
+            Self::Md5 => Hasher::Md5(Md5::new()),
+            Self::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+}
+
+pub enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Md5(hasher) => hasher.finalize().to_vec(),
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Checksum;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn known_digests() {
+        for (checksum, expected) in [
+            (Checksum::Md5, "900150983cd24fb0d6963f7d28e17f72"),
+            (
+                Checksum::Sha256,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+        ] {
+            let mut hasher = checksum.hasher();
+            hasher.update(b"a");
+            hasher.update(b"bc");
+            assert_eq!(hex(&hasher.finalize()), expected);
+            assert_eq!(Checksum::from_name(checksum.name()), Some(checksum));
+        }
+    }
+}
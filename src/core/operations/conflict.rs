@@ -0,0 +1,357 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use super::{Checksum, split_file_name};
+
+/// Modification times closer than this are equal, as FAT only stores even seconds
+const MODIFIED_WINDOW: Duration = Duration::from_secs(2);
+
+/// How a file that already exists at the destination is handled
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum FilePolicy {
+    /// Show the replace dialog
+    #[default]
+    Ask,
+    Replace,
+    Skip,
+    /// Replace the existing file if the new one was modified more recently
+    KeepNewer,
+    /// Replace the existing file if the new one is larger
+    KeepLarger,
+    /// Replace the existing file if the size or modification time differ
+    ReplaceIfDifferent,
+    /// Replace the existing file if the contents differ
+    ReplaceIfContentsDiffer,
+    /// Keep both, giving the new file a name built from the rename pattern
+    Rename,
+}
+
+impl FilePolicy {
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::Ask,
+            Self::Replace,
+            Self::Skip,
+            Self::KeepNewer,
+            Self::KeepLarger,
+            Self::ReplaceIfDifferent,
+            Self::ReplaceIfContentsDiffer,
+            Self::Rename,
+        ]
+    }
+
+    /// Short representation used by the operation journal
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Ask => "ask",
+            Self::Replace => "replace",
+            Self::Skip => "skip",
+            Self::KeepNewer => "keep-newer",
+            Self::KeepLarger => "keep-larger",
+            Self::ReplaceIfDifferent => "replace-if-different",
+            Self::ReplaceIfContentsDiffer => "replace-if-contents-differ",
+            Self::Rename => "rename",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|policy| policy.name() == name)
+    }
+
+    /// Policies that decide for themselves, offered when asked about a conflict
+    pub fn rules() -> &'static [Self] {
+        &[
+            Self::KeepNewer,
+            Self::KeepLarger,
+            Self::ReplaceIfDifferent,
+            Self::ReplaceIfContentsDiffer,
+            Self::Rename,
+        ]
+    }
+}
+
+/// How a directory that already exists at the destination is handled
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum DirPolicy {
+    /// Copy the contents into the existing directory, resolving files with the file policy
+    #[default]
+    Merge,
+    /// Leave the existing directory alone and do not copy anything inside of it
+    Skip,
+    /// Keep both, giving the new directory a name built from the rename pattern
+    Rename,
+}
+
+impl DirPolicy {
+    pub fn all() -> &'static [Self] {
+        &[Self::Merge, Self::Skip, Self::Rename]
+    }
+
+    /// Short representation used by the operation journal
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Merge => "merge",
+            Self::Skip => "skip",
+            Self::Rename => "rename",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|policy| policy.name() == name)
+    }
+}
+
+/// Rules applied to every conflict of an operation
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ConflictPolicy {
+    pub files: FilePolicy,
+    pub dirs: DirPolicy,
+    /// Suffix added to renamed items before their extension. `{n}` is replaced by a counter
+    /// starting at 2, otherwise a counter is appended if the name is taken.
+    pub rename_pattern: String,
+    /// Replace characters the destination filesystem does not allow with look-alikes, and
+    /// restore them where it allows the originals
+    pub sanitize_names: bool,
+}
+
+impl Default for ConflictPolicy {
+    fn default() -> Self {
+        Self {
+            files: FilePolicy::default(),
+            dirs: DirPolicy::default(),
+            rename_pattern: " ({n})".to_string(),
+            sanitize_names: false,
+        }
+    }
+}
+
+/// Outcome of applying a policy to one conflict
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Resolution {
+    Ask,
+    Replace,
+    Skip,
+    Rename(PathBuf),
+}
+
+/// What is known about the item that would replace an existing file
+#[derive(Clone, Copy, Debug)]
+pub struct Source<'a> {
+    /// Path of the item, if it is not inside of an archive
+    pub path: Option<&'a Path>,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl<'a> Source<'a> {
+    pub fn from_metadata(path: &'a Path, metadata: &fs::Metadata) -> Self {
+        Self {
+            path: Some(path),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+}
+
+impl ConflictPolicy {
+    /// Resolve `from` conflicting with the existing file `to`
+    pub fn resolve_file(&self, from: &Source<'_>, to: &Path) -> io::Result<Resolution> {
+        let to_metadata = fs::symlink_metadata(to)?;
+        let to_modified = to_metadata.modified().ok();
+        let replace_if = |replace: bool| {
+            if replace {
+                Resolution::Replace
+            } else {
+                Resolution::Skip
+            }
+        };
+        let differs = from.len != to_metadata.len() || !same_time(from.modified, to_modified);
+        Ok(match self.files {
+            FilePolicy::Ask => Resolution::Ask,
+            FilePolicy::Replace => Resolution::Replace,
+            FilePolicy::Skip => Resolution::Skip,
+            FilePolicy::KeepNewer => replace_if(match (from.modified, to_modified) {
+                (Some(from_modified), Some(to_modified)) => {
+                    from_modified > to_modified && !same_time(from.modified, Some(to_modified))
+                }
+                _ => false,
+            }),
+            FilePolicy::KeepLarger => replace_if(from.len > to_metadata.len()),
+            FilePolicy::ReplaceIfDifferent => replace_if(differs),
+            FilePolicy::ReplaceIfContentsDiffer => match from.path {
+                Some(from_path) if from.len == to_metadata.len() => {
+                    let checksum = Checksum::Sha256;
+                    replace_if(digest(from_path, checksum)? != digest(to, checksum)?)
+                }
+                Some(_) => Resolution::Replace,
+                // Archive entries cannot be read twice, so only the metadata is compared
+                None => replace_if(differs),
+            },
+            FilePolicy::Rename => Resolution::Rename(self.renamed_path(to)),
+        })
+    }
+
+    /// Unused path next to `to`, named with the rename pattern
+    pub fn renamed_path(&self, to: &Path) -> PathBuf {
+        let (Some(parent), Some((stem, ext))) = (to.parent(), split_file_name(to)) else {
+            return to.to_path_buf();
+        };
+        let pattern = &self.rename_pattern;
+        for n in 1u64.. {
+            let suffix = if pattern.contains("{n}") {
+                pattern.replace("{n}", &(n + 1).to_string())
+            } else if n == 1 {
+                pattern.clone()
+            } else {
+                format!("{pattern}{n}")
+            };
+            let path = parent.join(match &ext {
+                Some(ext) => format!("{stem}{suffix}.{ext}"),
+                None => format!("{stem}{suffix}"),
+            });
+            // Dangling symlinks also take up the name
+            if fs::symlink_metadata(&path).is_err() {
+                return path;
+            }
+        }
+        unreachable!()
+    }
+}
+
+fn same_time(a: Option<SystemTime>, b: Option<SystemTime>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let diff = a.duration_since(b).or_else(|_| b.duration_since(a));
+            diff.is_ok_and(|diff| diff < MODIFIED_WINDOW)
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn digest(path: &Path, checksum: Checksum) -> io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = checksum.hasher();
+    let mut buf = vec![0; 128 * 1024];
+    loop {
+        let count = file.read(&mut buf)?;
+        if count == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buf[..count]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs, io,
+        time::{Duration, SystemTime},
+    };
+
+    use tempfile::tempdir;
+
+    use super::{ConflictPolicy, FilePolicy, Resolution, Source};
+
+    #[test]
+    fn rename_pattern() -> io::Result<()> {
+        let dir = tempdir()?;
+        let to = dir.path().join("report.tar.gz");
+        fs::write(&to, b"existing")?;
+
+        let mut policy = ConflictPolicy::default();
+        assert_eq!(
+            policy.renamed_path(&to),
+            dir.path().join("report (2).tar.gz")
+        );
+
+        policy.rename_pattern = "_copy".to_string();
+        assert_eq!(
+            policy.renamed_path(&to),
+            dir.path().join("report_copy.tar.gz")
+        );
+        fs::write(dir.path().join("report_copy.tar.gz"), b"existing")?;
+        assert_eq!(
+            policy.renamed_path(&to),
+            dir.path().join("report_copy2.tar.gz")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rule_policies() -> io::Result<()> {
+        let dir = tempdir()?;
+        let to = dir.path().join("to");
+        fs::write(&to, b"1234")?;
+        let to_modified = fs::metadata(&to)?.modified()?;
+        let resolve = |files, len, modified| {
+            let policy = ConflictPolicy {
+                files,
+                ..Default::default()
+            };
+            let source = Source {
+                path: None,
+                len,
+                modified: Some(modified),
+            };
+            policy.resolve_file(&source, &to)
+        };
+        let newer = to_modified + Duration::from_secs(60);
+        let older = to_modified - Duration::from_secs(60);
+
+        assert_eq!(
+            resolve(FilePolicy::KeepNewer, 4, newer)?,
+            Resolution::Replace
+        );
+        assert_eq!(resolve(FilePolicy::KeepNewer, 4, older)?, Resolution::Skip);
+        assert_eq!(
+            resolve(FilePolicy::KeepLarger, 5, older)?,
+            Resolution::Replace
+        );
+        assert_eq!(resolve(FilePolicy::KeepLarger, 3, newer)?, Resolution::Skip);
+        assert_eq!(
+            resolve(FilePolicy::ReplaceIfDifferent, 4, to_modified)?,
+            Resolution::Skip
+        );
+        assert_eq!(
+            resolve(FilePolicy::ReplaceIfDifferent, 4, SystemTime::UNIX_EPOCH)?,
+            Resolution::Replace
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn contents_are_compared() -> io::Result<()> {
+        let dir = tempdir()?;
+        let from = dir.path().join("from");
+        let to = dir.path().join("to");
+        fs::write(&from, b"same")?;
+        fs::write(&to, b"same")?;
+        let policy = ConflictPolicy {
+            files: FilePolicy::ReplaceIfContentsDiffer,
+            ..Default::default()
+        };
+        let source = Source::from_metadata(&from, &fs::metadata(&from)?);
+        assert_eq!(policy.resolve_file(&source, &to)?, Resolution::Skip);
+
+        fs::write(&from, b"diff")?;
+        let source = Source::from_metadata(&from, &fs::metadata(&from)?);
+        assert_eq!(policy.resolve_file(&source, &to)?, Resolution::Replace);
+
+        Ok(())
+    }
+}
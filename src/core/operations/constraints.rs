@@ -0,0 +1,472 @@
+// Limits of the destination filesystem, checked before a copy or move starts
+//
+// FAT and exFAT sticks reject names that Linux filesystems allow and FAT cannot store files
+// of 4 GiB or more. Finding out while copying leaves half of the items behind, so the plan is
+// checked against the limits first. Invalid characters can be replaced with similar looking
+// ones, which are mapped back when the items are copied to a filesystem that allows them.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    Plan,
+    recursive::{Op, OpKind},
+};
+use crate::{fl, tab};
+
+/// Longest path accepted by the kernel, in bytes
+const MAX_PATH_LEN: usize = 4096;
+
+/// Characters that Windows filesystems do not allow in names, besides control characters
+const WINDOWS_INVALID: &[char] = &['"', '*', ':', '<', '>', '?', '\\', '|'];
+
+/// Replacements of invalid characters, which look alike but are allowed
+const LOOKALIKES: &[(char, char)] = &[
+    ('"', '\u{FF02}'),
+    ('*', '\u{FF0A}'),
+    (':', '\u{FF1A}'),
+    ('<', '\u{FF1C}'),
+    ('>', '\u{FF1E}'),
+    ('?', '\u{FF1F}'),
+    ('\\', '\u{FF3C}'),
+    ('|', '\u{FF5C}'),
+];
+
+/// Control characters are replaced by their symbol in the Control Pictures block
+const CONTROL_PICTURES: u32 = 0x2400;
+
+/// What a filesystem can store
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FsLimits {
+    /// Filesystem type as listed in /proc/mounts
+    pub fs_type: String,
+    pub max_file_size: Option<u64>,
+    /// Characters not allowed in names, besides '/' and NUL
+    pub invalid_chars: &'static [char],
+    pub invalid_control_chars: bool,
+    /// Names differing only in case refer to the same item
+    pub case_insensitive: bool,
+    /// Longest name, in UTF-16 units if `utf16_names` is set, otherwise in bytes
+    pub max_name_len: usize,
+    pub utf16_names: bool,
+}
+
+impl Default for FsLimits {
+    fn default() -> Self {
+        Self {
+            fs_type: String::new(),
+            max_file_size: None,
+            invalid_chars: &[],
+            invalid_control_chars: false,
+            case_insensitive: false,
+            max_name_len: 255,
+            utf16_names: false,
+        }
+    }
+}
+
+impl FsLimits {
+    pub fn for_type(fs_type: &str) -> Self {
+        let windows = Self {
+            fs_type: fs_type.to_string(),
+            invalid_chars: WINDOWS_INVALID,
+            invalid_control_chars: true,
+            case_insensitive: true,
+            utf16_names: true,
+            ..Default::default()
+        };
+        match fs_type {
+            "vfat" | "msdos" | "fat" => Self {
+                max_file_size: Some(u64::from(u32::MAX)),
+                ..windows
+            },
+            // NTFS mounted with ntfs-3g shows up as fuseblk
+            "exfat" | "ntfs" | "ntfs3" | "fuseblk" => windows,
+            _ => Self {
+                fs_type: fs_type.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Limits of the filesystem containing `path`, which does not have to exist yet
+    pub fn detect(path: &Path) -> Self {
+        let existing = path
+            .ancestors()
+            .find(|ancestor| ancestor.exists())
+            .and_then(|ancestor| fs::canonicalize(ancestor).ok());
+        let mounts = fs::read_to_string("/proc/mounts");
+        match (existing, mounts) {
+            (Some(existing), Ok(mounts)) => match fs_type_from_mounts(&mounts, &existing) {
+                Some(fs_type) => Self::for_type(&fs_type),
+                None => Self::default(),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    fn name_len(&self, name: &str) -> usize {
+        if self.utf16_names {
+            name.encode_utf16().count()
+        } else {
+            name.len()
+        }
+    }
+
+    fn is_invalid(&self, c: char) -> bool {
+        self.invalid_chars.contains(&c) || (self.invalid_control_chars && is_control(c))
+    }
+}
+
+fn is_control(c: char) -> bool {
+    ('\u{1}'..='\u{1F}').contains(&c)
+}
+
+/// Type of the filesystem mounted closest to `path` in the contents of /proc/mounts
+fn fs_type_from_mounts(mounts: &str, path: &Path) -> Option<String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_ascii_whitespace();
+            let _device = fields.next()?;
+            let mount_point = PathBuf::from(unescape_mount(fields.next()?));
+            let fs_type = fields.next()?;
+            path.starts_with(&mount_point)
+                .then(|| (mount_point, fs_type.to_string()))
+        })
+        // Later mounts on the same point hide earlier ones
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, fs_type)| fs_type)
+}
+
+/// Undo the octal escapes of spaces and other separators in /proc/mounts
+fn unescape_mount(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).filter(|digits| {
+            bytes[i] == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit))
+        });
+        if let Some(digits) = octal {
+            let value = digits
+                .iter()
+                .fold(0u32, |value, digit| value * 8 + u32::from(digit - b'0'));
+            unescaped.push(value as u8);
+            i += 4;
+            continue;
+        }
+        unescaped.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Replace the characters of `name` that `limits` does not allow with look-alikes
+pub fn sanitize_name<'a>(name: &'a str, limits: &FsLimits) -> Cow<'a, str> {
+    if !name.chars().any(|c| limits.is_invalid(c)) {
+        return Cow::Borrowed(name);
+    }
+    name.chars()
+        .map(|c| {
+            if !limits.is_invalid(c) {
+                c
+            } else if is_control(c) {
+                char::from_u32(CONTROL_PICTURES + c as u32).unwrap_or(c)
+            } else {
+                LOOKALIKES
+                    .iter()
+                    .find(|(invalid, _)| *invalid == c)
+                    .map_or('_', |(_, lookalike)| *lookalike)
+            }
+        })
+        .collect::<String>()
+        .into()
+}
+
+/// Reverse `sanitize_name`, restoring the original characters
+pub fn restore_name(name: &str) -> Cow<'_, str> {
+    let restored = |c: char| {
+        if let Some((invalid, _)) = LOOKALIKES.iter().find(|(_, lookalike)| *lookalike == c) {
+            Some(*invalid)
+        } else {
+            match c as u32 {
+                // Only the pictures of the characters replaced by `sanitize_name`
+                picture @ 0x2401..=0x241F => char::from_u32(picture - CONTROL_PICTURES),
+                _ => None,
+            }
+        }
+    };
+    if !name.chars().any(|c| restored(c).is_some()) {
+        return Cow::Borrowed(name);
+    }
+    name.chars()
+        .map(|c| restored(c).unwrap_or(c))
+        .collect::<String>()
+        .into()
+}
+
+/// Name of an item copied to a filesystem with `limits` when names are sanitized. Sanitized
+/// names are restored on filesystems that allow every character.
+pub fn destination_name(name: &OsStr, limits: &FsLimits) -> OsString {
+    let Some(name) = name.to_str() else {
+        return name.to_os_string();
+    };
+    let name = if limits.invalid_chars.is_empty() && !limits.invalid_control_chars {
+        restore_name(name)
+    } else {
+        sanitize_name(name, limits)
+    };
+    OsString::from(name.into_owned())
+}
+
+/// Something the destination filesystem cannot store
+#[derive(Clone, Debug)]
+pub enum Violation {
+    NoSpace {
+        needed: u64,
+        available: u64,
+    },
+    TooLarge {
+        path: PathBuf,
+        size: u64,
+        max: u64,
+    },
+    /// The name contains characters the filesystem does not allow
+    InvalidName(PathBuf),
+    /// The names differ only in case, which the filesystem does not distinguish
+    NameCollision {
+        path: PathBuf,
+        other: PathBuf,
+    },
+    NameTooLong(PathBuf),
+    PathTooLong(PathBuf),
+}
+
+impl Violation {
+    /// Whether sanitizing names fixes the violation
+    pub fn sanitizable(&self) -> bool {
+        matches!(self, Self::InvalidName(_))
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lossy = |path: &Path| path.to_string_lossy().into_owned();
+        f.write_str(&match self {
+            Self::NoSpace { needed, available } => fl!(
+                "violation-no-space",
+                needed = tab::format_size(*needed),
+                available = tab::format_size(*available)
+            ),
+            Self::TooLarge { path, size, max } => fl!(
+                "violation-too-large",
+                path = lossy(path),
+                size = tab::format_size(*size),
+                max = tab::format_size(*max)
+            ),
+            Self::InvalidName(path) => fl!("violation-invalid-name", path = lossy(path)),
+            Self::NameCollision { path, other } => fl!(
+                "violation-name-collision",
+                path = lossy(path),
+                other = lossy(other)
+            ),
+            Self::NameTooLong(path) => fl!("violation-name-too-long", path = lossy(path)),
+            Self::PathTooLong(path) => fl!("violation-path-too-long", path = lossy(path)),
+        })
+    }
+}
+
+/// Check the items created by `plan` against the limits of the destination directory `to`
+pub fn check(plan: &Plan, to: &Path) -> Vec<Violation> {
+    let limits = &plan.limits;
+    let mut violations = Vec::new();
+
+    let needed = plan.bytes_needed(to);
+    if let Some(available) = available_space(to) {
+        if needed > available {
+            violations.push(Violation::NoSpace { needed, available });
+        }
+    }
+
+    // Names already in or planned for each directory, folded to lower case. Existing names are
+    // owned, planned names borrowed from the ops.
+    let mut folded_names = HashMap::<(&Path, String), Cow<Path>>::new();
+    let mut listed_dirs = HashSet::<&Path>::new();
+    for op in &plan.ops {
+        let (Some(parent), Some(name)) = (op.to.parent(), op.to.file_name()) else {
+            continue;
+        };
+        if limits.case_insensitive && listed_dirs.insert(parent) {
+            for entry in fs::read_dir(parent).into_iter().flatten().flatten() {
+                let folded = entry.file_name().to_string_lossy().to_lowercase();
+                folded_names
+                    .entry((parent, folded))
+                    .or_insert(Cow::Owned(entry.path()));
+            }
+        }
+        let name = name.to_string_lossy();
+        if name.chars().any(|c| limits.is_invalid(c)) {
+            violations.push(Violation::InvalidName(op.to.clone()));
+        }
+        if limits.name_len(&name) > limits.max_name_len {
+            violations.push(Violation::NameTooLong(op.to.clone()));
+        } else if op.to.as_os_str().len() >= MAX_PATH_LEN {
+            violations.push(Violation::PathTooLong(op.to.clone()));
+        }
+        if let (Some(max), Some(size)) = (limits.max_file_size, file_size(op)) {
+            if size > max {
+                violations.push(Violation::TooLarge {
+                    path: op.from.clone(),
+                    size,
+                    max,
+                });
+            }
+        }
+        if limits.case_insensitive {
+            let key = (parent, name.to_lowercase());
+            if let Some(other) = folded_names.insert(key, Cow::Borrowed(op.to.as_path())) {
+                // An existing item found under the planned name is a conflict, which the
+                // conflict policy already resolved
+                let resolved = matches!(other, Cow::Owned(_)) && op.to.symlink_metadata().is_ok();
+                if other != op.to && !resolved {
+                    violations.push(Violation::NameCollision {
+                        path: op.to.clone(),
+                        other: other.to_path_buf(),
+                    });
+                }
+            }
+        }
+    }
+    violations
+}
+
+fn file_size(op: &Op) -> Option<u64> {
+    match op.kind {
+        OpKind::Copy | OpKind::Move { .. } => fs::symlink_metadata(&op.from)
+            .ok()
+            .map(|metadata| metadata.len()),
+        _ => None,
+    }
+}
+
+/// Free space of the filesystem containing `path`, which does not have to exist yet
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    let existing = path.ancestors().find(|ancestor| ancestor.exists())?;
+    let path_c = CString::new(existing.as_os_str().as_bytes()).ok()?;
+    let mut statvfs = unsafe { std::mem::zeroed::<libc::statvfs>() };
+    if unsafe { libc::statvfs(path_c.as_ptr(), &mut statvfs) } != 0 {
+        log::warn!(
+            "failed to statvfs {:?}: {}",
+            existing,
+            std::io::Error::last_os_error()
+        );
+        return None;
+    }
+    // The field types differ between targets
+    #[allow(clippy::unnecessary_cast)]
+    let available = statvfs.f_bavail as u64 * statvfs.f_frsize as u64;
+    Some(available)
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        ffi::OsStr,
+        fs, io,
+        path::{Path, PathBuf},
+        rc::Rc,
+    };
+
+    use super::{
+        FsLimits, Violation, check, destination_name, fs_type_from_mounts, restore_name,
+        sanitize_name,
+    };
+    use crate::core::operations::{
+        Plan,
+        recursive::{Op, OpKind, Skip},
+    };
+
+    #[test]
+    fn mounts_closest_wins() {
+        let mounts = "/dev/sda2 / ext4 rw,relatime 0 0\n\
+            /dev/sdb1 /run/media/user/MY\\040STICK vfat rw,nosuid 0 0\n\
+            tmpfs /tmp tmpfs rw 0 0\n";
+        assert_eq!(
+            fs_type_from_mounts(mounts, Path::new("/run/media/user/MY STICK/photos")).as_deref(),
+            Some("vfat")
+        );
+        assert_eq!(
+            fs_type_from_mounts(mounts, Path::new("/run/media/user/MY")).as_deref(),
+            Some("ext4")
+        );
+        assert_eq!(
+            fs_type_from_mounts(mounts, Path::new("/tmpfile")).as_deref(),
+            Some("ext4")
+        );
+    }
+
+    #[test]
+    fn sanitized_names_restore() {
+        let vfat = FsLimits::for_type("vfat");
+        let ext4 = FsLimits::for_type("ext4");
+        let name = "notes: what?\u{1}.txt";
+        let sanitized = sanitize_name(name, &vfat);
+        assert!(!sanitized.chars().any(|c| vfat.is_invalid(c)));
+        assert_eq!(restore_name(&sanitized), name);
+        assert_eq!(sanitize_name("plain.txt", &vfat), "plain.txt");
+        assert_eq!(sanitize_name(name, &ext4), name);
+
+        let on_stick = destination_name(OsStr::new(name), &vfat);
+        assert_eq!(destination_name(&on_stick, &ext4), OsStr::new(name));
+    }
+
+    #[test]
+    fn collisions_with_existing_names() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let to = dir.path();
+        fs::write(to.join("Notes.txt"), b"")?;
+        let op = |name: &str| Op {
+            kind: OpKind::Mkdir,
+            from: PathBuf::from("/src").join(name),
+            to: to.join(name),
+            skipped: Rc::new(Skip {
+                normal: Cell::new(false),
+                cleanup: Cell::new(false),
+            }),
+            is_cleanup: false,
+            resume_offset: 0,
+        };
+        let plan = |names: &[&str]| Plan {
+            ops: names.iter().map(|name| op(name)).collect(),
+            limits: FsLimits::for_type("vfat"),
+            ..Plan::default()
+        };
+
+        let violations = check(&plan(&["notes.txt", "other"]), to);
+        assert_eq!(violations.len(), 1);
+        assert!(matches!(
+            &violations[0],
+            Violation::NameCollision { path, other }
+                if *path == to.join("notes.txt") && *other == to.join("Notes.txt")
+        ));
+
+        // The same name is a conflict resolved by the conflict policy
+        assert!(check(&plan(&["Notes.txt"]), to).is_empty());
+
+        Ok(())
+    }
+}
@@ -1,6 +1,16 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 use tokio::sync::Notify;
 
+use super::FailedItem;
+use crate::{fl, tab};
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ControllerState {
     Cancelled,
@@ -9,10 +19,100 @@ pub enum ControllerState {
     Running,
 }
 
+/// Period over which the transfer rate is averaged
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Transfer rate and remaining time of an operation
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgressDetails {
+    /// Name of the item being processed
+    pub current_name: Option<String>,
+    /// Bytes per second averaged over the last few seconds, or `None` until enough is known
+    pub bytes_per_second: Option<f64>,
+    /// Estimated time remaining, or `None` if the rate is unknown or zero
+    pub eta: Option<Duration>,
+}
+
+impl ProgressDetails {
+    /// Current item, rate and remaining time, if anything is known about them
+    pub fn text(&self) -> Option<String> {
+        let name = self.current_name.as_deref()?;
+        Some(match (self.bytes_per_second, self.eta) {
+            (Some(bytes_per_second), Some(eta)) => fl!(
+                "progress-details-eta",
+                name = name,
+                rate = tab::format_size(bytes_per_second as u64),
+                eta = format_duration(eta)
+            ),
+            (Some(_), None) => fl!("progress-details-stalled", name = name),
+            (None, _) => name.to_string(),
+        })
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        fl!(
+            "duration-hours",
+            hours = secs / 3600,
+            minutes = (secs % 3600) / 60
+        )
+    } else if secs >= 60 {
+        fl!("duration-minutes", minutes = secs / 60, seconds = secs % 60)
+    } else {
+        fl!("duration-seconds", seconds = secs)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Transfer {
+    current_name: Option<String>,
+    total_bytes: u64,
+    /// Bytes transferred at points in time, oldest first
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl Transfer {
+    /// Forget samples outside of the window, except one that the average starts from
+    fn prune(&mut self, now: Instant) {
+        while self.samples.len() > 1 && now.duration_since(self.samples[1].0) > RATE_WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    fn details(&mut self, now: Instant) -> ProgressDetails {
+        self.prune(now);
+        let mut details = ProgressDetails {
+            current_name: self.current_name.clone(),
+            ..Default::default()
+        };
+        if let (Some(&(start, start_bytes)), Some(&(_, bytes))) =
+            (self.samples.front(), self.samples.back())
+        {
+            // Measured up to now instead of the last sample, so a stall shows as a falling rate
+            let elapsed = now.duration_since(start).as_secs_f64();
+            if elapsed >= 1.0 {
+                let bytes_per_second = bytes.saturating_sub(start_bytes) as f64 / elapsed;
+                details.bytes_per_second = Some(bytes_per_second);
+                if bytes_per_second > 0.0 {
+                    let remaining = self.total_bytes.saturating_sub(bytes) as f64;
+                    details.eta = Some(Duration::from_secs_f64(remaining / bytes_per_second));
+                }
+            }
+        }
+        details
+    }
+}
+
 #[derive(Debug)]
 struct ControllerInner {
     state: Mutex<ControllerState>,
     progress: Mutex<f32>,
+    transfer: Mutex<Transfer>,
+    warnings: Mutex<Vec<String>>,
+    continue_on_error: AtomicBool,
+    failures: Mutex<Vec<FailedItem>>,
     notify: Notify,
 }
 
@@ -29,6 +129,10 @@ impl Default for Controller {
             inner: Arc::new(ControllerInner {
                 state: Mutex::new(ControllerState::Running),
                 progress: Mutex::new(0.0),
+                transfer: Mutex::new(Transfer::default()),
+                warnings: Mutex::new(Vec::new()),
+                continue_on_error: AtomicBool::new(false),
+                failures: Mutex::new(Vec::new()),
                 notify: Notify::new(),
             }),
         }
@@ -57,6 +161,49 @@ impl Controller {
         *self.inner.progress.lock().unwrap() = progress;
     }
 
+    /// Record that `bytes` of `total_bytes` were transferred while processing `current_name`
+    pub fn set_transferred(&self, current_name: Option<String>, bytes: u64, total_bytes: u64) {
+        let mut transfer = self.inner.transfer.lock().unwrap();
+        transfer.current_name = current_name;
+        transfer.total_bytes = total_bytes;
+        let now = Instant::now();
+        transfer.samples.push_back((now, bytes));
+        transfer.prune(now);
+    }
+
+    pub fn details(&self) -> ProgressDetails {
+        self.inner.transfer.lock().unwrap().details(Instant::now())
+    }
+
+    /// Problems that did not stop the operation
+    pub fn warnings(&self) -> Vec<String> {
+        self.inner.warnings.lock().unwrap().clone()
+    }
+
+    pub fn warn(&self, warning: String) {
+        self.inner.warnings.lock().unwrap().push(warning);
+    }
+
+    /// Whether items that fail are reported at the end instead of failing the operation
+    pub fn continue_on_error(&self) -> bool {
+        self.inner.continue_on_error.load(Ordering::Relaxed)
+    }
+
+    pub fn set_continue_on_error(&self, continue_on_error: bool) {
+        self.inner
+            .continue_on_error
+            .store(continue_on_error, Ordering::Relaxed);
+    }
+
+    /// Items that failed while the operation continued
+    pub fn failures(&self) -> Vec<FailedItem> {
+        self.inner.failures.lock().unwrap().clone()
+    }
+
+    pub fn fail_item(&self, failure: FailedItem) {
+        self.inner.failures.lock().unwrap().push(failure);
+    }
+
     pub fn state(&self) -> ControllerState {
         *self.inner.state.lock().unwrap()
     }
@@ -110,3 +257,37 @@ impl Drop for Controller {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::Transfer;
+
+    #[test]
+    fn rate_falls_when_stalled() {
+        let start = Instant::now();
+        let mut transfer = Transfer {
+            total_bytes: 3000,
+            ..Default::default()
+        };
+        for (secs, bytes) in [(0, 0), (1, 500), (2, 1000)] {
+            let time = start + Duration::from_secs(secs);
+            transfer.samples.push_back((time, bytes));
+        }
+
+        let details = transfer.details(start + Duration::from_secs(2));
+        assert_eq!(details.bytes_per_second, Some(500.0));
+        assert_eq!(details.eta, Some(Duration::from_secs(4)));
+
+        // No progress for a while halves the rate and doubles the estimate
+        let details = transfer.details(start + Duration::from_secs(4));
+        assert_eq!(details.bytes_per_second, Some(250.0));
+        assert_eq!(details.eta, Some(Duration::from_secs(8)));
+
+        // Once the window holds no progress at all, nothing is being transferred
+        let details = transfer.details(start + Duration::from_secs(30));
+        assert_eq!(details.bytes_per_second, Some(0.0));
+        assert_eq!(details.eta, None);
+    }
+}
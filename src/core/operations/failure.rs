@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use super::OperationErrorType;
+
+/// An item that failed while the rest of the operation continued
+#[derive(Clone, Debug)]
+pub struct FailedItem {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub kind: OperationErrorType,
+}
+
+/// Sources of failed items with the targets they failed to be written to, so a retry does not
+/// resolve their conflicts again. Items inside of a failed directory are left out, as retrying
+/// the directory includes them.
+pub fn retry_items(failures: &[FailedItem]) -> Vec<(PathBuf, PathBuf)> {
+    let mut items = Vec::<(PathBuf, PathBuf)>::new();
+    for failure in failures {
+        let inside_failed = failures
+            .iter()
+            .any(|other| other.from != failure.from && failure.from.starts_with(&other.from));
+        if inside_failed || items.iter().any(|(from, _)| *from == failure.from) {
+            continue;
+        }
+        items.push((failure.from.clone(), failure.to.clone()));
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{FailedItem, retry_items};
+    use crate::core::operations::OperationErrorType;
+
+    fn failed(from: &str, to: &str) -> FailedItem {
+        FailedItem {
+            from: PathBuf::from(from),
+            to: PathBuf::from(to),
+            kind: OperationErrorType::PermissionDenied(PathBuf::from(from)),
+        }
+    }
+
+    #[test]
+    fn retry_keeps_targets() {
+        let failures = [
+            failed("/src/a", "/dst/a"),
+            failed("/src/dir", "/dst/dir"),
+            failed("/src/dir/b", "/dst/dir/b"),
+            failed("/src/other/c", "/dst/other/c"),
+            failed("/src/d", "/dst/d (2)"),
+        ];
+        let item = |from: &str, to: &str| (PathBuf::from(from), PathBuf::from(to));
+        assert_eq!(
+            retry_items(&failures),
+            vec![
+                item("/src/a", "/dst/a"),
+                item("/src/dir", "/dst/dir"),
+                item("/src/other/c", "/dst/other/c"),
+                // Renamed after a conflict, retried with the same name
+                item("/src/d", "/dst/d (2)"),
+            ]
+        );
+    }
+}
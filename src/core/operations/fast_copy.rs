@@ -0,0 +1,209 @@
+// Kernel assisted copies, used before falling back to copying through a userspace buffer
+//
+// A reflink shares the extents of the source, making the copy instant on btrfs, XFS and
+// other copy-on-write filesystems. Otherwise `copy_file_range` lets the kernel (or an NFS or
+// SMB server) copy the data. Only the data segments of sparse files are copied, so holes
+// stay holes. These calls block, so operations make them on the blocking thread pool through
+// `Files`.
+
+use std::{fs, io, path::Path, sync::Arc};
+
+/// Largest amount of data copied by one call, so progress and cancellation stay responsive
+pub const MAX_CHUNK: u64 = 8 * 1024 * 1024;
+
+/// Source and destination opened for kernel assisted copies
+#[derive(Clone)]
+pub struct Files {
+    from: Arc<fs::File>,
+    to: Arc<fs::File>,
+}
+
+impl Files {
+    /// Open `from` for reading and the existing `to` for writing
+    pub async fn open(from: &Path, to: &Path) -> io::Result<Self> {
+        let (from, to) = (from.to_path_buf(), to.to_path_buf());
+        blocking(move || {
+            Ok(Self {
+                from: Arc::new(fs::File::open(from)?),
+                to: Arc::new(fs::OpenOptions::new().write(true).open(to)?),
+            })
+        })
+        .await
+    }
+
+    pub async fn reflink(&self) -> io::Result<()> {
+        let files = self.clone();
+        blocking(move || reflink(&files.from, &files.to)).await
+    }
+
+    pub async fn copy_range(&self, offset: u64, len: u64) -> io::Result<u64> {
+        let files = self.clone();
+        blocking(move || copy_range(&files.from, &files.to, offset, len)).await
+    }
+
+    pub async fn data_segments(&self, start: u64, len: u64) -> Vec<(u64, u64)> {
+        let from = self.from.clone();
+        blocking(move || Ok(data_segments(&from, start, len)))
+            .await
+            .unwrap_or_else(|_| vec![(start, len)])
+    }
+
+    pub async fn set_len(&self, len: u64) -> io::Result<()> {
+        let to = self.to.clone();
+        blocking(move || to.set_len(len)).await
+    }
+}
+
+/// Run `f` on the blocking thread pool of the runtime
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> io::Result<T> + Send + 'static,
+) -> io::Result<T> {
+    compio::runtime::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+/// Share all extents of `from` with `to`, which must be empty
+#[cfg(target_os = "linux")]
+pub fn reflink(from: &fs::File, to: &fs::File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    // _IOW(0x94, 9, int), not exported by every version of libc
+    const FICLONE: libc::c_ulong = 0x4004_9409;
+    if unsafe { libc::ioctl(to.as_raw_fd(), FICLONE as _, from.as_raw_fd()) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn reflink(_from: &fs::File, _to: &fs::File) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Copy up to `len` bytes at `offset` in the kernel, returning the number of bytes copied
+#[cfg(target_os = "linux")]
+pub fn copy_range(from: &fs::File, to: &fs::File, offset: u64, len: u64) -> io::Result<u64> {
+    use std::os::fd::AsRawFd;
+    let mut off_in = offset as libc::loff_t;
+    let mut off_out = offset as libc::loff_t;
+    let count = unsafe {
+        libc::copy_file_range(
+            from.as_raw_fd(),
+            &mut off_in,
+            to.as_raw_fd(),
+            &mut off_out,
+            len.min(MAX_CHUNK) as usize,
+            0,
+        )
+    };
+    if count < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(count as u64)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn copy_range(_from: &fs::File, _to: &fs::File, _offset: u64, _len: u64) -> io::Result<u64> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// Errors meaning a tier is not available for these files, rather than a failed copy
+pub fn is_unsupported(err: &io::Error) -> bool {
+    if err.kind() == io::ErrorKind::Unsupported {
+        return true;
+    }
+    #[cfg(unix)]
+    {
+        matches!(
+            err.raw_os_error(),
+            Some(
+                libc::EXDEV
+                    | libc::ENOSYS
+                    | libc::EOPNOTSUPP
+                    | libc::EINVAL
+                    | libc::ENOTTY
+                    | libc::EBADF
+                    | libc::EPERM
+            )
+        )
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Ranges of `file` between `start` and `len` that contain data, skipping holes
+#[cfg(target_os = "linux")]
+pub fn data_segments(file: &fs::File, start: u64, len: u64) -> Vec<(u64, u64)> {
+    use std::os::fd::AsRawFd;
+    let fd = file.as_raw_fd();
+    let mut segments = Vec::new();
+    let mut pos = start;
+    while pos < len {
+        let data = unsafe { libc::lseek(fd, pos as libc::off_t, libc::SEEK_DATA) };
+        if data < 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::ENXIO) {
+                // No data after pos, the rest of the file is a hole
+                break;
+            }
+            // Holes are not supported by this filesystem
+            return vec![(start, len)];
+        }
+        let hole = unsafe { libc::lseek(fd, data, libc::SEEK_HOLE) };
+        let end = if hole < 0 {
+            len
+        } else {
+            (hole as u64).min(len)
+        };
+        if (data as u64) < end {
+            segments.push((data as u64, end));
+        }
+        pos = end;
+    }
+    segments
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn data_segments(_file: &fs::File, start: u64, len: u64) -> Vec<(u64, u64)> {
+    vec![(start, len)]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{self, Seek, SeekFrom, Write},
+    };
+
+    use tempfile::tempdir;
+
+    use super::data_segments;
+
+    #[test]
+    fn data_segments_cover_data() -> io::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("sparse");
+        let mut file = fs::File::create(&path)?;
+        file.set_len(4 * 1024 * 1024)?;
+        file.seek(SeekFrom::Start(2 * 1024 * 1024))?;
+        file.write_all(b"data")?;
+        file.sync_all()?;
+
+        let file = fs::File::open(&path)?;
+        let segments = data_segments(&file, 0, 4 * 1024 * 1024);
+        let data_start = 2 * 1024 * 1024;
+        assert!(
+            segments
+                .iter()
+                .any(|&(start, end)| start <= data_start && end >= data_start + 4),
+            "{segments:?} do not contain the written data"
+        );
+        assert!(segments.iter().all(|&(start, end)| start < end));
+
+        Ok(())
+    }
+}
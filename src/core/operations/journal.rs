@@ -0,0 +1,850 @@
+// Journal of copy and move operations, allowing them to be resumed after a crash
+//
+// Each running operation appends one record per line to its own file in the XDG state
+// directory. Fields are separated by tabs, with tabs, newlines and backslashes in paths
+// escaped. The file is locked while the operation runs, so that other instances do not
+// offer to resume an operation that is still in progress.
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    Checksum, ConflictPolicy, DirPolicy, FilePolicy, Operation, Preserve,
+    recursive::{Op, OpKind, Skip},
+};
+
+const HEADER: &[u8] = b"vortex-fm-journal 1";
+const EXTENSION: &str = "journal";
+
+fn journal_dir() -> Option<PathBuf> {
+    dirs::state_dir().map(|dir| dir.join("vortex-fm").join("journal"))
+}
+
+#[cfg(unix)]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    std::ffi::OsString::from_vec(bytes).into()
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    String::from_utf8_lossy(&bytes).into_owned().into()
+}
+
+fn escape(field: &[u8], line: &mut Vec<u8>) {
+    for &byte in field {
+        match byte {
+            b'\\' => line.extend_from_slice(b"\\\\"),
+            b'\t' => line.extend_from_slice(b"\\t"),
+            b'\n' => line.extend_from_slice(b"\\n"),
+            _ => line.push(byte),
+        }
+    }
+}
+
+fn unescape(field: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut iter = field.iter();
+    while let Some(&byte) = iter.next() {
+        if byte == b'\\' {
+            bytes.push(match iter.next()? {
+                b'\\' => b'\\',
+                b't' => b'\t',
+                b'n' => b'\n',
+                _ => return None,
+            });
+        } else {
+            bytes.push(byte);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(unix)]
+fn try_lock(file: &fs::File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    // The lock is released by the kernel when the process exits, even if it crashed
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &fs::File) -> io::Result<()> {
+    //TODO: lock journals on other platforms
+    Ok(())
+}
+
+fn kind_fields(kind: &OpKind) -> (&'static [u8], Option<&Path>) {
+    match kind {
+        OpKind::Copy => (b"copy", None),
+        OpKind::Move {
+            cross_device_copy: false,
+        } => (b"move", None),
+        OpKind::Move {
+            cross_device_copy: true,
+        } => (b"move-cross", None),
+        OpKind::Mkdir => (b"mkdir", None),
+        OpKind::Remove => (b"remove", None),
+        OpKind::Rmdir => (b"rmdir", None),
+        OpKind::Symlink { target } => (b"symlink", Some(target)),
+    }
+}
+
+fn parse_verify(name: &[u8]) -> Option<Option<Checksum>> {
+    match name {
+        b"-" => Some(None),
+        name => Checksum::from_name(std::str::from_utf8(name).ok()?).map(Some),
+    }
+}
+
+fn parse_preserve(flags: &[u8]) -> Option<Preserve> {
+    Preserve::from_flags(std::str::from_utf8(flags).ok()?)
+}
+
+fn conflict_fields(conflicts: &ConflictPolicy) -> [&[u8]; 5] {
+    [
+        b"conflicts",
+        conflicts.files.name().as_bytes(),
+        conflicts.dirs.name().as_bytes(),
+        if conflicts.sanitize_names { b"1" } else { b"0" },
+        conflicts.rename_pattern.as_bytes(),
+    ]
+}
+
+fn parse_file_policy(name: &[u8]) -> Option<FilePolicy> {
+    FilePolicy::from_name(std::str::from_utf8(name).ok()?)
+}
+
+fn parse_conflicts(fields: &[Vec<u8>]) -> Option<ConflictPolicy> {
+    Some(ConflictPolicy {
+        files: parse_file_policy(fields.get(1)?)?,
+        dirs: DirPolicy::from_name(std::str::from_utf8(fields.get(2)?).ok()?)?,
+        sanitize_names: fields.get(3)?.as_slice() == b"1",
+        rename_pattern: String::from_utf8(fields.get(4)?.clone()).ok()?,
+    })
+}
+
+fn parse_kind(kind: &[u8], target: Option<PathBuf>) -> Option<OpKind> {
+    Some(match kind {
+        b"copy" => OpKind::Copy,
+        b"move" => OpKind::Move {
+            cross_device_copy: false,
+        },
+        b"move-cross" => OpKind::Move {
+            cross_device_copy: true,
+        },
+        b"mkdir" => OpKind::Mkdir,
+        b"remove" => OpKind::Remove,
+        b"rmdir" => OpKind::Rmdir,
+        b"symlink" => OpKind::Symlink { target: target? },
+        _ => return None,
+    })
+}
+
+/// Journal of a running copy or move
+pub struct Journal {
+    path: PathBuf,
+    file: fs::File,
+}
+
+impl Journal {
+    /// Record the plan of a copy or move before any of its ops run
+    pub fn create(operation: &Operation, ops: &[Op]) -> io::Result<Self> {
+        let dir = journal_dir().ok_or_else(|| io::Error::other("no state directory"))?;
+        fs::create_dir_all(&dir)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos());
+        let path = dir.join(format!(
+            "{}-{}-{}.{}",
+            timestamp,
+            std::process::id(),
+            next_sequence(),
+            EXTENSION
+        ));
+        let file = fs::OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)?;
+        try_lock(&file)?;
+        let mut journal = Self { path, file };
+
+        let mut plan = Vec::new();
+        plan.extend_from_slice(HEADER);
+        plan.push(b'\n');
+        let verify_name = |verify: &Option<Checksum>| -> &'static [u8] {
+            verify.map_or(&b"-"[..], |checksum| checksum.name().as_bytes())
+        };
+        let (paths, to) = match operation {
+            Operation::Copy {
+                paths,
+                to,
+                verify,
+                preserve,
+                conflicts,
+            } => {
+                push_line(
+                    &mut plan,
+                    &[
+                        b"copy",
+                        verify_name(verify),
+                        preserve.to_flags().as_bytes(),
+                        &path_to_bytes(to),
+                    ],
+                );
+                push_line(&mut plan, &conflict_fields(conflicts));
+                (paths, to)
+            }
+            Operation::Move {
+                paths,
+                to,
+                cross_device_copy,
+                verify,
+                preserve,
+                conflicts,
+            } => {
+                let cross: &[u8] = if *cross_device_copy { b"1" } else { b"0" };
+                push_line(
+                    &mut plan,
+                    &[
+                        b"move",
+                        cross,
+                        verify_name(verify),
+                        preserve.to_flags().as_bytes(),
+                        &path_to_bytes(to),
+                    ],
+                );
+                push_line(&mut plan, &conflict_fields(conflicts));
+                (paths, to)
+            }
+            _ => return Err(io::Error::other("only copies and moves can be journaled")),
+        };
+        log::debug!(
+            "journaling {} items to {:?} in {:?}",
+            paths.len(),
+            to,
+            journal.path
+        );
+        for path in paths.iter() {
+            push_line(&mut plan, &[b"path", &path_to_bytes(path)]);
+        }
+
+        // Cleanup ops share their skip state with the op they clean up after
+        let mut normal_indexes = HashMap::new();
+        for (index, op) in ops.iter().enumerate() {
+            let skip_ptr = Rc::as_ptr(&op.skipped) as usize;
+            let cleanup_of = if op.is_cleanup {
+                normal_indexes
+                    .get(&skip_ptr)
+                    .map(|index: &usize| index.to_string())
+            } else {
+                normal_indexes.insert(skip_ptr, index);
+                None
+            };
+            let (kind, target) = kind_fields(&op.kind);
+            let mut fields: Vec<Vec<u8>> = vec![
+                b"op".to_vec(),
+                kind.to_vec(),
+                cleanup_of.map_or_else(|| b"-".to_vec(), String::into_bytes),
+                path_to_bytes(&op.from),
+                path_to_bytes(&op.to),
+            ];
+            if let Some(target) = target {
+                fields.push(path_to_bytes(target));
+            }
+            push_line(
+                &mut plan,
+                &fields.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+            );
+        }
+        // Marks the plan as complete, a crash while writing it leaves nothing to resume
+        push_line(&mut plan, &[b"planned"]);
+        journal.file.write_all(&plan)?;
+        journal.file.sync_data()?;
+        Ok(journal)
+    }
+
+    /// Reopen the journal of an interrupted operation to resume it
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = fs::OpenOptions::new().append(true).open(path)?;
+        try_lock(&file)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    fn record(&mut self, fields: &[&[u8]]) {
+        let mut line = Vec::new();
+        push_line(&mut line, fields);
+        // A single write per record keeps records intact if the process is killed
+        if let Err(err) = self.file.write_all(&line) {
+            log::warn!("failed to write journal {:?}: {}", self.path, err);
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The op is about to create `to`, which may be left partially written
+    pub fn started(&mut self, index: usize, to: &Path) {
+        self.record(&[b"start", index.to_string().as_bytes(), &path_to_bytes(to)]);
+    }
+
+    /// The first `bytes` of the op's destination are written and synced
+    pub fn bytes(&mut self, index: usize, bytes: u64) {
+        self.record(&[
+            b"bytes",
+            index.to_string().as_bytes(),
+            bytes.to_string().as_bytes(),
+        ]);
+        self.sync();
+    }
+
+    /// The remaining conflicts are resolved with `files`, as the user chose
+    pub fn rule(&mut self, files: FilePolicy) {
+        self.record(&[b"rule", files.name().as_bytes()]);
+        self.sync();
+    }
+
+    /// The op writes to `to` instead of its planned destination, to keep an existing file
+    pub fn renamed(&mut self, index: usize, to: &Path) {
+        self.record(&[b"rename", index.to_string().as_bytes(), &path_to_bytes(to)]);
+        self.sync();
+    }
+
+    /// The op is about to create its directory, which gets its attributes once filled
+    pub fn created(&mut self, index: usize) {
+        self.record(&[b"created", index.to_string().as_bytes()]);
+    }
+
+    pub fn skipped(&mut self, index: usize) {
+        self.record(&[b"skip", index.to_string().as_bytes()]);
+        self.sync();
+    }
+
+    pub fn cleanup_skipped(&mut self, index: usize) {
+        self.record(&[b"skip-cleanup", index.to_string().as_bytes()]);
+        self.sync();
+    }
+
+    pub fn done(&mut self, index: usize) {
+        self.record(&[b"done", index.to_string().as_bytes()]);
+    }
+
+    fn sync(&mut self) {
+        if let Err(err) = self.file.sync_data() {
+            log::warn!("failed to sync journal {:?}: {}", self.path, err);
+        }
+    }
+
+    /// The operation finished, failed or was cancelled and no longer needs to be resumed
+    pub fn remove(self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            log::warn!("failed to remove journal {:?}: {}", self.path, err);
+        }
+    }
+}
+
+fn next_sequence() -> u32 {
+    // Avoids collisions between operations started in the same nanosecond
+    use std::sync::atomic::{AtomicU32, Ordering};
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+fn push_line(line: &mut Vec<u8>, fields: &[&[u8]]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            line.push(b'\t');
+        }
+        escape(field, line);
+    }
+    line.push(b'\n');
+}
+
+/// The op that was running when the operation was interrupted
+#[derive(Debug)]
+pub struct InProgress {
+    pub index: usize,
+    pub to: PathBuf,
+    pub bytes: u64,
+}
+
+/// Everything recorded in a journal
+pub struct JournalState {
+    pub operation: Operation,
+    pub ops: Vec<Op>,
+    /// Index of the first op that did not complete
+    pub next: usize,
+    pub in_progress: Option<InProgress>,
+    /// Indexes of the ops that created their directory
+    pub created_dirs: Vec<usize>,
+}
+
+impl JournalState {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        Self::parse(&data).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid journal {:?}", path),
+            )
+        })
+    }
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        // A crash can leave the last record half written, which is ignored
+        let end = data.iter().rposition(|&byte| byte == b'\n')?;
+        let mut lines = data[..end].split(|&byte| byte == b'\n');
+        if lines.next()? != HEADER {
+            return None;
+        }
+
+        let mut operation_opt = None;
+        let mut conflicts = ConflictPolicy::default();
+        let mut planned = false;
+        let mut paths = Vec::new();
+        let mut ops: Vec<Op> = Vec::new();
+        let mut done = 0;
+        let mut started: HashMap<usize, (PathBuf, u64)> = HashMap::new();
+        let mut created_dirs = Vec::new();
+        for line in lines {
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = Vec::new();
+            for field in line.split(|&byte| byte == b'\t') {
+                fields.push(unescape(field)?);
+            }
+            let index =
+                || -> Option<usize> { std::str::from_utf8(fields.get(1)?).ok()?.parse().ok() };
+            match fields[0].as_slice() {
+                b"copy" => {
+                    operation_opt = Some((
+                        None,
+                        parse_verify(fields.get(1)?)?,
+                        parse_preserve(fields.get(2)?)?,
+                        path_from_bytes(fields.get(3)?.clone()),
+                    ));
+                }
+                b"move" => {
+                    let cross_device_copy = fields.get(1)?.as_slice() == b"1";
+                    operation_opt = Some((
+                        Some(cross_device_copy),
+                        parse_verify(fields.get(2)?)?,
+                        parse_preserve(fields.get(3)?)?,
+                        path_from_bytes(fields.get(4)?.clone()),
+                    ));
+                }
+                b"conflicts" => conflicts = parse_conflicts(&fields)?,
+                b"path" => paths.push(path_from_bytes(fields.get(1)?.clone())),
+                b"planned" => planned = true,
+                b"op" => {
+                    let target = fields.get(5).cloned().map(path_from_bytes);
+                    let kind = parse_kind(fields.get(1)?, target)?;
+                    let cleanup_of = match fields.get(2)?.as_slice() {
+                        b"-" => None,
+                        other => Some(std::str::from_utf8(other).ok()?.parse::<usize>().ok()?),
+                    };
+                    let skipped = match cleanup_of {
+                        Some(normal) => ops.get(normal)?.skipped.clone(),
+                        None => Rc::new(Skip {
+                            normal: Cell::new(false),
+                            cleanup: Cell::new(false),
+                        }),
+                    };
+                    ops.push(Op {
+                        kind,
+                        from: path_from_bytes(fields.get(3)?.clone()),
+                        to: path_from_bytes(fields.get(4)?.clone()),
+                        skipped,
+                        is_cleanup: cleanup_of.is_some(),
+                        resume_offset: 0,
+                    });
+                }
+                b"start" => {
+                    started.insert(index()?, (path_from_bytes(fields.get(2)?.clone()), 0));
+                }
+                b"bytes" => {
+                    let bytes = std::str::from_utf8(fields.get(2)?).ok()?.parse().ok()?;
+                    if let Some(entry) = started.get_mut(&index()?) {
+                        entry.1 = bytes;
+                    }
+                }
+                b"rule" => conflicts.files = parse_file_policy(fields.get(1)?)?,
+                b"rename" => ops.get_mut(index()?)?.to = path_from_bytes(fields.get(2)?.clone()),
+                b"created" => created_dirs.push(index()?),
+                b"skip" => ops.get(index()?)?.skipped.normal.set(true),
+                b"skip-cleanup" => ops.get(index()?)?.skipped.cleanup.set(true),
+                b"done" => {
+                    let index = index()?;
+                    // The destination of a completed op may differ from the plan
+                    if let Some((to, _)) = started.remove(&index) {
+                        ops.get_mut(index)?.to = to;
+                    }
+                    done = done.max(index + 1);
+                }
+                _ => return None,
+            }
+        }
+
+        if !planned {
+            return None;
+        }
+
+        let operation = match operation_opt? {
+            (None, verify, preserve, to) => Operation::Copy {
+                paths,
+                to,
+                verify,
+                preserve,
+                conflicts,
+            },
+            (Some(cross_device_copy), verify, preserve, to) => Operation::Move {
+                paths,
+                to,
+                cross_device_copy,
+                verify,
+                preserve,
+                conflicts,
+            },
+        };
+        let in_progress = started.remove(&done).map(|(to, bytes)| InProgress {
+            index: done,
+            to,
+            bytes,
+        });
+        Some(Self {
+            operation,
+            ops,
+            next: done,
+            in_progress,
+            created_dirs,
+        })
+    }
+
+    /// Undo the partial work of the interrupted op, so everything after `next` can run again
+    pub fn rollback(&mut self) -> io::Result<()> {
+        let Some(in_progress) = self.in_progress.take() else {
+            return Ok(());
+        };
+        let Some(op) = self.ops.get(in_progress.index) else {
+            return Ok(());
+        };
+        let Ok(to_metadata) = fs::symlink_metadata(&in_progress.to) else {
+            // The destination was never created
+            return Ok(());
+        };
+        match op.kind {
+            // The source of a move is only removed by its cleanup op, which never started
+            OpKind::Copy | OpKind::Move { .. } if to_metadata.is_file() => {
+                fs::remove_file(&in_progress.to)
+            }
+            OpKind::Symlink { .. } if to_metadata.is_symlink() => fs::remove_file(&in_progress.to),
+            _ => Ok(()),
+        }
+    }
+
+    /// Prepare the interrupted op to continue where it stopped, truncating its destination to
+    /// the last synced byte count
+    pub fn prepare_resume(&mut self) -> io::Result<()> {
+        let Some(in_progress) = self.in_progress.take() else {
+            // Removals are not journaled when they start, but may have completed
+            if let Some(op) = self.ops.get(self.next) {
+                if matches!(op.kind, OpKind::Remove | OpKind::Rmdir)
+                    && fs::symlink_metadata(&op.from).is_err()
+                {
+                    self.next += 1;
+                }
+            }
+            return Ok(());
+        };
+        let Some(op) = self.ops.get_mut(in_progress.index) else {
+            return Ok(());
+        };
+        let to_metadata = fs::symlink_metadata(&in_progress.to).ok();
+        match (&op.kind, to_metadata) {
+            (OpKind::Copy | OpKind::Move { .. }, Some(to_metadata)) => {
+                if is_same_file(&op.from, &to_metadata) {
+                    // The hard link was created, only recording it as done was interrupted
+                    self.next += 1;
+                    return Ok(());
+                }
+                if in_progress.bytes > 0 && to_metadata.is_file() {
+                    fs::OpenOptions::new()
+                        .write(true)
+                        .open(&in_progress.to)?
+                        .set_len(in_progress.bytes)?;
+                    // Moves only fall back to copying, the source is removed by a cleanup op
+                    op.kind = OpKind::Copy;
+                    op.to = in_progress.to;
+                    op.resume_offset = in_progress.bytes;
+                    return Ok(());
+                }
+                self.in_progress = Some(in_progress);
+                self.rollback()
+            }
+            _ => {
+                self.in_progress = Some(in_progress);
+                self.rollback()
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(path: &Path, metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    fs::symlink_metadata(path)
+        .is_ok_and(|other| other.dev() == metadata.dev() && other.ino() == metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_path: &Path, _metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// A copy or move that was interrupted before it could finish
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Interrupted {
+    pub journal: PathBuf,
+    pub operation: Operation,
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Find operations interrupted by a crash, skipping those still running in another instance
+pub fn interrupted() -> Vec<Interrupted> {
+    let Some(dir) = journal_dir() else {
+        return Vec::new();
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            if err.kind() != io::ErrorKind::NotFound {
+                log::warn!("failed to read journal directory {:?}: {}", dir, err);
+            }
+            return Vec::new();
+        }
+    };
+
+    let mut interrupted = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        // The lock is held by running operations and released when this handle is dropped
+        let Ok(journal) = Journal::open(&path) else {
+            continue;
+        };
+        match JournalState::load(&path) {
+            Ok(state) => interrupted.push(Interrupted {
+                journal: path,
+                operation: state.operation,
+                completed: state.next,
+                total: state.ops.len(),
+            }),
+            Err(err) => {
+                log::warn!("removing unreadable journal: {}", err);
+                journal.remove();
+            }
+        }
+    }
+    interrupted.sort_by(|a, b| a.journal.cmp(&b.journal));
+    interrupted
+}
+
+/// Roll back the partial work of an interrupted operation and forget about it
+pub fn discard(path: &Path) -> io::Result<()> {
+    let journal = Journal::open(path)?;
+    let mut state = JournalState::load(path)?;
+    state.rollback()?;
+    journal.remove();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, io, path::PathBuf, rc::Rc};
+
+    use super::{HEADER, JournalState, conflict_fields, escape, push_line, unescape};
+    use crate::core::operations::{
+        Checksum, ConflictPolicy, FilePolicy, Operation, Preserve,
+        recursive::{Op, OpKind, Skip},
+    };
+
+    #[test]
+    fn escape_round_trip() {
+        let field = b"a\tb\nc\\d";
+        let mut line = Vec::new();
+        escape(field, &mut line);
+        assert!(!line.contains(&b'\t') && !line.contains(&b'\n'));
+        assert_eq!(unescape(&line).as_deref(), Some(&field[..]));
+    }
+
+    #[test]
+    fn parse_interrupted_copy() -> io::Result<()> {
+        let mut data = HEADER.to_vec();
+        data.push(b'\n');
+        push_line(&mut data, &[b"copy", b"sha256", b"tm", b"/dst"]);
+        push_line(&mut data, &[b"path", b"/src/dir"]);
+        push_line(
+            &mut data,
+            &[b"op", b"mkdir", b"-", b"/src/dir", b"/dst/dir"],
+        );
+        push_line(
+            &mut data,
+            &[b"op", b"copy", b"-", b"/src/dir/a", b"/dst/dir/a"],
+        );
+        push_line(
+            &mut data,
+            &[b"op", b"copy", b"-", b"/src/dir/b", b"/dst/dir/b"],
+        );
+        push_line(&mut data, &[b"planned"]);
+        push_line(&mut data, &[b"created", b"0"]);
+        push_line(&mut data, &[b"done", b"0"]);
+        push_line(&mut data, &[b"start", b"1", b"/dst/dir/a"]);
+        push_line(&mut data, &[b"bytes", b"1", b"4096"]);
+
+        let state = JournalState::parse(&data).expect("journal should parse");
+        assert_eq!(
+            state.operation,
+            Operation::Copy {
+                paths: vec![PathBuf::from("/src/dir")],
+                to: PathBuf::from("/dst"),
+                verify: Some(Checksum::Sha256),
+                preserve: Preserve {
+                    timestamps: true,
+                    mode: true,
+                    ..Preserve::none()
+                },
+                conflicts: ConflictPolicy::default(),
+            }
+        );
+        assert_eq!(state.ops.len(), 3);
+        assert_eq!(state.next, 1);
+        // The directory gets its attributes when the operation is resumed
+        assert_eq!(state.created_dirs, vec![0]);
+        let in_progress = state.in_progress.expect("op 1 was in progress");
+        assert_eq!(in_progress.index, 1);
+        assert_eq!(in_progress.bytes, 4096);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cleanup_ops_share_skip_state() {
+        let skipped = Rc::new(Skip {
+            normal: Cell::new(false),
+            cleanup: Cell::new(false),
+        });
+        let op = Op {
+            kind: OpKind::Move {
+                cross_device_copy: false,
+            },
+            from: PathBuf::from("/src/a"),
+            to: PathBuf::from("/dst/a"),
+            skipped: skipped.clone(),
+            is_cleanup: false,
+            resume_offset: 0,
+        };
+
+        let mut data = HEADER.to_vec();
+        data.push(b'\n');
+        push_line(&mut data, &[b"move", b"0", b"-", b"-", b"/dst"]);
+        push_line(&mut data, &[b"path", b"/src/a"]);
+        push_line(&mut data, &[b"op", b"move", b"-", b"/src/a", b"/dst/a"]);
+        push_line(&mut data, &[b"op", b"remove", b"0", b"/src/a", b"/dst/a"]);
+        push_line(&mut data, &[b"planned"]);
+        push_line(&mut data, &[b"skip", b"0"]);
+        push_line(&mut data, &[b"done", b"0"]);
+
+        let state = JournalState::parse(&data).expect("journal should parse");
+        assert!(matches!(state.ops[0].kind, OpKind::Move { .. }));
+        assert_eq!(state.ops[0].from, op.from);
+        // The source must not be removed when its move was skipped
+        assert!(state.ops[1].is_cleanup);
+        assert!(state.ops[1].skipped.normal.get());
+    }
+
+    #[test]
+    fn torn_records() {
+        let mut data = HEADER.to_vec();
+        data.push(b'\n');
+        push_line(&mut data, &[b"copy", b"-", b"-", b"/dst"]);
+        push_line(&mut data, &[b"path", b"/src/a"]);
+        push_line(&mut data, &[b"op", b"copy", b"-", b"/src/a", b"/dst/a"]);
+        push_line(&mut data, &[b"op", b"copy", b"-", b"/src/b", b"/dst/b"]);
+
+        // Nothing ran before the plan was complete
+        assert!(JournalState::parse(&data).is_none());
+
+        push_line(&mut data, &[b"planned"]);
+        push_line(&mut data, &[b"done", b"0"]);
+        let complete = data.clone();
+        data.extend_from_slice(b"start\t1\t/dst/");
+
+        // The half written record of a crash is ignored
+        let state = JournalState::parse(&data).expect("journal should parse");
+        assert_eq!(state.ops.len(), 2);
+        assert_eq!(state.next, 1);
+        assert!(state.in_progress.is_none());
+
+        // A complete but corrupt record is not
+        let mut data = complete;
+        push_line(&mut data, &[b"bytes", b"1", b"many"]);
+        assert!(JournalState::parse(&data).is_none());
+    }
+
+    #[test]
+    fn conflict_decisions() {
+        let mut data = HEADER.to_vec();
+        data.push(b'\n');
+        push_line(&mut data, &[b"copy", b"-", b"-", b"/dst"]);
+        push_line(&mut data, &conflict_fields(&ConflictPolicy::default()));
+        push_line(&mut data, &[b"path", b"/src/a"]);
+        push_line(&mut data, &[b"op", b"copy", b"-", b"/src/a", b"/dst/a"]);
+        push_line(&mut data, &[b"op", b"copy", b"-", b"/src/b", b"/dst/b"]);
+        push_line(&mut data, &[b"planned"]);
+        push_line(&mut data, &[b"rename", b"0", b"/dst/a (2)"]);
+        push_line(&mut data, &[b"rule", b"keep-newer"]);
+
+        let state = JournalState::parse(&data).expect("journal should parse");
+        // Kept files are written to the chosen name, and rules apply to remaining conflicts
+        assert_eq!(state.ops[0].to, PathBuf::from("/dst/a (2)"));
+        let Operation::Copy { conflicts, .. } = state.operation else {
+            panic!("journal should be of a copy");
+        };
+        assert_eq!(
+            conflicts,
+            ConflictPolicy {
+                files: FilePolicy::KeepNewer,
+                ..ConflictPolicy::default()
+            }
+        );
+    }
+}
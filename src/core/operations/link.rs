@@ -0,0 +1,174 @@
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{Controller, OperationError, OperationErrorType, OperationSelection, copy_unique_path};
+use crate::fl;
+
+/// Kind of link created when pasting as a link
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LinkKind {
+    /// Symbolic link, pointing to the item with a path relative to the link if `relative` is set
+    Symbolic {
+        relative: bool,
+    },
+    Hard,
+}
+
+/// Create links to `paths` inside of the directory `to`
+pub fn link(
+    paths: Vec<PathBuf>,
+    to: PathBuf,
+    kind: LinkKind,
+    controller: &Controller,
+) -> Result<OperationSelection, OperationError> {
+    let mut op_sel = OperationSelection {
+        ignored: paths.clone(),
+        selected: Vec::with_capacity(paths.len()),
+        ..Default::default()
+    };
+    let total = paths.len();
+    for (i, path) in paths.into_iter().enumerate() {
+        futures::executor::block_on(controller.check())
+            .map_err(|state| OperationError::from_state(state, controller))?;
+        controller.set_progress(i as f32 / total as f32);
+
+        let link_path = copy_unique_path(&path, &to);
+        let result = match kind {
+            LinkKind::Symbolic { relative } => {
+                let target = if relative {
+                    relative_target(&path, &link_path)
+                } else {
+                    Ok(path.clone())
+                };
+                target.and_then(|target| symlink(&target, &link_path))
+            }
+            LinkKind::Hard => hard_link(&path, &link_path, controller)?,
+        };
+        result.map_err(|err| {
+            OperationError::from_kind(OperationErrorType::from_io(&link_path, &err), controller)
+        })?;
+        op_sel.selected.push(link_path);
+    }
+    Ok(op_sel)
+}
+
+fn hard_link(
+    path: &Path,
+    link_path: &Path,
+    controller: &Controller,
+) -> Result<io::Result<()>, OperationError> {
+    if path.is_dir() {
+        return Err(OperationError::from_err(
+            fl!("hard-link-directory", name = path.to_string_lossy()),
+            controller,
+        ));
+    }
+    match fs::hard_link(path, link_path) {
+        #[cfg(unix)]
+        Err(err) if err.raw_os_error() == Some(libc::EXDEV) => Err(OperationError::from_err(
+            fl!("hard-link-cross-device", name = path.to_string_lossy()),
+            controller,
+        )),
+        result => Ok(result),
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link_path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link_path)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link_path: &Path) -> io::Result<()> {
+    // Relative targets are resolved from the directory of the link
+    let resolved = link_path
+        .parent()
+        .map_or(target.to_path_buf(), |parent| parent.join(target));
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link_path)
+    } else {
+        std::os::windows::fs::symlink_file(target, link_path)
+    }
+}
+
+/// Path of `target` relative to the directory that will contain `link_path`. Both parent
+/// directories are resolved first, so symbolic links along either path do not break the result.
+pub fn relative_target(target: &Path, link_path: &Path) -> io::Result<PathBuf> {
+    let resolve_parent = |path: &Path| -> io::Result<PathBuf> {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => fs::canonicalize(parent),
+            _ => std::env::current_dir(),
+        }
+    };
+    let target_name = target
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "target has no name"))?;
+    let target = resolve_parent(target)?.join(target_name);
+    let link_dir = resolve_parent(link_path)?;
+    Ok(relative_path(&target, &link_dir))
+}
+
+/// Lexical path from the directory `from` to `to`, both of which must be absolute
+fn relative_path(to: &Path, from: &Path) -> PathBuf {
+    let to_components: Vec<Component> = to.components().collect();
+    let from_components: Vec<Component> = from.components().collect();
+    let common = to_components
+        .iter()
+        .zip(from_components.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in common..from_components.len() {
+        relative.push("..");
+    }
+    for component in &to_components[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, path::Path};
+
+    use tempfile::tempdir;
+
+    use super::{relative_path, relative_target};
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative_path(Path::new("/a/b/file"), Path::new("/a/c/d")),
+            Path::new("../../b/file")
+        );
+        assert_eq!(
+            relative_path(Path::new("/a/file"), Path::new("/a")),
+            Path::new("file")
+        );
+        assert_eq!(
+            relative_path(Path::new("/file"), Path::new("/a/b")),
+            Path::new("../../file")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn relative_link_resolves() -> io::Result<()> {
+        let dir = tempdir()?;
+        let target = dir.path().join("src/nested/file");
+        fs::create_dir_all(target.parent().unwrap())?;
+        fs::write(&target, b"target")?;
+        let link_dir = dir.path().join("dst/other");
+        fs::create_dir_all(&link_dir)?;
+        let link_path = link_dir.join("file");
+
+        let relative = relative_target(&target, &link_path)?;
+        assert_eq!(relative, Path::new("../../src/nested/file"));
+        std::os::unix::fs::symlink(&relative, &link_path)?;
+        assert_eq!(fs::read(&link_path)?, b"target");
+
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     app::Message,
-    views::dialogs::dialog_pages::{ArchiveType, DialogPage},
+    views::dialogs::dialog_pages::{ArchiveType, DialogPage, ZipEncryption},
     core::config::IconSizes,
     fl,
     utils::spawn_detached::spawn_detached,
@@ -19,15 +19,56 @@ use tokio::sync::{Mutex as TokioMutex, mpsc};
 use walkdir::WalkDir;
 use zip::AesMode::Aes256;
 
-pub use self::controller::{Controller, ControllerState};
+pub use self::checksum::Checksum;
+pub mod checksum;
+
+pub use self::conflict::{ConflictPolicy, DirPolicy, FilePolicy};
+pub mod conflict;
+
+pub use self::constraints::{FsLimits, Violation};
+pub mod constraints;
+
+pub use self::controller::{Controller, ControllerState, ProgressDetails};
 pub mod controller;
 
+pub use self::failure::FailedItem;
+pub mod failure;
+
+mod fast_copy;
+
 pub use self::reader::OpReader;
 pub mod reader;
 
+pub use self::journal::Interrupted;
+pub mod journal;
+
+pub use self::link::LinkKind;
+pub mod link;
+
+pub use self::plan::{Plan, PlanSummary};
+pub mod plan;
+
+pub use self::permissions::{Accounts, ModeChange, ModeMask};
+pub mod permissions;
+
+pub use self::preserve::Preserve;
+pub mod preserve;
+
 use self::recursive::{Context, Method};
 pub mod recursive;
 
+pub use self::safety::{ExtractLimits, UnsafeEntry};
+pub mod safety;
+
+pub use self::scheduler::Scheduler;
+pub mod scheduler;
+
+pub use self::shred::ShredPass;
+pub mod shred;
+
+pub use self::undo::{Inverse, PendingUndo, UndoEntry, UndoSnapshot, UndoStack};
+pub mod undo;
+
 async fn handle_replace(
     msg_tx: Arc<TokioMutex<Sender<Message>>>,
     file_from: PathBuf,
@@ -41,7 +82,16 @@ async fn handle_replace(
             return ReplaceResult::Cancel;
         }
     };
+    ask_replace(msg_tx, item_from, file_to, multiple).await
+}
 
+/// Show the replace dialog for writing `item_from` to the existing `file_to`
+async fn ask_replace(
+    msg_tx: Arc<TokioMutex<Sender<Message>>>,
+    item_from: tab::Item,
+    file_to: PathBuf,
+    multiple: bool,
+) -> ReplaceResult {
     let item_to = match tab::item_from_path(file_to, IconSizes::default()) {
         Ok(ok) => ok,
         Err(err) => {
@@ -80,13 +130,84 @@ pub enum ReplaceResult {
     Replace(bool),
     KeepBoth,
     Skip(bool),
+    /// Resolve this and all remaining conflicts with a rule
+    Policy(FilePolicy),
     Cancel,
 }
 
+/// Options of a copy or move that are not part of the journaled ops
+fn copy_or_move_options(operation: &Operation) -> (Option<Checksum>, Preserve, ConflictPolicy) {
+    match operation {
+        Operation::Copy {
+            verify,
+            preserve,
+            conflicts,
+            ..
+        }
+        | Operation::Move {
+            verify,
+            preserve,
+            conflicts,
+            ..
+        } => (*verify, *preserve, conflicts.clone()),
+        _ => (None, Preserve::default(), ConflictPolicy::default()),
+    }
+}
+
+/// Copy or move operation as the paths and method used by the recursive context
+fn copy_or_move_method(
+    operation: &Operation,
+    controller: &Controller,
+) -> Result<(Vec<PathBuf>, PathBuf, Method), OperationError> {
+    match operation {
+        Operation::Copy { paths, to, .. } => Ok((paths.clone(), to.clone(), Method::Copy)),
+        Operation::Move {
+            paths,
+            to,
+            cross_device_copy,
+            ..
+        } => Ok((
+            paths.clone(),
+            to.clone(),
+            Method::Move {
+                cross_device_copy: *cross_device_copy,
+            },
+        )),
+        _ => Err(OperationError::from_err(
+            format!("{operation:?} is not a copy or move"),
+            controller,
+        )),
+    }
+}
+
+/// Destination of each top level item
+fn from_to_pairs(paths: Vec<PathBuf>, to: &Path, method: Method) -> Vec<(PathBuf, PathBuf)> {
+    // Handle duplicate file names by renaming paths
+    paths
+        .into_iter()
+        .filter_map(|from| {
+            if matches!(from.parent(), Some(parent) if parent == to)
+                && matches!(method, Method::Copy)
+            {
+                // `from`'s parent is equal to `to` which means we're copying to the same
+                // directory (duplicating files)
+                let to = copy_unique_path(&from, to);
+                Some((from, to))
+            } else if let Some(name) = from.file_name() {
+                let to = to.join(name);
+                Some((from, to))
+            } else {
+                //TODO: how to handle from missing file name?
+                None
+            }
+        })
+        .collect()
+}
+
+/// Copy or move the items of `operation`, or only `retry_items` to their given targets
 async fn copy_or_move(
-    paths: Vec<PathBuf>,
-    to: PathBuf,
-    method: Method,
+    operation: Operation,
+    retry_items: Option<Vec<(PathBuf, PathBuf)>>,
     msg_tx: &Arc<TokioMutex<Sender<Message>>>,
     controller: Controller,
 ) -> Result<OperationSelection, OperationError> {
@@ -95,6 +216,8 @@ async fn copy_or_move(
 
     compio::runtime::spawn(async move {
         let controller = controller_c;
+        let (paths, to, method) = copy_or_move_method(&operation, &controller)?;
+        let (verify, preserve, conflicts) = copy_or_move_options(&operation);
         log::info!(
             "{} {:?} to {:?}",
             match method {
@@ -105,30 +228,21 @@ async fn copy_or_move(
             to
         );
 
-        // Handle duplicate file names by renaming paths
-        let mut from_to_pairs: Vec<(PathBuf, PathBuf)> = paths
-            .into_iter()
-            .zip(std::iter::repeat(to.as_path()))
-            .filter_map(|(from, to)| {
-                if matches!(from.parent(), Some(parent) if parent == to)
-                    && matches!(method, Method::Copy)
-                {
-                    // `from`'s parent is equal to `to` which means we're copying to the same
-                    // directory (duplicating files)
-                    let to = copy_unique_path(&from, to);
-                    Some((from, to))
-                } else if let Some(name) = from.file_name() {
-                    let to = to.join(name);
-                    Some((from, to))
-                } else {
-                    //TODO: how to handle from missing file name?
-                    None
+        let mut from_to_pairs = retry_items.unwrap_or_else(|| from_to_pairs(paths, &to, method));
+        if conflicts.sanitize_names {
+            // Quick renames skip planning, so the top level names are sanitized here
+            let limits = FsLimits::detect(&to);
+            for (_, to) in from_to_pairs.iter_mut() {
+                if let Some(name) = to.file_name() {
+                    let name = constraints::destination_name(name, &limits);
+                    to.set_file_name(name);
                 }
-            })
-            .collect();
+            }
+        }
 
         // Attempt quick and simple renames
         //TODO: allow rename to be used for directories in recursive context?
+        let mut renamed = Vec::new();
         if matches!(method, Method::Move { .. }) {
             from_to_pairs.retain(|(from, to)| {
                 //TODO: show replace dialog here?
@@ -140,6 +254,7 @@ async fn copy_or_move(
                 match fs::rename(from, to) {
                     Ok(()) => {
                         log::info!("renamed {from:?} to {to:?}");
+                        renamed.push((from.clone(), to.clone()));
                         false
                     }
                     Err(err) => {
@@ -152,35 +267,12 @@ async fn copy_or_move(
             });
         }
 
-        let mut context = Context::new(controller.clone());
-
-        {
-            let controller = controller.clone();
-            context = context.on_progress(move |_op, progress| {
-                let item_progress = match progress.total_bytes {
-                    Some(total_bytes) => {
-                        if total_bytes == 0 {
-                            1.0
-                        } else {
-                            progress.current_bytes as f32 / total_bytes as f32
-                        }
-                    }
-                    None => 0.0,
-                };
-                let total_progress =
-                    (item_progress + progress.current_ops as f32) / progress.total_ops as f32;
-                controller.set_progress(total_progress);
-            });
-        }
-
-        {
-            let msg_tx = msg_tx.clone();
-            context = context.on_replace(move |op| {
-                let msg_tx = msg_tx.clone();
-                Box::pin(handle_replace(msg_tx, op.from.clone(), op.to.clone(), true))
-            });
-        }
-
+        let mut context = copy_or_move_context(msg_tx, controller)
+            .verify(verify)
+            .preserve(preserve)
+            .conflicts(conflicts)
+            .journal(operation);
+        context.op_sel.created = renamed;
         context
             .recursive_copy_or_move(from_to_pairs, method)
             .await?;
@@ -191,7 +283,78 @@ async fn copy_or_move(
     .map_err(wrap_compio_spawn_error)?
 }
 
-fn copy_unique_path(from: &Path, to: &Path) -> PathBuf {
+async fn resume_copy_or_move(
+    journal: PathBuf,
+    operation: Operation,
+    msg_tx: &Arc<TokioMutex<Sender<Message>>>,
+    controller: Controller,
+) -> Result<OperationSelection, OperationError> {
+    let msg_tx = msg_tx.clone();
+    let controller_c = controller.clone();
+
+    compio::runtime::spawn(async move {
+        let (verify, preserve, conflicts) = copy_or_move_options(&operation);
+        let mut context = copy_or_move_context(msg_tx, controller_c)
+            .verify(verify)
+            .preserve(preserve)
+            .conflicts(conflicts);
+        context.resume(&journal).await?;
+        Result::<OperationSelection, OperationError>::Ok(context.op_sel)
+    })
+    .await
+    .map_err(wrap_compio_spawn_error)?
+}
+
+fn copy_or_move_context(
+    msg_tx: Arc<TokioMutex<Sender<Message>>>,
+    controller: Controller,
+) -> Context {
+    let mut context = Context::new(controller.clone());
+
+    {
+        let controller = controller.clone();
+        context = context.on_progress(move |op, progress| {
+            let item_progress = match progress.total_bytes {
+                Some(total_bytes) => {
+                    if total_bytes == 0 {
+                        1.0
+                    } else {
+                        progress.current_bytes as f32 / total_bytes as f32
+                    }
+                }
+                None => 0.0,
+            };
+            let total_progress =
+                (item_progress + progress.current_ops as f32) / progress.total_ops as f32;
+            controller.set_progress(total_progress);
+
+            let current_name = op
+                .from
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned());
+            let current_bytes = progress.current_bytes.saturating_sub(op.resume_offset);
+            controller.set_transferred(
+                current_name,
+                progress.completed_bytes + current_bytes,
+                progress.operation_bytes,
+            );
+        });
+    }
+
+    {
+        let msg_tx = msg_tx.clone();
+        context = context.on_replace(move |op| {
+            let msg_tx = msg_tx.clone();
+            Box::pin(handle_replace(msg_tx, op.from.clone(), op.to.clone(), true))
+        });
+    }
+
+    context
+}
+
+/// Split the file name of `path` into a stem and extension, keeping compound extensions such as
+/// `.tar.gz` together. Directories have no extension.
+fn split_file_name(path: &Path) -> Option<(String, Option<String>)> {
     // List of compound extensions to check
     const COMPOUND_EXTENSIONS: &[&str] = &[
         ".tar.gz",
@@ -207,36 +370,42 @@ fn copy_unique_path(from: &Path, to: &Path) -> PathBuf {
         ".tar.pz",
     ];
 
-    let mut to = to.to_owned();
-    if let Some(file_name) = from.file_name().and_then(|name| name.to_str()) {
-        let (stem, ext) = if from.is_dir() {
-            (file_name.to_string(), None)
-        } else {
-            let file_name = file_name.to_string();
-            COMPOUND_EXTENSIONS
-                .iter()
-                .find(|&&ext| file_name.ends_with(ext))
-                .map(|&ext| {
-                    (
-                        file_name.strip_suffix(ext).unwrap().to_string(),
-                        Some(ext[1..].to_string()),
-                    )
-                })
-                .unwrap_or_else(|| {
-                    from.file_stem()
-                        .and_then(|s| s.to_str())
-                        .map(|stem| {
-                            (
-                                stem.to_string(),
-                                from.extension()
-                                    .and_then(|e| e.to_str())
-                                    .map(|e| e.to_string()),
-                            )
-                        })
-                        .unwrap_or((file_name, None))
-                })
-        };
+    let file_name = path.file_name().and_then(|name| name.to_str())?;
+    Some(if path.is_dir() {
+        (file_name.to_string(), None)
+    } else {
+        let file_name = file_name.to_string();
+        COMPOUND_EXTENSIONS
+            .iter()
+            .find(|&&ext| file_name.ends_with(ext))
+            .map(|&ext| {
+                (
+                    file_name.strip_suffix(ext).unwrap().to_string(),
+                    Some(ext[1..].to_string()),
+                )
+            })
+            .unwrap_or_else(|| {
+                path.file_stem()
+                    .and_then(|s| s.to_str())
+                    .map(|stem| {
+                        (
+                            stem.to_string(),
+                            path.extension()
+                                .and_then(|e| e.to_str())
+                                .map(|e| e.to_string()),
+                        )
+                    })
+                    .unwrap_or((file_name, None))
+            })
+    })
+}
 
+fn copy_unique_path(from: &Path, to: &Path) -> PathBuf {
+    let mut to = to.to_owned();
+    if let (Some(file_name), Some((stem, ext))) = (
+        from.file_name().and_then(|name| name.to_str()),
+        split_file_name(from),
+    ) {
         for n in 0.. {
             let new_name = if n == 0 {
                 file_name.to_string()
@@ -259,6 +428,233 @@ fn copy_unique_path(from: &Path, to: &Path) -> PathBuf {
     to
 }
 
+/// Extract an archive with only `root` at the top to the unused path `new_path`. It is unpacked
+/// by `extract` into a hidden folder first, so a single directory named like the archive is not
+/// nested inside of another one.
+fn extract_single_root(
+    root: &Path,
+    new_path: PathBuf,
+    dir_name: &str,
+    controller: &Controller,
+    extract: impl FnOnce(&Path) -> Result<(), OperationError>,
+) -> Result<PathBuf, OperationError> {
+    let to = new_path.parent().unwrap_or(Path::new("/"));
+    let staging = copy_unique_path(Path::new(&format!(".{dir_name}.extracting")), to);
+    let result = extract(&staging).and_then(|()| {
+        fs::rename(staging.join(root), &new_path)
+            .map(|()| new_path)
+            .map_err(|e| OperationError::from_err(e, controller))
+    });
+    if let Err(err) = fs::remove_dir_all(&staging) {
+        log::warn!("failed to remove {}: {}", staging.display(), err);
+    }
+    result
+}
+
+/// Where an archive is extracted to when it would create `path`, following the folder policy if
+/// `path` exists. Returns `None` if the archive is skipped, and `path` itself if the extracted
+/// items are merged into it.
+fn extract_path(conflicts: &ConflictPolicy, path: PathBuf) -> Option<PathBuf> {
+    let Ok(metadata) = fs::symlink_metadata(&path) else {
+        return Some(path);
+    };
+    match conflicts.dirs {
+        // Files are merged with the file policy
+        DirPolicy::Merge if metadata.is_dir() => Some(path),
+        DirPolicy::Skip => None,
+        _ => Some(conflicts.renamed_path(&path)),
+    }
+}
+
+/// Ask about an existing file at `to` with the replace dialog, for an entry of `archive`. The
+/// controller is cancelled if the dialog is, so extraction stops.
+fn ask_replace_entry(
+    msg_tx: &Arc<TokioMutex<Sender<Message>>>,
+    archive: &Path,
+    entry: &crate::utils::archive::Entry,
+    to: &Path,
+    controller: &Controller,
+) -> ReplaceResult {
+    let item_from = tab::item_from_archive_entry(archive, entry.clone(), 0, IconSizes::default());
+    let replace_result = futures::executor::block_on(ask_replace(
+        msg_tx.clone(),
+        item_from,
+        to.to_path_buf(),
+        true,
+    ));
+    if replace_result == ReplaceResult::Cancel {
+        controller.cancel();
+    }
+    replace_result
+}
+
+/// Extract the archives of an extract operation, or only the items paired with the target an
+/// archive that failed before was extracted to when retrying
+async fn extract_archives(
+    operation: Operation,
+    retry_items: Option<Vec<(PathBuf, PathBuf)>>,
+    msg_tx: &Arc<TokioMutex<Sender<Message>>>,
+    controller: Controller,
+) -> Result<OperationSelection, OperationError> {
+    let Operation::Extract {
+        paths,
+        to,
+        password,
+        conflicts,
+        smart,
+        limits,
+    } = operation
+    else {
+        return Err(OperationError::from_err(
+            "only extract operations extract archives",
+            &controller,
+        ));
+    };
+    let items: Vec<(PathBuf, Option<PathBuf>)> = match retry_items {
+        Some(items) => items
+            .into_iter()
+            .map(|(path, target)| (path, Some(target)))
+            .collect(),
+        None => paths.into_iter().map(|path| (path, None)).collect(),
+    };
+    let msg_tx = msg_tx.clone();
+    compio::runtime::spawn_blocking(move || -> Result<OperationSelection, OperationError> {
+        // Ask for a password before extracting anything
+        if password.is_none()
+            && items
+                .iter()
+                .any(|(path, _)| crate::utils::archive::needs_password(path).unwrap_or(false))
+        {
+            return Err(OperationError::from_kind(
+                OperationErrorType::PasswordRequired,
+                &controller,
+            ));
+        }
+        let total_paths = items.len();
+        let mut op_sel = OperationSelection::default();
+        for (i, (path, target)) in items.into_iter().enumerate() {
+            futures::executor::block_on(async {
+                controller
+                    .check()
+                    .await
+                    .map_err(|s| OperationError::from_state(s, &controller))
+            })?;
+
+            controller.set_progress((i as f32) / total_paths as f32);
+
+            let Some(file_name) = path.file_name().and_then(|f| f.to_str()) else {
+                continue;
+            };
+            let dir_name = get_directory_name(file_name);
+
+            // A single item at the top needs no folder around it
+            let single_root = if smart {
+                crate::utils::archive::list(&path)
+                    .ok()
+                    .and_then(|entries| crate::utils::archive::single_root(&entries))
+            } else {
+                None
+            };
+            let ask = |entry: &crate::utils::archive::Entry, to: &Path| {
+                ask_replace_entry(&msg_tx, &path, entry, to, &controller)
+            };
+            let extract = |new_dir: &Path| {
+                crate::utils::archive::extract(
+                    &path,
+                    new_dir,
+                    &password,
+                    &conflicts,
+                    Some(&ask),
+                    &limits,
+                    &controller,
+                )
+            };
+            op_sel.ignored.push(path.clone());
+            let mut existed = false;
+            let (new_path, result) = if let Some(root) = single_root {
+                let Some(new_path) = target.or_else(|| extract_path(&conflicts, to.join(&root)))
+                else {
+                    continue;
+                };
+                existed = new_path.is_dir();
+                let result = if existed {
+                    // The single item merges into the existing folder
+                    extract(&to)
+                } else {
+                    extract_single_root(&root, new_path.clone(), dir_name, &controller, extract)
+                        .map(|_| ())
+                };
+                (new_path, result)
+            } else {
+                let Some(new_dir) = target.or_else(|| extract_path(&conflicts, to.join(dir_name)))
+                else {
+                    continue;
+                };
+                existed = new_dir.exists();
+                let result = extract(&new_dir);
+                (new_dir, result)
+            };
+            match result {
+                Ok(()) => {
+                    op_sel.selected.push(new_path.clone());
+                    if existed {
+                        op_sel.merged.push(new_path);
+                    } else {
+                        op_sel.created.push((path, new_path));
+                    }
+                }
+                Err(err) => extract_failed(&path, new_path, err, &controller)?,
+            }
+        }
+
+        Ok(op_sel)
+    })
+    .await
+    .map_err(wrap_compio_spawn_error)?
+}
+
+/// Report an archive that failed to extract at the end if the operation continues on errors.
+/// Missing or wrong passwords and cancellation still stop the operation.
+fn extract_failed(
+    archive: &Path,
+    to: PathBuf,
+    err: OperationError,
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    if !controller.continue_on_error()
+        || controller.state() != ControllerState::Failed
+        || matches!(
+            err.kind,
+            OperationErrorType::PasswordRequired | OperationErrorType::WrongPassword
+        )
+    {
+        return Err(err);
+    }
+    log::warn!("failed to extract {archive:?} to {to:?}, continuing: {err}");
+    // The error failed the controller, the other archives are still extracted
+    controller.set_state(ControllerState::Running);
+    controller.fail_item(FailedItem {
+        from: archive.to_path_buf(),
+        to,
+        kind: err.kind,
+    });
+    Ok(())
+}
+
+/// Device of `path`, or of its closest existing ancestor if it is not created yet
+#[cfg(unix)]
+fn device_of(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    path.ancestors()
+        .find_map(|ancestor| fs::metadata(ancestor).ok())
+        .map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_path: &Path) -> Option<u64> {
+    None
+}
+
 fn file_name(path: &Path) -> Cow<'_, str> {
     path.file_name()
         .map_or_else(|| fl!("unknown-folder").into(), |x| x.to_string_lossy())
@@ -297,21 +693,41 @@ pub struct OperationSelection {
     pub ignored: Vec<PathBuf>,
     // Paths to select
     pub selected: Vec<PathBuf>,
+    // Top level items and the new paths they were copied, moved or extracted to
+    pub created: Vec<(PathBuf, PathBuf)>,
+    // Existing paths that top level items were merged into or replaced
+    pub merged: Vec<PathBuf>,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Operation {
+    /// Add items to the top of an existing zip or tar archive, replacing entries with their names
+    ArchiveAdd {
+        paths: Vec<PathBuf>,
+        archive: PathBuf,
+    },
+    /// Read archives without extracting them, reporting corrupt entries as failed items
+    ArchiveTest {
+        paths: Vec<PathBuf>,
+        password: Option<String>,
+    },
     /// Compress files
     Compress {
         paths: Vec<PathBuf>,
         to: PathBuf,
         archive_type: ArchiveType,
+        level: u32,
         password: Option<String>,
+        /// Encryption of zip archives with a password
+        encryption: ZipEncryption,
     },
-    /// Copy items
+    /// Copy items, optionally verifying each copied file
     Copy {
         paths: Vec<PathBuf>,
         to: PathBuf,
+        verify: Option<Checksum>,
+        preserve: Preserve,
+        conflicts: ConflictPolicy,
     },
     /// Move items to the trash
     Delete {
@@ -328,12 +744,34 @@ pub enum Operation {
         paths: Vec<PathBuf>,
         to: PathBuf,
         password: Option<String>,
+        conflicts: ConflictPolicy,
+        /// Only create a containing folder for archives with several items at the top
+        smart: bool,
+        limits: ExtractLimits,
+    },
+    /// Extract items inside of an archive, with everything inside of them
+    ExtractEntries {
+        archive: PathBuf,
+        entries: Vec<PathBuf>,
+        to: PathBuf,
+        password: Option<String>,
+        conflicts: ConflictPolicy,
+        limits: ExtractLimits,
+    },
+    /// Create symbolic or hard links to items
+    Link {
+        paths: Vec<PathBuf>,
+        to: PathBuf,
+        kind: LinkKind,
     },
-    /// Move items
+    /// Move items, optionally verifying files copied across devices
     Move {
         paths: Vec<PathBuf>,
         to: PathBuf,
         cross_device_copy: bool,
+        verify: Option<Checksum>,
+        preserve: Preserve,
+        conflicts: ConflictPolicy,
     },
     NewFile {
         path: PathBuf,
@@ -360,18 +798,180 @@ pub enum Operation {
     SetExecutableAndLaunch {
         path: PathBuf,
     },
+    /// Resume a copy or move interrupted by a crash
+    Resume {
+        journal: PathBuf,
+        operation: Box<Operation>,
+    },
+    /// Copy, move or extract items to the given targets, such as failed items retried with the
+    /// targets they failed to be written to, or moved items put back when undoing
+    Retry {
+        items: Vec<(PathBuf, PathBuf)>,
+        operation: Box<Operation>,
+    },
+    /// Set permissions of items, files and directories separately
+    SetModes {
+        paths: Vec<PathBuf>,
+        change: ModeChange,
+        recursive: bool,
+    },
+    /// Set the owner and group of items, leaving either unchanged if it is `None`
+    SetOwner {
+        paths: Vec<PathBuf>,
+        owner: Option<u32>,
+        group: Option<u32>,
+        /// Permissions set after the owner of each item, as changing it clears setuid bits
+        change: Option<ModeChange>,
+        recursive: bool,
+    },
     /// Set permissions
     SetPermissions {
         path: PathBuf,
         mode: u32,
     },
+    /// Overwrite items before permanently deleting them
+    Shred {
+        paths: Vec<PathBuf>,
+        passes: Vec<ShredPass>,
+    },
 }
 
 #[derive(Clone, Debug)]
 pub enum OperationErrorType {
     Generic(String),
     PasswordRequired,
+    /// The password given for an archive is wrong
+    WrongPassword,
+    /// A copied file differs from its source, the journal allows retrying from that file
+    ChecksumMismatch {
+        from: PathBuf,
+        path: PathBuf,
+        journal: Option<PathBuf>,
+    },
+    PermissionDenied(PathBuf),
+    /// The destination filesystem is full, or the disk quota was exceeded
+    NoSpace(PathBuf),
+    /// The name or path is too long for the destination filesystem
+    NameTooLong(PathBuf),
+    /// The destination filesystem cannot store some of the items, found before starting
+    Unsupported {
+        fs_type: String,
+        violations: Vec<Violation>,
+    },
+    Io {
+        path: PathBuf,
+        message: String,
+    },
+    /// An archive entry was refused, and nothing after it was extracted
+    UnsafeEntry {
+        entry: PathBuf,
+        reason: UnsafeEntry,
+    },
+    /// An archive entry could not be read back, or the archive itself if `entry` is empty
+    CorruptEntry {
+        archive: PathBuf,
+        entry: PathBuf,
+        message: String,
+    },
+}
+
+impl OperationErrorType {
+    /// Classify an I/O error on `path`
+    pub fn from_io(path: &Path, err: &io::Error) -> Self {
+        let path = path.to_path_buf();
+        // ErrorKind::InvalidFilename is newer than the minimum supported Rust version
+        #[cfg(unix)]
+        if err.raw_os_error() == Some(libc::ENAMETOOLONG) {
+            return Self::NameTooLong(path);
+        }
+        match err.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(path),
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => Self::NoSpace(path),
+            _ => Self::Io {
+                path,
+                message: err.to_string(),
+            },
+        }
+    }
+
+    /// What the user can do about the error, if anything
+    pub fn remedy(&self) -> Option<String> {
+        match self {
+            Self::PermissionDenied(_) => Some(fl!("remedy-permission-denied")),
+            Self::NoSpace(_) => Some(fl!("remedy-no-space")),
+            Self::NameTooLong(_) => Some(fl!("remedy-name-too-long")),
+            Self::Unsupported { .. } => Some(fl!("remedy-unsupported")),
+            Self::UnsafeEntry { .. } => Some(fl!("remedy-unsafe-entry")),
+            Self::CorruptEntry { .. } => Some(fl!("remedy-corrupt-entry")),
+            Self::Generic(_)
+            | Self::PasswordRequired
+            | Self::WrongPassword
+            | Self::ChecksumMismatch { .. }
+            | Self::Io { .. } => None,
+        }
+    }
+}
+
+impl std::fmt::Display for OperationErrorType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Generic(s) => s.fmt(f),
+            Self::PasswordRequired => f.write_str("Password required"),
+            Self::WrongPassword => f.write_str(&fl!("extract-password-wrong")),
+            Self::ChecksumMismatch { path, .. } => {
+                f.write_str(&fl!("checksum-mismatch", name = file_name(path)))
+            }
+            Self::PermissionDenied(path) => f.write_str(&fl!(
+                "error-permission-denied",
+                path = path.to_string_lossy()
+            )),
+            Self::NoSpace(path) => {
+                f.write_str(&fl!("error-no-space", path = path.to_string_lossy()))
+            }
+            Self::NameTooLong(path) => {
+                f.write_str(&fl!("error-name-too-long", path = path.to_string_lossy()))
+            }
+            Self::Unsupported {
+                fs_type,
+                violations,
+            } => f.write_str(&fl!(
+                "error-unsupported",
+                items = violations.len(),
+                fs = fs_type.as_str()
+            )),
+            Self::Io { path, message } => f.write_str(&fl!(
+                "error-io",
+                path = path.to_string_lossy(),
+                message = message.as_str()
+            )),
+            Self::UnsafeEntry { entry, reason } => f.write_str(&fl!(
+                "error-unsafe-entry",
+                name = entry.to_string_lossy(),
+                reason = reason.to_string()
+            )),
+            Self::CorruptEntry {
+                archive,
+                entry,
+                message,
+            } if entry.as_os_str().is_empty() => f.write_str(&fl!(
+                "error-corrupt-archive",
+                name = file_name(archive),
+                message = message.as_str()
+            )),
+            Self::CorruptEntry {
+                archive,
+                entry,
+                message,
+            } => f.write_str(&fl!(
+                "error-corrupt-entry",
+                name = entry.to_string_lossy(),
+                archive = file_name(archive),
+                message = message.as_str()
+            )),
+        }
+    }
 }
+
 #[derive(Clone, Debug)]
 pub struct OperationError {
     pub kind: OperationErrorType,
@@ -416,10 +1016,7 @@ impl std::error::Error for OperationError {}
 
 impl std::fmt::Display for OperationError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match &self.kind {
-            OperationErrorType::Generic(s) => s.fmt(f),
-            OperationErrorType::PasswordRequired => f.write_str("Password required"),
-        }
+        self.kind.fmt(f)
     }
 }
 
@@ -433,6 +1030,18 @@ impl Operation {
             ControllerState::Failed => fl!("progress-failed", percent = percent),
         };
         match self {
+            Self::ArchiveAdd { paths, archive } => fl!(
+                "adding-to-archive",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(archive),
+                progress = progress()
+            ),
+            Self::ArchiveTest { paths, .. } => fl!(
+                "testing-archives",
+                items = paths.len(),
+                progress = progress()
+            ),
             Self::Compress { paths, to, .. } => fl!(
                 "compressing",
                 items = paths.len(),
@@ -440,7 +1049,7 @@ impl Operation {
                 to = file_name(to),
                 progress = progress()
             ),
-            Self::Copy { paths, to } => fl!(
+            Self::Copy { paths, to, .. } => fl!(
                 "copying",
                 items = paths.len(),
                 from = paths_parent_name(paths),
@@ -458,12 +1067,27 @@ impl Operation {
                 fl!("deleting", items = items.len(), progress = progress())
             }
             Self::EmptyTrash => fl!("emptying-trash", progress = progress()),
-            Self::Extract {
-                paths,
+            Self::Extract { paths, to, .. } => fl!(
+                "extracting",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(to),
+                progress = progress()
+            ),
+            Self::ExtractEntries {
+                archive,
+                entries,
                 to,
-                password: _,
+                ..
             } => fl!(
                 "extracting",
+                items = entries.len(),
+                from = file_name(archive),
+                to = file_name(to),
+                progress = progress()
+            ),
+            Self::Link { paths, to, .. } => fl!(
+                "linking",
                 items = paths.len(),
                 from = paths_parent_name(paths),
                 to = file_name(to),
@@ -487,14 +1111,26 @@ impl Operation {
                 parent = parent_name(path)
             ),
             Self::PermanentlyDelete { paths } => fl!("permanently-deleting", items = paths.len()),
+            Self::Shred { paths, .. } => {
+                fl!("shredding", items = paths.len(), progress = progress())
+            }
             Self::Rename { from, to } => {
                 fl!("renaming", from = file_name(from), to = file_name(to))
             }
             Self::RemoveFromRecents { paths } => fl!("removing-from-recents", items = paths.len()),
             Self::Restore { items } => fl!("restoring", items = items.len(), progress = progress()),
+            Self::Resume { operation, .. } | Self::Retry { operation, .. } => {
+                operation.pending_text(ratio, state)
+            }
             Self::SetExecutableAndLaunch { path } => {
                 fl!("setting-executable-and-launching", name = file_name(path))
             }
+            Self::SetModes { paths, .. } => {
+                fl!("setting-modes", items = paths.len(), progress = progress())
+            }
+            Self::SetOwner { paths, .. } => {
+                fl!("setting-owner", items = paths.len(), progress = progress())
+            }
             Self::SetPermissions { path, mode } => {
                 fl!(
                     "setting-permissions",
@@ -507,13 +1143,20 @@ impl Operation {
 
     pub fn completed_text(&self) -> String {
         match self {
+            Self::ArchiveAdd { paths, archive } => fl!(
+                "added-to-archive",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(archive)
+            ),
+            Self::ArchiveTest { paths, .. } => fl!("tested-archives", items = paths.len()),
             Self::Compress { paths, to, .. } => fl!(
                 "compressed",
                 items = paths.len(),
                 from = paths_parent_name(paths),
                 to = file_name(to)
             ),
-            Self::Copy { paths, to } => fl!(
+            Self::Copy { paths, to, .. } => fl!(
                 "copied",
                 items = paths.len(),
                 from = paths_parent_name(paths),
@@ -527,12 +1170,25 @@ impl Operation {
             ),
             Self::DeleteTrash { items } => fl!("deleted", items = items.len()),
             Self::EmptyTrash => fl!("emptied-trash"),
-            Self::Extract {
-                paths,
+            Self::Extract { paths, to, .. } => fl!(
+                "extracted",
+                items = paths.len(),
+                from = paths_parent_name(paths),
+                to = file_name(to)
+            ),
+            Self::ExtractEntries {
+                archive,
+                entries,
                 to,
-                password: _,
+                ..
             } => fl!(
                 "extracted",
+                items = entries.len(),
+                from = file_name(archive),
+                to = file_name(to)
+            ),
+            Self::Link { paths, to, .. } => fl!(
+                "linked",
                 items = paths.len(),
                 from = paths_parent_name(paths),
                 to = file_name(to)
@@ -554,12 +1210,18 @@ impl Operation {
                 parent = parent_name(path)
             ),
             Self::PermanentlyDelete { paths } => fl!("permanently-deleted", items = paths.len()),
+            Self::Shred { paths, .. } => fl!("shredded", items = paths.len()),
             Self::RemoveFromRecents { paths } => fl!("removed-from-recents", items = paths.len()),
             Self::Rename { from, to } => fl!("renamed", from = file_name(from), to = file_name(to)),
             Self::Restore { items } => fl!("restored", items = items.len()),
+            Self::Resume { operation, .. } | Self::Retry { operation, .. } => {
+                operation.completed_text()
+            }
             Self::SetExecutableAndLaunch { path } => {
                 fl!("set-executable-and-launched", name = file_name(path))
             }
+            Self::SetModes { paths, .. } => fl!("set-modes", items = paths.len()),
+            Self::SetOwner { paths, .. } => fl!("set-owner", items = paths.len()),
             Self::SetPermissions { path, mode } => {
                 fl!(
                     "set-permissions",
@@ -573,16 +1235,25 @@ impl Operation {
     pub fn show_progress_notification(&self) -> bool {
         // Long running operations show a progress notification
         match self {
-            Self::Compress { .. }
+            Self::ArchiveAdd { .. }
+            | Self::ArchiveTest { .. }
+            | Self::Compress { .. }
             | Self::Copy { .. }
             | Self::Delete { .. }
             | Self::DeleteTrash { .. }
             | Self::EmptyTrash
             | Self::Extract { .. }
+            | Self::ExtractEntries { .. }
             | Self::Move { .. }
             | Self::PermanentlyDelete { .. }
-            | Self::Restore { .. } => true,
-            Self::NewFile { .. }
+            | Self::Restore { .. }
+            | Self::Resume { .. }
+            | Self::Retry { .. }
+            | Self::SetModes { .. }
+            | Self::SetOwner { .. }
+            | Self::Shred { .. } => true,
+            Self::Link { .. }
+            | Self::NewFile { .. }
             | Self::NewFolder { .. }
             | Self::RemoveFromRecents { .. }
             | Self::Rename { .. }
@@ -591,11 +1262,94 @@ impl Operation {
         }
     }
 
+    /// Devices the operation reads from or writes to, which it should not share with others
+    pub fn devices(&self) -> Vec<u64> {
+        let (paths, to): (&[PathBuf], _) = match self {
+            Self::Compress { paths, to, .. }
+            | Self::Copy { paths, to, .. }
+            | Self::Extract { paths, to, .. }
+            | Self::Link { paths, to, .. }
+            | Self::Move { paths, to, .. } => (paths, Some(to)),
+            Self::ArchiveAdd { paths, archive } => (paths, Some(archive)),
+            Self::ExtractEntries { archive, to, .. } => (std::slice::from_ref(archive), Some(to)),
+            Self::ArchiveTest { paths, .. }
+            | Self::Delete { paths }
+            | Self::PermanentlyDelete { paths }
+            | Self::SetModes { paths, .. }
+            | Self::SetOwner { paths, .. }
+            | Self::Shred { paths, .. } => (paths, None),
+            Self::Resume { operation, .. } | Self::Retry { operation, .. } => {
+                return operation.devices();
+            }
+            _ => return Vec::new(),
+        };
+        let mut devices = Vec::new();
+        for path in paths.iter().chain(to) {
+            if let Some(device) = device_of(path) {
+                if !devices.contains(&device) {
+                    devices.push(device);
+                }
+            }
+        }
+        devices
+    }
+
+    /// Operations that process the items that failed again
+    pub fn retry(&self, failures: &[FailedItem]) -> Vec<Self> {
+        let items = failure::retry_items(failures);
+        let paths: Vec<PathBuf> = items.iter().map(|(from, _)| from.clone()).collect();
+        match self {
+            Self::Copy { .. } | Self::Move { .. } | Self::Extract { .. } => {
+                let mut operation = self.clone();
+                if let Self::Copy { paths: retried, .. }
+                | Self::Move { paths: retried, .. }
+                | Self::Extract { paths: retried, .. } = &mut operation
+                {
+                    *retried = paths;
+                }
+                vec![Self::Retry {
+                    items,
+                    operation: Box::new(operation),
+                }]
+            }
+            Self::Delete { .. } => vec![Self::Delete { paths }],
+            Self::PermanentlyDelete { .. } => vec![Self::PermanentlyDelete { paths }],
+            Self::Resume { operation, .. } | Self::Retry { operation, .. } => {
+                operation.retry(failures)
+            }
+            Self::SetModes {
+                change, recursive, ..
+            } => vec![Self::SetModes {
+                paths,
+                change: *change,
+                recursive: *recursive,
+            }],
+            Self::SetOwner {
+                owner,
+                group,
+                change,
+                recursive,
+                ..
+            } => vec![Self::SetOwner {
+                paths,
+                owner: *owner,
+                group: *group,
+                change: *change,
+                recursive: *recursive,
+            }],
+            _ => Vec::new(),
+        }
+    }
+
     pub fn toast(&self) -> Option<String> {
         match self {
+            Self::ArchiveAdd { .. } => Some(self.completed_text()),
+            Self::ArchiveTest { .. } => Some(self.completed_text()),
             Self::Compress { .. } => Some(self.completed_text()),
             Self::Delete { .. } => Some(self.completed_text()),
             Self::Extract { .. } => Some(self.completed_text()),
+            Self::ExtractEntries { .. } => Some(self.completed_text()),
+            Self::Shred { .. } => Some(self.completed_text()),
             //TODO: more toasts
             _ => None,
         }
@@ -611,11 +1365,56 @@ impl Operation {
 
         //TODO: IF ERROR, RETURN AN Operation THAT CAN UNDO THE CURRENT STATE
         let paths: Result<OperationSelection, OperationError> = match self {
+            Self::ArchiveAdd { paths, archive } => {
+                let controller_clone = controller.clone();
+                compio::runtime::spawn_blocking(
+                    move || -> Result<OperationSelection, OperationError> {
+                        let controller = controller_clone;
+                        crate::utils::archive::add(&archive, &paths, &controller)?;
+                        Ok(OperationSelection {
+                            ignored: paths,
+                            selected: vec![archive],
+                            ..Default::default()
+                        })
+                    },
+                )
+                .await
+                .map_err(wrap_compio_spawn_error)?
+            }
+            Self::ArchiveTest { paths, password } => {
+                let controller_clone = controller.clone();
+                compio::runtime::spawn_blocking(
+                    move || -> Result<OperationSelection, OperationError> {
+                        let controller = controller_clone;
+                        // Corrupt entries do not stop the test, so every one is reported
+                        for path in paths.iter() {
+                            let corrupt =
+                                crate::utils::archive::test(path, &password, &controller)?;
+                            for (entry, message) in corrupt {
+                                controller.fail_item(FailedItem {
+                                    from: path.clone(),
+                                    to: path.clone(),
+                                    kind: OperationErrorType::CorruptEntry {
+                                        archive: path.clone(),
+                                        entry,
+                                        message,
+                                    },
+                                });
+                            }
+                        }
+                        Ok(OperationSelection::default())
+                    },
+                )
+                .await
+                .map_err(wrap_compio_spawn_error)?
+            }
             Self::Compress {
                 paths,
                 to,
                 archive_type,
+                level,
                 password,
+                encryption,
             } => {
                 let controller_c = controller.clone();
                 compio::runtime::spawn_blocking(
@@ -631,6 +1430,7 @@ impl Operation {
                         let op_sel = OperationSelection {
                             ignored: paths.clone(),
                             selected: vec![to.clone()],
+                            ..Default::default()
                         };
 
                         let mut paths = paths;
@@ -646,46 +1446,6 @@ impl Operation {
                         }
 
                         match archive_type {
-                            ArchiveType::Tgz => {
-                                let mut archive = fs::File::create(&to)
-                                    .map(io::BufWriter::new)
-                                    .map(|w| {
-                                        flate2::write::GzEncoder::new(
-                                            w,
-                                            flate2::Compression::default(),
-                                        )
-                                    })
-                                    .map(tar::Builder::new)
-                                    .map_err(|e| OperationError::from_err(e, &controller))?;
-
-                                let total_paths = paths.len();
-                                for (i, path) in paths.iter().enumerate() {
-                                    futures::executor::block_on(async {
-                                        controller
-                                            .check()
-                                            .await
-                                            .map_err(|e| OperationError::from_state(e, &controller))
-                                    })?;
-
-                                    controller.set_progress((i as f32) / total_paths as f32);
-
-                                    if let Some(relative_path) = path
-                                        .strip_prefix(relative_root)
-                                        .map_err(|e| OperationError::from_err(e, &controller))?
-                                        .to_str()
-                                    {
-                                        archive
-                                            .append_path_with_name(path, relative_path)
-                                            .map_err(|e| {
-                                                OperationError::from_err(e, &controller)
-                                            })?;
-                                    }
-                                }
-
-                                archive
-                                    .finish()
-                                    .map_err(|e| OperationError::from_err(e, &controller))?;
-                            }
                             ArchiveType::Zip => {
                                 let mut archive = fs::File::create(&to)
                                     .map(io::BufWriter::new)
@@ -704,12 +1464,16 @@ impl Operation {
 
                                     controller.set_progress((i as f32) / total_paths as f32);
 
-                                    let mut zip_options = zip::write::SimpleFileOptions::default();
-                                    if password.is_some() {
-                                        zip_options = zip_options.with_aes_encryption(
-                                            Aes256,
-                                            password.as_deref().unwrap(),
-                                        );
+                                    let mut zip_options = zip::write::SimpleFileOptions::default()
+                                        .compression_level(Some(i64::from(level)));
+                                    if let Some(password) = password.as_deref() {
+                                        zip_options = match encryption {
+                                            ZipEncryption::Aes256 => {
+                                                zip_options.with_aes_encryption(Aes256, password)
+                                            }
+                                            ZipEncryption::ZipCrypto => zip_options
+                                                .with_deprecated_encryption(password.as_bytes()),
+                                        };
                                     }
                                     if let Some(relative_path) = path
                                         .strip_prefix(relative_root)
@@ -778,6 +1542,60 @@ impl Operation {
                                     .finish()
                                     .map_err(|e| OperationError::from_err(e, &controller))?;
                             }
+                            #[cfg(feature = "sevenz-rust2")]
+                            ArchiveType::SevenZ => crate::utils::archive::sevenz_compress(
+                                &paths,
+                                relative_root,
+                                &to,
+                                level,
+                                &controller,
+                            )?,
+                            archive_type if archive_type.is_single_file() => {
+                                let [path] = paths.as_slice() else {
+                                    return Err(OperationError::from_err(
+                                        format!(
+                                            "only a single file can be compressed as {}",
+                                            archive_type.extension()
+                                        ),
+                                        &controller,
+                                    ));
+                                };
+                                if let Err(e) = crate::utils::archive::compress_file(
+                                    path,
+                                    &to,
+                                    archive_type,
+                                    level,
+                                    &controller,
+                                ) {
+                                    if let Err(err) = fs::remove_file(&to) {
+                                        log::warn!("failed to remove {:?}: {}", to, err);
+                                    }
+                                    // The reader fails when the operation is cancelled
+                                    futures::executor::block_on(controller.check())
+                                        .map_err(|s| OperationError::from_state(s, &controller))?;
+                                    return Err(OperationError::from_err(e, &controller));
+                                }
+                            }
+                            archive_type => {
+                                let encoder = fs::File::create(&to)
+                                    .map(io::BufWriter::new)
+                                    .and_then(|w| {
+                                        crate::utils::archive::TarEncoder::new(
+                                            w,
+                                            archive_type,
+                                            level,
+                                        )
+                                    })
+                                    .map_err(|e| OperationError::from_err(e, &controller))?;
+                                crate::utils::archive::tar_compress(
+                                    encoder,
+                                    &paths,
+                                    relative_root,
+                                    &controller,
+                                )?
+                                .flush()
+                                .map_err(|e| OperationError::from_err(e, &controller))?;
+                            }
                         }
 
                         Ok(op_sel)
@@ -786,9 +1604,18 @@ impl Operation {
                 .await
                 .map_err(wrap_compio_spawn_error)?
             }
-            Self::Copy { paths, to } => {
-                copy_or_move(paths, to, Method::Copy, msg_tx, controller).await
+            operation @ Self::Copy { .. } => {
+                copy_or_move(operation, None, msg_tx, controller).await
             }
+            Self::Resume { journal, operation } => {
+                resume_copy_or_move(journal, *operation, msg_tx, controller).await
+            }
+            Self::Retry { items, operation } => match *operation {
+                operation @ Self::Extract { .. } => {
+                    extract_archives(operation, Some(items), msg_tx, controller).await
+                }
+                operation => copy_or_move(operation, Some(items), msg_tx, controller).await,
+            },
             Self::Delete { paths } => {
                 let total = paths.len();
                 for (i, path) in paths.into_iter().enumerate() {
@@ -801,10 +1628,22 @@ impl Operation {
 
                     controller.set_progress((i as f32) / (total as f32));
 
-                    let _items_opt = compio::runtime::spawn_blocking(|| trash::delete(path))
-                        .await
-                        .map_err(wrap_compio_spawn_error)?;
-                    //TODO: items_opt allows for easy restore
+                    let result = compio::runtime::spawn_blocking({
+                        let path = path.clone();
+                        || trash::delete(path)
+                    })
+                    .await
+                    .map_err(wrap_compio_spawn_error)?;
+                    if let Err(err) = result {
+                        if !controller.continue_on_error() {
+                            return Err(OperationError::from_err(err, &controller));
+                        }
+                        controller.fail_item(FailedItem {
+                            from: path.clone(),
+                            to: path,
+                            kind: OperationErrorType::Generic(err.to_string()),
+                        });
+                    }
                 }
                 Ok(OperationSelection::default())
             }
@@ -882,63 +1721,59 @@ impl Operation {
                 }
                 Ok(OperationSelection::default())
             }
-            Self::Extract {
-                paths,
+            operation @ Self::Extract { .. } => {
+                extract_archives(operation, None, msg_tx, controller).await
+            }
+            Self::ExtractEntries {
+                archive,
+                entries,
                 to,
                 password,
+                conflicts,
+                limits,
             } => {
                 let controller_clone = controller.clone();
+                let msg_tx = msg_tx.clone();
                 compio::runtime::spawn_blocking(
                     move || -> Result<OperationSelection, OperationError> {
                         let controller = controller_clone;
-                        let total_paths = paths.len();
-                        let mut op_sel = OperationSelection::default();
-                        for (i, path) in paths.iter().enumerate() {
-                            futures::executor::block_on(async {
-                                controller
-                                    .check()
-                                    .await
-                                    .map_err(|s| OperationError::from_state(s, &controller))
-                            })?;
-
-                            controller.set_progress((i as f32) / total_paths as f32);
-
-                            if let Some(file_name) = path.file_name().and_then(|f| f.to_str()) {
-                                let dir_name = get_directory_name(file_name);
-                                let mut new_dir = to.join(dir_name);
-
-                                if new_dir.exists() {
-                                    if let Some(new_dir_parent) = new_dir.parent() {
-                                        new_dir = copy_unique_path(&new_dir, new_dir_parent);
-                                    }
-                                }
-
-                                op_sel.ignored.push(path.clone());
-                                op_sel.selected.push(new_dir.clone());
-
-                                crate::utils::archive::extract(path, &new_dir, &password, &controller)?;
-                            }
+                        if password.is_none()
+                            && crate::utils::archive::needs_password(&archive).unwrap_or(false)
+                        {
+                            return Err(OperationError::from_kind(
+                                OperationErrorType::PasswordRequired,
+                                &controller,
+                            ));
                         }
-
-                        Ok(op_sel)
+                        let ask = |entry: &crate::utils::archive::Entry, to: &Path| {
+                            ask_replace_entry(&msg_tx, &archive, entry, to, &controller)
+                        };
+                        crate::utils::archive::extract_entries(
+                            &archive,
+                            &entries,
+                            &to,
+                            &password,
+                            &conflicts,
+                            Some(&ask),
+                            &limits,
+                            &controller,
+                        )?;
+                        Ok(OperationSelection {
+                            ignored: Vec::new(),
+                            selected: entries
+                                .iter()
+                                .filter_map(|entry| entry.file_name())
+                                .map(|name| to.join(name))
+                                .collect(),
+                            ..Default::default()
+                        })
                     },
                 )
-            }
-            .await
-            .map_err(wrap_compio_spawn_error)?,
-            Self::Move {
-                paths,
-                to,
-                cross_device_copy,
-            } => {
-                copy_or_move(
-                    paths,
-                    to,
-                    Method::Move { cross_device_copy },
-                    msg_tx,
-                    controller,
-                )
                 .await
+                .map_err(wrap_compio_spawn_error)?
+            }
+            operation @ Self::Move { .. } => {
+                copy_or_move(operation, None, msg_tx, controller).await
             }
             Self::NewFolder { path } => {
                 let controller_clone = controller.clone();
@@ -954,6 +1789,7 @@ impl Operation {
                     Result::<_, OperationError>::Ok(OperationSelection {
                         ignored: Vec::new(),
                         selected: vec![path],
+                        ..Default::default()
                     })
                 })
             }
@@ -973,6 +1809,7 @@ impl Operation {
                     Result::<_, OperationError>::Ok(OperationSelection {
                         ignored: Vec::new(),
                         selected: vec![path],
+                        ..Default::default()
                     })
                 })
             }
@@ -988,25 +1825,53 @@ impl Operation {
 
                     controller.set_progress((idx as f32) / (total as f32));
 
-                    tokio::task::spawn_blocking(|| {
-                        if path.is_symlink() || path.is_file() {
-                            fs::remove_file(path)
-                        } else if path.is_dir() {
-                            fs::remove_dir_all(path)
-                        } else {
-                            Err(std::io::Error::new(
-                                std::io::ErrorKind::InvalidData,
-                                "File to delete is not symlink, file or directory",
-                            ))
+                    let result = compio::runtime::spawn_blocking({
+                        let path = path.clone();
+                        || {
+                            if path.is_symlink() || path.is_file() {
+                                fs::remove_file(path)
+                            } else if path.is_dir() {
+                                fs::remove_dir_all(path)
+                            } else {
+                                Err(std::io::Error::new(
+                                    std::io::ErrorKind::InvalidData,
+                                    "File to delete is not symlink, file or directory",
+                                ))
+                            }
                         }
                     })
                     .await
-                    .map_err(|e| OperationError::from_err(e, &controller))?
-                    .map_err(|e| OperationError::from_err(e, &controller))?;
+                    .map_err(wrap_compio_spawn_error)?;
+                    if let Err(err) = result {
+                        let kind = OperationErrorType::from_io(&path, &err);
+                        if !controller.continue_on_error() {
+                            return Err(OperationError::from_kind(kind, &controller));
+                        }
+                        controller.fail_item(FailedItem {
+                            from: path.clone(),
+                            to: path,
+                            kind,
+                        });
+                    }
                 }
 
                 Ok(OperationSelection::default())
             }
+            Self::Link { paths, to, kind } => {
+                let controller_c = controller.clone();
+                compio::runtime::spawn_blocking(move || link::link(paths, to, kind, &controller_c))
+                    .await
+                    .map_err(wrap_compio_spawn_error)?
+            }
+            Self::Shred { paths, passes } => {
+                let controller_c = controller.clone();
+                compio::runtime::spawn_blocking(move || {
+                    shred::shred(&paths, &passes, &controller_c)
+                        .map(|()| OperationSelection::default())
+                })
+                .await
+                .map_err(wrap_compio_spawn_error)?
+            }
             Self::RemoveFromRecents { paths } => {
                 tokio::task::spawn_blocking(move || {
                     let path_refs = paths.iter().map(|p| p.as_ref()).collect::<Vec<&Path>>();
@@ -1033,6 +1898,7 @@ impl Operation {
                     Result::<_, OperationError>::Ok(OperationSelection {
                         ignored: vec![from],
                         selected: vec![to],
+                        ..Default::default()
                     })
                 })
             }
@@ -1065,6 +1931,7 @@ impl Operation {
                 Ok(OperationSelection {
                     ignored: Vec::new(),
                     selected: paths,
+                    ..Default::default()
                 })
             }
             Self::SetExecutableAndLaunch { path } => {
@@ -1102,6 +1969,32 @@ impl Operation {
                 .map_err(|e| OperationError::from_err(e, &controller))?;
                 Ok(OperationSelection::default())
             }
+            Self::SetModes {
+                paths,
+                change,
+                recursive,
+            } => {
+                let controller_c = controller.clone();
+                compio::runtime::spawn_blocking(move || {
+                    permissions::set_modes(&paths, change, recursive, &controller_c)
+                })
+                .await
+                .map_err(wrap_compio_spawn_error)?
+            }
+            Self::SetOwner {
+                paths,
+                owner,
+                group,
+                change,
+                recursive,
+            } => {
+                let controller_c = controller.clone();
+                compio::runtime::spawn_blocking(move || {
+                    permissions::set_owner(&paths, owner, group, change, recursive, &controller_c)
+                })
+                .await
+                .map_err(wrap_compio_spawn_error)?
+            }
             Self::SetPermissions { path, mode } => {
                 controller
                     .check()
@@ -1154,7 +2047,8 @@ fn wrap_compio_spawn_error(err: Box<dyn std::any::Any + Send>) -> OperationError
 mod tests {
     use std::{
         fs::{self, File},
-        io,
+        io::{self, Seek, SeekFrom, Write},
+        os::unix::fs::MetadataExt,
         path::PathBuf,
     };
 
@@ -1163,7 +2057,10 @@ mod tests {
     use test_log::test;
     use tokio::sync;
 
-    use super::{Controller, Operation, OperationError, OperationSelection, ReplaceResult};
+    use super::{
+        ConflictPolicy, Controller, DirPolicy, ExtractLimits, FailedItem, FilePolicy, Operation,
+        OperationError, OperationErrorType, OperationSelection, Preserve, ReplaceResult,
+    };
     use crate::{
         app::{
             DialogPage, Message,
@@ -1190,6 +2087,9 @@ mod tests {
             Operation::Copy {
                 paths: paths_clone,
                 to: to_clone,
+                verify: None,
+                preserve: Preserve::default(),
+                conflicts: ConflictPolicy::default(),
             }
             .perform(&sync::Mutex::new(tx).into(), Controller::default())
             .await
@@ -1385,4 +2285,208 @@ mod tests {
 
         Ok(())
     }
+
+    #[test(compio::test)]
+    async fn copy_sparse_file() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+
+        let dir_path = path.join("cosmic");
+        fs::create_dir(&dir_path)?;
+        let file_path = path.join("ferris.img");
+        {
+            let mut file = File::create(&file_path)?;
+            file.set_len(8 * 1024 * 1024)?;
+            file.seek(SeekFrom::Start(3 * 1024 * 1024))?;
+            file.write_all(b"ferris")?;
+        }
+        let expected = dir_path.join("ferris.img");
+
+        debug!("Copying {} to {}", file_path.display(), expected.display());
+        operation_copy(vec![file_path.clone()], dir_path.clone())
+            .await
+            .expect("Copy operation should have succeeded");
+
+        assert_eq!(
+            fs::read(&file_path)?,
+            fs::read(&expected)?,
+            "Copy should have the same contents, including holes"
+        );
+        // Blocks are counted in units of 512 bytes
+        let source = fs::metadata(&file_path)?;
+        if source.blocks() * 512 < source.len() {
+            let copy = fs::metadata(&expected)?;
+            assert!(
+                copy.blocks() * 512 < copy.len(),
+                "Copy should keep the holes, {} blocks are allocated",
+                copy.blocks()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn smart_extract_avoids_nesting() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+
+        let src = path.join("src");
+        fs::create_dir(&src)?;
+        fs::write(src.join("a"), b"a")?;
+        fs::write(src.join("b"), b"b")?;
+        // One directory named like the archive, and several items at the top
+        let single = path.join("foo.tar");
+        let mut builder = tar::Builder::new(File::create(&single)?);
+        builder.append_dir_all("foo", &src)?;
+        builder.into_inner()?;
+        let several = path.join("bomb.tar");
+        let mut builder = tar::Builder::new(File::create(&several)?);
+        builder.append_dir_all("", &src)?;
+        builder.into_inner()?;
+
+        let out = path.join("out");
+        fs::create_dir(&out)?;
+        let (tx, _rx) = mpsc::channel(1);
+        let tx = sync::Mutex::new(tx).into();
+        // Extracting the same archive again goes to a renamed folder
+        let conflicts = ConflictPolicy {
+            dirs: DirPolicy::Rename,
+            ..ConflictPolicy::default()
+        };
+        for archive in [&single, &single, &several] {
+            Operation::Extract {
+                paths: vec![archive.clone()],
+                to: out.clone(),
+                password: None,
+                conflicts: conflicts.clone(),
+                smart: true,
+                limits: ExtractLimits::default(),
+            }
+            .perform(&tx, Controller::default())
+            .await
+            .expect("Extract operation should have succeeded");
+        }
+
+        assert_eq!(fs::read(out.join("foo/a"))?, b"a");
+        assert!(!out.join("foo/foo").exists(), "Folder should not be nested");
+        assert_eq!(fs::read(out.join("foo (2)/b"))?, b"b");
+        assert_eq!(fs::read(out.join("bomb/a"))?, b"a");
+        assert_eq!(
+            fs::read_dir(&out)?.count(),
+            3,
+            "Nothing else should be left"
+        );
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn extract_into_existing_folder() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+
+        let src = path.join("src");
+        fs::create_dir(&src)?;
+        fs::write(src.join("a"), b"new")?;
+        let archive = path.join("foo.tar");
+        let mut builder = tar::Builder::new(File::create(&archive)?);
+        builder.append_dir_all("", &src)?;
+        builder.into_inner()?;
+
+        let out = path.join("out");
+        fs::create_dir_all(out.join("foo"))?;
+        fs::write(out.join("foo/a"), b"old")?;
+        let (tx, _rx) = mpsc::channel(1);
+        let tx = sync::Mutex::new(tx).into();
+        let extract = |dirs, files| {
+            Operation::Extract {
+                paths: vec![archive.clone()],
+                to: out.clone(),
+                password: None,
+                conflicts: ConflictPolicy {
+                    files,
+                    dirs,
+                    ..ConflictPolicy::default()
+                },
+                smart: false,
+                limits: ExtractLimits::default(),
+            }
+            .perform(&tx, Controller::default())
+        };
+
+        extract(DirPolicy::Skip, FilePolicy::Replace)
+            .await
+            .expect("Extract operation should have succeeded");
+        assert_eq!(fs::read(out.join("foo/a"))?, b"old");
+        assert_eq!(fs::read_dir(&out)?.count(), 1, "Archive should be skipped");
+
+        extract(DirPolicy::Merge, FilePolicy::Skip)
+            .await
+            .expect("Extract operation should have succeeded");
+        assert_eq!(fs::read(out.join("foo/a"))?, b"old");
+
+        extract(DirPolicy::Merge, FilePolicy::Replace)
+            .await
+            .expect("Extract operation should have succeeded");
+        assert_eq!(fs::read(out.join("foo/a"))?, b"new");
+        assert_eq!(fs::read_dir(&out)?.count(), 1, "Folder should be merged");
+
+        Ok(())
+    }
+
+    #[test(compio::test)]
+    async fn retry_failed_items() -> io::Result<()> {
+        let fs = empty_fs()?;
+        let path = fs.path();
+
+        let src = path.join("src");
+        fs::create_dir(&src)?;
+        fs::write(src.join("a"), b"a")?;
+        let out = path.join("out");
+        fs::create_dir(&out)?;
+        fs::write(out.join("a"), b"old")?;
+
+        // The copy of `a` failed after its conflict was resolved by renaming
+        let operation = Operation::Copy {
+            paths: vec![src.join("a")],
+            to: out.clone(),
+            verify: None,
+            preserve: Preserve::default(),
+            conflicts: ConflictPolicy::default(),
+        };
+        let failures = [FailedItem {
+            from: src.join("a"),
+            to: out.join("a (2)"),
+            kind: OperationErrorType::NoSpace(out.join("a (2)")),
+        }];
+        let (tx, _rx) = mpsc::channel(1);
+        let tx = sync::Mutex::new(tx).into();
+        for retry in operation.retry(&failures) {
+            retry
+                .perform(&tx, Controller::default())
+                .await
+                .expect("Retry should have succeeded");
+        }
+        assert_eq!(fs::read(out.join("a"))?, b"old");
+        assert_eq!(fs::read(out.join("a (2)"))?, b"a");
+        // No other renamed copy is made
+        assert_eq!(fs::read_dir(&out)?.count(), 2);
+
+        // Deleting continues after an item fails
+        let controller = Controller::default();
+        controller.set_continue_on_error(true);
+        Operation::PermanentlyDelete {
+            paths: vec![src.join("missing"), src.join("a")],
+        }
+        .perform(&tx, controller.clone())
+        .await
+        .expect("Delete operation should have continued");
+        assert!(!src.join("a").exists());
+        let failures = controller.failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].from, src.join("missing"));
+
+        Ok(())
+    }
 }
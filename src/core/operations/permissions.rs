@@ -0,0 +1,385 @@
+// Changing the permissions and ownership of items and everything inside of them
+//
+// Symbolic links are never followed. Their permissions cannot be changed on Linux, so they
+// are skipped when changing modes, and their own owner is changed instead of the target's.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+use super::{Controller, FailedItem, OperationError, OperationErrorType, OperationSelection};
+use crate::fl;
+
+/// How the bits of a [`ModeChange`] combine with the current mode of an item
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ModeMask {
+    /// Replace the permission bits
+    #[default]
+    Set,
+    /// Only add bits, keeping the others
+    Add,
+    /// Only remove bits, keeping the others
+    Remove,
+}
+
+/// Permission bits for files and directories, which usually differ in the execute bits
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ModeChange {
+    pub files: u32,
+    pub dirs: u32,
+    pub mask: ModeMask,
+}
+
+impl Default for ModeChange {
+    fn default() -> Self {
+        Self {
+            files: 0o644,
+            dirs: 0o755,
+            mask: ModeMask::Set,
+        }
+    }
+}
+
+impl ModeChange {
+    /// New permission bits of an item with `mode`
+    pub fn apply(&self, mode: u32, is_dir: bool) -> u32 {
+        let bits = if is_dir { self.dirs } else { self.files } & 0o7777;
+        let mode = mode & 0o7777;
+        match self.mask {
+            ModeMask::Set => bits,
+            ModeMask::Add => mode | bits,
+            ModeMask::Remove => mode & !bits,
+        }
+    }
+}
+
+/// Parse octal permission bits like `644` or `0755`
+pub fn parse_mode(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if text.is_empty() || text.len() > 5 {
+        return None;
+    }
+    u32::from_str_radix(text, 8)
+        .ok()
+        .filter(|&mode| mode <= 0o7777)
+}
+
+/// Users or groups to choose from. The first entry leaves the owner or group unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct Accounts {
+    pub ids: Vec<Option<u32>>,
+    pub names: Vec<String>,
+}
+
+impl Accounts {
+    fn new(mut accounts: Vec<(u32, String)>) -> Self {
+        accounts.sort_by(|a, b| a.1.cmp(&b.1));
+        accounts.dedup_by_key(|(id, _)| *id);
+        let mut ids = vec![None];
+        let mut names = vec![fl!("unchanged")];
+        for (id, name) in accounts {
+            ids.push(Some(id));
+            names.push(name);
+        }
+        Self { ids, names }
+    }
+
+    /// All users of the system
+    #[cfg(unix)]
+    pub fn users() -> Self {
+        // The user database cannot be iterated by two threads at once
+        static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let _guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        // Safety: the user database is only iterated while holding the lock
+        let users = unsafe { uzers::all_users() };
+        Self::new(
+            users
+                .map(|user| (user.uid(), user.name().to_string_lossy().into_owned()))
+                .collect(),
+        )
+    }
+
+    /// Groups of the current user, which are the only ones it can give items to
+    #[cfg(unix)]
+    pub fn groups() -> Self {
+        let uid = uzers::get_current_uid();
+        let groups = uzers::get_user_by_uid(uid)
+            .and_then(|user| uzers::get_user_groups(user.name(), user.primary_group_id()))
+            .unwrap_or_default();
+        Self::new(
+            groups
+                .into_iter()
+                .map(|group| (group.gid(), group.name().to_string_lossy().into_owned()))
+                .collect(),
+        )
+    }
+
+    #[cfg(not(unix))]
+    pub fn users() -> Self {
+        Self::new(Vec::new())
+    }
+
+    #[cfg(not(unix))]
+    pub fn groups() -> Self {
+        Self::new(Vec::new())
+    }
+
+    pub fn position(&self, id: Option<u32>) -> Option<usize> {
+        self.ids.iter().position(|&other| other == id)
+    }
+}
+
+/// Call `f` on `paths`, and on everything inside of them if `recursive` is set. Failed items
+/// are collected by the controller if it continues on errors.
+fn for_each_item(
+    paths: &[PathBuf],
+    recursive: bool,
+    controller: &Controller,
+    mut f: impl FnMut(&Path, &fs::Metadata) -> io::Result<()>,
+) -> Result<OperationSelection, OperationError> {
+    let fail = |path: &Path, err: &io::Error| -> Result<(), OperationError> {
+        let kind = OperationErrorType::from_io(path, err);
+        if !controller.continue_on_error() {
+            return Err(OperationError::from_kind(kind, controller));
+        }
+        controller.fail_item(FailedItem {
+            from: path.to_path_buf(),
+            to: path.to_path_buf(),
+            kind,
+        });
+        Ok(())
+    };
+
+    // Walk first so progress can be shown
+    let mut entries = Vec::new();
+    for path in paths {
+        let walk = WalkDir::new(path)
+            .follow_root_links(false)
+            .max_depth(if recursive { usize::MAX } else { 0 });
+        for entry in walk {
+            futures::executor::block_on(controller.check())
+                .map_err(|state| OperationError::from_state(state, controller))?;
+            match entry.and_then(|entry| Ok((entry.metadata()?, entry.into_path()))) {
+                Ok((metadata, path)) => entries.push((path, metadata)),
+                Err(err) => {
+                    let path = err.path().unwrap_or(path.as_path()).to_path_buf();
+                    let err = io::Error::from(err);
+                    fail(&path, &err)?;
+                }
+            }
+        }
+    }
+
+    let total = entries.len();
+    for (i, (path, metadata)) in entries.iter().enumerate() {
+        futures::executor::block_on(controller.check())
+            .map_err(|state| OperationError::from_state(state, controller))?;
+        controller.set_progress(i as f32 / total as f32);
+        if let Err(err) = f(path, metadata) {
+            fail(path, &err)?;
+        }
+    }
+
+    Ok(OperationSelection {
+        ignored: Vec::new(),
+        selected: paths.to_vec(),
+        ..Default::default()
+    })
+}
+
+/// Change the permissions of `paths`, and of everything inside of them if `recursive` is set
+pub fn set_modes(
+    paths: &[PathBuf],
+    change: ModeChange,
+    recursive: bool,
+    controller: &Controller,
+) -> Result<OperationSelection, OperationError> {
+    for_each_item(paths, recursive, controller, |path, metadata| {
+        set_mode(path, metadata, change)
+    })
+}
+
+/// Change the permissions of one item, skipping symbolic links
+fn set_mode(path: &Path, metadata: &fs::Metadata, change: ModeChange) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.is_symlink() {
+            return Ok(());
+        }
+        let mode = change.apply(metadata.permissions().mode(), metadata.is_dir());
+        if mode != metadata.permissions().mode() & 0o7777 {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+    }
+    //TODO: what to do on non-Unix systems?
+    #[cfg(not(unix))]
+    let _ = (path, metadata, change);
+    Ok(())
+}
+
+/// Change the owner and group of `paths`, and of everything inside of them if `recursive` is
+/// set. Either is left unchanged if it is `None`. The permissions are changed right after the
+/// owner of each item if `change` is set, so the setuid bits cleared by the owner change can be
+/// set again.
+pub fn set_owner(
+    paths: &[PathBuf],
+    owner: Option<u32>,
+    group: Option<u32>,
+    change: Option<ModeChange>,
+    recursive: bool,
+    controller: &Controller,
+) -> Result<OperationSelection, OperationError> {
+    for_each_item(paths, recursive, controller, |path, _metadata| {
+        #[cfg(unix)]
+        std::os::unix::fs::lchown(path, owner, group)?;
+        #[cfg(not(unix))]
+        let _ = (path, owner, group);
+        match change {
+            // The mode may have changed with the owner
+            Some(change) => set_mode(path, &fs::symlink_metadata(path)?, change),
+            None => Ok(()),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, path::Path};
+
+    use tempfile::tempdir;
+
+    use super::{ModeChange, ModeMask, parse_mode, set_modes};
+    use crate::core::operations::Controller;
+
+    #[test]
+    fn mode_masks() {
+        let set = ModeChange::default();
+        assert_eq!(set.apply(0o100600, false), 0o644);
+        assert_eq!(set.apply(0o040700, true), 0o755);
+
+        let add = ModeChange {
+            files: 0o020,
+            dirs: 0o030,
+            mask: ModeMask::Add,
+        };
+        assert_eq!(add.apply(0o600, false), 0o620);
+        assert_eq!(add.apply(0o700, true), 0o730);
+
+        let remove = ModeChange {
+            files: 0o077,
+            dirs: 0o077,
+            mask: ModeMask::Remove,
+        };
+        assert_eq!(remove.apply(0o4755, false), 0o4700);
+
+        assert_eq!(parse_mode("755"), Some(0o755));
+        assert_eq!(parse_mode("04755"), Some(0o4755));
+        assert_eq!(parse_mode("8"), None);
+        assert_eq!(parse_mode("77777"), None);
+        assert_eq!(parse_mode(""), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn set_modes_recursive() -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir()?;
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("nested"))?;
+        fs::write(root.join("a"), b"a")?;
+        fs::write(root.join("nested/b"), b"b")?;
+        std::os::unix::fs::symlink("a", root.join("link"))?;
+        let mode = |path: &Path| -> io::Result<u32> {
+            Ok(fs::metadata(path)?.permissions().mode() & 0o7777)
+        };
+
+        let change = ModeChange {
+            files: 0o600,
+            dirs: 0o700,
+            mask: ModeMask::Set,
+        };
+        let controller = Controller::default();
+        assert!(set_modes(&[root.clone()], change, true, &controller).is_ok());
+        assert_eq!(mode(&root)?, 0o700);
+        assert_eq!(mode(&root.join("nested"))?, 0o700);
+        assert_eq!(mode(&root.join("a"))?, 0o600);
+        assert_eq!(mode(&root.join("nested/b"))?, 0o600);
+
+        // Without recursion only the item itself changes
+        let change = ModeChange {
+            mask: ModeMask::Add,
+            files: 0o044,
+            dirs: 0o055,
+        };
+        assert!(set_modes(&[root.clone()], change, false, &controller).is_ok());
+        assert_eq!(mode(&root)?, 0o755);
+        assert_eq!(mode(&root.join("a"))?, 0o600);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn set_modes_selected_link() -> io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir()?;
+        let target = dir.path().join("target");
+        fs::create_dir(&target)?;
+        fs::write(target.join("a"), b"a")?;
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o755))?;
+        fs::set_permissions(target.join("a"), fs::Permissions::from_mode(0o644))?;
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&target, &link)?;
+        let mode = |path: &Path| -> io::Result<u32> {
+            Ok(fs::metadata(path)?.permissions().mode() & 0o7777)
+        };
+
+        let change = ModeChange {
+            files: 0o600,
+            dirs: 0o700,
+            mask: ModeMask::Set,
+        };
+        let controller = Controller::default();
+        assert!(set_modes(&[link], change, true, &controller).is_ok());
+        assert_eq!(mode(&target)?, 0o755);
+        assert_eq!(mode(&target.join("a"))?, 0o644);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn set_owner_then_modes() -> io::Result<()> {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = tempdir()?;
+        let path = dir.path().join("a");
+        fs::write(&path, b"a")?;
+        let metadata = fs::metadata(&path)?;
+
+        // The setgid bit survives the owner change clearing it
+        let change = ModeChange {
+            files: 0o2755,
+            dirs: 0o755,
+            mask: ModeMask::Set,
+        };
+        let controller = Controller::default();
+        let result = set_owner(
+            &[path.clone()],
+            Some(metadata.uid()),
+            Some(metadata.gid()),
+            Some(change),
+            false,
+            &controller,
+        );
+        assert!(result.is_ok());
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o7777, 0o2755);
+
+        Ok(())
+    }
+}
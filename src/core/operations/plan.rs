@@ -0,0 +1,177 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    Controller, FailedItem, Operation, OperationError,
+    constraints::{self, FsLimits, Violation},
+    copy_or_move_method, copy_or_move_options, device_of, from_to_pairs,
+    recursive::{Context, Op, OpKind},
+};
+
+/// What a copy or move will do, built by walking the sources without changing anything
+#[derive(Debug, Default)]
+pub struct Plan {
+    /// Ops creating each item at the destination, in the order they run
+    pub ops: Vec<Op>,
+    /// Ops removing the sources of a move, in the order they were planned
+    pub cleanup_ops: Vec<Op>,
+    /// Items that are not files, directories or links, with their destination
+    pub special: Vec<(PathBuf, PathBuf)>,
+    /// Existing directories skipped by the conflict policy
+    pub skipped: Vec<PathBuf>,
+    /// Limits of the destination filesystem
+    pub limits: FsLimits,
+}
+
+impl Plan {
+    /// All ops in the order they run, cleanup ops last and in reverse
+    pub fn into_ops(self) -> Vec<Op> {
+        let mut ops = self.ops;
+        ops.extend(self.cleanup_ops.into_iter().rev());
+        ops
+    }
+
+    /// Bytes written to the device of the destination directory `to`
+    pub fn bytes_needed(&self, to: &Path) -> u64 {
+        let to_device = device_of(to);
+        self.ops
+            .iter()
+            .filter(|op| match op.kind {
+                OpKind::Copy => true,
+                // Moves within a device only rename, which needs no space
+                OpKind::Move { .. } => device_of(&op.from) != to_device,
+                _ => false,
+            })
+            .filter_map(|op| fs::symlink_metadata(&op.from).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// Count what the plan does to the destination directory `to`
+    pub fn summary(&self, to: &Path) -> PlanSummary {
+        let mut summary = PlanSummary {
+            bytes_needed: self.bytes_needed(to),
+            special: self.special.iter().map(|(from, _)| from.clone()).collect(),
+            skipped: self.skipped.clone(),
+            violations: constraints::check(self, to),
+            fs_type: self.limits.fs_type.clone(),
+            available: constraints::available_space(to),
+            ..Default::default()
+        };
+        for op in &self.ops {
+            match op.kind {
+                OpKind::Mkdir => summary.dirs += 1,
+                OpKind::Symlink { .. } => summary.links += 1,
+                OpKind::Copy | OpKind::Move { .. } => {
+                    summary.files += 1;
+                    summary.bytes +=
+                        fs::symlink_metadata(&op.from).map_or(0, |metadata| metadata.len());
+                }
+                OpKind::Remove | OpKind::Rmdir => {}
+            }
+            if !matches!(op.kind, OpKind::Mkdir) && fs::symlink_metadata(&op.to).is_ok() {
+                summary.conflicts.push(op.to.clone());
+            }
+        }
+        summary
+    }
+}
+
+/// Counts shown before a copy or move starts
+#[derive(Clone, Debug, Default)]
+pub struct PlanSummary {
+    pub files: usize,
+    pub dirs: usize,
+    pub links: usize,
+    pub bytes: u64,
+    /// Bytes written to the device of the destination
+    pub bytes_needed: u64,
+    /// Destinations of items that already exist, resolved by the file conflict policy
+    pub conflicts: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+    /// Sources that are not copied, such as sockets and device files
+    pub special: Vec<PathBuf>,
+    /// Items that could not be read while planning
+    pub failures: Vec<FailedItem>,
+    /// What the destination filesystem cannot store
+    pub violations: Vec<Violation>,
+    pub fs_type: String,
+    /// Free space at the destination, if known
+    pub available: Option<u64>,
+}
+
+impl PlanSummary {
+    /// Free space left at the destination once the operation completes, negative if it
+    /// does not fit
+    pub fn remaining(&self) -> Option<i128> {
+        self.available
+            .map(|available| i128::from(available) - i128::from(self.bytes_needed))
+    }
+}
+
+/// Plan `operation`, which must be a copy or move, without changing anything. Items that
+/// cannot be read are reported in the summary instead of failing the preview.
+pub fn preview(
+    operation: &Operation,
+    controller: &Controller,
+) -> Result<PlanSummary, OperationError> {
+    let (paths, to, method) = copy_or_move_method(operation, controller)?;
+    let (_, _, conflicts) = copy_or_move_options(operation);
+    controller.set_continue_on_error(true);
+    let mut context = Context::new(controller.clone()).conflicts(conflicts);
+    let plan =
+        futures::executor::block_on(context.plan(from_to_pairs(paths, &to, method), method))?;
+    let mut summary = plan.summary(&to);
+    summary.failures = controller.failures();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io};
+
+    use tempfile::tempdir;
+
+    use super::preview;
+    use crate::core::operations::{ConflictPolicy, Controller, DirPolicy, Operation, Preserve};
+
+    #[test]
+    fn preview_counts_without_changes() -> io::Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested"))?;
+        fs::create_dir_all(src.join("new"))?;
+        fs::write(src.join("a"), b"12345")?;
+        fs::write(src.join("nested/b"), b"123")?;
+        fs::write(src.join("new/c"), b"1")?;
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(dst.join("nested"))?;
+        fs::write(dst.join("a"), b"old")?;
+
+        let operation = Operation::Copy {
+            paths: vec![src.join("a"), src.join("nested"), src.join("new")],
+            to: dst.clone(),
+            verify: None,
+            preserve: Preserve::default(),
+            conflicts: ConflictPolicy {
+                dirs: DirPolicy::Skip,
+                ..Default::default()
+            },
+        };
+        let summary = preview(&operation, &Controller::default()).unwrap();
+        assert_eq!(summary.dirs, 1);
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.bytes, 6);
+        assert_eq!(summary.bytes_needed, 6);
+        assert_eq!(summary.conflicts, vec![dst.join("a")]);
+        assert_eq!(summary.skipped, vec![dst.join("nested")]);
+        assert!(summary.available.is_some());
+        // Nothing was copied
+        assert_eq!(fs::read(dst.join("a"))?, b"old");
+        assert!(!dst.join("new").exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+/// Attributes carried over from the source of a copy
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Preserve {
+    /// Access and modification times
+    pub timestamps: bool,
+    /// Permission bits
+    pub mode: bool,
+    /// Owner and group, where permitted
+    pub ownership: bool,
+    /// `user.*` extended attributes
+    pub xattrs: bool,
+    /// POSIX access control lists
+    pub acls: bool,
+}
+
+impl Default for Preserve {
+    fn default() -> Self {
+        Self {
+            timestamps: true,
+            mode: true,
+            ownership: true,
+            xattrs: true,
+            acls: true,
+        }
+    }
+}
+
+impl Preserve {
+    pub fn none() -> Self {
+        Self {
+            timestamps: false,
+            mode: false,
+            ownership: false,
+            xattrs: false,
+            acls: false,
+        }
+    }
+
+    /// Short representation used by the operation journal
+    pub fn to_flags(self) -> String {
+        let flags: String = [
+            (self.timestamps, 't'),
+            (self.mode, 'm'),
+            (self.ownership, 'o'),
+            (self.xattrs, 'x'),
+            (self.acls, 'a'),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, flag)| enabled.then_some(flag))
+        .collect();
+        if flags.is_empty() {
+            "-".to_string()
+        } else {
+            flags
+        }
+    }
+
+    pub fn from_flags(flags: &str) -> Option<Self> {
+        let mut preserve = Self::none();
+        if flags == "-" {
+            return Some(preserve);
+        }
+        for flag in flags.chars() {
+            match flag {
+                't' => preserve.timestamps = true,
+                'm' => preserve.mode = true,
+                'o' => preserve.ownership = true,
+                'x' => preserve.xattrs = true,
+                'a' => preserve.acls = true,
+                _ => return None,
+            }
+        }
+        Some(preserve)
+    }
+
+    /// Copy the attributes of `from` to `to`, which must not be written to afterwards. Returns
+    /// warnings about attributes that could not be preserved.
+    pub fn apply(&self, from: &Path, from_metadata: &fs::Metadata, to: &Path) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut warn = |what: &str, err: io::Error| {
+            log::warn!("failed to preserve {} of {:?}: {}", what, to, err);
+            warnings.push(format!("{}: {} ({})", to.display(), what, err));
+        };
+        let is_symlink = from_metadata.is_symlink();
+
+        // Changing the owner clears setuid and setgid bits, so it must come before the mode
+        if self.ownership {
+            if let Err(err) = preserve_ownership(from_metadata, to) {
+                warn("ownership", err);
+            }
+        }
+        // Symlinks have no permissions of their own
+        if self.mode && !is_symlink {
+            if let Err(err) = fs::set_permissions(to, from_metadata.permissions()) {
+                warn("permissions", err);
+            }
+        }
+        if self.xattrs || self.acls {
+            if let Err(err) = copy_xattrs(from, to, self.xattrs, self.acls) {
+                warn("extended attributes", err);
+            }
+        }
+        if self.timestamps {
+            if let Err(err) = preserve_timestamps(from_metadata, to) {
+                warn("timestamps", err);
+            }
+        }
+        warnings
+    }
+}
+
+fn is_not_permitted(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::PermissionDenied
+}
+
+#[cfg(unix)]
+fn preserve_ownership(from_metadata: &fs::Metadata, to: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{MetadataExt, lchown};
+    match lchown(to, Some(from_metadata.uid()), Some(from_metadata.gid())) {
+        Ok(()) => Ok(()),
+        // Only root may give files away, but the group may still be one of ours
+        Err(err) if is_not_permitted(&err) => match lchown(to, None, Some(from_metadata.gid())) {
+            Err(err) if is_not_permitted(&err) => Ok(()),
+            result => result,
+        },
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(not(unix))]
+fn preserve_ownership(_from_metadata: &fs::Metadata, _to: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn preserve_timestamps(from_metadata: &fs::Metadata, to: &Path) -> io::Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt, os::unix::fs::MetadataExt};
+    let to_c = CString::new(to.as_os_str().as_bytes())?;
+    let times = [
+        libc::timespec {
+            tv_sec: from_metadata.atime() as _,
+            tv_nsec: from_metadata.atime_nsec() as _,
+        },
+        libc::timespec {
+            tv_sec: from_metadata.mtime() as _,
+            tv_nsec: from_metadata.mtime_nsec() as _,
+        },
+    ];
+    if unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            to_c.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    } == 0
+    {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn preserve_timestamps(from_metadata: &fs::Metadata, to: &Path) -> io::Result<()> {
+    let times = fs::FileTimes::new()
+        .set_accessed(from_metadata.accessed()?)
+        .set_modified(from_metadata.modified()?);
+    fs::File::options().write(true).open(to)?.set_times(times)
+}
+
+#[cfg(target_os = "linux")]
+fn copy_xattrs(from: &Path, to: &Path, xattrs: bool, acls: bool) -> io::Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    // Retries with a larger buffer if the attributes changed between the calls
+    fn read_until_fits(mut f: impl FnMut(&mut [u8]) -> isize) -> io::Result<Vec<u8>> {
+        loop {
+            let size = f(&mut []);
+            if size < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut buf = vec![0; size as usize];
+            let size = f(&mut buf);
+            if size >= 0 {
+                buf.truncate(size as usize);
+                return Ok(buf);
+            }
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::ERANGE) {
+                return Err(err);
+            }
+        }
+    }
+
+    let from_c = CString::new(from.as_os_str().as_bytes())?;
+    let to_c = CString::new(to.as_os_str().as_bytes())?;
+    let names = match read_until_fits(|buf| unsafe {
+        libc::llistxattr(from_c.as_ptr(), buf.as_mut_ptr().cast(), buf.len())
+    }) {
+        Ok(names) => names,
+        // The source filesystem has no extended attributes to preserve
+        Err(err) if err.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    for name in names
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+    {
+        let wanted = (xattrs && name.starts_with(b"user."))
+            || (acls && name.starts_with(b"system.posix_acl_"));
+        if !wanted {
+            continue;
+        }
+        let name_c = CString::new(name)?;
+        let value = read_until_fits(|buf| unsafe {
+            libc::lgetxattr(
+                from_c.as_ptr(),
+                name_c.as_ptr(),
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+            )
+        })?;
+        if unsafe {
+            libc::lsetxattr(
+                to_c.as_ptr(),
+                name_c.as_ptr(),
+                value.as_ptr().cast(),
+                value.len(),
+                0,
+            )
+        } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_xattrs(_from: &Path, _to: &Path, _xattrs: bool, _acls: bool) -> io::Result<()> {
+    //TODO: preserve extended attributes on other platforms
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, time::Duration};
+
+    use tempfile::tempdir;
+
+    use super::Preserve;
+
+    #[test]
+    fn flags_round_trip() {
+        let mut preserve = Preserve::default();
+        assert_eq!(Preserve::from_flags(&preserve.to_flags()), Some(preserve));
+        preserve.ownership = false;
+        preserve.acls = false;
+        assert_eq!(preserve.to_flags(), "tmx");
+        assert_eq!(Preserve::from_flags(&preserve.to_flags()), Some(preserve));
+        assert_eq!(Preserve::from_flags("-"), Some(Preserve::none()));
+        assert_eq!(Preserve::from_flags("q"), None);
+    }
+
+    #[test]
+    fn timestamps_and_mode_are_preserved() -> io::Result<()> {
+        let dir = tempdir()?;
+        let from = dir.path().join("from");
+        let to = dir.path().join("to");
+        fs::write(&from, b"from")?;
+        fs::write(&to, b"from")?;
+        let modified = fs::metadata(&from)?.modified()? - Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&from)?
+            .set_modified(modified)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&from, fs::Permissions::from_mode(0o640))?;
+        }
+
+        let from_metadata = fs::symlink_metadata(&from)?;
+        let warnings = Preserve::default().apply(&from, &from_metadata, &to);
+        assert!(warnings.is_empty(), "unexpected warnings {warnings:?}");
+
+        let to_metadata = fs::metadata(&to)?;
+        assert_eq!(to_metadata.modified()?, modified);
+        assert_eq!(to_metadata.permissions(), from_metadata.permissions());
+
+        Ok(())
+    }
+}
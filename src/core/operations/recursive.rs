@@ -3,14 +3,34 @@ use compio::buf::{IntoInner, IoBuf};
 use compio::io::{AsyncReadAt, AsyncWriteAt};
 use std::future::Future;
 use std::pin::Pin;
-use std::time::Instant;
-use std::{cell::Cell, error::Error, fs, ops::ControlFlow, path::PathBuf, rc::Rc};
+use std::time::{Duration, Instant};
+use std::{
+    cell::Cell,
+    error::Error,
+    fs, io,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 use walkdir::WalkDir;
 
 use crate::core::operations::OperationError;
 
-use super::{Controller, OperationSelection, ReplaceResult, copy_unique_path};
+use super::{
+    Checksum, ConflictPolicy, Controller, DirPolicy, FailedItem, FilePolicy, Operation,
+    OperationErrorType, OperationSelection, Preserve, ReplaceResult,
+    conflict::{Resolution, Source},
+    constraints::{self, FsLimits},
+    copy_unique_path, fast_copy,
+    journal::{Journal, JournalState},
+    plan::Plan,
+    wrap_compio_spawn_error,
+};
 
+/// Interval between checkpoints of the bytes copied to a journaled file
+const JOURNAL_CHECKPOINT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug)]
 pub enum Method {
     Copy,
     Move { cross_device_copy: bool },
@@ -23,6 +43,22 @@ pub struct Context {
     on_replace: Pin<Box<dyn OnReplace>>,
     pub(crate) op_sel: OperationSelection,
     replace_result_opt: Option<ReplaceResult>,
+    conflicts: ConflictPolicy,
+    journal_operation: Option<Operation>,
+    journal: Option<Journal>,
+    current_op: usize,
+    verify: Option<Checksum>,
+    preserve: Preserve,
+    /// Directories created by this operation, which get their attributes once filled
+    created_dirs: Vec<(PathBuf, PathBuf)>,
+    /// Bytes copied by completed ops, used for the transfer rate
+    completed_bytes: u64,
+    /// Bytes to be copied by all ops
+    operation_bytes: u64,
+    /// File created by the current op, removed if the op fails and the operation continues
+    partial: Option<PathBuf>,
+    /// Directories that could not be created, whose contents are skipped
+    failed_dirs: Vec<PathBuf>,
 }
 
 pub trait OnProgress: Fn(&Op, &Progress) + 'static {}
@@ -47,6 +83,17 @@ impl Context {
             on_replace: Box::pin(|_op| Box::pin(async { ReplaceResult::Cancel })),
             op_sel: OperationSelection::default(),
             replace_result_opt: None,
+            conflicts: ConflictPolicy::default(),
+            journal_operation: None,
+            journal: None,
+            current_op: 0,
+            verify: None,
+            preserve: Preserve::default(),
+            created_dirs: Vec::new(),
+            completed_bytes: 0,
+            operation_bytes: 0,
+            partial: None,
+            failed_dirs: Vec::new(),
         }
     }
 
@@ -55,8 +102,53 @@ impl Context {
         from_to_pairs: Vec<(PathBuf, PathBuf)>,
         method: Method,
     ) -> Result<bool, OperationError> {
-        let mut ops = Vec::new();
-        let mut cleanup_ops = Vec::new();
+        let to = from_to_pairs
+            .first()
+            .and_then(|(_, to_parent)| to_parent.parent())
+            .map(Path::to_path_buf);
+        let plan = self.plan(from_to_pairs, method).await?;
+        if let Some(to) = to {
+            let violations = constraints::check(&plan, &to);
+            if !violations.is_empty() {
+                return Err(OperationError::from_kind(
+                    OperationErrorType::Unsupported {
+                        fs_type: plan.limits.fs_type.clone(),
+                        violations,
+                    },
+                    &self.controller,
+                ));
+            }
+        }
+        for (from, to) in &plan.special {
+            let message = format!("{} is not a known file type", from.display());
+            if !self.controller.continue_on_error() {
+                return Err(OperationError::from_err(message, &self.controller));
+            }
+            self.controller.fail_item(FailedItem {
+                from: from.clone(),
+                to: to.clone(),
+                kind: OperationErrorType::Generic(message),
+            });
+        }
+        let ops = plan.into_ops();
+
+        if let Some(operation) = self.journal_operation.take() {
+            match Journal::create(&operation, &ops) {
+                Ok(journal) => self.journal = Some(journal),
+                Err(err) => log::warn!("failed to create journal for {:?}: {}", operation, err),
+            }
+        }
+
+        self.run_ops(ops, 0).await
+    }
+
+    /// Walk the sources and decide what to do with each item, without changing anything
+    pub async fn plan(
+        &mut self,
+        from_to_pairs: Vec<(PathBuf, PathBuf)>,
+        method: Method,
+    ) -> Result<Plan, OperationError> {
+        let mut plan = Plan::default();
         for (from_parent, to_parent) in from_to_pairs {
             self.controller
                 .check()
@@ -68,18 +160,35 @@ impl Context {
                 continue;
             }
 
-            for entry in WalkDir::new(&from_parent).into_iter() {
+            plan.limits = FsLimits::detect(&to_parent);
+            // Directories renamed because of a conflict, and the new path of their contents
+            let mut renamed_dirs = Vec::<(PathBuf, PathBuf)>::new();
+            let mut walker = WalkDir::new(&from_parent).into_iter();
+            while let Some(entry) = walker.next() {
                 self.controller
                     .check()
                     .await
                     .map_err(|s| OperationError::from_state(s, &self.controller))?;
 
-                let entry = entry.map_err(|err| {
-                    OperationError::from_err(
-                        format!("failed to walk directory {:?}: {}", from_parent, err),
-                        &self.controller,
-                    )
-                })?;
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(err) if self.controller.continue_on_error() => {
+                        let from = err.path().unwrap_or(&from_parent).to_path_buf();
+                        let kind = match err.io_error() {
+                            Some(io_err) => OperationErrorType::from_io(&from, io_err),
+                            None => OperationErrorType::Generic(err.to_string()),
+                        };
+                        let to = planned_to(&from_parent, &to_parent, &from);
+                        self.controller.fail_item(FailedItem { from, to, kind });
+                        continue;
+                    }
+                    Err(err) => {
+                        return Err(OperationError::from_err(
+                            format!("failed to walk directory {:?}: {}", from_parent, err),
+                            &self.controller,
+                        ));
+                    }
+                };
                 let file_type = entry.file_type();
                 let from = entry.into_path();
                 let kind = if file_type.is_dir() {
@@ -90,21 +199,27 @@ impl Context {
                         Method::Move { cross_device_copy } => OpKind::Move { cross_device_copy },
                     }
                 } else if file_type.is_symlink() {
-                    let target = fs::read_link(&from).map_err(|err| {
-                        OperationError::from_err(
-                            format!("failed to read link {:?}: {}", from, err),
-                            &self.controller,
-                        )
-                    })?;
-                    OpKind::Symlink { target }
+                    match fs::read_link(&from) {
+                        Ok(target) => OpKind::Symlink { target },
+                        Err(err) if self.controller.continue_on_error() => {
+                            let kind = OperationErrorType::from_io(&from, &err);
+                            let to = planned_to(&from_parent, &to_parent, &from);
+                            self.controller.fail_item(FailedItem { from, to, kind });
+                            continue;
+                        }
+                        Err(err) => {
+                            return Err(OperationError::from_err(
+                                format!("failed to read link {:?}: {}", from, err),
+                                &self.controller,
+                            ));
+                        }
+                    }
                 } else {
-                    //TODO: present dialog and allow continue
-                    return Err(OperationError::from_err(
-                        format!("{} is not a known file type", from.display()),
-                        &self.controller,
-                    ));
+                    let to = planned_to(&from_parent, &to_parent, &from);
+                    plan.special.push((from, to));
+                    continue;
                 };
-                let to = if from == from_parent {
+                let mut to = if from == from_parent {
                     // When copying a file, from matches from_parent, and to_parent must be used
                     to_parent.clone()
                 } else {
@@ -120,6 +235,39 @@ impl Context {
                     //TODO: ensure to is inside of to_parent?
                     to_parent.join(relative)
                 };
+                // Renames are applied in order, as nested ones refer to the renamed parent
+                for (old, new) in &renamed_dirs {
+                    if let Ok(relative) = to.strip_prefix(old) {
+                        to = new.join(relative);
+                    }
+                }
+                if self.conflicts.sanitize_names {
+                    if let Some(name) = to.file_name() {
+                        let name = constraints::destination_name(name, &plan.limits);
+                        let sanitized = to.with_file_name(name);
+                        if sanitized != to {
+                            if matches!(kind, OpKind::Mkdir) {
+                                renamed_dirs.push((to, sanitized.clone()));
+                            }
+                            to = sanitized;
+                        }
+                    }
+                }
+                if matches!(kind, OpKind::Mkdir) && to.is_dir() {
+                    match self.conflicts.dirs {
+                        DirPolicy::Merge => {}
+                        DirPolicy::Skip => {
+                            walker.skip_current_dir();
+                            plan.skipped.push(to);
+                            continue;
+                        }
+                        DirPolicy::Rename => {
+                            let new = self.conflicts.renamed_path(&to);
+                            renamed_dirs.push((to, new.clone()));
+                            to = new;
+                        }
+                    }
+                }
                 let op = Op {
                     kind,
                     from,
@@ -129,50 +277,140 @@ impl Context {
                         cleanup: Cell::new(false),
                     }),
                     is_cleanup: false,
+                    resume_offset: 0,
                 };
                 if matches!(method, Method::Move { .. }) {
                     if let Some(cleanup_op) = op.move_cleanup_op() {
-                        cleanup_ops.push(cleanup_op);
+                        plan.cleanup_ops.push(cleanup_op);
                     }
                 }
-                ops.push(op);
+                plan.ops.push(op);
             }
 
             self.op_sel.ignored.push(from_parent);
         }
+        Ok(plan)
+    }
 
-        // Add cleanup ops after standard ops, in reverse
-        for cleanup_op in cleanup_ops.into_iter().rev() {
-            ops.push(cleanup_op);
+    /// Resume an interrupted copy or move from its journal
+    pub async fn resume(&mut self, journal_path: &Path) -> Result<bool, OperationError> {
+        let journal = Journal::open(journal_path).map_err(|err| {
+            OperationError::from_err(
+                format!("failed to open journal {:?}: {}", journal_path, err),
+                &self.controller,
+            )
+        })?;
+        let mut state = JournalState::load(journal_path)
+            .and_then(|mut state| state.prepare_resume().map(|()| state))
+            .map_err(|err| {
+                OperationError::from_err(
+                    format!("failed to resume from journal {:?}: {}", journal_path, err),
+                    &self.controller,
+                )
+            })?;
+        log::info!(
+            "resuming {:?} at op {} of {}",
+            state.operation,
+            state.next,
+            state.ops.len()
+        );
+        if let Operation::Copy { paths, .. } | Operation::Move { paths, .. } = &state.operation {
+            self.op_sel.ignored.extend(paths.iter().cloned());
         }
+        // Directories created before the interruption still need their attributes. Those not
+        // created yet are added again by their op.
+        for &index in &state.created_dirs {
+            if let Some(op) = state.ops.get(index).filter(|op| op.to.is_dir()) {
+                self.created_dirs.push((op.from.clone(), op.to.clone()));
+            }
+        }
+        self.journal = Some(journal);
+        let first = state.next;
+        self.run_ops(std::mem::take(&mut state.ops), first).await
+    }
 
+    async fn run_ops(&mut self, ops: Vec<Op>, first: usize) -> Result<bool, OperationError> {
+        let mut result = self.run_ops_inner(ops, first).await;
+        if let Some(journal) = self.journal.take() {
+            match &mut result {
+                // Keep the journal, so the operation can be retried from the corrupted file
+                Err(OperationError {
+                    kind:
+                        OperationErrorType::ChecksumMismatch {
+                            journal: journal_path,
+                            ..
+                        },
+                }) => *journal_path = Some(journal.path().to_path_buf()),
+                // Otherwise the journal is only needed to recover from crashes
+                _ => journal.remove(),
+            }
+        }
+        result
+    }
+
+    async fn run_ops_inner(&mut self, ops: Vec<Op>, first: usize) -> Result<bool, OperationError> {
         let total_ops = ops.len();
-        for (current_ops, mut op) in ops.into_iter().enumerate() {
+        // Moves within a filesystem only rename, so their bytes are added if they need a copy
+        self.completed_bytes = 0;
+        self.operation_bytes = ops[first.min(total_ops)..]
+            .iter()
+            .filter(|op| matches!(op.kind, OpKind::Copy) && !op.skipped.normal.get())
+            .filter_map(|op| {
+                let metadata = fs::symlink_metadata(&op.from).ok()?;
+                Some(metadata.len().saturating_sub(op.resume_offset))
+            })
+            .sum();
+        self.failed_dirs.clear();
+        for (current_ops, mut op) in ops.into_iter().enumerate().skip(first) {
+            self.current_op = current_ops;
             self.controller
                 .check()
                 .await
                 .map_err(|s| OperationError::from_state(s, &self.controller))?;
 
+            // The failure of the directory was already reported
+            if !op.is_cleanup && self.failed_dirs.iter().any(|dir| op.to.starts_with(dir)) {
+                self.skip_op(&op);
+                continue;
+            }
+
             let progress = Progress {
                 current_ops,
                 total_ops,
                 current_bytes: 0,
                 total_bytes: None,
+                completed_bytes: self.completed_bytes,
+                operation_bytes: self.operation_bytes,
             };
             (self.on_progress)(&op, &progress);
-            if op.run(self, progress).await.map_err(|err| {
-                OperationError::from_err(
-                    format!(
-                        "failed to {:?} {:?} to {:?}: {}",
-                        op.kind, op.from, op.to, err
-                    ),
-                    &self.controller,
-                )
-            })? {
-                // The from path is ignored in the operation selection if it is a top level item
-                if self.op_sel.ignored.contains(&op.from) {
+            let top_level = !op.is_cleanup && self.op_sel.ignored.contains(&op.from);
+            // Undoing must not remove an item that existed before
+            let existing_to =
+                (top_level && fs::symlink_metadata(&op.to).is_ok()).then(|| op.to.clone());
+            let result = op.run(self, progress).await;
+            let partial = self.partial.take();
+            let ret = match result {
+                Ok(ret) => ret,
+                Err(err) => {
+                    self.op_failed(&op, partial, err)?;
+                    continue;
+                }
+            };
+            if ret {
+                if let Some(journal) = &mut self.journal {
+                    journal.done(current_ops);
+                }
+                // The from path is ignored in the operation selection if it is a top level item,
+                // unless it was skipped and `to` still points to a pre-existing item
+                if self.op_sel.ignored.contains(&op.from) && !op.skipped.normal.get() {
                     // So add the to path to the selection
                     self.op_sel.selected.push(op.to.clone());
+                    // A conflict resolved by keeping both items moved `to` to a new path
+                    if existing_to.as_ref() == Some(&op.to) {
+                        self.op_sel.merged.push(op.to.clone());
+                    } else if top_level {
+                        self.op_sel.created.push((op.from.clone(), op.to.clone()));
+                    }
                 }
             } else {
                 // Cancelled
@@ -180,9 +418,85 @@ impl Context {
             }
         }
 
+        // Adding entries changed the modification times, so directories are handled last
+        for (from, to) in std::mem::take(&mut self.created_dirs).into_iter().rev() {
+            self.preserve_attributes(&from, &to);
+        }
+
         Ok(true)
     }
 
+    /// Record the failure of `op` if the operation continues past failed items, otherwise
+    /// return the error that fails the operation
+    fn op_failed(
+        &mut self,
+        op: &Op,
+        partial: Option<PathBuf>,
+        err: Box<dyn Error>,
+    ) -> Result<(), OperationError> {
+        // Only errors of the item itself are skipped, not cancellation or other failures
+        let (kind, partial) = if let Some(OperationError {
+            kind: kind @ OperationErrorType::ChecksumMismatch { .. },
+        }) = err.downcast_ref::<OperationError>()
+        {
+            // The corrupted copy was already removed
+            (kind.clone(), None)
+        } else if let Some(io_err) = err.downcast_ref::<io::Error>() {
+            (
+                OperationErrorType::from_io(op.error_path(io_err), io_err),
+                partial,
+            )
+        } else {
+            return Err(OperationError::from_err(
+                format!(
+                    "failed to {:?} {:?} to {:?}: {}",
+                    op.kind, op.from, op.to, err
+                ),
+                &self.controller,
+            ));
+        };
+        if !self.controller.continue_on_error() {
+            return Err(OperationError::from_kind(kind, &self.controller));
+        }
+
+        log::warn!(
+            "failed to {:?} {:?} to {:?}, continuing: {}",
+            op.kind,
+            op.from,
+            op.to,
+            err
+        );
+        if let Some(partial) = partial {
+            if let Err(err) = fs::remove_file(&partial) {
+                log::warn!("failed to remove partial copy {:?}: {}", partial, err);
+            }
+        }
+        if matches!(op.kind, OpKind::Mkdir) {
+            self.failed_dirs.push(op.to.clone());
+        }
+        self.skip_op(op);
+        self.controller.fail_item(FailedItem {
+            from: op.from.clone(),
+            to: op.to.clone(),
+            kind,
+        });
+        Ok(())
+    }
+
+    /// Skip `op` and its cleanup, leaving the source of a move in place
+    fn skip_op(&mut self, op: &Op) {
+        op.skipped.normal.set(true);
+        if let Some(journal) = &mut self.journal {
+            journal.skipped(self.current_op);
+        }
+        if matches!(op.kind, OpKind::Copy) {
+            if let Ok(metadata) = fs::symlink_metadata(&op.from) {
+                let remaining = metadata.len().saturating_sub(op.resume_offset);
+                self.operation_bytes = self.operation_bytes.saturating_sub(remaining);
+            }
+        }
+    }
+
     pub fn on_progress<F: OnProgress>(mut self, f: F) -> Self {
         self.on_progress = Box::new(f);
         self
@@ -193,32 +507,154 @@ impl Context {
         self
     }
 
+    /// Journal the operation, so that it can be resumed if the application crashes
+    pub fn journal(mut self, operation: Operation) -> Self {
+        self.journal_operation = Some(operation);
+        self
+    }
+
+    /// Rules for items that already exist at the destination
+    pub fn conflicts(mut self, conflicts: ConflictPolicy) -> Self {
+        self.conflicts = conflicts;
+        self
+    }
+
+    /// Attributes copied from the source of each file
+    pub fn preserve(mut self, preserve: Preserve) -> Self {
+        self.preserve = preserve;
+        self
+    }
+
+    fn preserve_attributes(&self, from: &Path, to: &Path) {
+        let warnings = match fs::symlink_metadata(from) {
+            Ok(metadata) => self.preserve.apply(from, &metadata, to),
+            Err(err) => vec![format!("{}: {}", from.display(), err)],
+        };
+        for warning in warnings {
+            self.controller.warn(warning);
+        }
+    }
+
+    /// Verify copied files by comparing the hashes of their source and destination
+    pub fn verify(mut self, verify: Option<Checksum>) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    async fn digest(&mut self, path: &Path, checksum: Checksum) -> Result<Vec<u8>, Box<dyn Error>> {
+        let file = compio::fs::File::open(path).await?;
+        let mut hasher = checksum.hasher();
+        let mut buf_in = std::mem::take(&mut self.buf);
+        let mut pos = 0;
+        loop {
+            let BufResult(result, buf_out) = file.read_at(buf_in, pos).await;
+            let count = match result {
+                Ok(0) => {
+                    self.buf = buf_out;
+                    break;
+                }
+                Ok(count) => count,
+                Err(why) => {
+                    self.buf = buf_out;
+                    return Err(why.into());
+                }
+            };
+            hasher.update(&buf_out[..count]);
+            pos += count as u64;
+
+            if let Err(state) = self.controller.check().await {
+                self.buf = buf_out;
+                return Err(OperationError::from_state(state, &self.controller).into());
+            }
+
+            buf_in = buf_out;
+        }
+        Ok(hasher.finalize())
+    }
+
+    fn journal_started(&mut self, to: &Path) {
+        if let Some(journal) = &mut self.journal {
+            journal.started(self.current_op, to);
+        }
+    }
+
+    fn journal_renamed(&mut self, to: &Path) {
+        if let Some(journal) = &mut self.journal {
+            journal.renamed(self.current_op, to);
+        }
+    }
+
+    fn journal_rule(&mut self, files: FilePolicy) {
+        if let Some(journal) = &mut self.journal {
+            journal.rule(files);
+        }
+    }
+
+    /// Resolve a conflict of `op` with the existing file at its destination, journaling the
+    /// decision so a resumed operation does not decide again
     async fn replace(&mut self, op: &Op) -> Result<ControlFlow<bool, PathBuf>, Box<dyn Error>> {
-        let replace_result = match self.replace_result_opt {
-            Some(result) => result,
-            None => (self.on_replace)(op).await,
+        let replace_result = loop {
+            // Comparing contents reads both files
+            let conflicts = self.conflicts.clone();
+            let (from, to) = (op.from.clone(), op.to.clone());
+            let resolution = compio::runtime::spawn_blocking(move || {
+                let from_metadata = fs::symlink_metadata(&from)?;
+                conflicts.resolve_file(&Source::from_metadata(&from, &from_metadata), &to)
+            })
+            .await
+            .map_err(wrap_compio_spawn_error)??;
+            match resolution {
+                Resolution::Ask => {}
+                Resolution::Replace => break ReplaceResult::Replace(false),
+                Resolution::Skip => break ReplaceResult::Skip(false),
+                Resolution::Rename(to) => {
+                    self.journal_renamed(&to);
+                    return Ok(ControlFlow::Continue(to));
+                }
+            }
+            let result = match self.replace_result_opt {
+                Some(result) => result,
+                None => (self.on_replace)(op).await,
+            };
+            match result {
+                // The rule applies to this and all remaining conflicts
+                ReplaceResult::Policy(files) if files != FilePolicy::Ask => {
+                    self.conflicts.files = files;
+                    self.journal_rule(files);
+                }
+                result => break result,
+            }
         };
 
         match replace_result {
             ReplaceResult::Replace(apply_to_all) => {
-                if apply_to_all {
+                if apply_to_all && self.replace_result_opt.is_none() {
                     self.replace_result_opt = Some(replace_result);
+                    self.journal_rule(FilePolicy::Replace);
                 }
                 compio::fs::remove_file(&op.to).await?;
                 Ok(ControlFlow::Continue(op.to.clone()))
             }
             ReplaceResult::KeepBoth => match op.to.parent() {
-                Some(to_parent) => Ok(ControlFlow::Continue(copy_unique_path(&op.from, to_parent))),
+                Some(to_parent) => {
+                    let to = copy_unique_path(&op.from, to_parent);
+                    self.journal_renamed(&to);
+                    Ok(ControlFlow::Continue(to))
+                }
                 None => Err(format!("failed to get parent of {:?}", op.to).into()),
             },
             ReplaceResult::Skip(apply_to_all) => {
-                if apply_to_all {
+                if apply_to_all && self.replace_result_opt.is_none() {
                     self.replace_result_opt = Some(replace_result);
+                    self.journal_rule(FilePolicy::Skip);
                 }
                 op.skipped.normal.set(true);
+                if let Some(journal) = &mut self.journal {
+                    journal.skipped(self.current_op);
+                }
                 Ok(ControlFlow::Break(true))
             }
-            ReplaceResult::Cancel => Ok(ControlFlow::Break(false)),
+            ReplaceResult::Policy(_) | ReplaceResult::Cancel => Ok(ControlFlow::Break(false)),
         }
     }
 }
@@ -229,6 +665,10 @@ pub struct Progress {
     pub total_ops: usize,
     pub current_bytes: u64,
     pub total_bytes: Option<u64>,
+    /// Bytes copied by the ops before this one
+    pub completed_bytes: u64,
+    /// Bytes copied by the whole operation once it completes
+    pub operation_bytes: u64,
 }
 
 #[derive(Debug)]
@@ -256,9 +696,25 @@ pub struct Op {
     pub to: PathBuf,
     pub skipped: Rc<Skip>,
     pub is_cleanup: bool,
+    /// Bytes already copied to `to` before the operation was interrupted
+    pub resume_offset: u64,
 }
 
 impl Op {
+    /// Path that an error of this op most likely refers to
+    fn error_path(&self, err: &io::Error) -> &Path {
+        match self.kind {
+            OpKind::Remove | OpKind::Rmdir => &self.from,
+            OpKind::Copy | OpKind::Move { .. }
+                if err.kind() == io::ErrorKind::PermissionDenied
+                    && fs::File::open(&self.from).is_err() =>
+            {
+                &self.from
+            }
+            _ => &self.to,
+        }
+    }
+
     fn move_cleanup_op(&self) -> Option<Self> {
         let kind = match self.kind {
             OpKind::Copy | OpKind::Move { .. } | OpKind::Symlink { .. } => OpKind::Remove,
@@ -272,6 +728,7 @@ impl Op {
             to: self.to.clone(),
             skipped: self.skipped.clone(),
             is_cleanup: true,
+            resume_offset: 0,
         })
     }
 
@@ -285,8 +742,8 @@ impl Op {
         }
         match self.kind {
             OpKind::Copy => {
-                // Remove `to` if overwriting and it is an existing file
-                if self.to.is_file() {
+                // Remove `to` if overwriting and it is an existing file, unless resuming a copy
+                if self.resume_offset == 0 && self.to.is_file() {
                     match ctx.replace(self).await? {
                         ControlFlow::Continue(to) => {
                             self.to = to;
@@ -297,6 +754,10 @@ impl Op {
                     }
                 }
 
+                let resume_offset = self.resume_offset;
+                if resume_offset == 0 {
+                    ctx.journal_started(&self.to);
+                }
                 let (from_file, metadata, mut to_file) = futures::try_join!(
                     async {
                         compio::fs::OpenOptions::new()
@@ -308,71 +769,180 @@ impl Op {
                     // This is atomic and ensures `to` is not created by any other process
                     async {
                         compio::fs::OpenOptions::new()
-                            .create_new(true)
+                            .create_new(resume_offset == 0)
                             .write(true)
                             .open(&self.to)
                             .await
                     }
                 )?;
+                if resume_offset == 0 {
+                    ctx.partial = Some(self.to.clone());
+                }
 
                 progress.total_bytes = Some(metadata.len());
                 (ctx.on_progress)(self, &progress);
-                if let Err(err) = to_file.set_permissions(metadata.permissions()).await {
-                    // This error is not propagated upwards as some filesystems do not support setting permissions
-                    log::warn!("failed to set permissions for {:?}: {}", self.to, err);
+                if ctx.preserve.mode {
+                    if let Err(err) = to_file.set_permissions(metadata.permissions()).await {
+                        // This error is not propagated upwards as some filesystems do not support setting permissions
+                        log::warn!("failed to set permissions for {:?}: {}", self.to, err);
+                    }
                 }
 
+                let mut len = metadata.len();
+                // Kernel assisted copies need the raw file descriptors
+                let std_files = match fast_copy::Files::open(&self.from, &self.to).await {
+                    Ok(files) => Some(files),
+                    Err(err) => {
+                        log::warn!("failed to open {:?} for a fast copy: {}", self.from, err);
+                        None
+                    }
+                };
+
+                // A reflink shares the extents of `from`, which completes the copy immediately
+                let reflinked = match &std_files {
+                    Some(files) if resume_offset == 0 && len > 0 => match files.reflink().await {
+                        Ok(()) => true,
+                        Err(err) => {
+                            log::debug!("failed to reflink {:?}: {}", self.from, err);
+                            false
+                        }
+                    },
+                    _ => false,
+                };
+                let segments = match &std_files {
+                    Some(_) if reflinked => Vec::new(),
+                    Some(files) => files.data_segments(resume_offset, len).await,
+                    None => vec![(resume_offset, len)],
+                };
+
                 // Prevent spamming the progress callbacks.
                 let mut last_progress_update = Instant::now();
-                // io_uring/IOCP requires transferring ownership of the buffer to the kernel.
-                let mut buf_in = std::mem::take(&mut ctx.buf);
-                // Track where the current read/write position is at.
-                let mut pos = 0;
-
-                loop {
-                    let BufResult(result, buf_out) = from_file.read_at(buf_in, pos).await;
-
-                    let count = match result {
-                        Ok(0) => {
-                            ctx.buf = buf_out;
-                            break;
-                        }
-                        Ok(count) => count,
-                        Err(why) => {
-                            ctx.buf = buf_out;
-                            return Err(why.into());
-                        }
-                    };
+                let mut last_checkpoint = last_progress_update;
+                let mut kernel_copy = std_files.is_some();
+                progress.current_bytes = resume_offset;
 
-                    let BufResult(result, buf_out_slice) =
-                        to_file.write_at(buf_out.slice(..count), pos).await;
-                    let buf_out = buf_out_slice.into_inner();
+                'segments: for (start, end) in segments {
+                    // Track where the current read/write position is at.
+                    let mut pos = start;
+                    while pos < end {
+                        let count = match &std_files {
+                            Some(files) if kernel_copy => {
+                                match files.copy_range(pos, end - pos).await {
+                                    // `from` was truncated while copying
+                                    Ok(0) => {
+                                        len = pos;
+                                        break 'segments;
+                                    }
+                                    Ok(count) => count,
+                                    Err(err) if fast_copy::is_unsupported(&err) => {
+                                        log::debug!(
+                                            "failed to copy range of {:?}, using buffered copy: {}",
+                                            self.from,
+                                            err
+                                        );
+                                        kernel_copy = false;
+                                        continue;
+                                    }
+                                    Err(err) => return Err(err.into()),
+                                }
+                            }
+                            _ => {
+                                // io_uring/IOCP requires transferring ownership of the buffer to the kernel.
+                                let buf_in = std::mem::take(&mut ctx.buf);
+                                let BufResult(result, buf_out) =
+                                    from_file.read_at(buf_in, pos).await;
+                                let count = match result {
+                                    Ok(0) => {
+                                        ctx.buf = buf_out;
+                                        len = pos;
+                                        break 'segments;
+                                    }
+                                    // Data read past the end of the segment is not needed
+                                    Ok(count) => (count as u64).min(end - pos) as usize,
+                                    Err(why) => {
+                                        ctx.buf = buf_out;
+                                        return Err(why.into());
+                                    }
+                                };
 
-                    if let Err(why) = result {
-                        ctx.buf = buf_out;
-                        return Err(why.into());
-                    }
+                                let BufResult(result, buf_out_slice) =
+                                    to_file.write_at(buf_out.slice(..count), pos).await;
+                                ctx.buf = buf_out_slice.into_inner();
+                                result?;
+                                count as u64
+                            }
+                        };
 
-                    progress.current_bytes += count as u64;
-                    pos += count as u64;
+                        pos += count;
+                        // Holes count as copied, as they are preserved without writing them
+                        progress.current_bytes = pos;
 
-                    // Avoid spamming progress messages too early.
-                    let current = Instant::now();
-                    if current.duration_since(last_progress_update).as_millis() > 49 {
-                        last_progress_update = current;
-                        (ctx.on_progress)(self, &progress);
+                        // Avoid spamming progress messages too early.
+                        let current = Instant::now();
+                        if current.duration_since(last_progress_update).as_millis() > 49 {
+                            last_progress_update = current;
+                            (ctx.on_progress)(self, &progress);
 
-                        // Also check if the progress was cancelled.
-                        if let Err(state) = ctx.controller.check().await {
-                            ctx.buf = buf_out;
-                            return Err(OperationError::from_state(state, &ctx.controller).into());
+                            // Also check if the progress was cancelled.
+                            if let Err(state) = ctx.controller.check().await {
+                                return Err(
+                                    OperationError::from_state(state, &ctx.controller).into()
+                                );
+                            }
+
+                            // Only bytes that reached the disk may be recorded
+                            if ctx.journal.is_some()
+                                && current.duration_since(last_checkpoint) >= JOURNAL_CHECKPOINT
+                            {
+                                last_checkpoint = current;
+                                to_file.sync_data().await?;
+                                if let Some(journal) = &mut ctx.journal {
+                                    journal.bytes(ctx.current_op, pos);
+                                }
+                            }
                         }
                     }
+                }
 
-                    buf_in = buf_out;
+                // Trailing holes are not written, so the length must be set explicitly
+                if let Some(files) = &std_files {
+                    if !reflinked {
+                        files.set_len(len).await?;
+                    }
                 }
+                progress.current_bytes = len;
+                (ctx.on_progress)(self, &progress);
+                ctx.completed_bytes += len.saturating_sub(resume_offset);
 
                 to_file.sync_all().await?;
+
+                if let Some(checksum) = ctx.verify {
+                    // Read the destination back from the disk instead of the page cache
+                    drop_cached_pages(&self.to);
+                    let from_digest = ctx.digest(&self.from, checksum).await?;
+                    let to_digest = ctx.digest(&self.to, checksum).await?;
+                    if from_digest != to_digest {
+                        log::warn!(
+                            "{} of {:?} does not match {:?}",
+                            checksum.name(),
+                            self.to,
+                            self.from
+                        );
+                        // Never leave a corrupted copy behind
+                        compio::fs::remove_file(&self.to).await?;
+                        return Err(OperationError {
+                            kind: OperationErrorType::ChecksumMismatch {
+                                from: self.from.clone(),
+                                path: self.to.clone(),
+                                journal: None,
+                            },
+                        }
+                        .into());
+                    }
+                }
+
+                // Timestamps are only final once nothing else writes to or reads from `to`
+                ctx.preserve_attributes(&self.from, &self.to);
             }
             OpKind::Move { cross_device_copy } => {
                 // Remove `to` if overwriting and it is an existing file
@@ -386,6 +956,7 @@ impl Op {
                         }
                     }
                 }
+                ctx.journal_started(&self.to);
                 // This is atomic and ensures `to` is not created by any other process
                 match compio::fs::hard_link(&self.from, &self.to).await {
                     Ok(()) => {}
@@ -400,6 +971,13 @@ impl Op {
                             if cross_device_copy {
                                 // Do not clean up if cross_device_copy is set
                                 self.skipped.cleanup.set(true);
+                                if let Some(journal) = &mut ctx.journal {
+                                    journal.cleanup_skipped(ctx.current_op);
+                                }
+                            }
+                            if let Ok(metadata) = fs::symlink_metadata(&self.from) {
+                                ctx.operation_bytes += metadata.len();
+                                progress.operation_bytes = ctx.operation_bytes;
                             }
                             // Try standard copy if hard link fails with cross device error
                             let mut copy_op = Op {
@@ -408,6 +986,7 @@ impl Op {
                                 to: self.to.clone(),
                                 skipped: self.skipped.clone(),
                                 is_cleanup: self.is_cleanup,
+                                resume_offset: 0,
                             };
                             return Box::pin(copy_op.run(ctx, progress)).await;
                         } else {
@@ -417,14 +996,26 @@ impl Op {
                 }
             }
             OpKind::Mkdir => {
+                // Attributes of existing directories that are merged into are left alone
+                if !self.to.exists() {
+                    ctx.created_dirs.push((self.from.clone(), self.to.clone()));
+                    if let Some(journal) = &mut ctx.journal {
+                        journal.created(ctx.current_op);
+                    }
+                }
                 compio::fs::create_dir_all(&self.to).await?;
             }
             OpKind::Remove => {
                 compio::fs::remove_file(&self.from).await?;
             }
-            OpKind::Rmdir => {
-                compio::fs::remove_dir(&self.from).await?;
-            }
+            OpKind::Rmdir => match compio::fs::remove_dir(&self.from).await {
+                Ok(()) => {}
+                // Items that were skipped because of a conflict are left in place
+                Err(err) if err.kind() == io::ErrorKind::DirectoryNotEmpty => {
+                    log::info!("keeping {:?} as it is not empty", self.from);
+                }
+                Err(err) => return Err(err.into()),
+            },
             OpKind::Symlink { ref target } => {
                 // Remove `to` if overwriting and it is an existing file
                 if self.to.is_file() {
@@ -437,6 +1028,7 @@ impl Op {
                         }
                     }
                 }
+                ctx.journal_started(&self.to);
                 #[cfg(unix)]
                 {
                     std::os::unix::fs::symlink(target, &self.to)?;
@@ -449,8 +1041,32 @@ impl Op {
                         std::os::windows::fs::symlink_file(target, &self.to)?;
                     }
                 }
+                ctx.preserve_attributes(&self.from, &self.to);
             }
         }
         Ok(true)
     }
 }
+
+/// Destination of an item that failed while planning, before any conflicts are resolved
+fn planned_to(from_parent: &Path, to_parent: &Path, from: &Path) -> PathBuf {
+    match from.strip_prefix(from_parent) {
+        Ok(relative) if !relative.as_os_str().is_empty() => to_parent.join(relative),
+        _ => to_parent.to_path_buf(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn drop_cached_pages(path: &Path) {
+    use std::os::fd::AsRawFd;
+    // Only clean pages are dropped, so this must happen after syncing
+    match fs::File::open(path) {
+        Ok(file) => unsafe {
+            libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+        },
+        Err(err) => log::warn!("failed to open {:?} to drop cached pages: {}", path, err),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn drop_cached_pages(_path: &Path) {}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+use crate::fl;
+
+/// Limits on what extracting an archive may write, to stop decompression bombs
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ExtractLimits {
+    /// Total size of the extracted files in bytes
+    pub max_size: Option<u64>,
+    /// Extracted size divided by the size of the archive
+    pub max_ratio: Option<u64>,
+}
+
+impl ExtractLimits {
+    /// Size limits offered in the settings
+    pub fn sizes() -> &'static [Option<u64>] {
+        &[
+            None,
+            Some(1_000_000_000),
+            Some(10_000_000_000),
+            Some(100_000_000_000),
+        ]
+    }
+
+    /// Ratio limits offered in the settings
+    pub fn ratios() -> &'static [Option<u64>] {
+        &[None, Some(100), Some(1_000), Some(10_000)]
+    }
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            max_ratio: Some(1_000),
+        }
+    }
+}
+
+/// Why an archive entry was refused while extracting
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UnsafeEntry {
+    /// The path is absolute or goes up with `..`
+    Traversal,
+    /// A symbolic link points outside of the destination, or an entry is written through one
+    SymlinkEscape,
+    /// A hard link points outside of the destination
+    HardLinkEscape,
+    /// A device node or FIFO
+    Special,
+    /// The extracted files are larger than [`ExtractLimits::max_size`]
+    TooLarge,
+    /// The extracted files are larger than [`ExtractLimits::max_ratio`] allows
+    TooCompressed,
+}
+
+impl std::fmt::Display for UnsafeEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&match self {
+            Self::Traversal => fl!("unsafe-entry-traversal"),
+            Self::SymlinkEscape => fl!("unsafe-entry-symlink"),
+            Self::HardLinkEscape => fl!("unsafe-entry-hard-link"),
+            Self::Special => fl!("unsafe-entry-special"),
+            Self::TooLarge => fl!("unsafe-entry-too-large"),
+            Self::TooCompressed => fl!("unsafe-entry-too-compressed"),
+        })
+    }
+}
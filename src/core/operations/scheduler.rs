@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+/// Operation waiting for the devices it touches
+#[derive(Debug)]
+struct Queued {
+    id: u64,
+    /// `None` while the devices are looked up, which keeps the operation from starting
+    devices: Option<Vec<u64>>,
+    /// Paused by the user before it started, letting later operations on its devices run
+    held: bool,
+}
+
+/// Decides when pending operations start. Operations touching the same device run one at a
+/// time in queue order, while operations on unrelated devices run in parallel.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    queue: Vec<Queued>,
+    running: BTreeMap<u64, Vec<u64>>,
+}
+
+impl Scheduler {
+    /// Add an operation to the end of the queue. Operations without devices start immediately.
+    pub fn push(&mut self, id: u64, devices: Vec<u64>) {
+        self.queue.push(Queued {
+            id,
+            devices: Some(devices),
+            held: false,
+        });
+    }
+
+    /// Add an operation to the end of the queue, which waits for [`Self::set_devices`]. It does
+    /// not hold back later operations, as its devices are not known yet.
+    pub fn push_unknown(&mut self, id: u64) {
+        self.queue.push(Queued {
+            id,
+            devices: None,
+            held: false,
+        });
+    }
+
+    /// Set the devices of an operation added with [`Self::push_unknown`], returning false if it
+    /// is no longer queued
+    pub fn set_devices(&mut self, id: u64, devices: Vec<u64>) -> bool {
+        match self.queue.iter_mut().find(|queued| queued.id == id) {
+            Some(queued) => {
+                queued.devices = Some(devices);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove operations from the queue that may run now, in queue order
+    pub fn start_ready(&mut self) -> Vec<u64> {
+        let mut busy: Vec<u64> = self.running.values().flatten().copied().collect();
+        let mut ready = Vec::new();
+        let mut i = 0;
+        while i < self.queue.len() {
+            let queued = &self.queue[i];
+            let Some(devices) = &queued.devices else {
+                i += 1;
+                continue;
+            };
+            let blocked = devices.iter().any(|device| busy.contains(device));
+            if queued.held || blocked {
+                // Later operations may not overtake this one on its devices
+                if !queued.held {
+                    busy.extend(devices);
+                }
+                i += 1;
+                continue;
+            }
+            let queued = self.queue.remove(i);
+            let devices = queued.devices.unwrap_or_default();
+            busy.extend(&devices);
+            self.running.insert(queued.id, devices);
+            ready.push(queued.id);
+        }
+        ready
+    }
+
+    /// Forget a finished or cancelled operation
+    pub fn finish(&mut self, id: u64) {
+        self.running.remove(&id);
+        self.queue.retain(|queued| queued.id != id);
+    }
+
+    pub fn is_queued(&self, id: u64) -> bool {
+        self.position(id).is_some()
+    }
+
+    /// Position of a waiting operation, starting at 0
+    pub fn position(&self, id: u64) -> Option<usize> {
+        self.queue.iter().position(|queued| queued.id == id)
+    }
+
+    /// Waiting operations in queue order
+    pub fn queued(&self) -> impl Iterator<Item = u64> + '_ {
+        self.queue.iter().map(|queued| queued.id)
+    }
+
+    /// Keep a waiting operation from starting, returning false if it already started
+    pub fn hold(&mut self, id: u64, held: bool) -> bool {
+        match self.queue.iter_mut().find(|queued| queued.id == id) {
+            Some(queued) => {
+                queued.held = held;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move a waiting operation one place towards the front of the queue, or the back
+    pub fn reorder(&mut self, id: u64, earlier: bool) {
+        let Some(i) = self.position(id) else {
+            return;
+        };
+        if earlier && i > 0 {
+            self.queue.swap(i, i - 1);
+        } else if !earlier && i + 1 < self.queue.len() {
+            self.queue.swap(i, i + 1);
+        }
+    }
+
+    /// Move a waiting operation to the front of the queue, so it runs next on its devices
+    pub fn prioritize(&mut self, id: u64) {
+        if let Some(i) = self.position(id) {
+            let queued = self.queue.remove(i);
+            self.queue.insert(0, queued);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scheduler;
+
+    #[test]
+    fn same_device_is_serialized() {
+        let mut scheduler = Scheduler::default();
+        scheduler.push(0, vec![1]);
+        scheduler.push(1, vec![1, 2]);
+        scheduler.push(2, vec![3]);
+        scheduler.push(3, vec![2]);
+        scheduler.push(4, Vec::new());
+        // 3 may not overtake 1, which is waiting for device 2
+        assert_eq!(scheduler.start_ready(), vec![0, 2, 4]);
+        assert_eq!(scheduler.start_ready(), Vec::<u64>::new());
+
+        scheduler.finish(0);
+        assert_eq!(scheduler.start_ready(), vec![1]);
+        scheduler.finish(1);
+        assert_eq!(scheduler.start_ready(), vec![3]);
+    }
+
+    #[test]
+    fn held_and_prioritized() {
+        let mut scheduler = Scheduler::default();
+        scheduler.push(0, vec![1]);
+        scheduler.push(1, vec![1]);
+        scheduler.push(2, vec![1]);
+        scheduler.push(3, vec![1]);
+        assert_eq!(scheduler.start_ready(), vec![0]);
+
+        assert!(scheduler.hold(1, true));
+        scheduler.prioritize(3);
+        assert_eq!(scheduler.queued().collect::<Vec<_>>(), vec![3, 1, 2]);
+        scheduler.reorder(2, true);
+        assert_eq!(scheduler.queued().collect::<Vec<_>>(), vec![3, 2, 1]);
+
+        scheduler.finish(0);
+        assert_eq!(scheduler.start_ready(), vec![3]);
+        scheduler.finish(3);
+        // The held operation lets the next one run
+        scheduler.reorder(2, false);
+        assert_eq!(scheduler.start_ready(), vec![2]);
+        assert!(!scheduler.hold(2, true));
+    }
+
+    #[test]
+    fn unknown_devices_wait() {
+        let mut scheduler = Scheduler::default();
+        scheduler.push_unknown(0);
+        scheduler.push(1, vec![1]);
+        assert_eq!(scheduler.start_ready(), vec![1]);
+
+        assert!(scheduler.set_devices(0, vec![1]));
+        assert_eq!(scheduler.start_ready(), Vec::<u64>::new());
+        scheduler.finish(1);
+        assert_eq!(scheduler.start_ready(), vec![0]);
+        assert!(!scheduler.set_devices(0, Vec::new()));
+    }
+}
@@ -0,0 +1,357 @@
+// Overwriting the contents of files before removing them
+//
+// Each pass overwrites the whole file and syncs it to the disk. Names are replaced with
+// random ones before unlinking, so they do not remain in the directory either. This only
+// works if the filesystem writes in place, which is not true for copy-on-write filesystems,
+// and flash storage remaps writes internally.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::RandomState,
+    fs,
+    hash::BuildHasher,
+    io::{self, Seek, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use walkdir::WalkDir;
+
+use super::{Controller, OperationError, OperationErrorType};
+use crate::{fl, tab};
+
+/// Data written by one pass over a file
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ShredPass {
+    Zeros,
+    Random,
+}
+
+impl ShredPass {
+    /// Combinations of passes offered in the settings
+    pub fn presets() -> &'static [&'static [Self]] {
+        &[
+            &[Self::Zeros],
+            &[Self::Random],
+            &[Self::Random, Self::Random, Self::Random, Self::Zeros],
+            &[Self::Random; 7],
+        ]
+    }
+
+    pub fn default_passes() -> Vec<Self> {
+        Self::presets()[2].to_vec()
+    }
+}
+
+/// Fast generator for overwriting data and names, which do not need to be unpredictable
+struct Rng(u64);
+
+impl Rng {
+    fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        Self(RandomState::new().hash_one(nanos) | 1)
+    }
+
+    // xorshift64*
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+/// Why overwriting `path` may leave its contents on the disk, if it may
+pub fn unreliable_reason(path: &Path) -> Option<String> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    match tab::fs_kind(&metadata) {
+        tab::FsKind::Local => {}
+        tab::FsKind::Remote | tab::FsKind::Gvfs => return Some(fl!("shred-unreliable-remote")),
+    }
+    if is_copy_on_write(path) {
+        return Some(fl!("shred-unreliable-copy-on-write"));
+    }
+    if is_flash(&metadata) {
+        return Some(fl!("shred-unreliable-flash"));
+    }
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn is_copy_on_write(path: &Path) -> bool {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    const BTRFS_SUPER_MAGIC: u32 = 0x9123_683e;
+    const ZFS_SUPER_MAGIC: u32 = 0x2fc1_2fc1;
+    const BCACHEFS_SUPER_MAGIC: u32 = 0xca45_1a4e;
+    const F2FS_SUPER_MAGIC: u32 = 0xf2f5_2010;
+    const NILFS_SUPER_MAGIC: u32 = 0x3434;
+    const JFFS2_SUPER_MAGIC: u32 = 0x72b6;
+    const UBIFS_SUPER_MAGIC: u32 = 0x2405_1905;
+
+    let Ok(path_c) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut statfs = unsafe { std::mem::zeroed::<libc::statfs>() };
+    if unsafe { libc::statfs(path_c.as_ptr(), &mut statfs) } != 0 {
+        log::warn!(
+            "failed to statfs {:?}: {}",
+            path,
+            io::Error::last_os_error()
+        );
+        return false;
+    }
+    // Log-structured filesystems never overwrite in place either
+    matches!(
+        // The magic numbers fit in 32 bits, which is the size of f_type on some targets
+        statfs.f_type as u32,
+        BTRFS_SUPER_MAGIC
+            | ZFS_SUPER_MAGIC
+            | BCACHEFS_SUPER_MAGIC
+            | F2FS_SUPER_MAGIC
+            | NILFS_SUPER_MAGIC
+            | JFFS2_SUPER_MAGIC
+            | UBIFS_SUPER_MAGIC
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_copy_on_write(_path: &Path) -> bool {
+    false
+}
+
+/// Whether the block device holding `metadata` is not a spinning disk
+#[cfg(target_os = "linux")]
+fn is_flash(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let dev = metadata.dev();
+    let sys = PathBuf::from(format!(
+        "/sys/dev/block/{}:{}",
+        libc::major(dev),
+        libc::minor(dev)
+    ));
+    let Ok(device) = fs::canonicalize(sys) else {
+        return false;
+    };
+    // Partitions share the queue of their disk
+    for dir in [Some(device.as_path()), device.parent()]
+        .into_iter()
+        .flatten()
+    {
+        if let Ok(rotational) = fs::read_to_string(dir.join("queue/rotational")) {
+            return rotational.trim() == "0";
+        }
+    }
+    false
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_flash(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Overwrite `paths` and everything inside of them with `passes`, then remove them
+pub fn shred(
+    paths: &[PathBuf],
+    passes: &[ShredPass],
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    let io_err = |path: &Path, err: io::Error| {
+        OperationError::from_kind(OperationErrorType::from_io(path, &err), controller)
+    };
+
+    // Contents come before their directory, which is removed once empty. A selected link to a
+    // directory is removed without touching the directory it points to
+    let mut entries = Vec::new();
+    for path in paths {
+        for entry in WalkDir::new(path)
+            .contents_first(true)
+            .follow_root_links(false)
+        {
+            let entry = entry.map_err(|err| OperationError::from_err(err, controller))?;
+            let len = if entry.file_type().is_file() {
+                entry
+                    .metadata()
+                    .map_err(|err| OperationError::from_err(err, controller))?
+                    .len()
+            } else {
+                0
+            };
+            entries.push((entry.into_path(), len));
+        }
+    }
+    let total_bytes = entries.iter().map(|(_, len)| len).sum::<u64>() * passes.len() as u64;
+
+    let mut rng = Rng::new();
+    let mut buf = vec![0; 128 * 1024];
+    let mut written = 0;
+    for (path, len) in entries {
+        let metadata = fs::symlink_metadata(&path).map_err(|err| io_err(&path, err))?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if metadata.is_file() {
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .map_err(|err| io_err(&path, err))?;
+            for &pass in passes {
+                overwrite(&mut file, len, pass, &mut rng, &mut buf, |count| {
+                    written += count;
+                    controller.set_transferred(Some(name.clone()), written, total_bytes);
+                    if total_bytes > 0 {
+                        controller.set_progress(written as f32 / total_bytes as f32);
+                    }
+                    futures::executor::block_on(controller.check())
+                        .map_err(|state| OperationError::from_state(state, controller))
+                })
+                .map_err(|err| match err {
+                    Overwrite::Io(err) => io_err(&path, err),
+                    Overwrite::Stopped(err) => err,
+                })?;
+            }
+        }
+
+        let path = rename_random(&path, &mut rng).map_err(|err| io_err(&path, err))?;
+        if metadata.is_dir() {
+            fs::remove_dir(&path)
+        } else {
+            fs::remove_file(&path)
+        }
+        .map_err(|err| io_err(&path, err))?;
+    }
+    Ok(())
+}
+
+enum Overwrite {
+    Io(io::Error),
+    /// The operation was paused and cancelled, or failed
+    Stopped(OperationError),
+}
+
+/// Overwrite the first `len` bytes of `file` with one pass and sync them to the disk.
+/// `on_written` is called with the bytes written by each write.
+fn overwrite(
+    file: &mut fs::File,
+    len: u64,
+    pass: ShredPass,
+    rng: &mut Rng,
+    buf: &mut [u8],
+    mut on_written: impl FnMut(u64) -> Result<(), OperationError>,
+) -> Result<(), Overwrite> {
+    file.seek(io::SeekFrom::Start(0)).map_err(Overwrite::Io)?;
+    if pass == ShredPass::Zeros {
+        buf.fill(0);
+    }
+    let mut pos = 0;
+    while pos < len {
+        let count = (len - pos).min(buf.len() as u64) as usize;
+        if pass == ShredPass::Random {
+            rng.fill(&mut buf[..count]);
+        }
+        file.write_all(&buf[..count]).map_err(Overwrite::Io)?;
+        pos += count as u64;
+        on_written(count as u64).map_err(Overwrite::Stopped)?;
+    }
+    file.sync_data().map_err(Overwrite::Io)
+}
+
+/// Rename `path` to a random name of the same length, returning the new path
+fn rename_random(path: &Path, rng: &mut Rng) -> io::Result<PathBuf> {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(path.to_path_buf());
+    };
+    let name_len = name.len().max(1);
+    for _ in 0..16 {
+        let name: String = (0..name_len)
+            .map(|_| CHARS[(rng.next() % CHARS.len() as u64) as usize] as char)
+            .collect();
+        let new_path = parent.join(name);
+        if fs::symlink_metadata(&new_path).is_err() {
+            fs::rename(path, &new_path)?;
+            return Ok(new_path);
+        }
+    }
+    // Unlikely unless the directory is full of short names, removing it is what matters
+    Ok(path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io};
+
+    use tempfile::tempdir;
+
+    use super::{Rng, ShredPass, overwrite, shred};
+    use crate::core::operations::Controller;
+
+    #[test]
+    fn passes_overwrite_contents() -> io::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("secret");
+        fs::write(&path, vec![0xaa; 300 * 1024])?;
+
+        let mut file = fs::OpenOptions::new().write(true).open(&path)?;
+        let mut buf = vec![0; 128 * 1024];
+        let mut written = 0;
+        let result = overwrite(
+            &mut file,
+            300 * 1024,
+            ShredPass::Zeros,
+            &mut Rng::new(),
+            &mut buf,
+            |count| {
+                written += count;
+                Ok(())
+            },
+        );
+        assert!(result.is_ok());
+        assert_eq!(written, 300 * 1024);
+        assert!(fs::read(&path)?.iter().all(|&byte| byte == 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn shred_removes_everything() -> io::Result<()> {
+        let dir = tempdir()?;
+        let folder = dir.path().join("folder");
+        fs::create_dir(&folder)?;
+        fs::write(folder.join("a"), b"secret")?;
+        fs::write(dir.path().join("b"), b"secret")?;
+
+        let paths = [folder, dir.path().join("b")];
+        let controller = Controller::default();
+        assert!(shred(&paths, &ShredPass::default_passes(), &controller).is_ok());
+        assert_eq!(fs::read_dir(dir.path())?.count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn shred_keeps_link_target() -> io::Result<()> {
+        let dir = tempdir()?;
+        let target = dir.path().join("target");
+        fs::create_dir(&target)?;
+        fs::write(target.join("a"), b"keep")?;
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&target, &link)?;
+
+        let controller = Controller::default();
+        assert!(shred(&[link.clone()], &ShredPass::default_passes(), &controller).is_ok());
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert_eq!(fs::read(target.join("a"))?, b"keep");
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,478 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{ConflictPolicy, Operation, OperationSelection};
+
+/// Maximum number of entries kept on the undo stack
+const MAX_UNDO_ENTRIES: usize = 64;
+
+/// State of a path that must still hold for an undo to be safe
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PathState {
+    is_dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+    mode: u32,
+}
+
+impl PathState {
+    pub fn capture(path: &Path) -> Option<Self> {
+        let metadata = fs::symlink_metadata(path).ok()?;
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.permissions().mode() & 0o7777
+        };
+        #[cfg(not(unix))]
+        let mode = 0;
+        Some(Self {
+            is_dir: metadata.is_dir(),
+            // Directory sizes are filesystem specific, the modified time covers their contents
+            len: if metadata.is_dir() { 0 } else { metadata.len() },
+            modified: metadata.modified().ok(),
+            mode,
+        })
+    }
+}
+
+fn capture_all(paths: &[PathBuf]) -> Vec<(PathBuf, Option<PathState>)> {
+    paths
+        .iter()
+        .map(|path| (path.clone(), PathState::capture(path)))
+        .collect()
+}
+
+fn is_vacant(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_err()
+}
+
+/// State captured before an operation runs, needed to build its inverse
+#[derive(Clone, Debug, Default)]
+pub struct UndoSnapshot(Vec<(PathBuf, Option<PathState>)>);
+
+impl UndoSnapshot {
+    pub fn capture(operation: &Operation) -> Self {
+        match operation {
+            Operation::SetPermissions { path, .. } => Self(capture_all(std::slice::from_ref(path))),
+            _ => Self::default(),
+        }
+    }
+
+    fn get(&self, path: &Path) -> Option<&PathState> {
+        self.0
+            .iter()
+            .find(|(snapshot_path, _)| snapshot_path == path)
+            .and_then(|(_, state)| state.as_ref())
+    }
+}
+
+/// What has to be done to revert an operation
+#[derive(Clone, Debug)]
+pub enum Inverse {
+    Operation(Operation),
+    /// Trash items must be looked up by their original path before they can be restored
+    RestoreFromTrash(Vec<PathBuf>),
+}
+
+/// A completed operation that can be undone
+#[derive(Clone, Debug)]
+pub struct UndoEntry {
+    pub operation: Operation,
+    pub inverse: Inverse,
+    /// Paths read by the inverse, with their state once the operation completed
+    expected: Vec<(PathBuf, Option<PathState>)>,
+    /// Paths written by the inverse, which must not exist when undoing
+    vacant: Vec<PathBuf>,
+}
+
+impl UndoEntry {
+    /// Build the undo entry of a completed operation, if it can be undone
+    pub fn new(
+        operation: Operation,
+        before: &UndoSnapshot,
+        op_sel: &OperationSelection,
+    ) -> Option<Self> {
+        let (inverse, expected, vacant) = match &operation {
+            Operation::Compress { to, .. } => (
+                Inverse::Operation(Operation::Delete {
+                    paths: vec![to.clone()],
+                }),
+                vec![to.clone()],
+                Vec::new(),
+            ),
+            Operation::Copy { .. } | Operation::Extract { .. } | Operation::Link { .. } => {
+                // Deleting items merged into existing ones would delete what was there before
+                if op_sel.selected.is_empty() || !op_sel.merged.is_empty() {
+                    return None;
+                }
+                (
+                    Inverse::Operation(Operation::Delete {
+                        paths: op_sel.selected.clone(),
+                    }),
+                    op_sel.selected.clone(),
+                    Vec::new(),
+                )
+            }
+            Operation::Delete { paths } => (
+                Inverse::RestoreFromTrash(paths.clone()),
+                Vec::new(),
+                paths.clone(),
+            ),
+            Operation::Move {
+                verify, preserve, ..
+            } => {
+                // Items that replaced existing ones cannot be moved back without losing those
+                if op_sel.created.is_empty() || !op_sel.merged.is_empty() {
+                    return None;
+                }
+                // Items are moved back to their original names, which a conflict or sanitizing
+                // may have changed
+                let mut by_parent = BTreeMap::<&Path, Vec<(PathBuf, PathBuf)>>::new();
+                for (from, moved) in op_sel.created.iter() {
+                    by_parent
+                        .entry(from.parent()?)
+                        .or_default()
+                        .push((moved.clone(), from.clone()));
+                }
+                // Items moved from several folders would need several moves to be reverted
+                //TODO: support undoing moves from search results and recents
+                if by_parent.len() != 1 {
+                    return None;
+                }
+                let (parent, items) = by_parent.pop_first()?;
+                let (moved, sources): (Vec<_>, Vec<_>) = items.iter().cloned().unzip();
+                (
+                    Inverse::Operation(Operation::Retry {
+                        items,
+                        operation: Box::new(Operation::Move {
+                            paths: moved.clone(),
+                            to: parent.to_path_buf(),
+                            cross_device_copy: false,
+                            verify: *verify,
+                            preserve: *preserve,
+                            conflicts: ConflictPolicy::default(),
+                        }),
+                    }),
+                    moved,
+                    sources,
+                )
+            }
+            Operation::NewFile { path } | Operation::NewFolder { path } => (
+                Inverse::Operation(Operation::PermanentlyDelete {
+                    paths: vec![path.clone()],
+                }),
+                vec![path.clone()],
+                Vec::new(),
+            ),
+            Operation::Rename { from, to } => (
+                Inverse::Operation(Operation::Rename {
+                    from: to.clone(),
+                    to: from.clone(),
+                }),
+                vec![to.clone()],
+                vec![from.clone()],
+            ),
+            Operation::SetPermissions { path, .. } => {
+                let previous = before.get(path)?;
+                (
+                    Inverse::Operation(Operation::SetPermissions {
+                        path: path.clone(),
+                        mode: previous.mode,
+                    }),
+                    vec![path.clone()],
+                    Vec::new(),
+                )
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            operation,
+            inverse,
+            expected: capture_all(&expected),
+            vacant,
+        })
+    }
+
+    /// Make sure nothing changed since the operation completed, returning the first changed path
+    pub fn check_undo(&self) -> Result<(), &Path> {
+        for (path, state) in self.expected.iter() {
+            if PathState::capture(path) != *state {
+                return Err(path);
+            }
+        }
+        for path in self.vacant.iter() {
+            if !is_vacant(path) {
+                return Err(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure the operation can be performed again without overwriting anything
+    pub fn check_redo(&self) -> Result<(), &Path> {
+        match &self.operation {
+            Operation::Compress { to: path, .. }
+            | Operation::NewFile { path }
+            | Operation::NewFolder { path }
+            | Operation::Rename { to: path, .. } => {
+                if !is_vacant(path) {
+                    return Err(path);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// How the completion of a pending operation affects the undo stack
+#[derive(Clone, Debug)]
+pub enum PendingUndo {
+    /// A new operation, recorded on completion
+    Record(UndoSnapshot),
+    /// The inverse of an undone entry, moved to the redo stack on completion
+    Undo(UndoEntry),
+    /// An operation performed again, recorded without clearing the redo stack
+    Redo(UndoSnapshot),
+}
+
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: Vec<UndoEntry>,
+    redo: Vec<UndoEntry>,
+}
+
+impl UndoStack {
+    /// Record a new entry, which invalidates everything that was undone
+    pub fn push(&mut self, entry: UndoEntry) {
+        self.redo.clear();
+        self.push_undo(entry);
+    }
+
+    pub fn push_undo(&mut self, entry: UndoEntry) {
+        if self.undo.len() >= MAX_UNDO_ENTRIES {
+            self.undo.remove(0);
+        }
+        self.undo.push(entry);
+    }
+
+    pub fn push_redo(&mut self, entry: UndoEntry) {
+        self.redo.push(entry);
+    }
+
+    pub fn pop_undo(&mut self) -> Option<UndoEntry> {
+        self.undo.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<UndoEntry> {
+        self.redo.pop()
+    }
+
+    pub fn peek_undo(&self) -> Option<&UndoEntry> {
+        self.undo.last()
+    }
+
+    pub fn peek_redo(&self) -> Option<&UndoEntry> {
+        self.redo.last()
+    }
+
+    /// Entries that can be undone, most recent first
+    pub fn undo_entries(&self) -> impl Iterator<Item = &UndoEntry> {
+        self.undo.iter().rev()
+    }
+
+    /// Drop entries of an operation that was reverted outside of the undo stack
+    pub fn forget(&mut self, operation: &Operation) {
+        self.undo.retain(|entry| &entry.operation != operation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, path::PathBuf};
+
+    use cosmic::iced::futures::channel::mpsc;
+    use tempfile::tempdir;
+    use tokio::sync;
+
+    use super::{Inverse, UndoEntry, UndoSnapshot, UndoStack};
+    use crate::core::operations::{
+        ConflictPolicy, Controller, FilePolicy, Operation, OperationSelection, Preserve,
+    };
+
+    async fn perform(operation: &Operation) -> OperationSelection {
+        let (tx, _rx) = mpsc::channel(1);
+        operation
+            .clone()
+            .perform(&sync::Mutex::new(tx).into(), Controller::default())
+            .await
+            .expect("operation should have succeeded")
+    }
+
+    fn rename_entry(from: PathBuf, to: PathBuf) -> UndoEntry {
+        let operation = Operation::Rename { from, to };
+        UndoEntry::new(
+            operation.clone(),
+            &UndoSnapshot::capture(&operation),
+            &OperationSelection::default(),
+        )
+        .expect("rename should be undoable")
+    }
+
+    #[test]
+    fn rename_inverse_swaps_paths() -> io::Result<()> {
+        let dir = tempdir()?;
+        let from = dir.path().join("a");
+        let to = dir.path().join("b");
+        fs::write(&to, b"b")?;
+
+        let entry = rename_entry(from.clone(), to.clone());
+        match entry.inverse {
+            Inverse::Operation(Operation::Rename {
+                from: ref inv_from,
+                to: ref inv_to,
+            }) => {
+                assert_eq!(inv_from, &to);
+                assert_eq!(inv_to, &from);
+            }
+            ref other => panic!("unexpected inverse {other:?}"),
+        }
+        assert!(entry.check_undo().is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn undo_refused_when_changed() -> io::Result<()> {
+        let dir = tempdir()?;
+        let from = dir.path().join("a");
+        let to = dir.path().join("b");
+        fs::write(&to, b"b")?;
+
+        let entry = rename_entry(from.clone(), to.clone());
+        fs::write(&to, b"newer data")?;
+        assert_eq!(entry.check_undo(), Err(to.as_path()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn undo_refused_when_target_exists() -> io::Result<()> {
+        let dir = tempdir()?;
+        let from = dir.path().join("a");
+        let to = dir.path().join("b");
+        fs::write(&to, b"b")?;
+
+        let entry = rename_entry(from.clone(), to.clone());
+        fs::write(&from, b"someone else")?;
+        assert_eq!(entry.check_undo(), Err(from.as_path()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn move_from_several_folders_is_not_undoable() {
+        let operation = Operation::Move {
+            paths: vec![PathBuf::from("/a/x"), PathBuf::from("/b/y")],
+            to: PathBuf::from("/c"),
+            cross_device_copy: false,
+            verify: None,
+            preserve: Preserve::default(),
+            conflicts: ConflictPolicy::default(),
+        };
+        let op_sel = OperationSelection {
+            created: vec![
+                (PathBuf::from("/a/x"), PathBuf::from("/c/x")),
+                (PathBuf::from("/b/y"), PathBuf::from("/c/y")),
+            ],
+            ..Default::default()
+        };
+        assert!(UndoEntry::new(operation, &UndoSnapshot::default(), &op_sel).is_none());
+    }
+
+    #[test_log::test(compio::test)]
+    async fn undo_move_keeping_both() -> io::Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir(&src)?;
+        fs::create_dir(&dst)?;
+        fs::write(src.join("a"), b"moved")?;
+        fs::write(dst.join("a"), b"existing")?;
+
+        let operation = Operation::Move {
+            paths: vec![src.join("a")],
+            to: dst.clone(),
+            cross_device_copy: false,
+            verify: None,
+            preserve: Preserve::default(),
+            conflicts: ConflictPolicy {
+                files: FilePolicy::Rename,
+                ..Default::default()
+            },
+        };
+        let op_sel = perform(&operation).await;
+        let entry = UndoEntry::new(operation, &UndoSnapshot::default(), &op_sel)
+            .expect("move should be undoable");
+        assert!(entry.check_undo().is_ok());
+        let Inverse::Operation(inverse) = &entry.inverse else {
+            panic!("unexpected inverse {:?}", entry.inverse);
+        };
+        perform(inverse).await;
+
+        // The moved item is put back, the existing one stays
+        assert_eq!(fs::read(src.join("a"))?, b"moved");
+        assert_eq!(fs::read(dst.join("a"))?, b"existing");
+        assert_eq!(fs::read_dir(&dst)?.count(), 1);
+
+        Ok(())
+    }
+
+    #[test_log::test(compio::test)]
+    async fn merged_copy_is_not_undoable() -> io::Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("folder"))?;
+        fs::create_dir_all(dst.join("folder"))?;
+        fs::write(src.join("folder/new"), b"new")?;
+        fs::write(dst.join("folder/existing"), b"existing")?;
+
+        let operation = Operation::Copy {
+            paths: vec![src.join("folder")],
+            to: dst.clone(),
+            verify: None,
+            preserve: Preserve::default(),
+            conflicts: ConflictPolicy::default(),
+        };
+        let op_sel = perform(&operation).await;
+        assert_eq!(op_sel.merged, vec![dst.join("folder")]);
+        assert!(UndoEntry::new(operation, &UndoSnapshot::default(), &op_sel).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn push_clears_redo() -> io::Result<()> {
+        let dir = tempdir()?;
+        let to = dir.path().join("b");
+        fs::write(&to, b"b")?;
+
+        let mut stack = UndoStack::default();
+        stack.push(rename_entry(dir.path().join("a"), to.clone()));
+        let entry = stack.pop_undo().expect("entry was pushed");
+        stack.push_redo(entry);
+        assert!(stack.peek_redo().is_some());
+
+        stack.push(rename_entry(dir.path().join("c"), to));
+        assert!(stack.peek_redo().is_none());
+        assert_eq!(stack.undo_entries().count(), 1);
+
+        Ok(())
+    }
+}
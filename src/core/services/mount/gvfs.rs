@@ -176,6 +176,7 @@ fn network_scan(uri: &str, sizes: IconSizes) -> Result<Vec<tab::Item>, String> {
             overlaps_drag_rect: false,
             //TODO: scan directory size on gvfs mounts?
             dir_size: DirSize::NotDirectory,
+            search_matches: None,
             cut: false,
         });
     }
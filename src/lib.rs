@@ -6,6 +6,7 @@ use std::{env, fs, path::PathBuf, process};
 
 use app::{App, Flags};
 pub mod app;
+mod cli;
 pub mod utils;
 mod common;
 mod core;
@@ -92,13 +93,25 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     utils::localize::localize();
 
+    // Subcommands run an operation without opening a window
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(command) = cli::Command::parse(&args) {
+        process::exit(match command {
+            Ok(command) => cli::run(command),
+            Err(err) => {
+                eprintln!("{}\n\n{}", err, fl!("cli-usage"));
+                cli::EXIT_USAGE
+            }
+        });
+    }
+
     let (config_handler, config) = Config::load();
     let (state_handler, state) = State::load();
 
     let mut daemonize = true;
     let mut locations = Vec::new();
     let mut uris = Vec::new();
-    for arg in env::args().skip(1) {
+    for arg in args {
         let location = if &arg == "--no-daemon" {
             daemonize = false;
             continue;
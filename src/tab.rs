@@ -75,6 +75,8 @@ use walkdir::WalkDir;
 use crate::{
     app::{Action, PreviewItem, PreviewKind},
     utils::clipboard::{ClipboardCopy, ClipboardKind, ClipboardPaste},
+    utils::content_search::{self, FileMatches},
+    utils::search_query::{Query, QueryError},
     core::config::{DesktopConfig, ICON_SCALE_MAX, ICON_SIZE_GRID, IconSizes, TabConfig, ThumbCfg},
     views::dialogs::dialog::DialogKind,
     fl,
@@ -94,6 +96,8 @@ pub const HOVER_DURATION: Duration = Duration::from_millis(1600);
 //TODO: best limit for search items
 const MAX_SEARCH_LATENCY: Duration = Duration::from_millis(20);
 const MAX_SEARCH_RESULTS: usize = 200;
+/// Height added to a list row for each line of content search matches
+const SEARCH_MATCH_HEIGHT: u16 = 16;
 //TODO: configurable thumbnail size?
 const THUMBNAIL_SIZE: u32 = (ICON_SIZE_GRID as u32) * (ICON_SCALE_MAX as u32);
 
@@ -372,7 +376,7 @@ pub fn trash_icon_symbolic(icon_size: u16) -> widget::icon::Handle {
 }
 
 //TODO: translate, add more levels?
-fn format_size(size: u64) -> String {
+pub fn format_size(size: u64) -> String {
     const KB: u64 = 1000;
     const MB: u64 = 1000 * KB;
     const GB: u64 = 1000 * MB;
@@ -706,6 +710,7 @@ pub fn item_from_gvfs_info(path: PathBuf, file_info: gio::FileInfo, sizes: IconS
         highlighted: false,
         overlaps_drag_rect: false,
         dir_size,
+        search_matches: None,
         cut: false,
     }
 }
@@ -847,6 +852,7 @@ pub fn item_from_entry(
         highlighted: false,
         overlaps_drag_rect: false,
         dir_size,
+        search_matches: None,
         cut: false,
     }
 }
@@ -976,27 +982,47 @@ pub fn scan_path(tab_path: &PathBuf, sizes: IconSizes) -> Vec<Item> {
     items
 }
 
-pub fn scan_search<F: Fn(&Path, &str, Metadata) -> bool + Sync>(
+/// Caption lines describing the matching lines of a content search result
+fn search_match_lines(file_matches: &FileMatches) -> Vec<String> {
+    let mut lines: Vec<String> = file_matches
+        .matches
+        .iter()
+        .map(|search_match| {
+            fl!(
+                "search-match-line",
+                line = search_match.line,
+                text = search_match.snippet.as_str()
+            )
+        })
+        .collect();
+    let more = file_matches.total - file_matches.matches.len();
+    if more > 0 {
+        lines.push(fl!("search-match-more", count = more));
+    }
+    lines
+}
+
+/// Parse a search term, along with the matcher for its text
+fn search_query(term: &str, mode: SearchMode) -> Result<(Query, Option<regex::Regex>), QueryError> {
+    let query = Query::parse(term)?;
+    let regex_opt = query.text_matcher(mode == SearchMode::ContentRegex)?;
+    Ok((query, regex_opt))
+}
+
+pub fn scan_search<F: Fn(&Path, &str, Metadata, Option<FileMatches>) -> bool + Sync>(
     tab_path: &PathBuf,
-    term: &str,
+    mut query: Query,
+    regex_opt: Option<regex::Regex>,
     show_hidden: bool,
+    mode: SearchMode,
     callback: F,
-) {
-    if term.is_empty() {
-        return;
+) -> Result<(), QueryError> {
+    if query.is_empty() {
+        return Ok(());
     }
-
-    let pattern = regex::escape(term);
-    let regex = match regex::RegexBuilder::new(&pattern)
-        .case_insensitive(true)
-        .build()
-    {
-        Ok(ok) => ok,
-        Err(err) => {
-            log::warn!("failed to parse regex {:?}: {}", pattern, err);
-            return;
-        }
-    };
+    query.resolve_owners()?;
+    // Without text to look for, content searches list the files matching the filters
+    let content_regex_opt = regex_opt.as_ref().filter(|_| mode.is_content());
 
     ignore::WalkBuilder::new(tab_path)
         .standard_filters(false)
@@ -1016,26 +1042,55 @@ pub fn scan_search<F: Fn(&Path, &str, Metadata) -> bool + Sync>(
                     return ignore::WalkState::Skip;
                 };
 
-                if regex.is_match(file_name) {
-                    let path = entry.path();
+                if content_regex_opt.is_some() {
+                    if !entry
+                        .file_type()
+                        .is_some_and(|file_type| file_type.is_file())
+                    {
+                        return ignore::WalkState::Continue;
+                    }
+                } else if let Some(regex) = &regex_opt {
+                    if !regex.is_match(file_name) {
+                        return ignore::WalkState::Continue;
+                    }
+                }
 
-                    let metadata = match entry.metadata() {
-                        Ok(ok) => ok,
+                let path = entry.path();
+
+                let metadata = match entry.metadata() {
+                    Ok(ok) => ok,
+                    Err(err) => {
+                        log::warn!("failed to read metadata for entry at {:?}: {}", path, err);
+                        return ignore::WalkState::Continue;
+                    }
+                };
+
+                if !query.matches(path, &metadata) {
+                    return ignore::WalkState::Continue;
+                }
+
+                let matches_opt = if let Some(regex) = content_regex_opt {
+                    match content_search::search_file(path, metadata.len(), regex) {
+                        Ok(Some(matches)) => Some(matches),
+                        Ok(None) => return ignore::WalkState::Continue,
                         Err(err) => {
-                            log::warn!("failed to read metadata for entry at {:?}: {}", path, err);
+                            log::debug!("failed to search contents of {:?}: {}", path, err);
                             return ignore::WalkState::Continue;
                         }
-                    };
-
-                    //TODO: use entry.into_path?
-                    if !callback(path, file_name, metadata) {
-                        return ignore::WalkState::Quit;
                     }
+                } else {
+                    None
+                };
+
+                //TODO: use entry.into_path?
+                if !callback(path, file_name, metadata, matches_opt) {
+                    return ignore::WalkState::Quit;
                 }
 
                 ignore::WalkState::Continue
             })
         });
+    Ok(())
 }
 
 // This config statement is from trash::os_limited, inverted
@@ -1120,6 +1175,7 @@ pub fn scan_trash(sizes: IconSizes) -> Vec<Item> {
                     highlighted: false,
                     overlaps_drag_rect: false,
                     dir_size: DirSize::NotDirectory,
+                    search_matches: None,
                     cut: false,
                 });
             }
@@ -1224,6 +1280,135 @@ pub fn scan_network(uri: &str, sizes: IconSizes) -> Vec<Item> {
     Vec::new()
 }
 
+pub fn scan_archive(archive: &Path, dir: &Path, sizes: IconSizes) -> Vec<Item> {
+    let entries = match crate::utils::archive::list(archive) {
+        Ok(entries) => entries,
+        Err(err) => {
+            log::warn!("failed to read archive {:?}: {}", archive, err);
+            return Vec::new();
+        }
+    };
+
+    let mut items: Vec<_> = crate::utils::archive::read_dir(&entries, dir)
+        .into_iter()
+        .map(|(entry, children)| item_from_archive_entry(archive, entry, children, sizes))
+        .collect();
+    items.sort_by(|a, b| match (a.metadata.is_dir(), b.metadata.is_dir()) {
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        _ => LANGUAGE_SORTER.compare(&a.display_name, &b.display_name),
+    });
+    items
+}
+
+/// Item of an entry inside of `archive`, with `children` items if it is a directory
+pub fn item_from_archive_entry(
+    archive: &Path,
+    entry: crate::utils::archive::Entry,
+    children: u64,
+    sizes: IconSizes,
+) -> Item {
+    let name = entry
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let display_name = Item::display_name(&name);
+
+    let (metadata, mime, icon_handle_grid, icon_handle_list, icon_handle_list_condensed) =
+        if entry.is_dir {
+            (
+                ItemMetadata::SimpleDir {
+                    entries: children,
+                    modified: entry.modified,
+                },
+                //TODO: make this a static
+                "inode/directory".parse().unwrap(),
+                folder_icon(&entry.path, sizes.grid()),
+                folder_icon(&entry.path, sizes.list()),
+                folder_icon(&entry.path, sizes.list_condensed()),
+            )
+        } else {
+            // This passes remote = true so it does not read from the path
+            let mime = mime_for_path(&entry.path, None, true);
+            (
+                ItemMetadata::SimpleFile {
+                    size: entry.size,
+                    modified: entry.modified,
+                },
+                mime.clone(),
+                mime_icon(mime.clone(), sizes.grid()),
+                mime_icon(mime.clone(), sizes.list()),
+                mime_icon(mime, sizes.list_condensed()),
+            )
+        };
+    // Only images and text are extracted for thumbnails
+    let thumbnail_opt = if mime.type_() == mime::IMAGE || mime.type_() == mime::TEXT {
+        None
+    } else {
+        Some(ItemThumbnail::NotImage)
+    };
+
+    Item {
+        hidden: name.starts_with('.'),
+        name,
+        display_name,
+        is_mount_point: false,
+        metadata,
+        location_opt: Some(Location::Archive(archive.to_path_buf(), entry.path)),
+        mime,
+        icon_handle_grid,
+        icon_handle_list,
+        icon_handle_list_condensed,
+        thumbnail_opt,
+        button_id: widget::Id::unique(),
+        pos_opt: Cell::new(None),
+        rect_opt: Cell::new(None),
+        selected: false,
+        highlighted: false,
+        overlaps_drag_rect: false,
+        dir_size: DirSize::NotDirectory,
+        search_matches: None,
+        cut: false,
+    }
+}
+
+/// Contents of an archive item, if it can be browsed like a folder
+fn archive_location(item: &Item) -> Option<Location> {
+    if !crate::utils::archive::can_browse(&item.mime) {
+        return None;
+    }
+    let path = item.path_opt()?;
+    Some(Location::Archive(path.clone(), PathBuf::new()))
+}
+
+/// Extract an archive entry to the cache and open it
+fn open_archive_entry(archive: &Path, entry: &Path) -> Command {
+    let (archive, entry) = (archive.to_path_buf(), entry.to_path_buf());
+    Command::Iced(
+        cosmic::Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    crate::utils::archive::cache_entry(&archive, &entry)
+                })
+                .await
+            },
+            |result| match result {
+                Ok(Ok(path)) => cosmic::Action::App(Message::Open(Some(path))),
+                Ok(Err(err)) => {
+                    log::warn!("failed to extract archive entry: {}", err);
+                    cosmic::Action::None
+                }
+                Err(err) => {
+                    log::warn!("failed to extract archive entry: {}", err);
+                    cosmic::Action::None
+                }
+            },
+        )
+        .into(),
+    )
+}
+
 //TODO: organize desktop items based on display
 pub fn scan_desktop(
     tab_path: &PathBuf,
@@ -1277,6 +1462,7 @@ pub fn scan_desktop(
 
         let metadata = ItemMetadata::SimpleDir {
             entries: trash_entries() as u64,
+            modified: None,
         };
 
         let (mime, icon_handle_grid, icon_handle_list, icon_handle_list_condensed) = {
@@ -1307,6 +1493,7 @@ pub fn scan_desktop(
             highlighted: false,
             overlaps_drag_rect: false,
             dir_size: DirSize::NotDirectory,
+            search_matches: None,
             cut: false,
         })
     }
@@ -1364,20 +1551,44 @@ impl From<Location> for EditLocation {
     }
 }
 
+/// What a recursive search matches against
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum SearchMode {
+    #[default]
+    Name,
+    /// File contents containing the term
+    Content,
+    /// File contents matching the term as a regular expression
+    ContentRegex,
+}
+
+impl SearchMode {
+    pub fn all() -> &'static [Self] {
+        &[Self::Name, Self::Content, Self::ContentRegex]
+    }
+
+    pub fn is_content(self) -> bool {
+        self != Self::Name
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Location {
+    /// Path of an archive and of a directory inside of it
+    Archive(PathBuf, PathBuf),
     Desktop(PathBuf, String, DesktopConfig),
     Network(String, String, Option<PathBuf>),
     Path(PathBuf),
     QuickAccess,
     Recents,
-    Search(PathBuf, String, bool, Instant),
+    Search(PathBuf, String, bool, SearchMode, Instant),
     Trash,
 }
 
 impl std::fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Self::Archive(archive, path) => write!(f, "{}", archive.join(path).display()),
             Self::Desktop(path, display, ..) => {
                 write!(f, "{} on display {display}", path.display())
             }
@@ -1416,6 +1627,15 @@ impl Location {
         ancestors
     }
 
+    /// Path of an archive entry as if the archive was a directory, like `/home/user/a.zip/file`.
+    /// Nothing exists at this path, see [`crate::utils::archive::split_entry_path`].
+    pub fn archive_entry_path(&self) -> Option<PathBuf> {
+        match self {
+            Self::Archive(archive, path) => Some(archive.join(path)),
+            _ => None,
+        }
+    }
+
     pub fn path_opt(&self) -> Option<&PathBuf> {
         match self {
             Self::Desktop(path, ..) => Some(path),
@@ -1427,14 +1647,21 @@ impl Location {
         }
     }
 
+    pub fn search_mode(&self) -> SearchMode {
+        match self {
+            Self::Search(_, _, _, mode, _) => *mode,
+            _ => SearchMode::default(),
+        }
+    }
+
     pub fn with_path(&self, path: PathBuf) -> Self {
         match self {
             Self::Desktop(_, display, desktop_config) => {
                 Self::Desktop(path, display.clone(), *desktop_config)
             }
             Self::Path(..) => Self::Path(path),
-            Self::Search(_, term, show_hidden, time) => {
-                Self::Search(path, term.clone(), *show_hidden, *time)
+            Self::Search(_, term, show_hidden, mode, time) => {
+                Self::Search(path, term.clone(), *show_hidden, *mode, *time)
             }
             Self::Network(id, name, path) => Self::Network(id.clone(), name.clone(), path.clone()),
             Self::QuickAccess => Self::QuickAccess,
@@ -1445,6 +1672,7 @@ impl Location {
 
     pub fn scan(&self, sizes: IconSizes) -> (Option<Item>, Vec<Item>) {
         let items = match self {
+            Self::Archive(archive, path) => scan_archive(archive, path, sizes),
             Self::Desktop(path, display, desktop_config) => {
                 scan_desktop(path, display, *desktop_config, sizes)
             }
@@ -1477,6 +1705,11 @@ impl Location {
 
     pub fn title(&self) -> String {
         match self {
+            Self::Archive(archive, path) => path
+                .file_name()
+                .or_else(|| archive.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
             Self::Desktop(path, _, _) => {
                 let (name, _) = folder_name(path);
                 name
@@ -1520,12 +1753,15 @@ impl fmt::Debug for TaskWrapper {
 pub enum Command {
     Action(Action),
     AddNetworkDrive,
+    /// Offer to add dropped items to an archive
+    AddToArchive(PathBuf, Vec<PathBuf>),
     AddToSidebar(PathBuf),
     AutoScroll(Option<f32>),
     ChangeLocation(String, Location, Option<Vec<PathBuf>>),
     ContextMenu(Option<Point>, Option<window::Id>),
     Delete(Vec<PathBuf>),
     DropFiles(PathBuf, ClipboardPaste),
+    EditPermissions(Vec<PathBuf>),
     EmptyTrash,
     #[cfg(feature = "desktop")]
     ExecEntryAction(cosmic::desktop::DesktopEntryData, usize),
@@ -1561,6 +1797,7 @@ pub enum Message {
     EditLocationComplete(usize),
     EditLocationEnable,
     EditLocationSubmit,
+    EditPermissions,
     OpenInNewTab(PathBuf),
     EmptyTrash,
     #[cfg(feature = "desktop")]
@@ -1586,6 +1823,7 @@ pub enum Message {
     Scroll(Viewport),
     ScrollTab(f32),
     SearchContext(Location, SearchContextWrapper),
+    SearchError(Location, String),
     SearchReady(bool),
     SelectAll,
     SelectFirst,
@@ -1645,9 +1883,11 @@ pub enum ItemMetadata {
     },
     SimpleDir {
         entries: u64,
+        modified: Option<SystemTime>,
     },
     SimpleFile {
         size: u64,
+        modified: Option<SystemTime>,
     },
     #[cfg(feature = "gvfs")]
     GvfsPath {
@@ -1679,6 +1919,7 @@ impl ItemMetadata {
             Self::GvfsPath { mtime, .. } => {
                 Some(SystemTime::UNIX_EPOCH + Duration::from_secs(*mtime))
             }
+            Self::SimpleDir { modified, .. } | Self::SimpleFile { modified, .. } => *modified,
             _ => None,
         }
     }
@@ -1695,6 +1936,7 @@ impl ItemMetadata {
             },
             #[cfg(feature = "gvfs")]
             Self::GvfsPath { size_opt, .. } => *size_opt,
+            Self::SimpleFile { size, .. } => Some(*size),
             _ => None,
         }
     }
@@ -2021,6 +2263,8 @@ pub struct Item {
     pub cut: bool,
     pub overlaps_drag_rect: bool,
     pub dir_size: DirSize,
+    /// Matching lines of a content search
+    pub search_matches: Option<FileMatches>,
 }
 
 impl Item {
@@ -2405,7 +2649,7 @@ impl Mode {
 }
 
 struct SearchContext {
-    results_rx: mpsc::Receiver<(PathBuf, String, Metadata)>,
+    results_rx: mpsc::Receiver<(PathBuf, String, Metadata, Option<FileMatches>)>,
     ready: Arc<atomic::AtomicBool>,
     last_modified_opt: Arc<RwLock<Option<SystemTime>>>,
 }
@@ -2460,6 +2704,10 @@ pub struct Tab {
     modifiers: Modifiers,
     last_right_click: Option<usize>,
     search_context: Option<SearchContext>,
+    /// Parsed search term of the location, which the search is started with
+    search_query: Option<(Query, Option<regex::Regex>)>,
+    /// Explanation of why the search term of the location is invalid
+    pub search_error: Option<String>,
     date_time_formatter: DateTimeFormatter<fieldsets::YMDT>,
     time_formatter: DateTimeFormatter<fieldsets::T>,
     watch_drag: bool,
@@ -2581,6 +2829,8 @@ impl Tab {
             modifiers: Modifiers::default(),
             last_right_click: None,
             search_context: None,
+            search_query: None,
+            search_error: None,
             date_time_formatter: date_time_formatter(config.military_time),
             time_formatter: time_formatter(config.military_time),
             watch_drag: true,
@@ -2892,6 +3142,15 @@ impl Tab {
         self.scroll_opt = None;
         self.select_focus = None;
         self.search_context = None;
+        let search_opt = match &self.location {
+            Location::Search(_, term, _, mode, _) => Some(search_query(term, *mode)),
+            _ => None,
+        };
+        self.search_error = search_opt
+            .as_ref()
+            .and_then(|search| search.as_ref().err())
+            .map(ToString::to_string);
+        self.search_query = search_opt.and_then(Result::ok);
         if let Some(history_i) = history_i_opt {
             // Navigating in history
             self.history_i = history_i;
@@ -2982,6 +3241,7 @@ impl Tab {
                 self.watch_drag = true;
             }
             Message::DoubleClick(click_i_opt) => {
+                let mut reveal_opt = None;
                 if let Some(clicked_item) = self
                     .items_opt
                     .as_ref()
@@ -2990,8 +3250,17 @@ impl Tab {
                     if let Some(location) = &clicked_item.location_opt {
                         if clicked_item.metadata.is_dir() {
                             cd = Some(location.clone());
+                        } else if clicked_item.search_matches.is_some() {
+                            // Reveal content search results in their folder
+                            reveal_opt = location.path_opt().map(Path::to_path_buf);
+                        } else if let Some(archive) = archive_location(clicked_item)
+                            .filter(|_| matches!(self.mode, Mode::App))
+                        {
+                            cd = Some(archive);
                         } else if let Some(path) = location.path_opt() {
                             commands.push(Command::OpenFile(vec![path.to_path_buf()]));
+                        } else if let Location::Archive(archive, entry) = location {
+                            commands.push(open_archive_entry(archive, entry));
                         } else {
                             log::warn!("no path for item {:?}", clicked_item);
                         }
@@ -3001,6 +3270,17 @@ impl Tab {
                 } else {
                     log::warn!("no item for click index {:?}", click_i_opt);
                 }
+                if let Some(path) = reveal_opt {
+                    if let Some(parent) = path.parent() {
+                        let location = Location::Path(parent.to_path_buf());
+                        self.change_location(&location, None);
+                        commands.push(Command::ChangeLocation(
+                            self.title(),
+                            location,
+                            Some(vec![path]),
+                        ));
+                    }
+                }
             }
             Message::Click(click_i_opt) => {
                 self.selected_clicked = false;
@@ -3125,11 +3405,12 @@ impl Tab {
                     self.time_formatter = time_formatter(self.config.military_time);
                 }
                 if show_hidden_changed {
-                    if let Location::Search(path, term, ..) = &self.location {
+                    if let Location::Search(path, term, _, mode, _) = &self.location {
                         cd = Some(Location::Search(
                             path.clone(),
                             term.clone(),
                             self.config.show_hidden,
+                            *mode,
                             Instant::now(),
                         ));
                     }
@@ -3259,6 +3540,20 @@ impl Tab {
                     cd = edit_location.resolve();
                 }
             }
+            Message::EditPermissions => {
+                let mut paths: Vec<PathBuf> = self
+                    .selected_locations()
+                    .iter()
+                    .filter_map(|location| location.path_opt().cloned())
+                    .collect();
+                // The properties of the current folder are shown if nothing is selected
+                if paths.is_empty() {
+                    paths.extend(self.location.path_opt().cloned());
+                }
+                if !paths.is_empty() {
+                    commands.push(Command::EditPermissions(paths));
+                }
+            }
             Message::OpenInNewTab(path) => {
                 commands.push(Command::OpenInNewTab(path));
             }
@@ -3537,6 +3832,19 @@ impl Tab {
                     if let Some(parent) = path.parent() {
                         cd = Some(Location::Path(parent.to_owned()));
                     }
+                } else if let Location::Archive(archive, path) = self.location.clone() {
+                    if let Some(parent) = path.parent() {
+                        cd = Some(Location::Archive(archive, parent.to_owned()));
+                    } else if let Some(parent) = archive.parent() {
+                        // Leaving the root of an archive goes to the archive's directory
+                        let location = Location::Path(parent.to_owned());
+                        self.change_location(&location, None);
+                        commands.push(Command::ChangeLocation(
+                            self.title(),
+                            location,
+                            Some(vec![archive]),
+                        ));
+                    }
                 }
             }
             Message::ModifiersChanged(modifiers) => {
@@ -3552,6 +3860,7 @@ impl Tab {
                         }
                     }
                     None => {
+                        let browse_archives = matches!(self.mode, Mode::App);
                         if let Some(ref mut items) = self.items_opt {
                             let mut open_files = Vec::new();
                             for item in items.iter() {
@@ -3560,8 +3869,14 @@ impl Tab {
                                         if item.metadata.is_dir() {
                                             //TODO: allow opening multiple tabs?
                                             cd = Some(location.clone());
+                                        } else if let Some(archive) =
+                                            archive_location(item).filter(|_| browse_archives)
+                                        {
+                                            cd = Some(archive);
                                         } else if let Some(path) = location.path_opt() {
                                             open_files.push(path.to_path_buf());
+                                        } else if let Location::Archive(archive, entry) = location {
+                                            commands.push(open_archive_entry(archive, entry));
                                         }
                                     } else {
                                         //TODO: open properties?
@@ -3686,12 +4001,19 @@ impl Tab {
                     );
                 }
             }
+            Message::SearchError(location, error) => {
+                if location == self.location {
+                    self.search_error = Some(error);
+                }
+            }
             Message::SearchReady(finished) => {
                 if let Some(context) = &mut self.search_context {
                     if let Some(items) = &mut self.items_opt {
                         if finished || context.ready.swap(false, atomic::Ordering::SeqCst) {
                             let duration = Instant::now();
-                            while let Ok((path, name, metadata)) = context.results_rx.try_recv() {
+                            while let Ok((path, name, metadata, matches_opt)) =
+                                context.results_rx.try_recv()
+                            {
                                 //TODO: combine this with column_sort logic, they must match!
                                 let item_modified = metadata.modified().ok();
                                 let index = match items.binary_search_by(|other| {
@@ -3702,10 +4024,10 @@ impl Tab {
                                 };
                                 if index < MAX_SEARCH_RESULTS {
                                     //TODO: use correct IconSizes
-                                    items.insert(
-                                        index,
-                                        item_from_entry(path, name, metadata, IconSizes::default()),
-                                    );
+                                    let mut item =
+                                        item_from_entry(path, name, metadata, IconSizes::default());
+                                    item.search_matches = matches_opt;
+                                    items.insert(index, item);
                                 }
                                 // Ensure that updates make it to the GUI in a timely manner
                                 if !finished && duration.elapsed() >= MAX_SEARCH_LATENCY {
@@ -3799,9 +4121,16 @@ impl Tab {
             }
             Message::Thumbnail(path, thumbnail) => {
                 if let Some(ref mut items) = self.items_opt {
-                    let location = Location::Path(path);
+                    let location = Location::Path(path.clone());
                     for item in items.iter_mut() {
-                        if item.location_opt.as_ref() == Some(&location) {
+                        if item.location_opt.as_ref() == Some(&location)
+                            || item
+                                .location_opt
+                                .as_ref()
+                                .and_then(Location::archive_entry_path)
+                                .as_ref()
+                                == Some(&path)
+                        {
                             let handle_opt = match &thumbnail {
                                 ItemThumbnail::NotImage => None,
                                 ItemThumbnail::Image(handle, _) => Some(widget::icon::Handle {
@@ -3850,6 +4179,12 @@ impl Tab {
             Message::Drop(Some((to, mut from))) => {
                 self.dnd_hovered = None;
                 match to {
+                    Location::Desktop(to, ..) | Location::Path(to) if to.is_file() => {
+                        from.paths.retain(|p| *p != to);
+                        if !from.paths.is_empty() {
+                            commands.push(Command::AddToArchive(to, from.paths));
+                        }
+                    }
                     Location::Desktop(to, ..)
                     | Location::Path(to)
                     | Location::Network(_, _, Some(to)) => {
@@ -4047,8 +4382,8 @@ impl Tab {
                             trash::TrashItemSize::Entries(entries) => (true, entries as u64),
                             trash::TrashItemSize::Bytes(bytes) => (false, bytes),
                         },
-                        ItemMetadata::SimpleDir { entries } => (true, *entries),
-                        ItemMetadata::SimpleFile { size } => (false, *size),
+                        ItemMetadata::SimpleDir { entries, .. } => (true, *entries),
+                        ItemMetadata::SimpleFile { size, .. } => (false, *size),
                         #[cfg(feature = "gvfs")]
                         ItemMetadata::GvfsPath {
                             size_opt,
@@ -4627,6 +4962,43 @@ impl Tab {
             Location::QuickAccess => {
                 // QuickAccess doesn't need breadcrumb navigation
             }
+            Location::Archive(archive, path) => {
+                let root = archive
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let mut ancestor = PathBuf::new();
+                let components =
+                    std::iter::once((root, PathBuf::new())).chain(path.iter().map(|component| {
+                        ancestor.push(component);
+                        (component.to_string_lossy().into_owned(), ancestor.clone())
+                    }));
+                for (name, ancestor) in components {
+                    if !children.is_empty() {
+                        children.push(
+                            widget::icon::from_name("go-next-symbolic")
+                                .size(16)
+                                .icon()
+                                .into(),
+                        );
+                    }
+                    let text = if ancestor == *path {
+                        widget::text::heading(name)
+                    } else {
+                        widget::text::body(name)
+                    };
+                    children.push(
+                        widget::button::custom(text.wrapping(text::Wrapping::None))
+                            .padding(space_xxxs)
+                            .class(theme::Button::Link)
+                            .on_press(Message::Location(Location::Archive(
+                                archive.clone(),
+                                ancestor,
+                            )))
+                            .into(),
+                    );
+                }
+            }
         }
 
         for child in children {
@@ -4853,12 +5225,14 @@ impl Tab {
                         }
                     }
 
-                    let column: Element<Message> =
-                        if item.metadata.is_dir() && item.location_opt.is_some() {
-                            self.dnd_dest(&item.location_opt.clone().unwrap(), column)
-                        } else {
-                            column.into()
-                        };
+                    let column: Element<Message> = if (item.metadata.is_dir()
+                        || crate::utils::archive::can_add(&item.mime))
+                        && item.location_opt.is_some()
+                    {
+                        self.dnd_dest(&item.location_opt.clone().unwrap(), column)
+                    } else {
+                        column.into()
+                    };
 
                     if item.selected {
                         dnd_items.push((i, (row, col), item));
@@ -5097,6 +5471,17 @@ impl Tab {
                 }
 
                 item.pos_opt.set(Some((count, 0)));
+                let match_lines = item
+                    .search_matches
+                    .as_ref()
+                    .map(search_match_lines)
+                    .unwrap_or_default();
+                let row_height = row_height + match_lines.len() as u16 * SEARCH_MATCH_HEIGHT;
+                let match_captions = || {
+                    match_lines
+                        .iter()
+                        .map(|line| Element::from(widget::text::caption(line.clone())))
+                };
                 let item_rect = Rectangle::new(
                     Point::new(space_s as f32, y),
                     Size::new(size.width - (2 * space_s) as f32, row_height as f32),
@@ -5122,7 +5507,11 @@ impl Tab {
                             Some(mtime) => self.format_time(mtime).to_string(),
                             None => String::new(),
                         },
-                        _ => String::new(),
+                        ItemMetadata::SimpleDir { .. } | ItemMetadata::SimpleFile { .. } => item
+                            .metadata
+                            .modified()
+                            .map(|time| self.format_time(time).to_string())
+                            .unwrap_or_default(),
                     };
 
                     let size_text = match &item.metadata {
@@ -5156,7 +5545,7 @@ impl Tab {
                             }
                             trash::TrashItemSize::Bytes(bytes) => format_size(bytes),
                         },
-                        ItemMetadata::SimpleDir { entries } => {
+                        ItemMetadata::SimpleDir { entries, .. } => {
                             //TODO: translate
                             if *entries == 1 {
                                 format!("{} item", entries)
@@ -5164,7 +5553,7 @@ impl Tab {
                                 format!("{} items", entries)
                             }
                         }
-                        ItemMetadata::SimpleFile { size } => format_size(*size),
+                        ItemMetadata::SimpleFile { size, .. } => format_size(*size),
                         #[cfg(feature = "gvfs")]
                         ItemMetadata::GvfsPath {
                             size_opt,
@@ -5194,6 +5583,7 @@ impl Tab {
                                 widget::text::caption(format!("{} - {}", modified_text, size_text))
                                     .into(),
                             ])
+                            .extend(match_captions())
                             .into(),
                         ])
                         .height(Length::Fixed(row_height as f32))
@@ -5213,6 +5603,7 @@ impl Tab {
                                 })
                                 .into(),
                             ])
+                            .extend(match_captions())
                             .width(Length::Fill)
                             .into(),
                             widget::text::body(modified_text.clone())
@@ -5281,12 +5672,14 @@ impl Tab {
                     };
 
                     let button_row = button(row.into());
-                    let button_row: Element<_> =
-                        if item.metadata.is_dir() && item.location_opt.is_some() {
-                            self.dnd_dest(item.location_opt.as_ref().unwrap(), button_row)
-                        } else {
-                            button_row.into()
-                        };
+                    let button_row: Element<_> = if (item.metadata.is_dir()
+                        || crate::utils::archive::can_add(&item.mime))
+                        && item.location_opt.is_some()
+                    {
+                        self.dnd_dest(item.location_opt.as_ref().unwrap(), button_row)
+                    } else {
+                        button_row.into()
+                    };
 
                     if item.selected || !drag_items.is_empty() {
                         let dnd_row = if !item.selected {
@@ -5306,6 +5699,7 @@ impl Tab {
                                     ))
                                     .into(),
                                 ])
+                                .extend(match_captions())
                                 .into(),
                             ])
                             .align_y(Alignment::Center)
@@ -5325,6 +5719,7 @@ impl Tab {
                                     })
                                     .into(),
                                 ])
+                                .extend(match_captions())
                                 .width(Length::Fill)
                                 .into(),
                                 widget::text::body(modified_text.clone())
@@ -5453,7 +5848,13 @@ impl Tab {
                 items
                     .iter()
                     .filter(|item| item.selected)
-                    .filter_map(|item| item.path_opt().cloned())
+                    .filter_map(|item| {
+                        item.path_opt().cloned().or_else(|| {
+                            item.location_opt
+                                .as_ref()
+                                .and_then(Location::archive_entry_path)
+                        })
+                    })
                     .collect::<Vec<PathBuf>>()
             })
             .unwrap_or_default();
@@ -5645,15 +6046,27 @@ impl Tab {
                     }
                 }
 
-                let Some(path) = item.path_opt().map(|path| path.to_path_buf()) else {
+                let Some(path) = item.path_opt().map(|path| path.to_path_buf()).or_else(|| {
+                    item.location_opt
+                        .as_ref()
+                        .and_then(Location::archive_entry_path)
+                }) else {
                     continue;
                 };
+                // Archive entries are extracted to a cache to be thumbnailed
+                let archive_entry = match &item.location_opt {
+                    Some(Location::Archive(archive, entry)) => {
+                        Some((archive.clone(), entry.clone()))
+                    }
+                    _ => None,
+                };
 
                 let metadata = item.metadata.clone();
                 let can_thumbnail = match metadata {
                     ItemMetadata::Path { .. } => true,
                     #[cfg(feature = "gvfs")]
                     ItemMetadata::GvfsPath { .. } => true,
+                    ItemMetadata::SimpleFile { .. } => archive_entry.is_some(),
                     _ => false,
                 };
                 if can_thumbnail {
@@ -5670,15 +6083,39 @@ impl Tab {
                                 _ = THUMB_SEMAPHORE.acquire().await;
                                 tokio::task::spawn_blocking(move || {
                                     let start = Instant::now();
-                                    let thumbnail = ItemThumbnail::new(
-                                        &path,
-                                        metadata,
-                                        mime,
-                                        THUMBNAIL_SIZE,
-                                        max_mb,
-                                        max_jobs,
-                                        max_size,
-                                    );
+                                    let thumbnail_path = match archive_entry {
+                                        Some(_)
+                                            if metadata.file_size().unwrap_or_default()
+                                                > max_size * 1000 * 1000 =>
+                                        {
+                                            None
+                                        }
+                                        Some((archive, entry)) => {
+                                            crate::utils::archive::cache_entry(&archive, &entry)
+                                                .inspect_err(|err| {
+                                                    log::warn!(
+                                                        "failed to extract {:?} from {:?}: {}",
+                                                        entry,
+                                                        archive,
+                                                        err
+                                                    );
+                                                })
+                                                .ok()
+                                        }
+                                        None => Some(path.clone()),
+                                    };
+                                    let thumbnail = match thumbnail_path {
+                                        Some(thumbnail_path) => ItemThumbnail::new(
+                                            &thumbnail_path,
+                                            metadata,
+                                            mime,
+                                            THUMBNAIL_SIZE,
+                                            max_mb,
+                                            max_jobs,
+                                            max_size,
+                                        ),
+                                        None => ItemThumbnail::NotImage,
+                                    };
                                     log::debug!("thumbnailed {:?} in {:?}", path, start.elapsed());
                                     Message::Thumbnail(path.clone(), thumbnail)
                                 })
@@ -5767,11 +6204,14 @@ impl Tab {
         }
 
         // Load search items incrementally
-        if let Location::Search(path, term, show_hidden, start) = &self.location {
+        if let Location::Search(path, term, show_hidden, mode, start) = &self.location {
             let location = self.location.clone();
             let path = path.clone();
             let term = term.clone();
+            // An invalid term is empty, which finishes the search without results
+            let (query, regex_opt) = self.search_query.clone().unwrap_or_default();
             let show_hidden = *show_hidden;
+            let mode = *mode;
             let start = *start;
             subscriptions.push(Subscription::run_with_id(
                 location.clone(),
@@ -5794,14 +6234,16 @@ impl Tab {
                         .unwrap();
 
                     let output = Arc::new(tokio::sync::Mutex::new(output));
-                    {
+                    let result = {
                         let output = output.clone();
                         tokio::task::spawn_blocking(move || {
-                            scan_search(
+                            let result = scan_search(
                                 &path,
-                                &term,
+                                query,
+                                regex_opt,
                                 show_hidden,
-                                move |path, name, metadata| -> bool {
+                                mode,
+                                move |path, name, metadata, matches_opt| -> bool {
                                     // Don't send if the result is too old
                                     if let Some(last_modified) = *last_modified_opt.read().unwrap()
                                     {
@@ -5818,6 +6260,7 @@ impl Tab {
                                         path.to_path_buf(),
                                         name.to_string(),
                                         metadata,
+                                        matches_opt,
                                     )) {
                                         Ok(()) => {
                                             if !ready.swap(true, atomic::Ordering::SeqCst) {
@@ -5844,9 +6287,18 @@ impl Tab {
                                 path,
                                 start.elapsed(),
                             );
+                            result
                         })
                         .await
-                        .unwrap();
+                        .unwrap()
+                    };
+                    if let Err(err) = result {
+                        log::warn!("failed to search for {:?}: {}", location, err);
+                        let _ = output
+                            .lock()
+                            .await
+                            .send(Message::SearchError(location, err.to_string()))
+                            .await;
                     }
 
                     // Send final ready
@@ -1,14 +1,27 @@
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, BTreeSet, HashSet, VecDeque, hash_map::DefaultHasher},
+    ffi::{OsStr, OsString},
     fs,
-    io::{self, Read, Write},
-    path::Path,
+    hash::{Hash, Hasher},
+    io::{self, BufRead, Read, Write},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
+use walkdir::WalkDir;
 use zip::result::ZipError;
 
 use crate::{
-    utils::mime_icon::mime_for_path,
-    core::operations::{Controller, OpReader, OperationError, OperationErrorType},
+    core::operations::{
+        ConflictPolicy, Controller, ExtractLimits, FilePolicy, OpReader, OperationError,
+        OperationErrorType, ReplaceResult, UnsafeEntry,
+        conflict::{Resolution, Source},
+    },
+    utils::{
+        mime_icon::mime_for_path,
+        package::{self, Kind},
+    },
+    views::dialogs::dialog_pages::ArchiveType,
 };
 
 pub const SUPPORTED_ARCHIVE_TYPES: &[&str] = &[
@@ -28,87 +41,1898 @@ pub const SUPPORTED_ARCHIVE_TYPES: &[&str] = &[
     "application/x-xz",
     #[cfg(feature = "lzma-rust2")]
     "application/x-xz-compressed-tar",
+    #[cfg(feature = "sevenz-rust2")]
+    "application/x-7z-compressed",
+    #[cfg(feature = "zstd")]
+    "application/zstd",
+    #[cfg(feature = "zstd")]
+    "application/x-zstd-compressed-tar",
+    // Disk images and packages, see [`package::PACKAGE_TYPES`]
+    "application/vnd.debian.binary-package",
+    "application/vnd.efi.iso",
+    "application/x-archive",
+    "application/x-cd-image",
+    "application/x-cpio",
+    "application/x-deb",
+    "application/x-debian-package",
+    "application/x-iso9660-image",
+    "application/x-redhat-package-manager",
+    "application/x-rpm",
 ];
 
 pub const SUPPORTED_EXTENSIONS: &[&str] = &[
+    ".7z",
     ".tar.bz2",
     ".tar.gz",
     ".tar.lzma",
     ".tar.xz",
+    ".tar.zst",
     ".tgz",
     ".tar",
     ".zip",
+    ".cpio",
+    ".deb",
+    ".iso",
+    ".rpm",
+    // After the tar archives, which end with them too
+    ".bz2",
+    ".gz",
+    ".xz",
+    ".zst",
 ];
 
+/// Compressors of a single stream, which is either a tar archive or a plain file
+const COMPRESSED_FILE_TYPES: &[&str] = &[
+    "application/gzip",
+    "application/x-bzip",
+    "application/x-bzip2",
+    "application/x-xz",
+    "application/zstd",
+];
+
+/// Archives that can be browsed like folders
+pub const BROWSABLE_ARCHIVE_TYPES: &[&str] = &[
+    "application/x-compressed-tar",
+    "application/x-tar",
+    "application/zip",
+    #[cfg(feature = "bzip2")]
+    "application/x-bzip-compressed-tar",
+    #[cfg(feature = "bzip2")]
+    "application/x-bzip2-compressed-tar",
+    #[cfg(feature = "lzma-rust2")]
+    "application/x-xz-compressed-tar",
+    #[cfg(feature = "sevenz-rust2")]
+    "application/x-7z-compressed",
+    #[cfg(feature = "zstd")]
+    "application/x-zstd-compressed-tar",
+    // Disk images and packages, see [`package::PACKAGE_TYPES`]
+    "application/vnd.debian.binary-package",
+    "application/vnd.efi.iso",
+    "application/x-archive",
+    "application/x-cd-image",
+    "application/x-cpio",
+    "application/x-deb",
+    "application/x-debian-package",
+    "application/x-iso9660-image",
+    "application/x-redhat-package-manager",
+    "application/x-rpm",
+];
+
+/// Bytes read from the start of each file to estimate the size of an archive
+const SAMPLE_PER_FILE: u64 = 64 * 1024;
+/// Bytes read in total to estimate the size of an archive
+const SAMPLE_LIMIT: usize = 4 * 1024 * 1024;
+/// Extracted bytes from which the compression ratio is checked, as small archives of repetitive
+/// files can be compressed very well
+const RATIO_MIN_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Asks how to resolve a file that exists where an entry would be extracted to, like the replace
+/// dialog of pasting
+pub type AskReplace<'a> = dyn Fn(&Entry, &Path) -> ReplaceResult + 'a;
+
+/// Checks the entries of an archive while it is extracted, so nothing is written outside of the
+/// destination and decompression bombs are stopped. The first entry refused is kept, as the
+/// errors of the archive readers cannot carry it.
+struct Guard<'a> {
+    dst: &'a Path,
+    limits: ExtractLimits,
+    /// Size of the archive, for the compression ratio
+    compressed: u64,
+    written: u64,
+    refused: Option<OperationErrorType>,
+    ask: Option<&'a AskReplace<'a>>,
+    /// Answer applied to all remaining conflicts
+    replace_result_opt: Option<ReplaceResult>,
+    /// Rule chosen for all remaining conflicts instead of the policy
+    rule_opt: Option<FilePolicy>,
+}
+
+impl<'a> Guard<'a> {
+    fn new(
+        archive: &Path,
+        dst: &'a Path,
+        limits: ExtractLimits,
+        ask: Option<&'a AskReplace<'a>>,
+    ) -> Self {
+        Self {
+            dst,
+            limits,
+            compressed: fs::metadata(archive).map_or(0, |metadata| metadata.len()),
+            written: 0,
+            refused: None,
+            ask,
+            replace_result_opt: None,
+            rule_opt: None,
+        }
+    }
+
+    /// Where an entry is extracted to when `outpath` may exist, or `None` if it is skipped.
+    /// Conflicts the policy would ask about are asked with `ask`, or keep both files without it.
+    fn resolve(
+        &mut self,
+        conflicts: &ConflictPolicy,
+        outpath: &Path,
+        len: u64,
+        modified: Option<SystemTime>,
+    ) -> io::Result<Option<PathBuf>> {
+        // Files are merged into existing directories
+        if !fs::symlink_metadata(outpath).is_ok_and(|metadata| !metadata.is_dir()) {
+            return Ok(Some(outpath.to_path_buf()));
+        }
+        let source = Source {
+            path: None,
+            len,
+            modified,
+        };
+        let resolution = match self.rule_opt {
+            Some(files) => ConflictPolicy {
+                files,
+                ..conflicts.clone()
+            }
+            .resolve_file(&source, outpath)?,
+            None => conflicts.resolve_file(&source, outpath)?,
+        };
+        let replace_result = match resolution {
+            Resolution::Replace => ReplaceResult::Replace(false),
+            Resolution::Skip => return Ok(None),
+            Resolution::Rename(path) => return Ok(Some(path)),
+            Resolution::Ask => match (self.replace_result_opt, self.ask) {
+                (Some(replace_result), _) => replace_result,
+                (None, Some(ask)) => {
+                    let entry = Entry {
+                        path: outpath
+                            .strip_prefix(self.dst)
+                            .unwrap_or(outpath)
+                            .to_path_buf(),
+                        is_dir: false,
+                        size: len,
+                        modified,
+                    };
+                    ask(&entry, outpath)
+                }
+                (None, None) => ReplaceResult::KeepBoth,
+            },
+        };
+        match replace_result {
+            ReplaceResult::Replace(apply_to_all) => {
+                if apply_to_all {
+                    self.replace_result_opt = Some(replace_result);
+                }
+                // Never write through an existing symlink
+                fs::remove_file(outpath)?;
+                Ok(Some(outpath.to_path_buf()))
+            }
+            ReplaceResult::KeepBoth => Ok(Some(conflicts.renamed_path(outpath))),
+            ReplaceResult::Skip(apply_to_all) => {
+                if apply_to_all {
+                    self.replace_result_opt = Some(replace_result);
+                }
+                Ok(None)
+            }
+            // The rule applies to this and all remaining conflicts
+            ReplaceResult::Policy(files) if files != FilePolicy::Ask => {
+                self.rule_opt = Some(files);
+                self.resolve(conflicts, outpath, len, modified)
+            }
+            // Cancelling stops the controller, which ends the extraction at the next entry
+            ReplaceResult::Policy(_) | ReplaceResult::Cancel => Ok(None),
+        }
+    }
+
+    /// Refuse `entry`, returning an error to stop extracting
+    fn refuse(&mut self, entry: &Path, reason: UnsafeEntry) -> io::Error {
+        self.refused.get_or_insert(OperationErrorType::UnsafeEntry {
+            entry: entry.to_path_buf(),
+            reason,
+        });
+        io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+    }
+
+    /// The error of extracting, which is the refused entry if there is one
+    fn error<E: ToString>(&mut self, err: E, controller: &Controller) -> OperationError {
+        match self.refused.take() {
+            Some(kind) => OperationError::from_kind(kind, controller),
+            None => OperationError::from_err(err, controller),
+        }
+    }
+
+    /// Like [`Self::error`], telling apart a missing or wrong password
+    fn zip_error(
+        &mut self,
+        err: ZipError,
+        password: bool,
+        controller: &Controller,
+    ) -> OperationError {
+        let kind = match err {
+            ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => {
+                OperationErrorType::PasswordRequired
+            }
+            ZipError::InvalidPassword if password => OperationErrorType::WrongPassword,
+            ZipError::InvalidPassword => OperationErrorType::PasswordRequired,
+            _ => return self.error(err, controller),
+        };
+        OperationError::from_kind(kind, controller)
+    }
+
+    /// Like [`Self::zip_error`] for 7z archives
+    #[cfg(feature = "sevenz-rust2")]
+    fn sevenz_error(
+        &mut self,
+        err: sevenz_rust2::Error,
+        controller: &Controller,
+    ) -> OperationError {
+        let kind = match err {
+            sevenz_rust2::Error::PasswordRequired => OperationErrorType::PasswordRequired,
+            sevenz_rust2::Error::MaybeBadPassword(_) => OperationErrorType::WrongPassword,
+            _ => return self.error(err, controller),
+        };
+        OperationError::from_kind(kind, controller)
+    }
+
+    /// Path of `entry` relative to the destination, refused if it is absolute or goes up
+    fn relative(&mut self, entry: &Path) -> io::Result<PathBuf> {
+        tar_out_path(Path::new(""), entry).ok_or_else(|| self.refuse(entry, UnsafeEntry::Traversal))
+    }
+
+    /// Path of `entry` inside of the destination, see [`Self::relative`] and [`Self::parents`]
+    fn out_path(&mut self, entry: &Path) -> io::Result<PathBuf> {
+        let relative = self.relative(entry)?;
+        let outpath = self.dst.join(relative);
+        self.parents(entry, &outpath)?;
+        Ok(outpath)
+    }
+
+    /// If `path` is inside of a symbolic link extracted before, which could lead anywhere
+    fn through_symlink(&self, path: &Path) -> bool {
+        path.ancestors()
+            .skip(1)
+            .take_while(|ancestor| ancestor.starts_with(self.dst) && *ancestor != self.dst)
+            .any(|ancestor| fs::symlink_metadata(ancestor).is_ok_and(|m| m.is_symlink()))
+    }
+
+    /// Refuse `entry` if `outpath` is inside of a symbolic link, see [`Self::through_symlink`]
+    fn parents(&mut self, entry: &Path, outpath: &Path) -> io::Result<()> {
+        if self.through_symlink(outpath) {
+            return Err(self.refuse(entry, UnsafeEntry::SymlinkEscape));
+        }
+        Ok(())
+    }
+
+    /// Refuse a symbolic link at `outpath` to `target` if it points outside of the destination.
+    /// `..` is only allowed at the start, as after another link it would go up from its target.
+    fn symlink(&mut self, entry: &Path, outpath: &Path, target: &Path) -> io::Result<()> {
+        let mut depth = outpath
+            .parent()
+            .and_then(|parent| parent.strip_prefix(self.dst).ok())
+            .map_or(0, |parent| parent.components().count());
+        let mut down = false;
+        let inside = target.components().all(|component| match component {
+            Component::Prefix(_) | Component::RootDir => false,
+            Component::CurDir => true,
+            Component::ParentDir if down || depth == 0 => false,
+            Component::ParentDir => {
+                depth -= 1;
+                true
+            }
+            Component::Normal(_) => {
+                down = true;
+                true
+            }
+        });
+        if !inside {
+            return Err(self.refuse(entry, UnsafeEntry::SymlinkEscape));
+        }
+        Ok(())
+    }
+
+    /// Refuse a hard link to `target` unless it is a path inside of the destination
+    fn hard_link(&mut self, entry: &Path, target: &Path) -> io::Result<()> {
+        match tar_out_path(self.dst, target) {
+            Some(target) if !self.through_symlink(&target) => Ok(()),
+            _ => Err(self.refuse(entry, UnsafeEntry::HardLinkEscape)),
+        }
+    }
+
+    /// Count `len` more bytes written for `entry`, refused if they go over the limits
+    fn add(&mut self, entry: &Path, len: u64) -> io::Result<()> {
+        self.written = self.written.saturating_add(len);
+        if self.limits.max_size.is_some_and(|max| self.written > max) {
+            return Err(self.refuse(entry, UnsafeEntry::TooLarge));
+        }
+        if self.written > RATIO_MIN_SIZE
+            && self
+                .limits
+                .max_ratio
+                .is_some_and(|max| self.written / self.compressed.max(1) > max)
+        {
+            return Err(self.refuse(entry, UnsafeEntry::TooCompressed));
+        }
+        Ok(())
+    }
+
+    /// Copy the contents of `entry`, counting what is written as it goes, since the sizes in
+    /// headers may be wrong
+    fn copy(
+        &mut self,
+        entry: &Path,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+    ) -> io::Result<u64> {
+        let mut buffer = [0; 64 * 1024];
+        let mut total = 0;
+        loop {
+            let count = match reader.read(&mut buffer) {
+                Ok(0) => return Ok(total),
+                Ok(count) => count,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            self.add(entry, count as u64)?;
+            writer.write_all(&buffer[..count])?;
+            total += count as u64;
+        }
+    }
+}
+
+/// Extract the archive at `path` into `new_dir`. Entries that would be written outside of it,
+/// special files and archives over `limits` are refused with [`OperationErrorType::UnsafeEntry`].
+/// Existing files are resolved by `conflicts`, with `ask` when the policy is to ask.
 pub fn extract(
     path: &Path,
     new_dir: &Path,
     password: &Option<String>,
+    conflicts: &ConflictPolicy,
+    ask: Option<&AskReplace>,
+    limits: &ExtractLimits,
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    let mime = mime_for_path(path, None, false);
+    let password = password.clone();
+    let has_password = password.is_some();
+    let mut guard = Guard::new(path, new_dir, *limits, ask);
+    match mime.essence_str() {
+        essence if COMPRESSED_FILE_TYPES.contains(&essence) => {
+            decompress(path, essence, &[], conflicts, controller, &mut guard)
+                .map_err(|e| guard.error(e, controller))?
+        }
+        "application/x-compressed-tar" => OpReader::new(path, controller.clone())
+            .map(io::BufReader::new)
+            .map(flate2::read::GzDecoder::new)
+            .map(tar::Archive::new)
+            .and_then(|archive| tar_unpack(archive, new_dir, conflicts, &mut guard))
+            .map_err(|e| guard.error(e, controller))?,
+        "application/x-tar" => OpReader::new(path, controller.clone())
+            .map(io::BufReader::new)
+            .map(tar::Archive::new)
+            .and_then(|archive| tar_unpack(archive, new_dir, conflicts, &mut guard))
+            .map_err(|e| guard.error(e, controller))?,
+        "application/zip" => fs::File::open(path)
+            .map(io::BufReader::new)
+            .map(zip::ZipArchive::new)
+            .map_err(|e| OperationError::from_err(e, controller))?
+            .and_then(|mut archive| {
+                zip_extract(
+                    &mut archive,
+                    password,
+                    conflicts,
+                    controller.clone(),
+                    &mut guard,
+                )
+            })
+            .map_err(|e| guard.zip_error(e, has_password, controller))?,
+        #[cfg(feature = "bzip2")]
+        "application/x-bzip-compressed-tar" | "application/x-bzip2-compressed-tar" => {
+            OpReader::new(path, controller.clone())
+                .map(io::BufReader::new)
+                .map(bzip2::read::BzDecoder::new)
+                .map(tar::Archive::new)
+                .and_then(|archive| tar_unpack(archive, new_dir, conflicts, &mut guard))
+                .map_err(|e| guard.error(e, controller))?
+        }
+        #[cfg(feature = "lzma-rust2")]
+        "application/x-xz-compressed-tar" => OpReader::new(path, controller.clone())
+            .map(io::BufReader::new)
+            .map(|reader| lzma_rust2::XzReader::new(reader, true))
+            .map(tar::Archive::new)
+            .and_then(|archive| tar_unpack(archive, new_dir, conflicts, &mut guard))
+            .map_err(|e| guard.error(e, controller))?,
+        #[cfg(feature = "zstd")]
+        "application/x-zstd-compressed-tar" => OpReader::new(path, controller.clone())
+            .and_then(zstd::stream::read::Decoder::new)
+            .map(tar::Archive::new)
+            .and_then(|archive| tar_unpack(archive, new_dir, conflicts, &mut guard))
+            .map_err(|e| guard.error(e, controller))?,
+        #[cfg(feature = "sevenz-rust2")]
+        "application/x-7z-compressed" => sevenz_extract(
+            path,
+            &[],
+            password.as_deref(),
+            conflicts,
+            controller,
+            &mut guard,
+        )
+        .map_err(|e| guard.sevenz_error(e, controller))?,
+        essence if package::is_package(essence) => {
+            package_unpack(path, essence, &[], conflicts, controller, &mut guard)
+                .map_err(|e| guard.error(e, controller))?
+        }
+        _ => Err(OperationError::from_err(
+            format!("unsupported mime type {:?}", mime),
+            controller,
+        ))?,
+    }
+    Ok(())
+}
+
+/// Compressor of a tar archive, or of a single file
+pub enum TarEncoder<W: Write> {
+    /// Not compressed, for .tar archives
+    Plain(W),
+    Gz(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "bzip2")]
+    Bz2(bzip2::write::BzEncoder<W>),
+    #[cfg(feature = "lzma-rust2")]
+    Xz(lzma_rust2::XzWriter<W>),
+    #[cfg(feature = "zstd")]
+    Zst(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl<W: Write> TarEncoder<W> {
+    pub fn new(writer: W, archive_type: ArchiveType, level: u32) -> io::Result<Self> {
+        Ok(match archive_type {
+            ArchiveType::Tgz | ArchiveType::Gz => Self::Gz(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::new(level),
+            )),
+            #[cfg(feature = "bzip2")]
+            ArchiveType::TarBz2 | ArchiveType::Bz2 => Self::Bz2(bzip2::write::BzEncoder::new(
+                writer,
+                bzip2::Compression::new(level),
+            )),
+            #[cfg(feature = "lzma-rust2")]
+            ArchiveType::TarXz | ArchiveType::Xz => Self::Xz(lzma_rust2::XzWriter::new(
+                writer,
+                lzma_rust2::XzOptions::with_preset(level),
+            )?),
+            #[cfg(feature = "zstd")]
+            ArchiveType::TarZst | ArchiveType::Zst => {
+                Self::Zst(zstd::stream::write::Encoder::new(writer, level as i32)?)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("{:?} is not a tar archive", archive_type),
+                ));
+            }
+        })
+    }
+
+    /// Write the end of the compressed stream, returning the inner writer
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Self::Plain(writer) => Ok(writer),
+            Self::Gz(encoder) => encoder.finish(),
+            #[cfg(feature = "bzip2")]
+            Self::Bz2(encoder) => encoder.finish(),
+            #[cfg(feature = "lzma-rust2")]
+            Self::Xz(encoder) => encoder.finish(),
+            #[cfg(feature = "zstd")]
+            Self::Zst(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for TarEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            Self::Gz(encoder) => encoder.write(buf),
+            #[cfg(feature = "bzip2")]
+            Self::Bz2(encoder) => encoder.write(buf),
+            #[cfg(feature = "lzma-rust2")]
+            Self::Xz(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Self::Zst(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            Self::Gz(encoder) => encoder.flush(),
+            #[cfg(feature = "bzip2")]
+            Self::Bz2(encoder) => encoder.flush(),
+            #[cfg(feature = "lzma-rust2")]
+            Self::Xz(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Self::Zst(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Name of `path` inside of an archive of items in `relative_root`
+fn archive_name<'a>(
+    path: &'a Path,
+    relative_root: &Path,
+    controller: &Controller,
+) -> Result<Option<&'a str>, OperationError> {
+    Ok(path
+        .strip_prefix(relative_root)
+        .map_err(|e| OperationError::from_err(e, controller))?
+        .to_str())
+}
+
+/// Write `paths` to a tar archive compressed by `encoder`, returning the inner writer
+pub fn tar_compress<W: Write>(
+    encoder: TarEncoder<W>,
+    paths: &[PathBuf],
+    relative_root: &Path,
+    controller: &Controller,
+) -> Result<W, OperationError> {
+    let mut archive = tar::Builder::new(encoder);
+    let total_paths = paths.len();
+    for (i, path) in paths.iter().enumerate() {
+        futures::executor::block_on(controller.check())
+            .map_err(|s| OperationError::from_state(s, controller))?;
+
+        controller.set_progress((i as f32) / total_paths as f32);
+
+        if let Some(relative_path) = archive_name(path, relative_root, controller)? {
+            archive
+                .append_path_with_name(path, relative_path)
+                .map_err(|e| OperationError::from_err(e, controller))?;
+        }
+    }
+
+    archive
+        .into_inner()
+        .and_then(TarEncoder::finish)
+        .map_err(|e| OperationError::from_err(e, controller))
+}
+
+/// Compress the single file at `path` into `to` as `archive_type`, which must be
+/// [`ArchiveType::is_single_file`]. The compressed file keeps the modification time of `path`.
+pub fn compress_file(
+    path: &Path,
+    to: &Path,
+    archive_type: ArchiveType,
+    level: u32,
+    controller: &Controller,
+) -> io::Result<()> {
+    let modified = fs::metadata(path)?.modified().ok();
+    let mut reader = OpReader::new(path, controller.clone())?;
+    let writer = io::BufWriter::new(fs::File::create(to)?);
+    let mut encoder = TarEncoder::new(writer, archive_type, level)?;
+    io::copy(&mut reader, &mut encoder)?;
+    let file = encoder.finish()?.into_inner().map_err(|e| e.into_error())?;
+    if let Some(modified) = modified {
+        file.set_modified(modified)?;
+    }
+    Ok(())
+}
+
+/// Write `paths` to a 7z archive at `to`, compressed with LZMA2 at `level`
+#[cfg(feature = "sevenz-rust2")]
+pub fn sevenz_compress(
+    paths: &[PathBuf],
+    relative_root: &Path,
+    to: &Path,
+    level: u32,
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    use sevenz_rust2::{
+        ArchiveEntry, ArchiveWriter, EncoderConfiguration, EncoderMethod,
+        encoder_options::{EncoderOptions, Lzma2Options},
+    };
+
+    let mut archive =
+        ArchiveWriter::create(to).map_err(|e| OperationError::from_err(e, controller))?;
+    archive.set_content_methods(vec![
+        EncoderConfiguration::new(EncoderMethod::LZMA2)
+            .with_options(EncoderOptions::Lzma2(Lzma2Options::from_level(level))),
+    ]);
+
+    let total_paths = paths.len();
+    for (i, path) in paths.iter().enumerate() {
+        futures::executor::block_on(controller.check())
+            .map_err(|s| OperationError::from_state(s, controller))?;
+
+        controller.set_progress((i as f32) / total_paths as f32);
+
+        let Some(relative_path) = archive_name(path, relative_root, controller)? else {
+            continue;
+        };
+        let entry = ArchiveEntry::from_path(path, relative_path.to_string());
+        if path.is_dir() {
+            archive.push_archive_entry::<fs::File>(entry, None)
+        } else {
+            let file = fs::File::open(path).map_err(|e| OperationError::from_err(e, controller))?;
+            archive.push_archive_entry(entry, Some(file))
+        }
+        .map_err(|e| OperationError::from_err(e, controller))?;
+    }
+
+    archive
+        .finish()
+        .map_err(|e| OperationError::from_err(e, controller))?;
+    Ok(())
+}
+
+/// Like `sevenz_rust2::decompress`, with existing files resolved by the conflict policy. Only
+/// `entries` are extracted if there are any, see [`selected_path`].
+#[cfg(feature = "sevenz-rust2")]
+fn sevenz_extract(
+    path: &Path,
+    entries: &[PathBuf],
+    password: Option<&str>,
+    conflicts: &ConflictPolicy,
+    controller: &Controller,
+    guard: &mut Guard,
+) -> Result<(), sevenz_rust2::Error> {
+    let dst = guard.dst;
+    fs::create_dir_all(dst)?;
+    let password =
+        password.map_or_else(sevenz_rust2::Password::empty, sevenz_rust2::Password::from);
+    sevenz_rust2::decompress_with_extract_fn_and_password(
+        fs::File::open(path)?,
+        dst,
+        password,
+        |entry, reader, _| {
+            futures::executor::block_on(controller.check())
+                .map_err(|s| io::Error::other(OperationError::from_state(s, controller)))?;
+
+            let name = Path::new(entry.name());
+            let relative = guard.relative(name)?;
+            let Some(outpath) = selected_path(entries, &relative).map(|path| dst.join(path)) else {
+                io::copy(reader, &mut io::sink())?;
+                return Ok(true);
+            };
+            guard.parents(name, &outpath)?;
+            if entry.is_directory() {
+                fs::create_dir_all(&outpath)?;
+                return Ok(true);
+            }
+            if let Some(parent) = outpath.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            match guard.resolve(conflicts, &outpath, entry.size(), None)? {
+                Some(outpath) => guard.copy(name, reader, &mut fs::File::create(outpath)?)?,
+                // Solid archives are decompressed in one stream, so skipped entries are read too
+                None => io::copy(reader, &mut io::sink())?,
+            };
+            Ok(true)
+        },
+    )
+}
+
+/// Counts the bytes written to it
+#[derive(Default)]
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Estimate the size of an archive of `paths` by compressing the start of their files.
+/// Items that cannot be read are left out.
+pub fn estimate_size(paths: &[PathBuf], archive_type: ArchiveType, level: u32) -> io::Result<u64> {
+    let mut total = 0;
+    let mut sample = Vec::new();
+    for path in paths {
+        for entry in WalkDir::new(path).into_iter().filter_map(Result::ok) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            total += metadata.len();
+            if sample.len() < SAMPLE_LIMIT {
+                let len = SAMPLE_PER_FILE.min((SAMPLE_LIMIT - sample.len()) as u64);
+                if let Ok(file) = fs::File::open(entry.path()) {
+                    file.take(len).read_to_end(&mut sample)?;
+                }
+            }
+        }
+    }
+    if sample.is_empty() {
+        return Ok(0);
+    }
+
+    let compressed = match archive_type {
+        ArchiveType::Zip => {
+            let mut encoder = flate2::write::DeflateEncoder::new(
+                ByteCounter::default(),
+                flate2::Compression::new(level),
+            );
+            encoder.write_all(&sample)?;
+            encoder.finish()?.0
+        }
+        // Also compressed with LZMA2, in a different container
+        #[cfg(feature = "sevenz-rust2")]
+        ArchiveType::SevenZ => {
+            let mut encoder = TarEncoder::new(ByteCounter::default(), ArchiveType::TarXz, level)?;
+            encoder.write_all(&sample)?;
+            encoder.finish()?.0
+        }
+        _ => {
+            let mut encoder = TarEncoder::new(ByteCounter::default(), archive_type, level)?;
+            encoder.write_all(&sample)?;
+            encoder.finish()?.0
+        }
+    };
+    Ok((u128::from(compressed) * u128::from(total) / sample.len() as u128) as u64)
+}
+
+/// An item inside of an archive
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    /// Path relative to the root of the archive
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+pub fn can_browse(mime: &mime_guess::Mime) -> bool {
+    BROWSABLE_ARCHIVE_TYPES.contains(&mime.essence_str())
+}
+
+/// Decompressed stream of a tar archive, or `None` if `mime` is not a tar archive
+pub fn tar_decoder<'a, R: BufRead + 'a>(
+    reader: R,
+    mime: &str,
+) -> io::Result<Option<Box<dyn Read + 'a>>> {
+    Ok(Some(match mime {
+        "application/gzip" | "application/x-compressed-tar" => {
+            Box::new(flate2::read::GzDecoder::new(reader))
+        }
+        "application/x-tar" => Box::new(reader),
+        #[cfg(feature = "bzip2")]
+        "application/x-bzip"
+        | "application/x-bzip-compressed-tar"
+        | "application/x-bzip2"
+        | "application/x-bzip2-compressed-tar" => Box::new(bzip2::read::BzDecoder::new(reader)),
+        #[cfg(feature = "lzma-rust2")]
+        "application/x-xz" | "application/x-xz-compressed-tar" => {
+            Box::new(lzma_rust2::XzReader::new(reader, true))
+        }
+        #[cfg(feature = "zstd")]
+        "application/zstd" | "application/x-zstd-compressed-tar" => {
+            Box::new(zstd::stream::read::Decoder::with_buffer(reader)?)
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// If `head` starts with a tar header, found by its magic or by the checksum of older formats
+fn is_tar_header(head: &[u8]) -> bool {
+    let Some(header) = head.get(..512) else {
+        return false;
+    };
+    if header[257..262] == *b"ustar" {
+        return true;
+    }
+    // The checksum is computed with its own field as spaces
+    let sum: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| {
+            if (148..156).contains(&i) {
+                32
+            } else {
+                u64::from(byte)
+            }
+        })
+        .sum();
+    std::str::from_utf8(&header[148..156])
+        .ok()
+        .map(|field| field.trim_matches(|c| c == ' ' || c == '\0'))
+        .and_then(|field| u64::from_str_radix(field, 8).ok())
+        == Some(sum)
+}
+
+/// Read the start of a decompressed stream to tell if it is a tar archive, returning the stream
+/// with that start put back
+fn peek_tar<'a>(mut decoder: Box<dyn Read + 'a>) -> io::Result<(Box<dyn Read + 'a>, bool)> {
+    let mut head = Vec::with_capacity(512);
+    decoder.by_ref().take(512).read_to_end(&mut head)?;
+    let is_tar = is_tar_header(&head);
+    Ok((Box::new(io::Cursor::new(head).chain(decoder)), is_tar))
+}
+
+/// Name of a compressed single file once decompressed, without the extension of its compressor
+fn decompressed_name(path: &Path) -> PathBuf {
+    path.file_stem()
+        .map_or_else(|| PathBuf::from("decompressed"), PathBuf::from)
+}
+
+/// Read the entries of the archive at `path` without extracting it
+pub fn list(path: &Path) -> io::Result<Vec<Entry>> {
+    let mime = mime_for_path(path, None, false);
+    match mime.essence_str() {
+        "application/zip" => {
+            let mut archive = fs::File::open(path)
+                .map(io::BufReader::new)
+                .and_then(|reader| zip::ZipArchive::new(reader).map_err(io::Error::other))?;
+            let mut entries = Vec::with_capacity(archive.len());
+            for i in 0..archive.len() {
+                let file = archive.by_index_raw(i).map_err(io::Error::other)?;
+                let Some(path) = file.enclosed_name() else {
+                    continue;
+                };
+                entries.push(Entry {
+                    path,
+                    is_dir: file.is_dir(),
+                    size: file.size(),
+                    modified: file.last_modified().and_then(zip_modified),
+                });
+            }
+            Ok(entries)
+        }
+        #[cfg(feature = "sevenz-rust2")]
+        "application/x-7z-compressed" => {
+            let archive = sevenz_rust2::Archive::open(path).map_err(io::Error::other)?;
+            Ok(archive
+                .files
+                .iter()
+                .filter_map(|file| {
+                    Some(Entry {
+                        path: tar_out_path(Path::new(""), Path::new(file.name()))?,
+                        is_dir: file.is_directory(),
+                        size: file.size(),
+                        modified: file
+                            .has_last_modified_date
+                            .then(|| SystemTime::try_from(file.last_modified_date).ok())
+                            .flatten(),
+                    })
+                })
+                .collect())
+        }
+        essence if package::is_package(essence) => {
+            let mut entries = Vec::new();
+            package::for_each(path, essence, &Controller::default(), &mut |item, _| {
+                if let Some(path) = tar_out_path(Path::new(""), &item.path)
+                    .filter(|path| !path.as_os_str().is_empty())
+                {
+                    entries.push(Entry {
+                        path,
+                        is_dir: item.kind == Kind::Dir,
+                        size: item.size,
+                        modified: item.modified,
+                    });
+                }
+                Ok(true)
+            })?;
+            Ok(entries)
+        }
+        essence => {
+            let reader = io::BufReader::new(fs::File::open(path)?);
+            let Some(mut decoder) = tar_decoder(reader, essence)? else {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unsupported mime type {:?}", mime),
+                ));
+            };
+            if COMPRESSED_FILE_TYPES.contains(&essence) {
+                let is_tar;
+                (decoder, is_tar) = peek_tar(decoder)?;
+                if !is_tar {
+                    // The size is only known after decompressing everything
+                    return Ok(vec![Entry {
+                        path: decompressed_name(path),
+                        is_dir: false,
+                        size: 0,
+                        modified: fs::metadata(path)?.modified().ok(),
+                    }]);
+                }
+            }
+            let mut archive = tar::Archive::new(decoder);
+            let mut entries = Vec::new();
+            for entry in archive.entries()? {
+                let entry = entry?;
+                let Some(path) = tar_out_path(Path::new(""), &entry.path()?) else {
+                    continue;
+                };
+                // The root of the archive is sometimes stored as ./
+                if path.as_os_str().is_empty() {
+                    continue;
+                }
+                entries.push(Entry {
+                    path,
+                    is_dir: entry.header().entry_type().is_dir(),
+                    size: entry.size(),
+                    modified: entry
+                        .header()
+                        .mtime()
+                        .ok()
+                        .map(|mtime| UNIX_EPOCH + Duration::from_secs(mtime)),
+                });
+            }
+            Ok(entries)
+        }
+    }
+}
+
+/// If the archive at `path` has encrypted entries, so a password is needed to extract it
+pub fn needs_password(path: &Path) -> io::Result<bool> {
+    let mime = mime_for_path(path, None, false);
+    match mime.essence_str() {
+        "application/zip" => {
+            let mut archive = fs::File::open(path)
+                .map(io::BufReader::new)
+                .and_then(|reader| zip::ZipArchive::new(reader).map_err(io::Error::other))?;
+            for i in 0..archive.len() {
+                if archive
+                    .by_index_raw(i)
+                    .map_err(io::Error::other)?
+                    .encrypted()
+                {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        // The headers of 7z archives can be encrypted too, then they cannot be opened at all
+        #[cfg(feature = "sevenz-rust2")]
+        "application/x-7z-compressed" => match sevenz_rust2::Archive::open(path) {
+            Ok(archive) => Ok(archive.folders.iter().any(|folder| {
+                folder.coders.iter().any(|coder| {
+                    coder.encoder_method_id() == sevenz_rust2::EncoderMethod::ID_AES256SHA256
+                })
+            })),
+            Err(sevenz_rust2::Error::PasswordRequired) => Ok(true),
+            Err(err) => Err(io::Error::other(err)),
+        },
+        _ => Ok(false),
+    }
+}
+
+/// Items directly inside of `dir` in an archive, with the number of items inside of each.
+/// Directories without an entry of their own are added from the paths of their contents.
+pub fn read_dir(entries: &[Entry], dir: &Path) -> Vec<(Entry, u64)> {
+    let mut items: BTreeMap<OsString, (Entry, HashSet<OsString>)> = BTreeMap::new();
+    for entry in entries {
+        let Ok(relative) = entry.path.strip_prefix(dir) else {
+            continue;
+        };
+        let mut components = relative.components();
+        let Some(Component::Normal(name)) = components.next() else {
+            continue;
+        };
+        let (item, children) = items.entry(name.to_os_string()).or_insert_with(|| {
+            (
+                Entry {
+                    path: dir.join(name),
+                    is_dir: true,
+                    size: 0,
+                    modified: None,
+                },
+                HashSet::new(),
+            )
+        });
+        match components.next() {
+            Some(child) => {
+                item.is_dir = true;
+                children.insert(child.as_os_str().to_os_string());
+            }
+            None => *item = entry.clone(),
+        }
+    }
+    items
+        .into_values()
+        .map(|(item, children)| (item, children.len() as u64))
+        .collect()
+}
+
+/// The only item at the top of an archive, if it has a single file or directory with everything
+/// else inside of it
+pub fn single_root(entries: &[Entry]) -> Option<PathBuf> {
+    let mut root: Option<&OsStr> = None;
+    for entry in entries {
+        let Some(Component::Normal(name)) = entry.path.components().next() else {
+            continue;
+        };
+        match root {
+            Some(root) if root != name => return None,
+            _ => root = Some(name),
+        }
+    }
+    root.map(PathBuf::from)
+}
+
+/// Split a path inside of an archive, like `/home/user/a.zip/dir/file`, into the path of the
+/// archive and the path of the entry inside of it
+pub fn split_entry_path(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    if path.exists() {
+        return None;
+    }
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.is_file())?;
+    let entry = path.strip_prefix(archive).ok()?;
+    Some((archive.to_path_buf(), entry.to_path_buf()))
+}
+
+/// Split `paths` into the ones that exist and the archive entries among them, grouped by archive
+pub fn group_entry_paths(paths: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<(PathBuf, Vec<PathBuf>)>) {
+    let mut other = Vec::new();
+    let mut archives: Vec<(PathBuf, Vec<PathBuf>)> = Vec::new();
+    for path in paths {
+        match split_entry_path(&path) {
+            Some((archive, entry)) => match archives.iter_mut().find(|(x, _)| *x == archive) {
+                Some((_, entries)) => entries.push(entry),
+                None => archives.push((archive, vec![entry])),
+            },
+            None => other.push(path),
+        }
+    }
+    (other, archives)
+}
+
+/// Path of an archive member at `path` relative to where it is extracted, or `None` if it is
+/// not selected. Selected entries are extracted with everything inside of them, into the same
+/// directory. Everything is selected if `entries` is empty.
+fn selected_path(entries: &[PathBuf], path: &Path) -> Option<PathBuf> {
+    if entries.is_empty() {
+        return Some(path.to_path_buf());
+    }
+    let entry = entries.iter().find(|entry| path.starts_with(entry))?;
+    let relative = path.strip_prefix(entry.parent()?).ok()?;
+    Some(relative.to_path_buf())
+}
+
+/// Extract `entries` of the archive at `path` into `dst`, including everything inside of them.
+/// Entries are refused like in [`extract`].
+#[allow(clippy::too_many_arguments)]
+pub fn extract_entries(
+    path: &Path,
+    entries: &[PathBuf],
+    dst: &Path,
+    password: &Option<String>,
+    conflicts: &ConflictPolicy,
+    ask: Option<&AskReplace>,
+    limits: &ExtractLimits,
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    let mime = mime_for_path(path, None, false);
+    let mut guard = Guard::new(path, dst, *limits, ask);
+    match mime.essence_str() {
+        "application/zip" => fs::File::open(path)
+            .map(io::BufReader::new)
+            .map(zip::ZipArchive::new)
+            .map_err(|e| OperationError::from_err(e, controller))?
+            .and_then(|mut archive| {
+                zip_extract_entries(
+                    &mut archive,
+                    entries,
+                    password.as_deref(),
+                    conflicts,
+                    controller,
+                    &mut guard,
+                )
+            })
+            .map_err(|e| guard.zip_error(e, password.is_some(), controller)),
+        #[cfg(feature = "sevenz-rust2")]
+        "application/x-7z-compressed" => sevenz_extract(
+            path,
+            entries,
+            password.as_deref(),
+            conflicts,
+            controller,
+            &mut guard,
+        )
+        .map_err(|e| guard.sevenz_error(e, controller)),
+        essence if package::is_package(essence) => {
+            package_unpack(path, essence, entries, conflicts, controller, &mut guard)
+                .map_err(|e| guard.error(e, controller))
+        }
+        essence if COMPRESSED_FILE_TYPES.contains(&essence) => {
+            decompress(path, essence, entries, conflicts, controller, &mut guard)
+                .map_err(|e| guard.error(e, controller))
+        }
+        essence => {
+            let reader = OpReader::new(path, controller.clone())
+                .map(io::BufReader::new)
+                .map_err(|e| OperationError::from_err(e, controller))?;
+            match tar_decoder(reader, essence) {
+                Ok(Some(decoder)) => {
+                    tar_unpack_entries(tar::Archive::new(decoder), entries, conflicts, &mut guard)
+                        .map_err(|e| guard.error(e, controller))
+                }
+                Ok(None) => Err(OperationError::from_err(
+                    format!("unsupported mime type {:?}", mime),
+                    controller,
+                )),
+                Err(e) => Err(OperationError::from_err(e, controller)),
+            }
+        }
+    }
+}
+
+/// Archives that files can be added to
+pub fn can_add(mime: &mime_guess::Mime) -> bool {
+    mime.essence_str() == "application/zip" || tar_type(mime.essence_str()).is_some()
+}
+
+/// Format of a tar archive to write it again, `None` for uncompressed archives
+fn tar_type(mime: &str) -> Option<Option<ArchiveType>> {
+    Some(match mime {
+        "application/x-tar" => None,
+        "application/x-compressed-tar" => Some(ArchiveType::Tgz),
+        #[cfg(feature = "bzip2")]
+        "application/x-bzip-compressed-tar" | "application/x-bzip2-compressed-tar" => {
+            Some(ArchiveType::TarBz2)
+        }
+        #[cfg(feature = "lzma-rust2")]
+        "application/x-xz-compressed-tar" => Some(ArchiveType::TarXz),
+        #[cfg(feature = "zstd")]
+        "application/x-zstd-compressed-tar" => Some(ArchiveType::TarZst),
+        _ => return None,
+    })
+}
+
+/// Add `paths` to the top of the archive at `archive`, with everything inside of them. Entries
+/// with the same name are replaced. The archive is written again next to it and then renamed
+/// over it, so it is left as it was if anything fails.
+pub fn add(
+    archive: &Path,
+    paths: &[PathBuf],
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    let mut items = Vec::new();
+    for path in paths {
+        let Some(relative_root) = path.parent() else {
+            continue;
+        };
+        for entry in WalkDir::new(path) {
+            let entry = entry.map_err(|e| OperationError::from_err(e, controller))?;
+            if let Some(name) = archive_name(entry.path(), relative_root, controller)? {
+                items.push((entry.path().to_path_buf(), PathBuf::from(name)));
+            }
+        }
+    }
+    let replaced = |name: &Path| items.iter().any(|(_, item)| item == name);
+
+    let file_name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let tmp = archive.with_file_name(format!(".{file_name}.adding"));
+    let mime = mime_for_path(archive, None, false);
+    let written = match (mime.essence_str(), tar_type(mime.essence_str())) {
+        ("application/zip", _) => zip_add(archive, &tmp, &items, replaced, controller),
+        (_, Some(archive_type)) => tar_add(
+            archive,
+            &tmp,
+            mime.essence_str(),
+            archive_type,
+            &items,
+            replaced,
+            controller,
+        ),
+        _ => Err(OperationError::from_err(
+            format!("unsupported mime type {:?}", mime),
+            controller,
+        )),
+    };
+    let renamed = written.and_then(|()| {
+        if let Ok(metadata) = fs::metadata(archive) {
+            let _ = fs::set_permissions(&tmp, metadata.permissions());
+        }
+        fs::rename(&tmp, archive).map_err(|e| OperationError::from_err(e, controller))
+    });
+    if renamed.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    renamed
+}
+
+/// Copy the entries of a zip archive that are not replaced without decompressing them, then
+/// add the new items
+fn zip_add(
+    archive: &Path,
+    tmp: &Path,
+    items: &[(PathBuf, PathBuf)],
+    replaced: impl Fn(&Path) -> bool,
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    let err = |e: ZipError| OperationError::from_err(e, controller);
+    let mut reader = fs::File::open(archive)
+        .map(io::BufReader::new)
+        .map_err(|e| OperationError::from_err(e, controller))?;
+    let mut old = zip::ZipArchive::new(&mut reader).map_err(err)?;
+    let mut new = fs::File::create(tmp)
+        .map(io::BufWriter::new)
+        .map(zip::ZipWriter::new)
+        .map_err(|e| OperationError::from_err(e, controller))?;
+    let total = old.len() + items.len();
+    for i in 0..old.len() {
+        futures::executor::block_on(controller.check())
+            .map_err(|s| OperationError::from_state(s, controller))?;
+        controller.set_progress(i as f32 / total as f32);
+
+        let file = old.by_index_raw(i).map_err(err)?;
+        if !replaced(Path::new(file.name())) {
+            new.raw_copy_file(file).map_err(err)?;
+        }
+    }
+    for (i, (path, name)) in items.iter().enumerate() {
+        futures::executor::block_on(controller.check())
+            .map_err(|s| OperationError::from_state(s, controller))?;
+        controller.set_progress((old.len() + i) as f32 / total as f32);
+
+        let name = name.to_string_lossy();
+        let metadata = fs::metadata(path).map_err(|e| OperationError::from_err(e, controller))?;
+        let mut options = zip::write::SimpleFileOptions::default()
+            .large_file(metadata.len() >= 4 * 1024 * 1024 * 1024);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            options = options.unix_permissions(metadata.mode());
+        }
+        if metadata.is_dir() {
+            new.add_directory(name, options).map_err(err)?;
+        } else {
+            new.start_file(name, options).map_err(err)?;
+            fs::File::open(path)
+                .and_then(|mut file| io::copy(&mut file, &mut new))
+                .map_err(|e| OperationError::from_err(e, controller))?;
+        }
+    }
+    new.finish()
+        .map_err(err)?
+        .flush()
+        .map_err(|e| OperationError::from_err(e, controller))
+}
+
+/// Write the entries of a tar archive that are not replaced and the new items to a new archive
+/// of the same format. Compressed tar archives cannot be appended to.
+fn tar_add(
+    archive: &Path,
+    tmp: &Path,
+    mime: &str,
+    archive_type: Option<ArchiveType>,
+    items: &[(PathBuf, PathBuf)],
+    replaced: impl Fn(&Path) -> bool,
+    controller: &Controller,
+) -> Result<(), OperationError> {
+    let err = |e: io::Error| OperationError::from_err(e, controller);
+    let reader = OpReader::new(archive, controller.clone())
+        .map(io::BufReader::new)
+        .map_err(err)?;
+    let decoder = tar_decoder(reader, mime).map_err(err)?.ok_or_else(|| {
+        OperationError::from_err(format!("{mime} is not a tar archive"), controller)
+    })?;
+    let writer = fs::File::create(tmp).map(io::BufWriter::new).map_err(err)?;
+    let encoder = match archive_type {
+        Some(archive_type) => {
+            TarEncoder::new(writer, archive_type, archive_type.default_level()).map_err(err)?
+        }
+        None => TarEncoder::Plain(writer),
+    };
+    let mut new = tar::Builder::new(encoder);
+    let mut old = tar::Archive::new(decoder);
+    for entry in old.entries().map_err(err)? {
+        futures::executor::block_on(controller.check())
+            .map_err(|s| OperationError::from_state(s, controller))?;
+
+        let mut entry = entry.map_err(err)?;
+        let path = entry.path().map_err(err)?.into_owned();
+        if replaced(&path) {
+            continue;
+        }
+        // Long names are written again from the path, as the header only has the start of them
+        let mut header = entry.header().clone();
+        new.append_data(&mut header, path, &mut entry)
+            .map_err(err)?;
+    }
+    let total = items.len();
+    for (i, (path, name)) in items.iter().enumerate() {
+        futures::executor::block_on(controller.check())
+            .map_err(|s| OperationError::from_state(s, controller))?;
+        controller.set_progress(i as f32 / total as f32);
+
+        new.append_path_with_name(path, name).map_err(err)?;
+    }
+    new.into_inner()
+        .and_then(TarEncoder::finish)
+        .and_then(|mut writer| writer.flush())
+        .map_err(err)
+}
+
+/// Read every entry of the archive at `path` without writing anything, returning the entries
+/// that are corrupt with the reason. Entries are checked against their checksums, and an empty
+/// path is returned if the archive itself is damaged.
+pub fn test(
+    path: &Path,
+    password: &Option<String>,
     controller: &Controller,
-) -> Result<(), OperationError> {
+) -> Result<Vec<(PathBuf, String)>, OperationError> {
     let mime = mime_for_path(path, None, false);
-    let password = password.clone();
+    let mut corrupt = Vec::new();
     match mime.essence_str() {
-        "application/gzip" | "application/x-compressed-tar" => {
-            OpReader::new(path, controller.clone())
+        "application/zip" => {
+            let mut reader = fs::File::open(path)
                 .map(io::BufReader::new)
-                .map(flate2::read::GzDecoder::new)
-                .map(tar::Archive::new)
-                .and_then(|mut archive| archive.unpack(new_dir))
-                .map_err(|e| OperationError::from_err(e, controller))?
+                .map_err(|e| OperationError::from_err(e, controller))?;
+            let mut archive = match zip::ZipArchive::new(&mut reader) {
+                Ok(archive) => archive,
+                Err(e) => return Ok(vec![(PathBuf::new(), e.to_string())]),
+            };
+            let total = archive.len();
+            for i in 0..total {
+                futures::executor::block_on(controller.check())
+                    .map_err(|s| OperationError::from_state(s, controller))?;
+                controller.set_progress(i as f32 / total as f32);
+
+                let name = match archive.by_index_raw(i) {
+                    Ok(file) => PathBuf::from(file.name()),
+                    Err(e) => {
+                        corrupt.push((PathBuf::new(), e.to_string()));
+                        continue;
+                    }
+                };
+                let file = match password {
+                    None => archive.by_index(i),
+                    Some(password) => archive.by_index_decrypt(i, password.as_bytes()),
+                };
+                // Reading a file to the end checks its CRC
+                let read = match file {
+                    Ok(mut file) => io::copy(&mut file, &mut io::sink()).map_err(ZipError::from),
+                    Err(e) => Err(e),
+                };
+                match read {
+                    Ok(_) => {}
+                    Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)) => {
+                        return Err(OperationError::from_kind(
+                            OperationErrorType::PasswordRequired,
+                            controller,
+                        ));
+                    }
+                    Err(ZipError::InvalidPassword) => {
+                        let kind = match password {
+                            Some(_) => OperationErrorType::WrongPassword,
+                            None => OperationErrorType::PasswordRequired,
+                        };
+                        return Err(OperationError::from_kind(kind, controller));
+                    }
+                    Err(e) => corrupt.push((name, e.to_string())),
+                }
+            }
         }
-        "application/x-tar" => OpReader::new(path, controller.clone())
-            .map(io::BufReader::new)
-            .map(tar::Archive::new)
-            .and_then(|mut archive| archive.unpack(new_dir))
-            .map_err(|e| OperationError::from_err(e, controller))?,
-        "application/zip" => fs::File::open(path)
-            .map(io::BufReader::new)
-            .map(zip::ZipArchive::new)
-            .map_err(|e| OperationError::from_err(e, controller))?
-            .and_then(move |mut archive| {
-                zip_extract(&mut archive, new_dir, password, controller.clone())
-            })
-            .map_err(|e| match e {
-                ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)
-                | ZipError::InvalidPassword => {
-                    OperationError::from_kind(OperationErrorType::PasswordRequired, controller)
+        #[cfg(feature = "sevenz-rust2")]
+        "application/x-7z-compressed" => {
+            let password = password
+                .as_deref()
+                .map_or_else(sevenz_rust2::Password::empty, sevenz_rust2::Password::from);
+            let tested = fs::File::open(path)
+                .map_err(sevenz_rust2::Error::from)
+                .and_then(|file| sevenz_rust2::ArchiveReader::new(file, password))
+                .and_then(|mut archive| {
+                    archive.for_each_entries(|entry, reader| {
+                        futures::executor::block_on(controller.check()).map_err(|s| {
+                            io::Error::other(OperationError::from_state(s, controller))
+                        })?;
+                        if let Err(e) = io::copy(reader, &mut io::sink()) {
+                            corrupt.push((PathBuf::from(entry.name()), e.to_string()));
+                        }
+                        Ok(true)
+                    })
+                });
+            match tested {
+                Ok(()) => {}
+                Err(sevenz_rust2::Error::PasswordRequired) => {
+                    return Err(OperationError::from_kind(
+                        OperationErrorType::PasswordRequired,
+                        controller,
+                    ));
                 }
-                _ => OperationError::from_err(e, controller),
-            })?,
-        #[cfg(feature = "bzip2")]
-        "application/x-bzip"
-        | "application/x-bzip-compressed-tar"
-        | "application/x-bzip2"
-        | "application/x-bzip2-compressed-tar" => OpReader::new(path, controller.clone())
-            .map(io::BufReader::new)
-            .map(bzip2::read::BzDecoder::new)
-            .map(tar::Archive::new)
-            .and_then(|mut archive| archive.unpack(new_dir))
-            .map_err(|e| OperationError::from_err(e, controller))?,
-        #[cfg(feature = "lzma-rust2")]
-        "application/x-xz" | "application/x-xz-compressed-tar" => {
-            OpReader::new(path, controller.clone())
+                Err(sevenz_rust2::Error::MaybeBadPassword(_)) => {
+                    return Err(OperationError::from_kind(
+                        OperationErrorType::WrongPassword,
+                        controller,
+                    ));
+                }
+                Err(e) => {
+                    futures::executor::block_on(controller.check())
+                        .map_err(|s| OperationError::from_state(s, controller))?;
+                    corrupt.push((PathBuf::new(), e.to_string()));
+                }
+            }
+        }
+        essence if package::is_package(essence) => {
+            // Packages have no checksums of their own, but their compressed streams do
+            let tested = package::for_each(path, essence, controller, &mut |item, reader| {
+                futures::executor::block_on(controller.check())
+                    .map_err(|s| io::Error::other(OperationError::from_state(s, controller)))?;
+                if let Err(e) = io::copy(reader, &mut io::sink()) {
+                    corrupt.push((item.path.clone(), e.to_string()));
+                    return Ok(false);
+                }
+                Ok(true)
+            });
+            if let Err(e) = tested {
+                futures::executor::block_on(controller.check())
+                    .map_err(|s| OperationError::from_state(s, controller))?;
+                corrupt.push((PathBuf::new(), e.to_string()));
+            }
+        }
+        essence => {
+            let reader = OpReader::new(path, controller.clone())
                 .map(io::BufReader::new)
-                .map(|reader| lzma_rust2::XzReader::new(reader, true))
-                .map(tar::Archive::new)
-                .and_then(|mut archive| archive.unpack(new_dir))
+                .map_err(|e| OperationError::from_err(e, controller))?;
+            let Some(mut decoder) = tar_decoder(reader, essence)
                 .map_err(|e| OperationError::from_err(e, controller))?
+            else {
+                return Err(OperationError::from_err(
+                    format!("unsupported mime type {:?}", mime),
+                    controller,
+                ));
+            };
+            let mut is_tar = true;
+            if COMPRESSED_FILE_TYPES.contains(&essence) {
+                match peek_tar(decoder) {
+                    Ok(peeked) => (decoder, is_tar) = peeked,
+                    Err(e) => return Ok(vec![(PathBuf::new(), e.to_string())]),
+                }
+            }
+            // Reading a plain file to the end checks the checksum of its stream
+            let tested = if is_tar {
+                tar_test(tar::Archive::new(decoder), &mut corrupt, controller)
+            } else {
+                io::copy(&mut decoder, &mut io::sink()).map(|_| ())
+            };
+            if let Err(e) = tested {
+                futures::executor::block_on(controller.check())
+                    .map_err(|s| OperationError::from_state(s, controller))?;
+                corrupt.push((PathBuf::new(), e.to_string()));
+            }
         }
-        _ => Err(OperationError::from_err(
+    }
+    Ok(corrupt)
+}
+
+/// Read the entries of a tar archive for [`test`]. The stream cannot be read past an error, so
+/// the first corrupt entry ends it.
+fn tar_test<R: Read>(
+    mut archive: tar::Archive<R>,
+    corrupt: &mut Vec<(PathBuf, String)>,
+    controller: &Controller,
+) -> io::Result<()> {
+    for entry in archive.entries()? {
+        futures::executor::block_on(controller.check())
+            .map_err(|s| io::Error::other(OperationError::from_state(s, controller)))?;
+
+        let mut entry = entry?;
+        let name = entry.path()?.into_owned();
+        if let Err(e) = io::copy(&mut entry, &mut io::sink()) {
+            corrupt.push((name, e.to_string()));
+            return Ok(());
+        }
+    }
+    // The checksum of the compressed stream is after the end of the tar archive
+    io::copy(&mut archive.into_inner(), &mut io::sink())?;
+    Ok(())
+}
+
+/// Cache directories created by [`cache_entry`], removed by [`clear_caches`]
+static CACHE_ROOTS: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// Extract an entry into a cache directory, so it can be opened or thumbnailed, and return its
+/// path. Entries are extracted again only when the archive changes.
+pub fn cache_entry(archive: &Path, entry: &Path) -> io::Result<PathBuf> {
+    let root = cache_root(archive)?;
+    CACHE_ROOTS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(root.clone());
+    let cached = tar_out_path(&root, entry)
+        .filter(|cached| *cached != root)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid archive entry"))?;
+    if !cached.exists() {
+        let dst = cached.parent().unwrap_or(&root);
+        extract_entries(
+            archive,
+            &[entry.to_path_buf()],
+            dst,
+            &None,
+            &ConflictPolicy::default(),
+            None,
+            &ExtractLimits::default(),
+            &Controller::default(),
+        )
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    }
+    Ok(cached)
+}
+
+/// Remove all entries extracted by [`cache_entry`]. This is only done on exit, as entries may
+/// still be open in other applications or shown by other tabs.
+pub fn clear_caches() {
+    let roots = std::mem::take(&mut *CACHE_ROOTS.lock().unwrap_or_else(|err| err.into_inner()));
+    for root in roots {
+        match fs::remove_dir_all(&root) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                log::warn!("failed to clear cache {:?}: {}", root, err);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Cache directory of the entries of an archive, which changes with the archive. Each process
+/// has its own, so exiting does not remove entries used by another.
+fn cache_root(archive: &Path) -> io::Result<PathBuf> {
+    let metadata = fs::metadata(archive)?;
+    let mut hasher = DefaultHasher::new();
+    (
+        archive,
+        metadata.len(),
+        metadata.modified().ok(),
+        std::process::id(),
+    )
+        .hash(&mut hasher);
+    Ok(dirs::cache_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cache directory"))?
+        .join("vortex-fm")
+        .join("archives")
+        .join(format!("{:016x}", hasher.finish())))
+}
+
+/// Path of a tar entry inside of `dst`, or `None` if it is absolute or goes up
+fn tar_out_path(dst: &Path, path: &Path) -> Option<PathBuf> {
+    let mut outpath = dst.to_path_buf();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Prefix(_) | Component::RootDir | Component::ParentDir => return None,
+            Component::Normal(part) => outpath.push(part),
+        }
+    }
+    Some(outpath)
+}
+
+// Like `tar::Archive::unpack`, with existing files resolved by the conflict policy
+fn tar_unpack<R: io::Read>(
+    mut archive: tar::Archive<R>,
+    dst: &Path,
+    conflicts: &ConflictPolicy,
+    guard: &mut Guard,
+) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+    // Directories are unpacked last, so their permissions do not prevent writing their contents
+    let mut directories = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let outpath = guard.out_path(&path)?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            directories.push(entry);
+            continue;
+        }
+        tar_check(&entry, &path, &outpath, guard)?;
+        let modified = entry
+            .header()
+            .mtime()
+            .ok()
+            .map(|mtime| UNIX_EPOCH + Duration::from_secs(mtime));
+        match guard.resolve(conflicts, &outpath, entry.size(), modified)? {
+            Some(to) if to == outpath => {
+                entry.unpack_in(dst)?;
+            }
+            // Hard links point to a path inside of the archive, so they cannot be renamed
+            Some(_) if entry_type.is_hard_link() => {
+                log::warn!("skipping hard link {:?} as {:?} exists", path, outpath);
+            }
+            Some(to) => {
+                entry.unpack(&to)?;
+            }
+            None => {}
+        }
+    }
+    directories.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+    for mut dir in directories {
+        dir.unpack_in(dst)?;
+    }
+    Ok(())
+}
+
+/// Refuse special files, links outside of the destination and entries over the limits
+fn tar_check<R: io::Read>(
+    entry: &tar::Entry<R>,
+    path: &Path,
+    outpath: &Path,
+    guard: &mut Guard,
+) -> io::Result<()> {
+    let entry_type = entry.header().entry_type();
+    if entry_type.is_character_special() || entry_type.is_block_special() || entry_type.is_fifo() {
+        return Err(guard.refuse(path, UnsafeEntry::Special));
+    }
+    if let Some(target) = entry.link_name()? {
+        if entry_type.is_symlink() {
+            guard.symlink(path, outpath, &target)?;
+        } else if entry_type.is_hard_link() {
+            guard.hard_link(path, &target)?;
+        }
+    }
+    guard.add(path, entry.size())
+}
+
+/// Like [`tar_unpack`] for only `entries`, see [`selected_path`]
+fn tar_unpack_entries<R: io::Read>(
+    mut archive: tar::Archive<R>,
+    entries: &[PathBuf],
+    conflicts: &ConflictPolicy,
+    guard: &mut Guard,
+) -> io::Result<()> {
+    let dst = guard.dst;
+    fs::create_dir_all(dst)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let relative = guard.relative(&path)?;
+        let Some(outpath) = selected_path(entries, &relative).map(|path| dst.join(path)) else {
+            continue;
+        };
+        guard.parents(&path, &outpath)?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            fs::create_dir_all(&outpath)?;
+            continue;
+        }
+        // Hard links point to a path inside of the archive, which may not be extracted
+        if entry_type.is_hard_link() {
+            log::warn!("skipping hard link {:?}", path);
+            continue;
+        }
+        tar_check(&entry, &path, &outpath, guard)?;
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let modified = entry
+            .header()
+            .mtime()
+            .ok()
+            .map(|mtime| UNIX_EPOCH + Duration::from_secs(mtime));
+        if let Some(to) = guard.resolve(conflicts, &outpath, entry.size(), modified)? {
+            entry.unpack(&to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Like [`tar_unpack_entries`] for disk images and packages, see [`package::for_each`]. Files
+/// are left writable by their owner, as disk images mark everything read only.
+fn package_unpack(
+    path: &Path,
+    mime: &str,
+    entries: &[PathBuf],
+    conflicts: &ConflictPolicy,
+    controller: &Controller,
+    guard: &mut Guard,
+) -> io::Result<()> {
+    let dst = guard.dst;
+    fs::create_dir_all(dst)?;
+    #[cfg(unix)]
+    let mut modes = Vec::new();
+    package::for_each(path, mime, controller, &mut |item, reader| {
+        futures::executor::block_on(controller.check())
+            .map_err(|s| io::Error::other(OperationError::from_state(s, controller)))?;
+
+        let relative = guard.relative(&item.path)?;
+        let Some(outpath) = selected_path(entries, &relative)
+            .filter(|path| !path.as_os_str().is_empty())
+            .map(|path| dst.join(path))
+        else {
+            return Ok(true);
+        };
+        guard.parents(&item.path, &outpath)?;
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match &item.kind {
+            Kind::Dir => {
+                fs::create_dir_all(&outpath)?;
+                #[cfg(unix)]
+                modes.push((outpath, item.mode));
+            }
+            Kind::Special => return Err(guard.refuse(&item.path, UnsafeEntry::Special)),
+            Kind::Symlink(target) => {
+                guard.symlink(&item.path, &outpath, target)?;
+                #[cfg(unix)]
+                if let Some(to) = guard.resolve(conflicts, &outpath, 0, item.modified)? {
+                    std::os::unix::fs::symlink(target, to)?;
+                }
+            }
+            Kind::HardLink(target) => {
+                guard.hard_link(&item.path, target)?;
+                // The target may not be selected, or may have been renamed
+                let Some(original) = tar_out_path(Path::new(""), target)
+                    .and_then(|target| selected_path(entries, &target))
+                    .map(|target| dst.join(target))
+                    .filter(|original| original.is_file())
+                else {
+                    log::warn!("skipping hard link {:?}", item.path);
+                    return Ok(true);
+                };
+                if let Some(to) = guard.resolve(conflicts, &outpath, item.size, item.modified)? {
+                    fs::hard_link(original, to)?;
+                }
+            }
+            Kind::File => {
+                let Some(to) = guard.resolve(conflicts, &outpath, item.size, item.modified)? else {
+                    return Ok(true);
+                };
+                let mut file = fs::File::create(&to)?;
+                guard.copy(&item.path, reader, &mut file)?;
+                if let Some(modified) = item.modified {
+                    file.set_modified(modified)?;
+                }
+                #[cfg(unix)]
+                modes.push((to, item.mode));
+            }
+        }
+        Ok(true)
+    })?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Children are changed before their parents, which could become read only
+        modes.sort_by(|a, b| b.0.cmp(&a.0));
+        for (path, mode) in modes {
+            if mode != 0 {
+                fs::set_permissions(&path, fs::Permissions::from_mode(mode | 0o200))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Extract a single compressed stream, which holds either a tar archive or a plain file. Plain
+/// files are written into the destination with the modification time of the compressed file.
+fn decompress(
+    path: &Path,
+    mime: &str,
+    entries: &[PathBuf],
+    conflicts: &ConflictPolicy,
+    controller: &Controller,
+    guard: &mut Guard,
+) -> io::Result<()> {
+    let reader = io::BufReader::new(OpReader::new(path, controller.clone())?);
+    let decoder = tar_decoder(reader, mime)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
             format!("unsupported mime type {:?}", mime),
-            controller,
-        ))?,
+        )
+    })?;
+    let (mut decoder, is_tar) = peek_tar(decoder)?;
+    let dst = guard.dst;
+    if is_tar {
+        let archive = tar::Archive::new(decoder);
+        return if entries.is_empty() {
+            tar_unpack(archive, dst, conflicts, guard)
+        } else {
+            tar_unpack_entries(archive, entries, conflicts, guard)
+        };
+    }
+
+    let name = decompressed_name(path);
+    let Some(outpath) = selected_path(entries, &name).map(|name| dst.join(name)) else {
+        return Ok(());
+    };
+    fs::create_dir_all(dst)?;
+    let modified = fs::metadata(path)?.modified().ok();
+    if let Some(to) = guard.resolve(conflicts, &outpath, 0, modified)? {
+        let mut file = fs::File::create(&to)?;
+        guard.copy(&name, &mut decoder, &mut file)?;
+        if let Some(modified) = modified {
+            file.set_modified(modified)?;
+        }
+    }
+    Ok(())
+}
+
+/// Like [`zip_extract`] for only `entries`, see [`selected_path`]
+fn zip_extract_entries<R: io::Read + io::Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    entries: &[PathBuf],
+    password: Option<&str>,
+    conflicts: &ConflictPolicy,
+    controller: &Controller,
+    guard: &mut Guard,
+) -> zip::result::ZipResult<()> {
+    let dst = guard.dst;
+    fs::create_dir_all(dst)?;
+    let total_files = archive.len();
+    for i in 0..total_files {
+        futures::executor::block_on(controller.check())
+            .map_err(|s| io::Error::other(OperationError::from_state(s, controller)))?;
+
+        controller.set_progress((i as f32) / total_files as f32);
+
+        let Some(name) = archive.name_for_index(i).map(PathBuf::from) else {
+            continue;
+        };
+        let relative = guard.relative(&name)?;
+        let Some(outpath) = selected_path(entries, &relative).map(|path| dst.join(path)) else {
+            continue;
+        };
+        guard.parents(&name, &outpath)?;
+        let mut file = match password {
+            None => archive.by_index(i),
+            Some(pwd) => archive.by_index_decrypt(i, pwd.as_bytes()),
+        }?;
+        if file.is_dir() {
+            fs::create_dir_all(&outpath)?;
+            continue;
+        }
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Links are extracted as files with their target in them
+        let modified = file.last_modified().and_then(zip_modified);
+        if let Some(to) = guard.resolve(conflicts, &outpath, file.size(), modified)? {
+            guard.copy(&name, &mut file, &mut fs::File::create(to)?)?;
+        }
     }
     Ok(())
 }
 
+/// Modification time of a zip entry, which is stored in local time
+fn zip_modified(date_time: zip::DateTime) -> Option<SystemTime> {
+    let local = chrono::NaiveDate::from_ymd_opt(
+        date_time.year().into(),
+        date_time.month().into(),
+        date_time.day().into(),
+    )?
+    .and_hms_opt(
+        date_time.hour().into(),
+        date_time.minute().into(),
+        date_time.second().into(),
+    )?
+    .and_local_timezone(chrono::Local)
+    .earliest()?;
+    Some(local.into())
+}
+
 // From https://docs.rs/zip/latest/zip/read/struct.ZipArchive.html#method.extract, with cancellation and progress added
-fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
+fn zip_extract<R: io::Read + io::Seek>(
     archive: &mut zip::ZipArchive<R>,
-    directory: P,
     password: Option<String>,
+    conflicts: &ConflictPolicy,
     controller: Controller,
+    guard: &mut Guard,
 ) -> zip::result::ZipResult<()> {
     use std::{ffi::OsString, fs};
     use zip::result::ZipError;
@@ -149,16 +1973,15 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
             None => archive.by_index(i),
             Some(pwd) => archive.by_index_decrypt(i, pwd.as_bytes()),
         }?;
-        let filepath = file
-            .enclosed_name()
-            .ok_or(ZipError::InvalidArchive("Invalid file path".into()))?;
-
-        let outpath = directory.as_ref().join(filepath);
+        let filepath = PathBuf::from(file.name());
+        let outpath = guard.out_path(&filepath)?;
 
         if file.is_dir() {
             pending_directory_creates.push_back(outpath.clone());
             continue;
         }
+        let len = file.size();
+        let modified = file.last_modified().and_then(zip_modified);
         let symlink_target = if file.is_symlink() && (cfg!(unix) || cfg!(windows)) {
             let mut target = Vec::with_capacity(file.size() as usize);
             file.read_to_end(&mut target)?;
@@ -168,6 +1991,18 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
         };
         drop(file);
         if let Some(target) = symlink_target {
+            #[cfg(unix)]
+            {
+                use std::os::unix::ffi::OsStrExt;
+                guard.symlink(&filepath, &outpath, Path::new(OsStr::from_bytes(&target)))?;
+            }
+            #[cfg(not(unix))]
+            guard.symlink(
+                &filepath,
+                &outpath,
+                Path::new(&*String::from_utf8_lossy(&target)),
+            )?;
+
             // create all pending dirs
             while let Some(pending_dir) = pending_directory_creates.pop_front() {
                 make_writable_dir_all(pending_dir)?;
@@ -176,6 +2011,9 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
             if let Some(p) = outpath.parent() {
                 make_writable_dir_all(p)?;
             }
+            let Some(outpath) = guard.resolve(conflicts, &outpath, len, modified)? else {
+                continue;
+            };
 
             #[cfg(unix)]
             {
@@ -191,7 +2029,7 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
                 let target = target.into_boxed_str();
                 let target_is_dir_from_archive =
                     archive.shared.files.contains_key(&target) && is_dir(&target);
-                let target_path = directory.as_ref().join(OsString::from(target.to_string()));
+                let target_path = guard.dst.join(OsString::from(target.to_string()));
                 let target_is_dir = if target_is_dir_from_archive {
                     true
                 } else if let Ok(meta) = std::fs::metadata(&target_path) {
@@ -220,6 +2058,9 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
         if let Some(p) = outpath.parent() {
             make_writable_dir_all(p)?;
         }
+        let Some(outpath) = guard.resolve(conflicts, &outpath, len, modified)? else {
+            continue;
+        };
 
         let total = file.size();
         let mut outfile = fs::File::create(&outpath)?;
@@ -236,6 +2077,7 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
             if count == 0 {
                 break;
             }
+            guard.add(&filepath, count as u64)?;
             outfile.write_all(&buffer[..count])?;
             current += count as u64;
 
@@ -268,3 +2110,585 @@ fn zip_extract<R: io::Read + io::Seek, P: AsRef<Path>>(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{self, Write},
+        path::{Path, PathBuf},
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+
+    use super::{
+        Entry, TarEncoder, add, compress_file, estimate_size, extract, extract_entries, list,
+        needs_password, read_dir, single_root, tar_compress, test as test_archive,
+    };
+    use crate::{
+        core::operations::{
+            ConflictPolicy, Controller, ExtractLimits, FilePolicy, OperationErrorType,
+            ReplaceResult, UnsafeEntry,
+        },
+        views::dialogs::dialog_pages::ArchiveType,
+    };
+
+    /// Write a tar archive with entries of `(name, type, link target)`, which `tar::Builder`
+    /// would refuse to create. Files contain a single byte.
+    fn tar_fixture(path: &Path, entries: &[(&str, tar::EntryType, &str)]) -> io::Result<()> {
+        let mut builder = tar::Builder::new(fs::File::create(path)?);
+        for &(name, entry_type, link) in entries {
+            let mut header = tar::Header::new_gnu();
+            let old = header.as_old_mut();
+            old.name[..name.len()].copy_from_slice(name.as_bytes());
+            old.linkname[..link.len()].copy_from_slice(link.as_bytes());
+            header.set_entry_type(entry_type);
+            header.set_mode(0o644);
+            let data: &[u8] = if entry_type.is_file() { b"x" } else { b"" };
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data)?;
+        }
+        builder.into_inner()?;
+        Ok(())
+    }
+
+    /// Extract `archive` into an empty `out`, returning the entry refused and why
+    fn refused(
+        archive: &Path,
+        out: &Path,
+        limits: ExtractLimits,
+    ) -> Option<(PathBuf, UnsafeEntry)> {
+        let _ = fs::remove_dir_all(out);
+        let conflicts = ConflictPolicy::default();
+        let controller = Controller::default();
+        match extract(archive, out, &None, &conflicts, None, &limits, &controller) {
+            Err(err) => match err.kind {
+                OperationErrorType::UnsafeEntry { entry, reason } => Some((entry, reason)),
+                kind => panic!("unexpected error {kind}"),
+            },
+            Ok(()) => None,
+        }
+    }
+
+    #[test]
+    fn tar_round_trip() -> io::Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested"))?;
+        fs::write(src.join("a"), "a".repeat(1000))?;
+        fs::write(src.join("nested/b"), b"b")?;
+        let paths = vec![
+            src.clone(),
+            src.join("a"),
+            src.join("nested"),
+            src.join("nested/b"),
+        ];
+
+        let controller = Controller::default();
+        for &archive_type in ArchiveType::all() {
+            if matches!(archive_type, ArchiveType::Zip) {
+                continue;
+            }
+            #[cfg(feature = "sevenz-rust2")]
+            if archive_type == ArchiveType::SevenZ {
+                continue;
+            }
+
+            let level = archive_type.default_level();
+            let to = dir
+                .path()
+                .join(format!("archive{}", archive_type.extension()));
+            let encoder = TarEncoder::new(fs::File::create(&to)?, archive_type, level)?;
+            assert!(tar_compress(encoder, &paths, dir.path(), &controller).is_ok());
+
+            let out = dir.path().join(format!("out{}", archive_type.extension()));
+            let conflicts = ConflictPolicy::default();
+            let limits = ExtractLimits::default();
+            assert!(extract(&to, &out, &None, &conflicts, None, &limits, &controller).is_ok());
+            assert_eq!(fs::read(out.join("src/a"))?, "a".repeat(1000).as_bytes());
+            assert_eq!(fs::read(out.join("src/nested/b"))?, b"b");
+
+            // Repeated content compresses well
+            let estimate = estimate_size(&[src.clone()], archive_type, level)?;
+            assert!(estimate > 0 && estimate < 1001);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "sevenz-rust2")]
+    #[test]
+    fn sevenz_round_trip() -> io::Result<()> {
+        use super::sevenz_compress;
+
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested"))?;
+        fs::write(src.join("a"), "a".repeat(1000))?;
+        fs::write(src.join("nested/b"), b"b")?;
+        let paths = vec![
+            src.clone(),
+            src.join("a"),
+            src.join("nested"),
+            src.join("nested/b"),
+        ];
+
+        let controller = Controller::default();
+        let to = dir.path().join("archive.7z");
+        let level = ArchiveType::SevenZ.default_level();
+        assert!(sevenz_compress(&paths, dir.path(), &to, level, &controller).is_ok());
+        let mut names: Vec<_> = list(&to)?.into_iter().map(|entry| entry.path).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            ["src", "src/a", "src/nested", "src/nested/b"].map(PathBuf::from)
+        );
+
+        let out = dir.path().join("out");
+        let conflicts = ConflictPolicy::default();
+        let limits = ExtractLimits::default();
+        assert!(extract(&to, &out, &None, &conflicts, None, &limits, &controller).is_ok());
+        assert_eq!(fs::read(out.join("src/a"))?, "a".repeat(1000).as_bytes());
+        assert_eq!(fs::read(out.join("src/nested/b"))?, b"b");
+        assert!(test_archive(&to, &None, &controller).is_ok_and(|corrupt| corrupt.is_empty()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ask_about_existing_files() -> io::Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("archive.tar");
+        tar_fixture(
+            &archive,
+            &[
+                ("a", tar::EntryType::Regular, ""),
+                ("b", tar::EntryType::Regular, ""),
+            ],
+        )?;
+        let out = dir.path().join("out");
+        let reset = || -> io::Result<()> {
+            let _ = fs::remove_dir_all(&out);
+            fs::create_dir(&out)?;
+            fs::write(out.join("a"), b"old")?;
+            fs::write(out.join("b"), b"old")?;
+            Ok(())
+        };
+        let conflicts = ConflictPolicy {
+            files: FilePolicy::Ask,
+            ..ConflictPolicy::default()
+        };
+        let limits = ExtractLimits::default();
+        let controller = Controller::default();
+        let extract_asking = |replace_result: ReplaceResult| {
+            let asked = std::cell::Cell::new(0);
+            let ask = |entry: &Entry, to: &Path| {
+                assert_eq!(out.join(&entry.path), to);
+                asked.set(asked.get() + 1);
+                replace_result
+            };
+            let result = extract(
+                &archive,
+                &out,
+                &None,
+                &conflicts,
+                Some(&ask),
+                &limits,
+                &controller,
+            );
+            assert!(result.is_ok());
+            asked.get()
+        };
+
+        // Answers applied to all are only asked once
+        reset()?;
+        assert_eq!(extract_asking(ReplaceResult::Replace(true)), 1);
+        assert_eq!(fs::read(out.join("a"))?, b"x");
+        assert_eq!(fs::read(out.join("b"))?, b"x");
+
+        reset()?;
+        assert_eq!(extract_asking(ReplaceResult::Skip(false)), 2);
+        assert_eq!(fs::read(out.join("a"))?, b"old");
+        assert_eq!(fs::read(out.join("b"))?, b"old");
+
+        reset()?;
+        assert_eq!(extract_asking(ReplaceResult::Policy(FilePolicy::Rename)), 1);
+        assert_eq!(fs::read(out.join("a"))?, b"old");
+        assert_eq!(fs::read(out.join("a (2)"))?, b"x");
+        assert_eq!(fs::read(out.join("b (2)"))?, b"x");
+
+        Ok(())
+    }
+
+    #[test]
+    fn single_file_round_trip() -> io::Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("foo.log");
+        fs::write(&src, "line\n".repeat(1000))?;
+        let modified = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&src)?
+            .set_modified(modified)?;
+
+        let controller = Controller::default();
+        for &archive_type in ArchiveType::all() {
+            if !archive_type.is_single_file() {
+                continue;
+            }
+            let extension = archive_type.extension();
+            let to = dir.path().join(format!("foo.log{extension}"));
+            let level = archive_type.default_level();
+            compress_file(&src, &to, archive_type, level, &controller)?;
+            assert_eq!(fs::metadata(&to)?.modified()?, modified);
+            let entries = list(&to)?;
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].path, Path::new("foo.log"));
+
+            // Plain files are not mistaken for tar archives
+            let out = dir.path().join(format!("out{extension}"));
+            let conflicts = ConflictPolicy::default();
+            let limits = ExtractLimits::default();
+            assert!(extract(&to, &out, &None, &conflicts, None, &limits, &controller).is_ok());
+            assert_eq!(fs::read(out.join("foo.log"))?, fs::read(&src)?);
+            assert_eq!(fs::metadata(out.join("foo.log"))?.modified()?, modified);
+            assert!(test_archive(&to, &None, &controller).is_ok_and(|corrupt| corrupt.is_empty()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn read_archive_dirs() {
+        let entry = |path: &str, is_dir| Entry {
+            path: PathBuf::from(path),
+            is_dir,
+            size: 1,
+            modified: None,
+        };
+        // The directory "b" has no entry of its own
+        let entries = vec![
+            entry("a", true),
+            entry("a/x", false),
+            entry("a/y", false),
+            entry("b/c/z", false),
+            entry("d", false),
+        ];
+
+        let root = read_dir(&entries, Path::new(""));
+        let names: Vec<_> = root
+            .iter()
+            .map(|(entry, children)| (entry.path.clone(), entry.is_dir, *children))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (PathBuf::from("a"), true, 2),
+                (PathBuf::from("b"), true, 1),
+                (PathBuf::from("d"), false, 0),
+            ]
+        );
+        assert_eq!(read_dir(&entries, Path::new("b")).len(), 1);
+        assert_eq!(read_dir(&entries, Path::new("a/x")).len(), 0);
+
+        assert_eq!(single_root(&entries), None);
+        assert_eq!(single_root(&entries[..3]), Some(PathBuf::from("a")));
+        assert_eq!(single_root(&entries[4..]), Some(PathBuf::from("d")));
+        assert_eq!(single_root(&[]), None);
+    }
+
+    #[test]
+    fn extract_selected_entries() -> io::Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("nested"))?;
+        fs::write(src.join("a"), b"a")?;
+        fs::write(src.join("nested/b"), b"b")?;
+        let paths = vec![
+            src.clone(),
+            src.join("a"),
+            src.join("nested"),
+            src.join("nested/b"),
+        ];
+
+        let controller = Controller::default();
+        let to = dir.path().join("archive.tgz");
+        let encoder = TarEncoder::new(fs::File::create(&to)?, ArchiveType::Tgz, 6)?;
+        assert!(tar_compress(encoder, &paths, dir.path(), &controller).is_ok());
+        assert_eq!(list(&to)?.len(), 4);
+
+        // Selected entries end up directly inside of the destination
+        let out = dir.path().join("out");
+        let entries = [PathBuf::from("src/nested")];
+        let conflicts = ConflictPolicy::default();
+        let limits = ExtractLimits::default();
+        assert!(
+            extract_entries(
+                &to,
+                &entries,
+                &out,
+                &None,
+                &conflicts,
+                None,
+                &limits,
+                &controller
+            )
+            .is_ok()
+        );
+        assert_eq!(fs::read(out.join("nested/b"))?, b"b");
+        assert!(!out.join("a").exists());
+        assert!(!out.join("src").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuse_malicious_tar() -> io::Result<()> {
+        use UnsafeEntry::{HardLinkEscape, Special, SymlinkEscape, Traversal};
+        use tar::EntryType::{Block, Char, Directory, Fifo, Link, Regular, Symlink};
+
+        let dir = tempdir()?;
+        let out = dir.path().join("out");
+        let outside = dir.path().join("outside");
+        let absolute = outside.to_string_lossy().into_owned();
+        let limits = ExtractLimits::default();
+        let cases = [
+            ("../outside", Regular, "", Traversal),
+            ("a/../../outside", Regular, "", Traversal),
+            (absolute.as_str(), Regular, "", Traversal),
+            ("link", Symlink, "../outside", SymlinkEscape),
+            ("link", Symlink, "/etc", SymlinkEscape),
+            // Going up after another link could leave through it
+            ("link", Symlink, "a/../..", SymlinkEscape),
+            ("hard", Link, "../outside", HardLinkEscape),
+            ("hard", Link, "/etc/passwd", HardLinkEscape),
+            ("fifo", Fifo, "", Special),
+            ("null", Char, "", Special),
+            ("disk", Block, "", Special),
+        ];
+        for (i, (name, entry_type, link, reason)) in cases.into_iter().enumerate() {
+            let archive = dir.path().join(format!("{i}.tar"));
+            tar_fixture(&archive, &[(name, entry_type, link)])?;
+            assert_eq!(
+                refused(&archive, &out, limits),
+                Some((PathBuf::from(name), reason)),
+                "{name} {link}"
+            );
+            assert!(!outside.exists(), "{name} {link}");
+        }
+
+        // Writing through a link could lead anywhere, even if it points inside
+        let archive = dir.path().join("through.tar");
+        tar_fixture(
+            &archive,
+            &[
+                ("inner", Directory, ""),
+                ("link", Symlink, "inner"),
+                ("link/file", Regular, ""),
+            ],
+        )?;
+        assert_eq!(
+            refused(&archive, &out, limits),
+            Some((PathBuf::from("link/file"), SymlinkEscape))
+        );
+
+        // Links that stay inside are extracted
+        let archive = dir.path().join("safe.tar");
+        tar_fixture(
+            &archive,
+            &[
+                ("a/file", Regular, ""),
+                ("a/link", Symlink, "file"),
+                ("up", Symlink, "a/file"),
+                ("a/up", Symlink, "../a"),
+                ("hard", Link, "a/file"),
+            ],
+        )?;
+        assert_eq!(refused(&archive, &out, limits), None);
+        assert_eq!(fs::read(out.join("a/link"))?, b"x");
+        assert_eq!(fs::read(out.join("hard"))?, b"x");
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuse_malicious_zip() -> io::Result<()> {
+        let dir = tempdir()?;
+        let out = dir.path().join("out");
+        let options = SimpleFileOptions::default();
+        let limits = ExtractLimits::default();
+        let cases = [
+            ("../outside", None, UnsafeEntry::Traversal),
+            ("/outside", None, UnsafeEntry::Traversal),
+            ("link", Some("../outside"), UnsafeEntry::SymlinkEscape),
+        ];
+        for (i, (name, target, reason)) in cases.into_iter().enumerate() {
+            let archive = dir.path().join(format!("{i}.zip"));
+            let mut zip = zip::ZipWriter::new(fs::File::create(&archive)?);
+            let added = match target {
+                Some(target) => zip.add_symlink(name, target, options),
+                None => zip.start_file(name, options),
+            };
+            added.map_err(io::Error::other)?;
+            zip.finish().map_err(io::Error::other)?;
+            assert_eq!(
+                refused(&archive, &out, limits),
+                Some((PathBuf::from(name), reason))
+            );
+            assert!(!dir.path().join("outside").exists(), "{name}");
+        }
+
+        // A bomb of zeros, over both limits
+        let archive = dir.path().join("bomb.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive)?);
+        zip.start_file("zeros", options).map_err(io::Error::other)?;
+        let zeros = vec![0; 1024 * 1024];
+        for _ in 0..32 {
+            zip.write_all(&zeros)?;
+        }
+        zip.finish().map_err(io::Error::other)?;
+        let reason = |max_size, max_ratio| {
+            let limits = ExtractLimits {
+                max_size,
+                max_ratio,
+            };
+            refused(&archive, &out, limits).map(|(_, reason)| reason)
+        };
+        assert_eq!(reason(None, Some(100)), Some(UnsafeEntry::TooCompressed));
+        assert_eq!(reason(Some(1024), None), Some(UnsafeEntry::TooLarge));
+        assert_eq!(reason(None, None), None);
+        assert_eq!(fs::metadata(out.join("zeros"))?.len(), 32 * 1024 * 1024);
+
+        Ok(())
+    }
+
+    #[test]
+    fn zip_password() -> io::Result<()> {
+        let dir = tempdir()?;
+        let out = dir.path().join("out");
+        let archive = dir.path().join("secret.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive)?);
+        let options =
+            SimpleFileOptions::default().with_aes_encryption(zip::AesMode::Aes256, "correct horse");
+        zip.start_file("secret", options)
+            .map_err(io::Error::other)?;
+        zip.write_all(b"battery staple")?;
+        zip.finish().map_err(io::Error::other)?;
+        assert!(needs_password(&archive)?);
+
+        let conflicts = ConflictPolicy::default();
+        let limits = ExtractLimits::default();
+        let controller = Controller::default();
+        let kind = |password: Option<&str>| {
+            let password = password.map(String::from);
+            extract(
+                &archive,
+                &out,
+                &password,
+                &conflicts,
+                None,
+                &limits,
+                &controller,
+            )
+            .err()
+            .map(|err| err.kind)
+        };
+        assert!(matches!(
+            kind(None),
+            Some(OperationErrorType::PasswordRequired)
+        ));
+        assert!(matches!(
+            kind(Some("wrong")),
+            Some(OperationErrorType::WrongPassword)
+        ));
+        assert!(kind(Some("correct horse")).is_none());
+        assert_eq!(fs::read(out.join("secret"))?, b"battery staple");
+
+        // Archives without encrypted entries do not ask for a password
+        let plain = dir.path().join("plain.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&plain)?);
+        zip.start_file("plain", SimpleFileOptions::default())
+            .map_err(io::Error::other)?;
+        zip.finish().map_err(io::Error::other)?;
+        assert!(!needs_password(&plain)?);
+        Ok(())
+    }
+
+    #[test]
+    fn add_replaces_entries() -> io::Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src)?;
+        fs::write(src.join("a"), b"old")?;
+        fs::write(src.join("b"), b"b")?;
+        let controller = Controller::default();
+
+        let tgz = dir.path().join("archive.tgz");
+        let encoder = TarEncoder::new(fs::File::create(&tgz)?, ArchiveType::Tgz, 6)?;
+        assert!(tar_compress(encoder, &[src.join("a"), src.join("b")], &src, &controller).is_ok());
+        let zip_path = dir.path().join("archive.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path)?);
+        for name in ["a", "b"] {
+            zip.start_file(name, SimpleFileOptions::default())
+                .map_err(io::Error::other)?;
+            zip.write_all(&fs::read(src.join(name))?)?;
+        }
+        zip.finish().map_err(io::Error::other)?;
+
+        let new = dir.path().join("new");
+        fs::create_dir_all(new.join("c"))?;
+        fs::write(new.join("a"), b"new")?;
+        fs::write(new.join("c/d"), b"d")?;
+        for archive in [&tgz, &zip_path] {
+            assert!(add(archive, &[new.join("a"), new.join("c")], &controller).is_ok());
+            let mut names: Vec<_> = list(archive)?.into_iter().map(|entry| entry.path).collect();
+            names.sort();
+            assert_eq!(names, ["a", "b", "c", "c/d"].map(PathBuf::from));
+
+            let out = dir.path().join("out");
+            let _ = fs::remove_dir_all(&out);
+            let conflicts = ConflictPolicy::default();
+            let limits = ExtractLimits::default();
+            assert!(extract(archive, &out, &None, &conflicts, None, &limits, &controller).is_ok());
+            assert_eq!(fs::read(out.join("a"))?, b"new");
+            assert_eq!(fs::read(out.join("b"))?, b"b");
+            assert_eq!(fs::read(out.join("c/d"))?, b"d");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_finds_corrupt_entries() -> io::Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("archive.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive)?);
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, data) in [("good", b"good data"), ("bad", b"bad data!")] {
+            zip.start_file(name, options).map_err(io::Error::other)?;
+            zip.write_all(data)?;
+        }
+        zip.finish().map_err(io::Error::other)?;
+        let controller = Controller::default();
+        assert!(
+            test_archive(&archive, &None, &controller)
+                .map_err(io::Error::other)?
+                .is_empty()
+        );
+
+        // Damage the contents of one entry, leaving its CRC as it was
+        let mut bytes = fs::read(&archive)?;
+        let at = bytes
+            .windows(9)
+            .position(|window| window == b"bad data!")
+            .unwrap();
+        bytes[at] = b'B';
+        fs::write(&archive, bytes)?;
+        let corrupt = test_archive(&archive, &None, &controller).map_err(io::Error::other)?;
+        assert_eq!(corrupt.len(), 1);
+        assert_eq!(corrupt[0].0, PathBuf::from("bad"));
+        Ok(())
+    }
+}
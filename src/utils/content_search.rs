@@ -0,0 +1,166 @@
+// Full-text matching for recursive content search
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+
+/// Files larger than this are not searched
+pub const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Amount of data checked for NUL bytes to detect binary files
+const BINARY_CHECK_SIZE: usize = 8192;
+/// Maximum number of matching lines kept per file
+pub const MAX_MATCHES: usize = 3;
+/// Maximum number of characters kept around a match in a snippet
+const SNIPPET_CONTEXT: usize = 40;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchMatch {
+    /// One based line number
+    pub line: u64,
+    pub snippet: String,
+}
+
+/// Result of searching one file: the first matching lines and the total number of matching lines
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FileMatches {
+    pub matches: Vec<SearchMatch>,
+    pub total: usize,
+}
+
+/// Build the case insensitive matcher for a term, escaping it unless it is a regex
+pub fn matcher(term: &str, is_regex: bool) -> Result<regex::Regex, regex::Error> {
+    let pattern = if is_regex {
+        term.to_string()
+    } else {
+        regex::escape(term)
+    };
+    regex::RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .build()
+}
+
+/// Search the contents of a regular file, returning `None` when it does not match or is skipped
+pub fn search_file(
+    path: &Path,
+    size: u64,
+    regex: &regex::Regex,
+) -> io::Result<Option<FileMatches>> {
+    if size > MAX_FILE_SIZE {
+        return Ok(None);
+    }
+    search_reader(fs::File::open(path)?, regex)
+}
+
+pub fn search_reader<R: Read>(reader: R, regex: &regex::Regex) -> io::Result<Option<FileMatches>> {
+    let mut reader = BufReader::with_capacity(BINARY_CHECK_SIZE, reader);
+    if reader.fill_buf()?.contains(&0) {
+        return Ok(None);
+    }
+
+    let mut file_matches = FileMatches::default();
+    let mut buf = Vec::new();
+    let mut line = 0;
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        line += 1;
+        if buf.contains(&0) {
+            // Binary data after the checked prefix
+            return Ok(None);
+        }
+        let text = String::from_utf8_lossy(&buf);
+        let text = text.trim_end_matches(['\r', '\n']);
+        if let Some(found) = regex.find(text) {
+            file_matches.total += 1;
+            if file_matches.matches.len() < MAX_MATCHES {
+                file_matches.matches.push(SearchMatch {
+                    line,
+                    snippet: snippet(text, found.start(), found.end()),
+                });
+            }
+        }
+    }
+
+    Ok((file_matches.total > 0).then_some(file_matches))
+}
+
+/// Shorten a line to the match and some context on either side
+fn snippet(text: &str, start: usize, end: usize) -> String {
+    let context_len = |chars: &mut dyn Iterator<Item = char>| -> usize {
+        chars.take(SNIPPET_CONTEXT).map(char::len_utf8).sum()
+    };
+    let from = start - context_len(&mut text[..start].chars().rev());
+    let to = end + context_len(&mut text[end..].chars());
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.push_str(text[from..to].trim_start());
+    if to < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_and_regex() {
+        let data = "first line\nsecond Value=1\nthird value=22\n";
+
+        let literal = matcher("value=", false).unwrap();
+        let found = search_reader(data.as_bytes(), &literal).unwrap().unwrap();
+        assert_eq!(found.total, 2);
+        assert_eq!(
+            found.matches,
+            vec![
+                SearchMatch {
+                    line: 2,
+                    snippet: "second Value=1".to_string()
+                },
+                SearchMatch {
+                    line: 3,
+                    snippet: "third value=22".to_string()
+                },
+            ]
+        );
+
+        let regex = matcher(r"value=\d{2}", true).unwrap();
+        let found = search_reader(data.as_bytes(), &regex).unwrap().unwrap();
+        assert_eq!(found.total, 1);
+        assert_eq!(found.matches[0].line, 3);
+
+        // Regex syntax is literal unless requested
+        let literal = matcher(r"value=\d", false).unwrap();
+        assert_eq!(search_reader(data.as_bytes(), &literal).unwrap(), None);
+    }
+
+    #[test]
+    fn skips_binary() {
+        let regex = matcher("needle", false).unwrap();
+        let data = b"needle\0\x01\x02";
+        assert_eq!(search_reader(&data[..], &regex).unwrap(), None);
+    }
+
+    #[test]
+    fn shortens_snippets() {
+        let regex = matcher("needle", false).unwrap();
+        let line = format!("{}needle{}\n", "a".repeat(100), "é".repeat(100));
+        let found = search_reader(line.as_bytes(), &regex).unwrap().unwrap();
+        assert_eq!(
+            found.matches[0].snippet,
+            format!(
+                "…{}needle{}…",
+                "a".repeat(SNIPPET_CONTEXT),
+                "é".repeat(SNIPPET_CONTEXT)
+            )
+        );
+    }
+}
@@ -0,0 +1,565 @@
+//! Reader of ISO 9660 disk images, see ECMA-119. Long names and permissions come from the Rock
+//! Ridge extension if the image has it, or from Joliet.
+
+use std::{
+    collections::HashSet,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::package::{Item, Kind, Visit};
+
+const SECTOR: u64 = 2048;
+/// Volume descriptors start after the system area
+const FIRST_DESCRIPTOR: u64 = 16;
+/// Directories are read into memory, so larger ones are refused as corrupt
+const MAX_DIR_LEN: u64 = 64 * 1024 * 1024;
+/// Continuation areas followed for the Rock Ridge entries of one record
+const MAX_CONTINUATIONS: usize = 16;
+/// Directories are read recursively, so deeper nesting is refused as corrupt
+const MAX_DEPTH: usize = 255;
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Names {
+    /// Upper case 8.3 names with a version
+    Plain,
+    /// UCS-2 names from the supplementary volume descriptor
+    Joliet,
+    /// POSIX names and attributes in the system use area of each record
+    RockRidge {
+        /// Bytes skipped at the start of each system use area
+        skip: usize,
+    },
+}
+
+/// A directory record
+struct Record {
+    name: Vec<u8>,
+    extent: u64,
+    len: u64,
+    flags: u8,
+    modified: Option<SystemTime>,
+    system_use: Vec<u8>,
+}
+
+impl Record {
+    const DIR: u8 = 0x02;
+    /// The file continues in the next record
+    const MULTI_EXTENT: u8 = 0x80;
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let len = usize::from(*data.first()?);
+        let data = data.get(..len)?;
+        let name_len = usize::from(*data.get(32)?);
+        let name = data.get(33..33 + name_len)?.to_vec();
+        // The name is padded to an even length
+        let system_use = data
+            .get(33 + name_len + (1 - name_len % 2)..)
+            .unwrap_or_default();
+        Some(Self {
+            name,
+            extent: u64::from(u32::from_le_bytes(data[2..6].try_into().ok()?)),
+            len: u64::from(u32::from_le_bytes(data[10..14].try_into().ok()?)),
+            flags: data[25],
+            modified: record_time(&data[18..25]),
+            system_use: system_use.to_vec(),
+        })
+    }
+
+    /// The `.` and `..` records
+    fn is_special(&self) -> bool {
+        self.name == [0] || self.name == [1]
+    }
+}
+
+/// Time of a record, as years since 1900 to seconds and an offset in 15 minute intervals
+fn record_time(data: &[u8]) -> Option<SystemTime> {
+    let date = chrono::NaiveDate::from_ymd_opt(
+        1900 + i32::from(data[0]),
+        u32::from(data[1]),
+        u32::from(data[2]),
+    )?;
+    let time = date.and_hms_opt(u32::from(data[3]), u32::from(data[4]), u32::from(data[5]))?;
+    let offset = chrono::FixedOffset::east_opt(i32::from(data[6] as i8) * 15 * 60)?;
+    Some(time.and_local_timezone(offset).single()?.into())
+}
+
+/// Name of a plain or Joliet record, without its version
+fn record_name(name: &[u8], names: Names) -> String {
+    let name = match names {
+        Names::Joliet => String::from_utf16_lossy(
+            &name
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        _ => String::from_utf8_lossy(name).into_owned(),
+    };
+    let name = name.split_once(';').map_or(name.as_str(), |(name, _)| name);
+    // Files without an extension keep the dot before the version
+    name.strip_suffix('.').unwrap_or(name).to_string()
+}
+
+/// Rock Ridge entries of a record
+#[derive(Default)]
+struct RockRidge {
+    name: Option<String>,
+    mode: Option<u32>,
+    symlink: Option<PathBuf>,
+    /// A directory moved elsewhere to stay under the depth limit, at this extent
+    child_link: Option<u64>,
+    /// The moved directory itself, which is listed where [`Self::child_link`] points
+    relocated: bool,
+}
+
+/// Target of a symbolic link, built from the components of SL entries
+#[derive(Default)]
+struct LinkTarget {
+    target: String,
+    /// The last component continues in the next one
+    open: bool,
+}
+
+impl LinkTarget {
+    fn push(&mut self, mut data: &[u8]) {
+        while data.len() >= 2 {
+            let (flags, len) = (data[0], usize::from(data[1]));
+            let Some(content) = data.get(2..2 + len) else {
+                break;
+            };
+            if !self.open && !self.target.is_empty() && !self.target.ends_with('/') {
+                self.target.push('/');
+            }
+            if flags & 0x08 != 0 {
+                self.target.push('/');
+            } else if flags & 0x04 != 0 {
+                self.target.push_str("..");
+            } else if flags & 0x02 != 0 {
+                self.target.push('.');
+            } else {
+                self.target.push_str(&String::from_utf8_lossy(content));
+            }
+            self.open = flags & 0x01 != 0;
+            data = &data[2 + len..];
+        }
+    }
+}
+
+fn read_at<R: Read + Seek>(image: &mut R, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+    image.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    image.take(len).read_to_end(&mut data)?;
+    if (data.len() as u64) < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn le32(data: &[u8]) -> u64 {
+    u64::from(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+}
+
+/// Reads the contents of a file, which can be split in several extents
+struct Extents<'a, R> {
+    image: &'a mut R,
+    extents: std::vec::IntoIter<(u64, u64)>,
+    left: u64,
+}
+
+impl<R: Read + Seek> Read for Extents<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.left == 0 {
+            let Some((extent, len)) = self.extents.next() else {
+                return Ok(0);
+            };
+            self.image.seek(SeekFrom::Start(extent * SECTOR))?;
+            self.left = len;
+        }
+        let max = buf
+            .len()
+            .min(usize::try_from(self.left).unwrap_or(usize::MAX));
+        let count = self.image.read(&mut buf[..max])?;
+        if count == 0 && max > 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.left -= count as u64;
+        Ok(count)
+    }
+}
+
+struct Walker<R> {
+    image: R,
+    names: Names,
+    /// Extents of the directories read, as images can contain loops
+    visited: HashSet<u64>,
+}
+
+impl<R: Read + Seek> Walker<R> {
+    fn rock_ridge(&mut self, record: &Record) -> io::Result<RockRidge> {
+        let mut rock_ridge = RockRidge::default();
+        let Names::RockRidge { skip } = self.names else {
+            return Ok(rock_ridge);
+        };
+        let mut name = Vec::new();
+        let mut link: Option<LinkTarget> = None;
+        let mut area = record.system_use.get(skip..).unwrap_or_default().to_vec();
+        for _ in 0..MAX_CONTINUATIONS {
+            let mut continuation = None;
+            let mut pos = 0;
+            while pos + 4 <= area.len() {
+                let len = usize::from(area[pos + 2]);
+                if len < 4 || pos + len > area.len() {
+                    break;
+                }
+                let data = &area[pos + 4..pos + len];
+                match &area[pos..pos + 2] {
+                    // Names of . and .. are flagged and skipped
+                    b"NM" if !data.is_empty() && data[0] & 0x06 == 0 => {
+                        name.extend_from_slice(&data[1..])
+                    }
+                    b"PX" if data.len() >= 4 => rock_ridge.mode = Some(le32(data) as u32),
+                    b"SL" if !data.is_empty() => link.get_or_insert_default().push(&data[1..]),
+                    b"CL" if data.len() >= 4 => rock_ridge.child_link = Some(le32(data)),
+                    b"RE" => rock_ridge.relocated = true,
+                    b"CE" if data.len() >= 20 => {
+                        continuation = Some((le32(data), le32(&data[8..]), le32(&data[16..])))
+                    }
+                    b"ST" => break,
+                    _ => {}
+                }
+                pos += len;
+            }
+            let Some((extent, offset, len)) = continuation else {
+                break;
+            };
+            area = read_at(&mut self.image, extent * SECTOR + offset, len.min(SECTOR))?;
+        }
+        if !name.is_empty() {
+            rock_ridge.name = Some(String::from_utf8_lossy(&name).into_owned());
+        }
+        rock_ridge.symlink = link.map(|link| PathBuf::from(link.target));
+        Ok(rock_ridge)
+    }
+
+    /// Visit the contents of `dir` at `depth`, returning `false` if `visit` stopped
+    fn walk(
+        &mut self,
+        dir: &Record,
+        parent: &Path,
+        depth: usize,
+        visit: &mut Visit,
+    ) -> io::Result<bool> {
+        if dir.len > MAX_DIR_LEN {
+            return Err(invalid("directory too large"));
+        }
+        if depth > MAX_DEPTH {
+            return Err(invalid("directories nested too deeply"));
+        }
+        if !self.visited.insert(dir.extent) {
+            return Ok(true);
+        }
+        let data = read_at(&mut self.image, dir.extent * SECTOR, dir.len)?;
+        let mut extents = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            // Records do not cross sectors, the rest of which is zeroed
+            if data[pos] == 0 {
+                pos = (pos / SECTOR as usize + 1) * SECTOR as usize;
+                continue;
+            }
+            let mut record =
+                Record::parse(&data[pos..]).ok_or_else(|| invalid("invalid directory record"))?;
+            pos += usize::from(data[pos]);
+            if record.is_special() {
+                continue;
+            }
+            extents.push((record.extent, record.len));
+            if record.flags & Record::MULTI_EXTENT != 0 {
+                continue;
+            }
+            let extents = std::mem::take(&mut extents);
+
+            let rock_ridge = self.rock_ridge(&record)?;
+            if rock_ridge.relocated {
+                continue;
+            }
+            if let Some(extent) = rock_ridge.child_link {
+                let first = read_at(&mut self.image, extent * SECTOR, SECTOR)?;
+                let dot = Record::parse(&first).ok_or_else(|| invalid("invalid child link"))?;
+                record.extent = extent;
+                record.len = dot.len;
+                record.flags |= Record::DIR;
+            }
+            let name = match rock_ridge.name {
+                Some(name) => name,
+                None => record_name(&record.name, self.names),
+            };
+            // Names cannot contain separators, so this cannot go up
+            if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+                continue;
+            }
+            let mode = rock_ridge.mode.unwrap_or(0);
+            let mut item = Item {
+                path: parent.join(name),
+                kind: Kind::File,
+                mode: mode & 0o7777,
+                size: 0,
+                modified: record.modified,
+            };
+            if let Some(target) = rock_ridge.symlink {
+                item.kind = Kind::Symlink(target);
+                if !visit(&item, &mut io::empty())? {
+                    return Ok(false);
+                }
+            } else if record.flags & Record::DIR != 0 {
+                item.kind = Kind::Dir;
+                if !visit(&item, &mut io::empty())?
+                    || !self.walk(&record, &item.path, depth + 1, visit)?
+                {
+                    return Ok(false);
+                }
+            } else {
+                if matches!(mode & 0o170000, 0o010000 | 0o020000 | 0o060000 | 0o140000) {
+                    item.kind = Kind::Special;
+                }
+                item.size = extents.iter().map(|(_, len)| len).sum();
+                let mut reader = Extents {
+                    image: &mut self.image,
+                    extents: extents.into_iter(),
+                    left: 0,
+                };
+                if !visit(&item, &mut reader)? {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Read the files of an ISO 9660 image, each directory followed by its contents
+pub fn for_each<R: Read + Seek>(mut image: R, visit: &mut Visit) -> io::Result<()> {
+    let mut primary = None;
+    let mut joliet = None;
+    for sector in FIRST_DESCRIPTOR.. {
+        let descriptor = read_at(&mut image, sector * SECTOR, SECTOR)?;
+        if &descriptor[1..6] != b"CD001" {
+            return Err(invalid("not an ISO 9660 image"));
+        }
+        let root = || Record::parse(&descriptor[156..190]);
+        match descriptor[0] {
+            1 if primary.is_none() => primary = root(),
+            // Joliet is a supplementary descriptor with a UCS-2 escape sequence
+            2 if descriptor[88..90] == [0x25, 0x2f]
+                && matches!(descriptor[90], 0x40 | 0x43 | 0x45) =>
+            {
+                joliet = root()
+            }
+            255 => break,
+            _ => {}
+        }
+    }
+    let primary = primary.ok_or_else(|| invalid("no primary volume descriptor"))?;
+
+    // Rock Ridge is announced by an SP entry in the first record of the root directory
+    let first = read_at(&mut image, primary.extent * SECTOR, SECTOR)?;
+    let sharing = Record::parse(&first)
+        .map(|dot| dot.system_use)
+        .filter(|area| area.len() >= 7 && area[..2] == *b"SP" && area[4..6] == [0xbe, 0xef]);
+    let (root, names) = match (sharing, joliet) {
+        (Some(area), _) => (
+            primary,
+            Names::RockRidge {
+                skip: usize::from(area[6]),
+            },
+        ),
+        (None, Some(joliet)) => (joliet, Names::Joliet),
+        (None, None) => (primary, Names::Plain),
+    };
+    let mut walker = Walker {
+        image,
+        names,
+        visited: HashSet::new(),
+    };
+    walker.walk(&root, Path::new(""), 0, visit)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor};
+
+    use super::{MAX_DEPTH, SECTOR, for_each};
+
+    /// Directory record of `name` at `extent`
+    fn record(name: &[u8], extent: u32, len: u32, flags: u8, system_use: &[u8]) -> Vec<u8> {
+        let mut record = vec![0; 33];
+        record[2..6].copy_from_slice(&extent.to_le_bytes());
+        record[6..10].copy_from_slice(&extent.to_be_bytes());
+        record[10..14].copy_from_slice(&len.to_le_bytes());
+        record[14..18].copy_from_slice(&len.to_be_bytes());
+        record[18..25].copy_from_slice(&[124, 1, 2, 3, 4, 5, 0]);
+        record[25] = flags;
+        record[32] = name.len() as u8;
+        record.extend_from_slice(name);
+        if name.len() % 2 == 0 {
+            record.push(0);
+        }
+        record.extend_from_slice(system_use);
+        record[0] = record.len() as u8;
+        record
+    }
+
+    /// Image with a README file and a DOCS directory holding a symbolic link to it
+    fn image(rock_ridge: bool) -> Vec<u8> {
+        let sector = SECTOR as usize;
+        let mut image = vec![0; sector * 22];
+        let system_use = |entries: &[Vec<u8>]| -> Vec<u8> {
+            if rock_ridge {
+                entries.concat()
+            } else {
+                Vec::new()
+            }
+        };
+        let sp = [b"SP".as_slice(), &[7, 1, 0xbe, 0xef, 0]].concat();
+        let root = record(&[0], 18, SECTOR as u32, 2, &[]);
+
+        let descriptor = &mut image[sector * 16..sector * 17];
+        descriptor[0] = 1;
+        descriptor[1..6].copy_from_slice(b"CD001");
+        descriptor[156..156 + root.len()].copy_from_slice(&root);
+        let terminator = &mut image[sector * 17..sector * 18];
+        terminator[0] = 255;
+        terminator[1..6].copy_from_slice(b"CD001");
+
+        let nm = |name: &str| {
+            [
+                b"NM".as_slice(),
+                &[5 + name.len() as u8, 1, 0],
+                name.as_bytes(),
+            ]
+            .concat()
+        };
+        let px = |mode: u32| {
+            [
+                b"PX".as_slice(),
+                &[36, 1],
+                &mode.to_le_bytes(),
+                &mode.to_be_bytes(),
+                &[0; 24],
+            ]
+            .concat()
+        };
+        let sl = [b"SL".as_slice(), &[15, 1, 0, 4, 0, 0, 6], b"README"].concat();
+
+        let mut dir = record(&[0], 18, SECTOR as u32, 2, &system_use(&[sp]));
+        dir.extend(record(&[1], 18, SECTOR as u32, 2, &[]));
+        dir.extend(record(
+            b"README.TXT;1",
+            20,
+            5,
+            0,
+            &system_use(&[nm("ReadMe.txt"), px(0o100640)]),
+        ));
+        dir.extend(record(
+            b"DOCS",
+            19,
+            SECTOR as u32,
+            2,
+            &system_use(&[nm("docs")]),
+        ));
+        image[sector * 18..sector * 18 + dir.len()].copy_from_slice(&dir);
+
+        let mut docs = record(&[0], 19, SECTOR as u32, 2, &[]);
+        docs.extend(record(&[1], 18, SECTOR as u32, 2, &[]));
+        docs.extend(record(b"LINK.;1", 21, 0, 0, &system_use(&[nm("link"), sl])));
+        image[sector * 19..sector * 19 + docs.len()].copy_from_slice(&docs);
+
+        image[sector * 20..sector * 20 + 5].copy_from_slice(b"hello");
+        image
+    }
+
+    /// Image whose root holds a chain of `depth` nested D directories
+    fn deep_image(depth: u32) -> Vec<u8> {
+        let sector = SECTOR as usize;
+        let mut image = vec![0; sector * (19 + depth as usize)];
+        let root = record(&[0], 18, SECTOR as u32, 2, &[]);
+        let descriptor = &mut image[sector * 16..sector * 17];
+        descriptor[0] = 1;
+        descriptor[1..6].copy_from_slice(b"CD001");
+        descriptor[156..156 + root.len()].copy_from_slice(&root);
+        let terminator = &mut image[sector * 17..sector * 18];
+        terminator[0] = 255;
+        terminator[1..6].copy_from_slice(b"CD001");
+
+        for level in 0..=depth {
+            let extent = 18 + level;
+            let mut dir = record(&[0], extent, SECTOR as u32, 2, &[]);
+            dir.extend(record(&[1], extent.max(19) - 1, SECTOR as u32, 2, &[]));
+            if level < depth {
+                dir.extend(record(b"D", extent + 1, SECTOR as u32, 2, &[]));
+            }
+            let start = sector * extent as usize;
+            image[start..start + dir.len()].copy_from_slice(&dir);
+        }
+        image
+    }
+
+    fn read_all(image: Vec<u8>) -> io::Result<Vec<(String, u32, String)>> {
+        let mut items = Vec::new();
+        for_each(Cursor::new(image), &mut |item, reader| {
+            let mut data = String::new();
+            reader.read_to_string(&mut data)?;
+            items.push((
+                format!("{}: {:?}", item.path.display(), item.kind),
+                item.mode,
+                data,
+            ));
+            Ok(true)
+        })?;
+        Ok(items)
+    }
+
+    #[test]
+    fn plain_names() -> io::Result<()> {
+        assert_eq!(
+            read_all(image(false))?,
+            vec![
+                ("README.TXT: File".to_string(), 0, "hello".to_string()),
+                ("DOCS: Dir".to_string(), 0, String::new()),
+                ("DOCS/LINK: File".to_string(), 0, String::new()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rock_ridge_names() -> io::Result<()> {
+        assert_eq!(
+            read_all(image(true))?,
+            vec![
+                ("ReadMe.txt: File".to_string(), 0o640, "hello".to_string()),
+                ("docs: Dir".to_string(), 0, String::new()),
+                (
+                    "docs/link: Symlink(\"../README\")".to_string(),
+                    0,
+                    String::new()
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn deep_directories() -> io::Result<()> {
+        assert_eq!(read_all(deep_image(MAX_DEPTH as u32))?.len(), MAX_DEPTH);
+        let err = read_all(deep_image(MAX_DEPTH as u32 + 1)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        Ok(())
+    }
+}
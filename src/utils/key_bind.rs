@@ -73,6 +73,8 @@ pub fn key_binds(mode: &tab::Mode) -> HashMap<KeyBind, Action> {
         bind!([Shift], Key::Named(Named::Enter), OpenInNewWindow);
         bind!([Ctrl], Key::Character("v".into()), Paste);
         bind!([], Key::Named(Named::F2), Rename);
+        bind!([Ctrl], Key::Character("z".into()), Undo);
+        bind!([Ctrl, Shift], Key::Character("z".into()), Redo);
     }
 
     // App and dialog only keys
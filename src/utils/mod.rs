@@ -9,6 +9,10 @@ pub mod thumbnail_cacher;
 pub mod thumbnailer;
 pub mod clipboard;
 pub mod archive;
+pub mod content_search;
+pub mod iso9660;
+pub mod package;
+pub mod search_query;
 pub mod key_bind;
 pub mod command_utils;
 pub mod desktop_theme;
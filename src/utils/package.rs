@@ -0,0 +1,675 @@
+//! Readers of disk images and software packages, which can be extracted and browsed but not
+//! written. The formats are parsed here, so no external tools are needed.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Read},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{archive::tar_decoder, iso9660};
+use crate::core::operations::{Controller, OpReader};
+
+/// Disk images and packages that can be extracted and browsed
+pub const PACKAGE_TYPES: &[&str] = &[
+    "application/vnd.debian.binary-package",
+    "application/vnd.efi.iso",
+    "application/x-archive",
+    "application/x-cd-image",
+    "application/x-cpio",
+    "application/x-deb",
+    "application/x-debian-package",
+    "application/x-iso9660-image",
+    "application/x-redhat-package-manager",
+    "application/x-rpm",
+];
+
+/// Names are read into memory, so longer ones than PATH_MAX are refused as corrupt
+const MAX_NAME_LEN: u64 = 4096;
+
+/// What an item of a package is
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Kind {
+    Dir,
+    File,
+    Symlink(PathBuf),
+    /// A hard link to an item that came before it
+    HardLink(PathBuf),
+    /// A device node, FIFO or socket
+    Special,
+}
+
+/// An item of a disk image or package
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub path: PathBuf,
+    pub kind: Kind,
+    /// Permission bits, or 0 if the format has none
+    pub mode: u32,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Called with each item and a reader of its contents, returning `false` to stop
+pub type Visit<'a> = dyn FnMut(&Item, &mut dyn Read) -> io::Result<bool> + 'a;
+
+pub fn is_package(mime: &str) -> bool {
+    PACKAGE_TYPES.contains(&mime)
+}
+
+/// Read the items of the disk image or package at `path` in order
+pub fn for_each(
+    path: &Path,
+    mime: &str,
+    controller: &Controller,
+    visit: &mut Visit,
+) -> io::Result<()> {
+    // Disk images are read in the order of their directories, not of their contents
+    if matches!(
+        mime,
+        "application/vnd.efi.iso" | "application/x-cd-image" | "application/x-iso9660-image"
+    ) {
+        return iso9660::for_each(io::BufReader::new(fs::File::open(path)?), visit);
+    }
+    let reader = io::BufReader::new(OpReader::new(path, controller.clone())?);
+    match mime {
+        "application/x-archive" => ar_for_each(reader, visit),
+        "application/x-cpio" => cpio_for_each(reader, visit),
+        "application/vnd.debian.binary-package"
+        | "application/x-deb"
+        | "application/x-debian-package" => deb_for_each(reader, visit),
+        "application/x-redhat-package-manager" | "application/x-rpm" => rpm_for_each(reader, visit),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported mime type {:?}", mime),
+        )),
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Fill `buf`, returning `false` if the reader is already at its end
+fn read_or_end<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(count) => read += count,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(true)
+}
+
+fn skip<R: Read>(reader: &mut R, len: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(len), &mut io::sink())?;
+    if skipped < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+/// Number in a text field of a header, padded with spaces or NULs
+fn number(field: &[u8], radix: u32) -> io::Result<u64> {
+    let text = std::str::from_utf8(field).map_err(|_| invalid("invalid number in header"))?;
+    let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, radix).map_err(|_| invalid("invalid number in header"))
+}
+
+fn unix_time(secs: u64) -> Option<SystemTime> {
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// Read the members of an ar archive, with GNU and BSD long names
+fn ar_for_each<R: Read>(mut reader: R, visit: &mut Visit) -> io::Result<()> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != b"!<arch>\n" {
+        return Err(invalid("not an ar archive"));
+    }
+    let mut long_names = Vec::new();
+    let mut header = [0; 60];
+    while read_or_end(&mut reader, &mut header)? {
+        if &header[58..] != b"`\n" {
+            return Err(invalid("invalid ar header"));
+        }
+        let member = number(&header[48..58], 10)?;
+        let mut size = member;
+        let field = String::from_utf8_lossy(&header[..16])
+            .trim_end()
+            .to_string();
+        let name = if field == "/" || field == "/SYM64/" || field == "__.SYMDEF" {
+            // Symbol tables of static libraries
+            None
+        } else if field == "//" {
+            long_names.clear();
+            (&mut reader).take(size).read_to_end(&mut long_names)?;
+            size -= long_names.len() as u64;
+            None
+        } else if let Some(len) = field.strip_prefix("#1/") {
+            let len = number(len.as_bytes(), 10)?.min(size);
+            if len > MAX_NAME_LEN {
+                return Err(invalid("ar name too long"));
+            }
+            let mut name = vec![0; len as usize];
+            reader.read_exact(&mut name)?;
+            size -= len;
+            let name = String::from_utf8_lossy(&name);
+            Some(name.trim_end_matches('\0').to_string())
+        } else if let Some(offset) = field.strip_prefix('/') {
+            let offset = number(offset.as_bytes(), 10)? as usize;
+            let name = long_names
+                .get(offset..)
+                .and_then(|names| names.split(|&b| b == b'\n').next())
+                .ok_or_else(|| invalid("invalid ar long name"))?;
+            Some(
+                String::from_utf8_lossy(name)
+                    .trim_end_matches('/')
+                    .to_string(),
+            )
+        } else {
+            Some(field.trim_end_matches('/').to_string())
+        };
+
+        let mut data = (&mut reader).take(size);
+        if let Some(name) = name {
+            let item = Item {
+                path: PathBuf::from(name),
+                kind: Kind::File,
+                mode: number(&header[40..48], 8)? as u32 & 0o7777,
+                size,
+                modified: unix_time(number(&header[16..28], 10)?),
+            };
+            if !visit(&item, &mut data)? {
+                return Ok(());
+            }
+        }
+        io::copy(&mut data, &mut io::sink())?;
+        // Members start at even offsets
+        skip(&mut reader, member % 2)?;
+    }
+    Ok(())
+}
+
+/// Read the entries of a tar archive, with their paths inside of `prefix`. Returns `false` if
+/// `visit` stopped.
+fn tar_for_each<R: Read>(reader: R, prefix: &Path, visit: &mut Visit) -> io::Result<bool> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = prefix.join(entry.path()?);
+        let header = entry.header();
+        let entry_type = header.entry_type();
+        let link = || {
+            entry
+                .link_name()
+                .map(|link| link.map(|link| link.into_owned()).unwrap_or_default())
+        };
+        let kind = if entry_type.is_dir() {
+            Kind::Dir
+        } else if entry_type.is_symlink() {
+            Kind::Symlink(link()?)
+        } else if entry_type.is_hard_link() {
+            Kind::HardLink(prefix.join(link()?))
+        } else if entry_type.is_file() || entry_type.is_contiguous() || entry_type.is_gnu_sparse() {
+            Kind::File
+        } else {
+            Kind::Special
+        };
+        let item = Item {
+            path,
+            kind,
+            mode: header.mode().unwrap_or(0) & 0o7777,
+            size: entry.size(),
+            modified: header.mtime().ok().and_then(unix_time),
+        };
+        if !visit(&item, &mut entry)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Decompressor of a tar archive in a package, by its name
+fn tar_mime(name: &str) -> Option<&'static str> {
+    Some(match name.rsplit_once(".tar")?.1 {
+        "" => "application/x-tar",
+        ".gz" => "application/x-compressed-tar",
+        ".bz2" => "application/x-bzip2-compressed-tar",
+        ".xz" => "application/x-xz-compressed-tar",
+        ".zst" => "application/x-zstd-compressed-tar",
+        _ => return None,
+    })
+}
+
+/// Read the files installed by a Debian package, followed by its control files in `DEBIAN`
+/// like `dpkg-deb --raw-extract` does
+fn deb_for_each<R: Read>(reader: R, visit: &mut Visit) -> io::Result<()> {
+    ar_for_each(reader, &mut |member, data| {
+        let name = member.path.to_string_lossy();
+        let prefix = if name.starts_with("data.tar") {
+            Path::new("")
+        } else if name.starts_with("control.tar") {
+            Path::new("DEBIAN")
+        } else {
+            return Ok(true);
+        };
+        let decoder = tar_mime(&name)
+            .map(|mime| tar_decoder(io::BufReader::new(data), mime))
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unsupported compression of {name}"),
+                )
+            })?;
+        tar_for_each(decoder, prefix, visit)
+    })
+}
+
+/// Length of an RPM header structure after its 16 byte intro, see
+/// <https://rpm-software-management.github.io/rpm/manual/format_v4.html>
+fn rpm_header_len<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut intro = [0; 16];
+    reader.read_exact(&mut intro)?;
+    if intro[..4] != [0x8e, 0xad, 0xe8, 0x01] {
+        return Err(invalid("invalid rpm header"));
+    }
+    let index = u32::from_be_bytes([intro[8], intro[9], intro[10], intro[11]]);
+    let store = u32::from_be_bytes([intro[12], intro[13], intro[14], intro[15]]);
+    Ok(u64::from(index) * 16 + u64::from(store))
+}
+
+/// Read the files installed by an RPM package, from the cpio archive after its headers
+fn rpm_for_each<R: BufRead>(mut reader: R, visit: &mut Visit) -> io::Result<()> {
+    let mut lead = [0; 96];
+    reader.read_exact(&mut lead)?;
+    if lead[..4] != [0xed, 0xab, 0xee, 0xdb] {
+        return Err(invalid("not an rpm package"));
+    }
+    // The signature is padded to 8 bytes, the header after it is not
+    let signature = rpm_header_len(&mut reader)?;
+    skip(&mut reader, signature.next_multiple_of(8))?;
+    let header = rpm_header_len(&mut reader)?;
+    skip(&mut reader, header)?;
+
+    // The compression of the payload is in the header, but its magic bytes are simpler to read
+    let magic = reader.fill_buf()?;
+    let compression = if magic.starts_with(&[0x1f, 0x8b]) {
+        Some("application/gzip")
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0]) {
+        Some("application/x-xz")
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Some("application/zstd")
+    } else if magic.starts_with(b"BZh") {
+        Some("application/x-bzip2")
+    } else {
+        None
+    };
+    match compression {
+        Some(mime) => {
+            let decoder = tar_decoder(reader, mime)?.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unsupported rpm payload compression {mime}"),
+                )
+            })?;
+            cpio_for_each(decoder, visit)
+        }
+        None => cpio_for_each(reader, visit),
+    }
+}
+
+/// Read the entries of a cpio archive in the portable ASCII formats, which RPM uses too
+fn cpio_for_each<R: Read>(mut reader: R, visit: &mut Visit) -> io::Result<()> {
+    // In the newc format only the last link of a file has its contents
+    let mut links = HashMap::<u64, Vec<Item>>::new();
+    let mut magic = [0; 6];
+    while read_or_end(&mut reader, &mut magic)? {
+        let (newc, ino, mode, nlink, mtime, name_len, size) = match &magic {
+            b"070701" | b"070702" => {
+                let mut header = [0; 104];
+                reader.read_exact(&mut header)?;
+                let field = |i: usize| number(&header[i * 8..i * 8 + 8], 16);
+                (
+                    true,
+                    field(0)?,
+                    field(1)?,
+                    field(4)?,
+                    field(5)?,
+                    field(11)?,
+                    field(6)?,
+                )
+            }
+            b"070707" => {
+                let mut header = [0; 70];
+                reader.read_exact(&mut header)?;
+                let field = |range: std::ops::Range<usize>| number(&header[range], 8);
+                (
+                    false,
+                    field(6..12)?,
+                    field(12..18)?,
+                    field(30..36)?,
+                    field(42..53)?,
+                    field(53..59)?,
+                    field(59..70)?,
+                )
+            }
+            _ => return Err(invalid("invalid cpio header")),
+        };
+        if name_len > MAX_NAME_LEN {
+            return Err(invalid("cpio name too long"));
+        }
+        let mut name = vec![0; name_len as usize];
+        reader.read_exact(&mut name)?;
+        let name = String::from_utf8_lossy(&name)
+            .trim_end_matches('\0')
+            .to_string();
+        // Headers and contents of the newc format are padded to 4 bytes
+        let padding = |len: u64| {
+            if newc {
+                len.next_multiple_of(4) - len
+            } else {
+                0
+            }
+        };
+        skip(&mut reader, padding(110 + name_len))?;
+        if name == "TRAILER!!!" {
+            break;
+        }
+
+        let mut item = Item {
+            path: PathBuf::from(name),
+            kind: match mode as u32 & 0o170000 {
+                0o040000 => Kind::Dir,
+                0o100000 => Kind::File,
+                0o120000 => Kind::Symlink(PathBuf::new()),
+                _ => Kind::Special,
+            },
+            mode: mode as u32 & 0o7777,
+            size,
+            modified: unix_time(mtime),
+        };
+        let mut data = (&mut reader).take(size);
+        let more = match item.kind {
+            Kind::Symlink(_) => {
+                if size > MAX_NAME_LEN {
+                    return Err(invalid("cpio link target too long"));
+                }
+                let mut target = Vec::new();
+                data.read_to_end(&mut target)?;
+                item.kind = Kind::Symlink(PathBuf::from(String::from_utf8_lossy(&target).as_ref()));
+                item.size = 0;
+                visit(&item, &mut io::empty())?
+            }
+            Kind::File if nlink > 1 && size == 0 => {
+                links.entry(ino).or_default().push(item);
+                true
+            }
+            Kind::File if nlink > 1 => {
+                let mut more = visit(&item, &mut data)?;
+                for link in links.remove(&ino).unwrap_or_default() {
+                    if more {
+                        let link = Item {
+                            kind: Kind::HardLink(item.path.clone()),
+                            ..link
+                        };
+                        more = visit(&link, &mut io::empty())?;
+                    }
+                }
+                more
+            }
+            _ => visit(&item, &mut data)?,
+        };
+        io::copy(&mut data, &mut io::sink())?;
+        if !more {
+            return Ok(());
+        }
+        skip(&mut reader, padding(size))?;
+    }
+    // Links whose contents never came are empty files
+    for link in links.into_values().flatten() {
+        if !visit(&link, &mut io::empty())? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Read},
+        path::PathBuf,
+    };
+
+    use flate2::{Compression, write::GzEncoder};
+
+    use super::{Item, Kind, ar_for_each, cpio_for_each, deb_for_each, rpm_for_each};
+
+    /// Read the items and contents of a package with `for_each`
+    fn read_all(
+        for_each: impl FnOnce(
+            &mut dyn FnMut(&Item, &mut dyn Read) -> io::Result<bool>,
+        ) -> io::Result<()>,
+    ) -> io::Result<Vec<(PathBuf, Kind, Vec<u8>)>> {
+        let mut items = Vec::new();
+        for_each(&mut |item, reader| {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            items.push((item.path.clone(), item.kind.clone(), data));
+            Ok(true)
+        })?;
+        Ok(items)
+    }
+
+    fn ar_member(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut member = format!(
+            "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+            name,
+            0,
+            0,
+            0,
+            644,
+            data.len()
+        )
+        .into_bytes();
+        member.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            member.push(b'\n');
+        }
+        member
+    }
+
+    fn newc_entry(name: &str, ino: u32, mode: u32, nlink: u32, data: &[u8]) -> Vec<u8> {
+        let name_len = name.len() + 1;
+        let mut entry = format!(
+            "070701{ino:08x}{mode:08x}{:08x}{:08x}{nlink:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{name_len:08x}{:08x}",
+            0,
+            0,
+            0,
+            data.len(),
+            0,
+            0,
+            0,
+            0,
+            0
+        )
+        .into_bytes();
+        entry.extend_from_slice(name.as_bytes());
+        entry.push(0);
+        entry.resize(entry.len().next_multiple_of(4), 0);
+        entry.extend_from_slice(data);
+        entry.resize(entry.len().next_multiple_of(4), 0);
+        entry
+    }
+
+    /// Tar archive of files with their contents
+    fn tar(files: &[(&str, &[u8])]) -> io::Result<Vec<u8>> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *data)?;
+        }
+        builder.into_inner()
+    }
+
+    fn gzip(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        io::Write::write_all(&mut encoder, data)?;
+        encoder.finish()
+    }
+
+    /// RPM header structure with no index entries and `store` bytes of data
+    fn rpm_header(store: u32) -> Vec<u8> {
+        let mut header = vec![0x8e, 0xad, 0xe8, 0x01, 0, 0, 0, 0, 0, 0, 0, 0];
+        header.extend_from_slice(&store.to_be_bytes());
+        header.resize(header.len() + store as usize, 0);
+        header
+    }
+
+    #[test]
+    fn ar_long_names() -> io::Result<()> {
+        let long = "a-name-longer-than-sixteen-bytes.txt";
+        let mut archive = b"!<arch>\n".to_vec();
+        archive.extend(ar_member("//", format!("{long}/\n").as_bytes()));
+        archive.extend(ar_member("short.txt/", b"odd"));
+        archive.extend(ar_member("/0", b"long"));
+        archive.extend(ar_member(
+            &format!("#1/{}", long.len()),
+            &[long.as_bytes(), b"bsd"].concat(),
+        ));
+        let items = read_all(|visit| ar_for_each(archive.as_slice(), visit))?;
+        assert_eq!(
+            items,
+            vec![
+                (PathBuf::from("short.txt"), Kind::File, b"odd".to_vec()),
+                (PathBuf::from(long), Kind::File, b"long".to_vec()),
+                (PathBuf::from(long), Kind::File, b"bsd".to_vec()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn long_names_refused() {
+        let mut archive = b"!<arch>\n".to_vec();
+        archive.extend(ar_member("#1/9999999999", b"data"));
+        // Size field of the header
+        archive[56..66].copy_from_slice(b"9999999999");
+        let err = read_all(|visit| ar_for_each(archive.as_slice(), visit)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut archive = newc_entry("name", 1, 0o100644, 1, b"");
+        // Name length field of the header
+        archive[94..102].copy_from_slice(b"ffffffff");
+        let err = read_all(|visit| cpio_for_each(archive.as_slice(), visit)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut archive = newc_entry("link", 1, 0o120777, 1, b"target");
+        // File size field of the header, which is the length of link targets
+        archive[54..62].copy_from_slice(b"ffffffff");
+        let err = read_all(|visit| cpio_for_each(archive.as_slice(), visit)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn deb_contents() -> io::Result<()> {
+        let mut package = b"!<arch>\n".to_vec();
+        package.extend(ar_member("debian-binary", b"2.0\n"));
+        package.extend(ar_member(
+            "control.tar",
+            &tar(&[("control", b"Package: test\n")])?,
+        ));
+        package.extend(ar_member(
+            "data.tar.gz",
+            &gzip(&tar(&[("usr/bin/test", b"binary")])?)?,
+        ));
+        let items = read_all(|visit| deb_for_each(package.as_slice(), visit))?;
+        assert_eq!(
+            items,
+            vec![
+                (
+                    PathBuf::from("DEBIAN/control"),
+                    Kind::File,
+                    b"Package: test\n".to_vec()
+                ),
+                (
+                    PathBuf::from("usr/bin/test"),
+                    Kind::File,
+                    b"binary".to_vec()
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rpm_contents() -> io::Result<()> {
+        let mut payload = newc_entry("./usr/bin/test", 1, 0o100755, 1, b"binary");
+        payload.extend(newc_entry("TRAILER!!!", 0, 0, 1, b""));
+        let mut package = vec![0xed, 0xab, 0xee, 0xdb];
+        package.resize(96, 0);
+        // The signature is padded to 8 bytes, the header is not
+        package.extend(rpm_header(3));
+        package.resize(package.len().next_multiple_of(8), 0);
+        package.extend(rpm_header(5));
+        package.extend(gzip(&payload)?);
+        let items = read_all(|visit| rpm_for_each(package.as_slice(), visit))?;
+        assert_eq!(
+            items,
+            vec![(
+                PathBuf::from("./usr/bin/test"),
+                Kind::File,
+                b"binary".to_vec()
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cpio_links() -> io::Result<()> {
+        let mut archive = Vec::new();
+        archive.extend(newc_entry("./usr", 1, 0o040755, 2, b""));
+        archive.extend(newc_entry("./usr/first", 2, 0o100644, 2, b""));
+        archive.extend(newc_entry("./usr/second", 2, 0o100644, 2, b"shared"));
+        archive.extend(newc_entry("./usr/link", 3, 0o120777, 1, b"second"));
+        archive.extend(newc_entry("TRAILER!!!", 0, 0, 1, b""));
+        let items = read_all(|visit| cpio_for_each(archive.as_slice(), visit))?;
+        assert_eq!(
+            items,
+            vec![
+                (PathBuf::from("./usr"), Kind::Dir, Vec::new()),
+                (
+                    PathBuf::from("./usr/second"),
+                    Kind::File,
+                    b"shared".to_vec()
+                ),
+                (
+                    PathBuf::from("./usr/first"),
+                    Kind::HardLink(PathBuf::from("./usr/second")),
+                    Vec::new()
+                ),
+                (
+                    PathBuf::from("./usr/link"),
+                    Kind::Symlink(PathBuf::from("second")),
+                    Vec::new()
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
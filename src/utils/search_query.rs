@@ -0,0 +1,496 @@
+// Structured search queries
+//
+// Besides plain text, a search term may contain filters such as
+// `size:>100M modified:<7d type:image ext:rs,toml owner:me name:/regex/`. The remaining text
+// is matched against names or contents as before, the filters against the metadata of each
+// result. Double quotes group words and keep them from being read as a filter.
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    fs::Metadata,
+    os::unix::fs::MetadataExt,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use crate::{fl, utils::content_search, utils::mime_icon::mime_for_path};
+
+/// Keys of the supported filters
+const KEYS: &[&str] = &["ext", "modified", "name", "owner", "size", "type"];
+
+/// Top level mime types accepted by `type:`
+const MIME_TYPES: &[&str] = &[
+    "application",
+    "audio",
+    "font",
+    "image",
+    "model",
+    "text",
+    "video",
+];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum QueryError {
+    UnterminatedQuote,
+    MissingValue(String),
+    InvalidSize(String),
+    InvalidTime(String),
+    UnknownType(String),
+    UnknownUser(String),
+    InvalidRegex(String),
+}
+
+impl QueryError {
+    fn regex(err: regex::Error) -> Self {
+        // Syntax errors span several lines pointing at the pattern, the last one explains it
+        let err = err.to_string();
+        let line = err.lines().last().unwrap_or_default();
+        Self::InvalidRegex(line.trim_start_matches("error: ").to_string())
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&match self {
+            Self::UnterminatedQuote => fl!("search-error-quote"),
+            Self::MissingValue(key) => fl!("search-error-missing", key = key.as_str()),
+            Self::InvalidSize(value) => fl!("search-error-size", value = value.as_str()),
+            Self::InvalidTime(value) => fl!("search-error-modified", value = value.as_str()),
+            Self::UnknownType(value) => fl!("search-error-type", value = value.as_str()),
+            Self::UnknownUser(value) => fl!("search-error-owner", value = value.as_str()),
+            Self::InvalidRegex(error) => fl!("search-error-regex", error = error.as_str()),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Compare {
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+
+impl Compare {
+    /// Split a leading comparison operator from a value
+    fn parse(value: &str) -> (Option<Self>, &str) {
+        for (prefix, compare) in [
+            (">=", Self::GreaterEqual),
+            ("<=", Self::LessEqual),
+            (">", Self::Greater),
+            ("<", Self::Less),
+            ("=", Self::Equal),
+        ] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (Some(compare), rest);
+            }
+        }
+        (None, value)
+    }
+
+    fn test(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Less => ordering.is_lt(),
+            Self::LessEqual => ordering.is_le(),
+            Self::Equal => ordering.is_eq(),
+            Self::GreaterEqual => ordering.is_ge(),
+            Self::Greater => ordering.is_gt(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Kind {
+    Dir,
+    File,
+    Symlink,
+    /// Top level mime type, such as `image`
+    MimeType(String),
+    /// Full mime type, such as `image/png`
+    Mime(String),
+}
+
+#[derive(Clone, Debug)]
+enum Filter {
+    Size(Compare, u64),
+    /// Time since the last modification
+    Age(Compare, Duration),
+    /// Modification time within a day, given as its start and end
+    Day(Compare, SystemTime, SystemTime),
+    Type(Vec<Kind>),
+    Ext(Vec<String>),
+    Owner(u32),
+    /// User name of an owner, looked up by [`Query::resolve_owners`]
+    OwnerName(String),
+    Name(regex::Regex),
+}
+
+impl Filter {
+    fn parse(key: &str, value: &str) -> Result<Option<Self>, QueryError> {
+        let filter = match key {
+            "size" => {
+                let (compare, size) = Compare::parse(value);
+                let size = parse_size(size).ok_or_else(|| QueryError::InvalidSize(value.into()))?;
+                Self::Size(compare.unwrap_or(Compare::Equal), size)
+            }
+            "modified" => {
+                let (compare, time) = Compare::parse(value);
+                if let Some(age) = parse_age(time) {
+                    // A bare age means modified within that time
+                    Self::Age(compare.unwrap_or(Compare::LessEqual), age)
+                } else if let Some((start, end)) = parse_day(time) {
+                    Self::Day(compare.unwrap_or(Compare::Equal), start, end)
+                } else {
+                    return Err(QueryError::InvalidTime(value.into()));
+                }
+            }
+            "type" => Self::Type(
+                list(value)
+                    .map(|kind| parse_kind(&kind).ok_or(QueryError::UnknownType(kind)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            "ext" => Self::Ext(
+                list(value)
+                    .map(|ext| ext.trim_start_matches('.').to_string())
+                    .collect(),
+            ),
+            "owner" => match value {
+                "me" => Self::Owner(uzers::get_current_uid()),
+                _ => match value.parse() {
+                    Ok(uid) => Self::Owner(uid),
+                    Err(_) => Self::OwnerName(value.into()),
+                },
+            },
+            "name" => {
+                let regex = match value
+                    .strip_prefix('/')
+                    .and_then(|value| value.strip_suffix('/'))
+                {
+                    Some(pattern) => content_search::matcher(pattern, true),
+                    None => content_search::matcher(value, false),
+                };
+                Self::Name(regex.map_err(QueryError::regex)?)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(filter))
+    }
+
+    fn matches(&self, path: &Path, metadata: &Metadata) -> bool {
+        match self {
+            Self::Size(compare, size) => compare.test(metadata.len().cmp(size)),
+            Self::Age(compare, age) => match metadata.modified() {
+                Ok(modified) => {
+                    let elapsed = SystemTime::now()
+                        .duration_since(modified)
+                        .unwrap_or_default();
+                    compare.test(elapsed.cmp(age))
+                }
+                Err(_) => false,
+            },
+            Self::Day(compare, start, end) => match metadata.modified() {
+                Ok(modified) => match compare {
+                    Compare::Less => modified < *start,
+                    Compare::LessEqual => modified < *end,
+                    Compare::Equal => *start <= modified && modified < *end,
+                    Compare::GreaterEqual => modified >= *start,
+                    Compare::Greater => modified >= *end,
+                },
+                Err(_) => false,
+            },
+            Self::Type(kinds) => {
+                let mime = mime_for_path(path, Some(metadata), false);
+                kinds.iter().any(|kind| match kind {
+                    Kind::Dir => metadata.is_dir(),
+                    Kind::File => metadata.is_file(),
+                    Kind::Symlink => metadata.is_symlink(),
+                    Kind::MimeType(type_) => mime.type_() == type_.as_str(),
+                    Kind::Mime(essence) => mime.essence_str() == essence.as_str(),
+                })
+            }
+            Self::Ext(exts) => {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                exts.iter().any(|ext| {
+                    name.strip_suffix(ext.as_str())
+                        .is_some_and(|stem| stem.len() > 1 && stem.ends_with('.'))
+                })
+            }
+            Self::Owner(uid) => metadata.uid() == *uid,
+            // Names are resolved before searching
+            Self::OwnerName(_) => false,
+            Self::Name(regex) => path
+                .file_name()
+                .is_some_and(|name| regex.is_match(&name.to_string_lossy())),
+        }
+    }
+}
+
+/// Comma separated values, lowercased and without empty entries
+fn list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Split a number from its unit
+fn split_number(value: &str) -> (&str, &str) {
+    let end = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    value.split_at(end)
+}
+
+/// Size with an optional binary unit, such as `100M` or `1.5GiB`
+fn parse_size(value: &str) -> Option<u64> {
+    let (number, unit) = split_number(value);
+    let number: f64 = number.parse().ok()?;
+    let unit = unit.to_lowercase();
+    let power = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        _ => return None,
+    };
+    Some((number * 1024f64.powi(power)) as u64)
+}
+
+/// Age such as `30min`, `12h` or `7d`
+fn parse_age(value: &str) -> Option<Duration> {
+    let (number, unit) = split_number(value);
+    let number: f64 = number.parse().ok()?;
+    let secs = match unit.to_lowercase().as_str() {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(number * secs as f64).ok()
+}
+
+/// Local day such as `2024-01-31`
+fn parse_day(value: &str) -> Option<(SystemTime, SystemTime)> {
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let start = |date: chrono::NaiveDate| {
+        date.and_hms_opt(0, 0, 0)?
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .map(SystemTime::from)
+    };
+    Some((start(date)?, start(date.succ_opt()?)?))
+}
+
+fn parse_kind(value: &str) -> Option<Kind> {
+    Some(match value {
+        "dir" | "directory" | "folder" => Kind::Dir,
+        "file" => Kind::File,
+        "link" | "symlink" => Kind::Symlink,
+        _ => match value.split_once('/') {
+            Some((type_, "*")) if MIME_TYPES.contains(&type_) => Kind::MimeType(type_.into()),
+            Some(_) => Kind::Mime(value.parse::<mime_guess::Mime>().ok()?.essence_str().into()),
+            None if MIME_TYPES.contains(&value) => Kind::MimeType(value.into()),
+            None => return None,
+        },
+    })
+}
+
+/// Split a query into words, grouping quoted text. Quoted words are never filters
+fn tokenize(input: &str) -> Result<Vec<(String, bool)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut token: Option<(String, bool)> = None;
+    let mut in_quote = false;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quote = !in_quote;
+                token.get_or_insert_with(|| (String::new(), true));
+            }
+            '\\' if chars.as_str().starts_with('"') => {
+                token.get_or_insert_default().0.push('"');
+                chars.next();
+            }
+            c if c.is_whitespace() && !in_quote => tokens.extend(token.take()),
+            c => token.get_or_insert_default().0.push(c),
+        }
+    }
+    if in_quote {
+        return Err(QueryError::UnterminatedQuote);
+    }
+    tokens.extend(token);
+    Ok(tokens)
+}
+
+/// Search term split into text and filters
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    text: String,
+    filters: Vec<Filter>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let mut query = Self::default();
+        let mut words = Vec::new();
+        for (token, quoted) in tokenize(input)? {
+            if !quoted {
+                if let Some((key, value)) = token.split_once(':') {
+                    let key = key.to_lowercase();
+                    if value.is_empty() && KEYS.contains(&key.as_str()) {
+                        return Err(QueryError::MissingValue(key));
+                    }
+                    if let Some(filter) = Filter::parse(&key, value)? {
+                        query.filters.push(filter);
+                        continue;
+                    }
+                }
+            }
+            words.push(token);
+        }
+        query.text = words.join(" ");
+        Ok(query)
+    }
+
+    /// Look up the users named by owner filters. This may block on a network user database,
+    /// so it is done by the search instead of while the query is typed.
+    pub fn resolve_owners(&mut self) -> Result<(), QueryError> {
+        for filter in &mut self.filters {
+            if let Filter::OwnerName(name) = filter {
+                let user = uzers::get_user_by_name(name.as_str())
+                    .ok_or_else(|| QueryError::UnknownUser(name.clone()))?;
+                *filter = Filter::Owner(user.uid());
+            }
+        }
+        Ok(())
+    }
+
+    /// Text matched against names or contents
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.filters.is_empty()
+    }
+
+    /// Case insensitive matcher for the text, which is a regex only if requested
+    pub fn text_matcher(&self, is_regex: bool) -> Result<Option<regex::Regex>, QueryError> {
+        if self.text.is_empty() {
+            return Ok(None);
+        }
+        content_search::matcher(&self.text, is_regex)
+            .map(Some)
+            .map_err(QueryError::regex)
+    }
+
+    /// Check the filters against an item found by a search
+    pub fn matches(&self, path: &Path, metadata: &Metadata) -> bool {
+        self.filters
+            .iter()
+            .all(|filter| filter.matches(path, metadata))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn parse_filters() {
+        let query = Query::parse(r#"size:>100M config "ext:rs" ext:rs,.TOML value"#).unwrap();
+        assert_eq!(query.text(), "config ext:rs value");
+        assert_eq!(query.filters.len(), 2);
+        assert!(matches!(
+            query.filters[0],
+            Filter::Size(Compare::Greater, 104_857_600)
+        ));
+        assert!(matches!(&query.filters[1], Filter::Ext(exts) if exts == &["rs", "toml"]));
+
+        // Unknown keys are plain text
+        let query = Query::parse("http://localhost std::fs").unwrap();
+        assert_eq!(query.text(), "http://localhost std::fs");
+        assert!(query.filters.is_empty());
+
+        assert_eq!(parse_size("1.5k"), Some(1536));
+        assert_eq!(parse_size("2GiB"), Some(2 << 30));
+        assert_eq!(parse_age("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(
+            parse_kind("image/*"),
+            Some(Kind::MimeType("image".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (input, err) in [
+            ("size:>lots", QueryError::InvalidSize(">lots".into())),
+            ("modified:<7x", QueryError::InvalidTime("<7x".into())),
+            (
+                "modified:2024-13-01",
+                QueryError::InvalidTime("2024-13-01".into()),
+            ),
+            ("type:imag", QueryError::UnknownType("imag".into())),
+            ("ext:", QueryError::MissingValue("ext".into())),
+            ("\"open quote", QueryError::UnterminatedQuote),
+        ] {
+            assert_eq!(Query::parse(input).unwrap_err(), err, "{input}");
+        }
+        assert!(matches!(
+            Query::parse("name:/(unclosed/"),
+            Err(QueryError::InvalidRegex(_))
+        ));
+        assert!(matches!(
+            Query::parse("(unclosed").unwrap().text_matcher(true),
+            Err(QueryError::InvalidRegex(_))
+        ));
+
+        // User names are only looked up when resolved
+        let mut query = Query::parse("owner:no-such-user").unwrap();
+        assert_eq!(
+            query.resolve_owners(),
+            Err(QueryError::UnknownUser("no-such-user".into()))
+        );
+    }
+
+    #[test]
+    fn match_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Notes.tar.GZ");
+        fs::write(&path, vec![0; 2048]).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+
+        for (input, expected) in [
+            ("size:2k", true),
+            ("size:>2k", false),
+            ("size:<=1M", true),
+            ("modified:<1h", true),
+            ("modified:>1d", false),
+            ("type:file", true),
+            ("type:dir,image", false),
+            ("ext:gz", true),
+            ("ext:tar.gz", true),
+            ("ext:rs,toml", false),
+            ("owner:me", true),
+            ("name:/^notes\\.tar/", true),
+            ("name:notes", true),
+            ("name:/^tar/", false),
+        ] {
+            let mut query = Query::parse(input).unwrap();
+            query.resolve_owners().unwrap();
+            assert_eq!(query.matches(&path, &metadata), expected, "{input}");
+        }
+    }
+}
@@ -722,6 +722,7 @@ impl App {
                         path.to_path_buf(),
                         term,
                         self.tab.config.show_hidden,
+                        self.tab.location.search_mode(),
                         Instant::now(),
                     ),
                     true,
@@ -1147,14 +1148,15 @@ impl Application for App {
                         .into(),
                 );
             } else {
-                elements.push(
-                    widget::text_input::search_input("", term)
-                        .width(Length::Fixed(240.0))
-                        .id(self.search_id.clone())
-                        .on_clear(Message::SearchClear)
-                        .on_input(Message::SearchInput)
-                        .into(),
-                );
+                let mut search_input = widget::text_input::search_input("", term)
+                    .width(Length::Fixed(240.0))
+                    .id(self.search_id.clone())
+                    .on_clear(Message::SearchClear)
+                    .on_input(Message::SearchInput);
+                if let Some(error) = &self.tab.search_error {
+                    search_input = search_input.error(error.as_str());
+                }
+                elements.push(search_input.into());
             }
         } else {
             elements.push(
@@ -1935,16 +1937,15 @@ impl Application for App {
 
         if self.core.is_condensed() {
             if let Some(term) = self.search_get() {
-                col = col.push(
-                    widget::container(
-                        widget::text_input::search_input("", term)
-                            .width(Length::Fill)
-                            .id(self.search_id.clone())
-                            .on_clear(Message::SearchClear)
-                            .on_input(Message::SearchInput),
-                    )
-                    .padding(space_xxs),
-                )
+                let mut search_input = widget::text_input::search_input("", term)
+                    .width(Length::Fill)
+                    .id(self.search_id.clone())
+                    .on_clear(Message::SearchClear)
+                    .on_input(Message::SearchInput);
+                if let Some(error) = &self.tab.search_error {
+                    search_input = search_input.error(error.as_str());
+                }
+                col = col.push(widget::container(search_input).padding(space_xxs))
             }
         }
 
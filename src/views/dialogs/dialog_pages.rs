@@ -7,12 +7,16 @@ use cosmic::{
 };
 use std::{
     collections::VecDeque,
-    path::PathBuf,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
 };
 use tokio::sync::mpsc;
 use crate::{
     app::Message,
-    core::operations::ReplaceResult,
+    core::operations::{
+        Accounts, Controller, FailedItem, Interrupted, ModeMask, Operation, PlanSummary,
+        ReplaceResult, Violation,
+    },
     core::services::mount::{MounterAuth, MounterItem, MounterKey},
     tab,
     utils::mime_app::MimeApp,
@@ -21,19 +25,141 @@ use crate::{
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
 pub enum ArchiveType {
     Tgz,
+    #[cfg(feature = "bzip2")]
+    TarBz2,
+    #[cfg(feature = "lzma-rust2")]
+    TarXz,
+    #[cfg(feature = "zstd")]
+    TarZst,
+    #[cfg(feature = "sevenz-rust2")]
+    SevenZ,
     #[default]
     Zip,
+    Gz,
+    #[cfg(feature = "bzip2")]
+    Bz2,
+    #[cfg(feature = "lzma-rust2")]
+    Xz,
+    #[cfg(feature = "zstd")]
+    Zst,
 }
 
 impl ArchiveType {
+    const SINGLE_FILE: &'static [Self] = &[
+        Self::Gz,
+        #[cfg(feature = "bzip2")]
+        Self::Bz2,
+        #[cfg(feature = "lzma-rust2")]
+        Self::Xz,
+        #[cfg(feature = "zstd")]
+        Self::Zst,
+    ];
+
     pub fn all() -> &'static [Self] {
-        &[Self::Tgz, Self::Zip]
+        &[
+            Self::Tgz,
+            #[cfg(feature = "bzip2")]
+            Self::TarBz2,
+            #[cfg(feature = "lzma-rust2")]
+            Self::TarXz,
+            #[cfg(feature = "zstd")]
+            Self::TarZst,
+            #[cfg(feature = "sevenz-rust2")]
+            Self::SevenZ,
+            Self::Zip,
+            Self::Gz,
+            #[cfg(feature = "bzip2")]
+            Self::Bz2,
+            #[cfg(feature = "lzma-rust2")]
+            Self::Xz,
+            #[cfg(feature = "zstd")]
+            Self::Zst,
+        ]
+    }
+
+    /// Format of an archive named `name`. Compressed tarballs such as `foo.tar.gz` are never
+    /// taken for a compressed single file.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.ends_with(".tar.gz") {
+            return Some(Self::Tgz);
+        }
+        let archive_type = Self::all()
+            .iter()
+            .copied()
+            .find(|archive_type| name.ends_with(archive_type.extension()))?;
+        // Tarballs of other formats, such as those left out of the build, are refused
+        let stem = &name[..name.len() - archive_type.extension().len()];
+        if archive_type.is_single_file() && stem.ends_with(".tar") {
+            return None;
+        }
+        Some(archive_type)
+    }
+
+    /// Formats that can hold several files, see [`Self::is_single_file`]
+    pub fn archives() -> &'static [Self] {
+        let all = Self::all();
+        let end = all
+            .iter()
+            .position(Self::is_single_file)
+            .unwrap_or(all.len());
+        &all[..end]
+    }
+
+    /// If the format compresses a single file without a container around it
+    pub fn is_single_file(&self) -> bool {
+        Self::SINGLE_FILE.contains(self)
+    }
+
+    /// Name of an archive of `path` before the extension. Compressed single files keep their
+    /// own extension, like `foo.log.gz`.
+    pub fn base_name(&self, path: &Path) -> Option<String> {
+        let name = if self.is_single_file() {
+            path.file_name()
+        } else {
+            path.file_stem()
+        };
+        name.and_then(|name| name.to_str()).map(str::to_string)
     }
 
     pub fn extension(&self) -> &str {
         match self {
             ArchiveType::Tgz => ".tgz",
+            #[cfg(feature = "bzip2")]
+            ArchiveType::TarBz2 => ".tar.bz2",
+            #[cfg(feature = "lzma-rust2")]
+            ArchiveType::TarXz => ".tar.xz",
+            #[cfg(feature = "zstd")]
+            ArchiveType::TarZst => ".tar.zst",
+            #[cfg(feature = "sevenz-rust2")]
+            ArchiveType::SevenZ => ".7z",
             ArchiveType::Zip => ".zip",
+            ArchiveType::Gz => ".gz",
+            #[cfg(feature = "bzip2")]
+            ArchiveType::Bz2 => ".bz2",
+            #[cfg(feature = "lzma-rust2")]
+            ArchiveType::Xz => ".xz",
+            #[cfg(feature = "zstd")]
+            ArchiveType::Zst => ".zst",
+        }
+    }
+
+    /// Compression levels of the format, from fastest to smallest
+    pub fn levels(&self) -> RangeInclusive<u32> {
+        match self {
+            #[cfg(feature = "bzip2")]
+            ArchiveType::TarBz2 | ArchiveType::Bz2 => 1..=9,
+            // Higher levels need a lot more memory to decompress
+            #[cfg(feature = "zstd")]
+            ArchiveType::TarZst | ArchiveType::Zst => 1..=19,
+            _ => 0..=9,
+        }
+    }
+
+    pub fn default_level(&self) -> u32 {
+        match self {
+            #[cfg(feature = "zstd")]
+            ArchiveType::TarZst | ArchiveType::Zst => 3,
+            _ => 6,
         }
     }
 }
@@ -44,20 +170,61 @@ impl AsRef<str> for ArchiveType {
     }
 }
 
+/// How zip archives with a password are encrypted
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ZipEncryption {
+    #[default]
+    Aes256,
+    /// Legacy encryption, which is easily broken but can be opened by older programs
+    ZipCrypto,
+}
+
+impl ZipEncryption {
+    pub fn all() -> &'static [Self] {
+        &[Self::Aes256, Self::ZipCrypto]
+    }
+}
+
+impl AsRef<str> for ZipEncryption {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Aes256 => "AES-256",
+            Self::ZipCrypto => "ZipCrypto",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum DialogPage {
+    /// Confirm adding items dropped on an archive
+    ArchiveAdd {
+        archive: PathBuf,
+        paths: Vec<PathBuf>,
+    },
     Compress {
         paths: Vec<PathBuf>,
         to: PathBuf,
         name: String,
         archive_type: ArchiveType,
+        level: u32,
         password: Option<String>,
+        encryption: ZipEncryption,
+        /// Estimated size of the archive, calculated in the background
+        estimate: Option<u64>,
     },
     EmptyTrash,
     FailedOperation(u64),
+    /// Items that failed while the operation continued, and the operations retrying them
+    FailureReport {
+        failures: Vec<FailedItem>,
+        retry: Vec<Operation>,
+    },
+    InterruptedOperation(Interrupted),
+    /// Ask for the password of an archive, again if `wrong` is set
     ExtractPassword {
         id: u64,
         password: String,
+        wrong: bool,
     },
     MountError {
         mounter_key: MounterKey,
@@ -89,6 +256,25 @@ pub enum DialogPage {
     PermanentlyDelete {
         paths: Vec<PathBuf>,
     },
+    /// Change permissions and ownership of items. Modes are octal text, left unchanged if both
+    /// are empty.
+    Permissions {
+        paths: Vec<PathBuf>,
+        files: String,
+        dirs: String,
+        mask: ModeMask,
+        recursive: bool,
+        owner: Option<u32>,
+        group: Option<u32>,
+        users: Accounts,
+        groups: Accounts,
+    },
+    /// What a copy or move will do, planned by `controller` until `summary` is set
+    PlanPreview {
+        operation: Operation,
+        summary: Option<Result<PlanSummary, String>>,
+        controller: Controller,
+    },
     RenameItem {
         from: PathBuf,
         parent: PathBuf,
@@ -105,6 +291,23 @@ pub enum DialogPage {
     SetExecutableAndLaunch {
         path: PathBuf,
     },
+    /// Confirm shredding, warning if overwriting may not reach the disk
+    Shred {
+        paths: Vec<PathBuf>,
+        warning: Option<String>,
+    },
+    /// Items of a failed copy or move that the destination filesystem cannot store
+    Unsupported {
+        id: u64,
+        fs_type: String,
+        violations: Vec<Violation>,
+    },
+    VerifyFailed {
+        id: u64,
+        from: PathBuf,
+        path: PathBuf,
+        journal: Option<PathBuf>,
+    },
     FavoritePathError {
         path: PathBuf,
         entity: cosmic::widget::segmented_button::Entity,
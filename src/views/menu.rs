@@ -219,6 +219,7 @@ pub fn context_menu<'a>(
                 if selected_types.is_empty() {
                     children.push(menu_item(fl!("extract-here"), Action::ExtractHere).into());
                     children.push(menu_item(fl!("extract-to"), Action::ExtractTo).into());
+                    children.push(menu_item(fl!("test-archive"), Action::TestArchive).into());
                 }
                 children.push(menu_item(fl!("compress"), Action::Compress).into());
                 children.push(divider::horizontal::light().into());
@@ -244,6 +245,7 @@ pub fn context_menu<'a>(
                     } else {
                         children.push(menu_item(fl!("move-to-trash"), Action::Delete).into());
                     }
+                    children.push(menu_item(fl!("shred-items"), Action::Shred).into());
                 } else if selected == 1 {
                     children.push(menu_item(fl!("eject"), Action::Eject).into());
                 }
@@ -258,6 +260,8 @@ pub fn context_menu<'a>(
                     children.push(menu_item(fl!("select-all"), Action::SelectAll).into());
                 }
                 children.push(menu_item(fl!("paste"), Action::Paste).into());
+                children.push(menu_item(fl!("paste-as-link"), Action::PasteLink).into());
+                children.push(menu_item(fl!("paste-as-hard-link"), Action::PasteHardLink).into());
 
                 //TODO: only show if cosmic-settings is found?
                 if matches!(tab.mode, tab::Mode::Desktop) {
@@ -323,6 +327,30 @@ pub fn context_menu<'a>(
                 children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
             }
         }
+        (_, Location::Archive(..)) => {
+            if selected > 0 {
+                if selected_dir == 1 && selected == 1 || selected_dir == 0 {
+                    children.push(menu_item(fl!("open"), Action::Open).into());
+                }
+                if matches!(tab.mode, tab::Mode::App) {
+                    children.push(divider::horizontal::light().into());
+                    children.push(menu_item(fl!("copy"), Action::Copy).into());
+                    children.push(menu_item(fl!("extract-to"), Action::ExtractTo).into());
+                }
+                children.push(divider::horizontal::light().into());
+                children.push(menu_item(fl!("show-details"), Action::Preview).into());
+            } else {
+                if tab.mode.multiple() {
+                    children.push(menu_item(fl!("select-all"), Action::SelectAll).into());
+                }
+                if !children.is_empty() {
+                    children.push(divider::horizontal::light().into());
+                }
+                children.push(sort_item(fl!("sort-by-name"), HeadingOptions::Name));
+                children.push(sort_item(fl!("sort-by-modified"), HeadingOptions::Modified));
+                children.push(sort_item(fl!("sort-by-size"), HeadingOptions::Size));
+            }
+        }
         (_, Location::Network(..)) => {
             if selected > 0 {
                 if selected_dir == 1 && selected == 1 || selected_dir == 0 {
@@ -637,9 +665,18 @@ pub fn menu_bar<'a>(
                 (
                     (fl!("edit")),
                     vec![
+                        menu::Item::Button(fl!("undo"), None, Action::Undo),
+                        menu::Item::Button(fl!("redo"), None, Action::Redo),
+                        menu::Item::Divider,
                         menu_button_optional(fl!("cut"), Action::Cut, selected > 0),
                         menu_button_optional(fl!("copy"), Action::Copy, selected > 0),
                         menu_button_optional(fl!("paste"), Action::Paste, selected > 0),
+                        menu_button_optional(fl!("paste-as-link"), Action::PasteLink, selected > 0),
+                        menu_button_optional(
+                            fl!("paste-as-hard-link"),
+                            Action::PasteHardLink,
+                            selected > 0,
+                        ),
                         menu::Item::Button(fl!("select-all"), None, Action::SelectAll),
                         menu::Item::Divider,
                         menu::Item::Button(fl!("history"), None, Action::EditHistory),